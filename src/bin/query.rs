@@ -26,7 +26,7 @@ fn main() -> Result<()> {
         println!("==============================================");
         println!("Query: {query}");
         let emb = embedder
-            .embed(query)
+            .embed(&config.query_text(query))
             .map_err(|e| anyhow::anyhow!("Failed to embed query: {e}"))?;
         let results = db
             .search(&emb, 3)
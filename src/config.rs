@@ -2,28 +2,45 @@
 ///
 /// Handles loading, validating, and providing default configuration values.
 /// Mirrors the Go version's `internal/config/config.go`.
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 /// The vector dimension required by the sqlite-vec schema (vec_chunks float32[N]).
-const SCHEMA_VEC_DIMENSIONS: usize = 1024;
+const SCHEMA_VEC_DIMENSIONS: usize = crate::db::VEC_DIMENSIONS;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
+use crate::indexer::languages::LanguageConfig;
+
 // The set of extensions the indexer can handle
 const SUPPORTED_EXTENSIONS: &[&str] = &[
     // 代码
     "md", "rs", "go", "py", "js", "mjs", "cjs", "jsx", // JavaScript (标准 + ESM + CJS + JSX)
     "ts", "mts", "cts", "tsx", // TypeScript (标准 + ESM + CJS + TSX)
+    "java", "c", "h", "cpp", "cc", "cxx", "hpp", "hh", "hxx", // Java / C / C++
     // 纯文本
-    "txt", "log", // 结构化数据
+    "txt", "log", "rst", "adoc", // 结构化数据
     "json", "yaml", "yml", "toml", "csv", // HTML
     "html", "htm", // 二进制文档
     "pdf", "docx", "xls", "xlsx", "xlsb", "ods",
 ];
 
+/// Binary formats whose size on disk says little about the text extracted
+/// from them; `max_file_size_bytes` doesn't apply to these.
+const BINARY_DOCUMENT_EXTENSIONS: &[&str] = &["pdf", "docx", "xls", "xlsx", "xlsb", "ods"];
+
+/// Prose formats chunked like markdown by default. `txt` keeps the plain
+/// text path, which tracks line numbers.
+const DEFAULT_DOCUMENT_EXTENSIONS: &[&str] = &["md", "rst", "adoc"];
+
+/// Extensions handled only when built with the `notebook` feature.
+#[cfg(feature = "notebook")]
+const NOTEBOOK_EXTENSIONS: &[&str] = &["ipynb"];
+#[cfg(not(feature = "notebook"))]
+const NOTEBOOK_EXTENSIONS: &[&str] = &[];
+
 // ── Default value functions ──────────────────────────────────────────
 
 fn default_document_patterns() -> Vec<String> {
@@ -95,8 +112,35 @@ fn default_timeout_secs() -> u64 {
     30
 }
 
+fn default_cache_capacity() -> usize {
+    1024
+}
+
 fn default_file_extensions() -> Vec<String> {
-    SUPPORTED_EXTENSIONS.iter().map(|s| s.to_string()).collect()
+    SUPPORTED_EXTENSIONS
+        .iter()
+        .chain(NOTEBOOK_EXTENSIONS)
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn default_similarity_precision() -> usize {
+    4
+}
+
+fn default_max_file_size_bytes() -> Option<u64> {
+    Some(1024 * 1024)
+}
+
+fn default_max_backups() -> usize {
+    5
+}
+
+fn default_document_extensions() -> Vec<String> {
+    DEFAULT_DOCUMENT_EXTENSIONS
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
 }
 
 /// Expand `~` at the start of a path to the user's home directory.
@@ -106,10 +150,10 @@ fn default_file_extensions() -> Vec<String> {
 /// - `"/absolute/path"` → unchanged
 /// - `"./relative"` → unchanged
 fn expand_tilde(path: &str) -> PathBuf {
-    if path.starts_with("~/") || path.starts_with("~\\") {
-        if let Some(home) = dirs::home_dir() {
-            return home.join(&path[2..]);
-        }
+    if (path.starts_with("~/") || path.starts_with("~\\"))
+        && let Some(home) = dirs::home_dir()
+    {
+        return home.join(&path[2..]);
     }
     PathBuf::from(path)
 }
@@ -128,9 +172,28 @@ pub struct Config {
     #[serde(default = "default_exclude_patterns")]
     pub exclude_patterns: Vec<String>,
 
+    /// Extra `.gitignore`-style patterns applied when walking directories,
+    /// on top of any `.gitignore` files. `!pattern` re-includes a path
+    /// excluded by an earlier entry here (not one excluded by `.gitignore`
+    /// or `exclude_patterns`).
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+
+    /// Index files and directories whose name starts with a dot. Combine
+    /// with `ignore_patterns` (e.g. `[".*", "!.github/"]`) to include only
+    /// some of them.
+    #[serde(default = "default_true")]
+    pub include_hidden: bool,
+
     #[serde(default = "default_file_extensions")]
     pub file_extensions: Vec<String>,
 
+    /// Extensions indexed as prose documents: split into chunks the way
+    /// markdown is. Only routes files; an extension must also be listed in
+    /// `file_extensions` to be indexed. Defaults to `md`, `rst` and `adoc`.
+    #[serde(default = "default_document_extensions")]
+    pub document_extensions: Vec<String>,
+
     /// Base directory for all RustRAG data (models, database, etc.).
     /// Defaults to `~/.rustrag`. Supports `~` expansion.
     #[serde(default = "default_data_dir")]
@@ -142,15 +205,199 @@ pub struct Config {
     #[serde(default = "default_chunk_size")]
     pub chunk_size: usize,
 
+    /// How markdown files are split into chunks: `"size"` (the default) packs
+    /// paragraphs up to `chunk_size` regardless of structure, `"heading"`
+    /// keeps each section together (prefixed with its heading trail).
+    #[serde(default)]
+    pub chunk_strategy: ChunkStrategy,
+
+    /// Characters from the end of each size-split chunk that are repeated at
+    /// the start of the next one, so text cut at a boundary keeps its context.
+    /// Must be smaller than `chunk_size`; `0` disables overlap. Not applied
+    /// to markdown under `chunk_strategy: "heading"`.
+    #[serde(default)]
+    pub chunk_overlap: usize,
+
     #[serde(default = "default_search_top_k")]
     pub search_top_k: usize,
 
+    /// Default weight of the recency factor added to search scores
+    /// (0 disables it). See [`crate::db::search::rerank`].
+    #[serde(default)]
+    pub recency_boost: f64,
+
+    /// Search score multiplier per frontmatter `docType` (e.g. `{"api": 1.2}`).
+    /// Documents with other or no types keep their score.
+    #[serde(default)]
+    pub boost_doc_types: HashMap<String, f64>,
+
+    /// Append the split form of camelCase/snake_case identifiers in search
+    /// queries ("getUserById" → "get user by id") to the text that gets
+    /// embedded, so code-style queries also match prose.
+    #[serde(default)]
+    pub split_identifiers_in_query: bool,
+
+    /// Omit search results with a similarity of zero or below. Separate
+    /// from any explicit threshold; can be overridden per search.
+    #[serde(default = "default_true")]
+    pub suppress_nonpositive_similarity: bool,
+
+    /// Drop search results whose content is identical to a higher-ranked
+    /// result up to case and whitespace (e.g. code copied between files).
+    /// Can be overridden per search.
+    #[serde(default)]
+    pub dedup_results: bool,
+
+    /// Queries shorter than this many characters (ignoring surrounding
+    /// whitespace) embed poorly, so vector and hybrid search treat them as
+    /// configured by `short_query`. 0 disables the check.
+    #[serde(default)]
+    pub min_query_chars: usize,
+
+    /// What search does with a query below `min_query_chars`.
+    #[serde(default)]
+    pub short_query: ShortQueryAction,
+
+    /// Decimals `similarity` is rounded to in search results. The raw
+    /// cosine `distance` is reported unrounded next to it.
+    #[serde(default = "default_similarity_precision")]
+    pub similarity_precision: usize,
+
+    /// Prepended to search queries before embedding. Instruction-tuned
+    /// models such as multilingual-e5 expect `"query: "`; empty by default.
+    #[serde(default)]
+    pub query_prefix: String,
+
+    /// Prepended to indexed chunks before embedding (`"passage: "` for
+    /// multilingual-e5). Changing it requires a forced reindex.
+    #[serde(default)]
+    pub passage_prefix: String,
+
+    /// Word mappings extracted by `build_dictionary` with a confidence below
+    /// this (0.0–1.0) are dropped instead of stored. 0 keeps everything.
+    #[serde(default)]
+    pub dictionary_min_confidence: f32,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub update_check: Option<bool>,
 
+    /// GitHub repository (`owner/repo`) checked for new releases. Defaults
+    /// to the upstream RustRAG repository; malformed values are ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_repo: Option<String>,
+
+    /// Collapse runs of spaces and blank lines in markdown prose before it is
+    /// chunked and embedded. Fenced code blocks are left untouched, and other
+    /// text formats (YAML, JSON, TOML, ...) are never normalized.
+    #[serde(default)]
+    pub normalize_whitespace: bool,
+
+    /// Remove comments from the text fed to the embedder for code chunks.
+    /// The stored chunk content still includes them.
+    #[serde(default)]
+    pub strip_comments_from_code_embedding: bool,
+
+    /// Cut code chunk content fed to the embedder after this many characters,
+    /// at the last whitespace before the limit, so huge (e.g. generated)
+    /// functions embed their head instead of being cut off at an arbitrary
+    /// token by the model. The stored content stays complete. 0 disables it.
+    #[serde(default)]
+    pub max_embed_chars: usize,
+
+    /// Per-language limit on a code chunk's estimated token count
+    /// (e.g. `{"rust": 512}`). Symbols over the limit are split into
+    /// consecutive chunks at statement boundaries that all carry the
+    /// symbol's name; definition lookups and outlines still list the symbol
+    /// once. Languages without an entry are never split.
+    #[serde(default)]
+    pub max_code_chunk_tokens: HashMap<String, usize>,
+
+    /// Store each symbol's doc comment / docstring as an extra prose chunk
+    /// linked to the symbol's chunk. The doc text is then left out of the
+    /// symbol's own embedding so it is only embedded once.
+    #[serde(default)]
+    pub index_doc_comments: bool,
+
+    /// Also index fenced code blocks in markdown files whose info string
+    /// names a supported language (```` ```python ````) as code chunks.
+    #[serde(default)]
+    pub extract_markdown_code_blocks: bool,
+
+    /// Index a markdown file's YAML frontmatter as its own `frontmatter`
+    /// chunk. Prose chunks never include it either way.
+    #[serde(default)]
+    pub index_frontmatter: bool,
+
+    /// With `extract_markdown_code_blocks`, record the calls, imports and
+    /// inheritance found in those blocks in the relation graph, with the
+    /// markdown file as their source.
+    #[serde(default)]
+    pub fence_relations: bool,
+
+    /// Leave tests out of code indexing: test files by their language's
+    /// naming conventions (`*_test.go`, `test_*.py`, `*.spec.ts`, ...) and
+    /// test-only Rust items (`#[test]`, `#[cfg(test)]`).
+    #[serde(default)]
+    pub exclude_test_code: bool,
+
+    /// Use a markdown file's first `# Heading` as its title when the
+    /// frontmatter has no `title:`. On by default.
+    #[serde(default = "default_true")]
+    pub title_from_h1: bool,
+
+    /// Tree-sitter languages to parse code in (e.g. `["rust", "go"]`).
+    /// Files in other languages are left out and their grammars' queries
+    /// are never compiled. All languages when unset.
+    #[serde(default)]
+    pub enabled_languages: Option<Vec<String>>,
+
+    /// Store a file whose content is identical to an already indexed
+    /// document (e.g. a vendored copy) as an alias of that document instead
+    /// of embedding it again. Aliases are listed but never appear in search
+    /// results; when the original is deleted, an alias takes over its vectors.
+    #[serde(default)]
+    pub dedup_documents_by_hash: bool,
+
+    /// Index code and markdown files that look generated or minified
+    /// (a `@generated` / "DO NOT EDIT" marker near the top, or very long
+    /// lines on average). Skipped by default.
+    #[serde(default)]
+    pub index_generated: bool,
+
+    /// Re-run the differential sync (with prune) over the base directories
+    /// every this many seconds while the server runs, to catch changes the
+    /// file watcher missed. Read at startup; 0 disables it.
+    #[serde(default)]
+    pub resync_interval_secs: u64,
+
+    /// Skip text and code files larger than this many bytes (lockfiles,
+    /// vendored bundles, ...). Binary documents such as PDFs are exempt.
+    /// `null` removes the limit (JSON and YAML only; TOML has no null).
+    /// Default 1 MiB.
+    #[serde(default = "default_max_file_size_bytes")]
+    pub max_file_size_bytes: Option<u64>,
+
+    /// Copy the DB file to `<db>.bak-<timestamp>` before destructive operations
+    /// (forced reindex, document deletion).
+    #[serde(default)]
+    pub auto_backup_before_destructive: bool,
+
+    /// How many of those backups to keep; older ones are deleted after each
+    /// new backup. `0` keeps them all. Default 5.
+    #[serde(default = "default_max_backups")]
+    pub max_backups: usize,
+
+    /// Air-gapped mode: skip the update check and require a local
+    /// (loopback) embedding endpoint.
+    #[serde(default)]
+    pub offline: bool,
+
+    /// Deprecated: ignored since the ONNX backend was removed in v3.0.0.
+    /// Device selection now belongs to the embedding API server.
     #[serde(default)]
     pub compute: ComputeConfig,
 
+    /// Deprecated: ignored since v3.0.0; use `embedding.api_model` instead.
     #[serde(default)]
     pub model: ModelConfig,
 
@@ -159,6 +406,62 @@ pub struct Config {
     pub embedding: EmbeddingConfig,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkStrategy {
+    /// Paragraph packing by character count.
+    #[default]
+    Size,
+    /// One chunk per heading section, split further only when oversized.
+    Heading,
+}
+
+/// How search handles a query shorter than [`Config::min_query_chars`].
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShortQueryAction {
+    /// Run a keyword (full-text) search instead of a vector search.
+    #[default]
+    Keyword,
+    /// Reject the query, asking for a longer one.
+    Error,
+}
+
+/// Serialization format of a config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Format for a config path by extension: `.yaml`/`.yml` is YAML,
+    /// `.toml` is TOML and anything else JSON.
+    #[must_use]
+    pub fn from_path(path: &str) -> Self {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match ext.as_deref() {
+            Some("yaml" | "yml") => Self::Yaml,
+            Some("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+
+    /// Name used in error messages.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Json => "JSON",
+            Self::Yaml => "YAML",
+            Self::Toml => "TOML",
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ComputeConfig {
     #[serde(default = "default_device")]
@@ -213,6 +516,18 @@ pub struct EmbeddingConfig {
     /// Request timeout in seconds.
     #[serde(default = "default_timeout_secs")]
     pub timeout_secs: u64,
+
+    /// Give up on a single `embed` / `embed_batch` call after this many
+    /// seconds, failing the file being indexed instead of stalling the sync.
+    /// Covers the whole call, retries included. 0 disables the limit.
+    #[serde(default)]
+    pub embed_timeout_secs: u64,
+
+    /// Number of recently embedded texts whose vectors are kept in memory,
+    /// so identical chunks (license headers, boilerplate) are embedded once.
+    /// 0 disables the cache.
+    #[serde(default = "default_cache_capacity")]
+    pub cache_capacity: usize,
 }
 
 impl EmbeddingConfig {
@@ -223,14 +538,30 @@ impl EmbeddingConfig {
     #[must_use]
     pub fn resolve_api_key(&self) -> String {
         for var in &["RAG_API_KEY", "DASHSCOPE_API_KEY", "OPENAI_API_KEY"] {
-            if let Ok(key) = std::env::var(var) {
-                if !key.is_empty() {
-                    return key;
-                }
+            if let Ok(key) = std::env::var(var)
+                && !key.is_empty()
+            {
+                return key;
             }
         }
         self.api_key.clone()
     }
+
+    /// Whether `api_url` points at this machine (localhost or a loopback IP).
+    #[must_use]
+    pub fn is_local_endpoint(&self) -> bool {
+        let Ok(url) = reqwest::Url::parse(&self.api_url) else {
+            return false;
+        };
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        host.eq_ignore_ascii_case("localhost")
+            || host
+                .parse::<std::net::IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback())
+    }
 }
 
 // ── Default impls ────────────────────────────────────────────────────
@@ -241,12 +572,47 @@ impl Default for Config {
             documents_dir: None,
             document_patterns: default_document_patterns(),
             exclude_patterns: default_exclude_patterns(),
+            ignore_patterns: Vec::new(),
+            include_hidden: true,
             file_extensions: default_file_extensions(),
+            document_extensions: default_document_extensions(),
             data_dir: default_data_dir(),
             db_path: default_db_path(),
             chunk_size: default_chunk_size(),
+            chunk_strategy: ChunkStrategy::default(),
+            chunk_overlap: 0,
             search_top_k: default_search_top_k(),
+            recency_boost: 0.0,
+            boost_doc_types: HashMap::new(),
+            split_identifiers_in_query: false,
+            suppress_nonpositive_similarity: true,
+            dedup_results: false,
+            min_query_chars: 0,
+            short_query: ShortQueryAction::default(),
+            similarity_precision: default_similarity_precision(),
+            query_prefix: String::new(),
+            passage_prefix: String::new(),
+            dictionary_min_confidence: 0.0,
             update_check: None,
+            update_repo: None,
+            normalize_whitespace: false,
+            strip_comments_from_code_embedding: false,
+            max_embed_chars: 0,
+            max_code_chunk_tokens: HashMap::new(),
+            index_doc_comments: false,
+            extract_markdown_code_blocks: false,
+            index_frontmatter: false,
+            fence_relations: false,
+            exclude_test_code: false,
+            title_from_h1: true,
+            enabled_languages: None,
+            dedup_documents_by_hash: false,
+            index_generated: false,
+            resync_interval_secs: 0,
+            max_file_size_bytes: default_max_file_size_bytes(),
+            auto_backup_before_destructive: false,
+            max_backups: default_max_backups(),
+            offline: false,
             compute: ComputeConfig::default(),
             model: ModelConfig::default(),
             embedding: EmbeddingConfig::default(),
@@ -283,6 +649,8 @@ impl Default for EmbeddingConfig {
             batch_size: default_batch_size(),
             max_concurrent: default_max_concurrent(),
             timeout_secs: default_timeout_secs(),
+            embed_timeout_secs: 0,
+            cache_capacity: default_cache_capacity(),
         }
     }
 }
@@ -290,20 +658,65 @@ impl Default for EmbeddingConfig {
 // ── Config implementation ────────────────────────────────────────────
 
 impl Config {
-    /// Whether update checking is enabled (defaults to `true`).
+    /// Whether update checking is enabled (defaults to `true`, always `false`
+    /// in offline mode).
     #[must_use]
     pub fn is_update_check_enabled(&self) -> bool {
-        self.update_check.unwrap_or(true)
+        !self.offline && self.update_check.unwrap_or(true)
+    }
+
+    /// Text sent to the embedder for a search query.
+    #[must_use]
+    pub fn query_text(&self, query: &str) -> String {
+        format!("{}{query}", self.query_prefix)
+    }
+
+    /// The configured [`ShortQueryAction`] if `query` is shorter than
+    /// `min_query_chars`, counted in characters after trimming whitespace.
+    #[must_use]
+    pub fn short_query_action(&self, query: &str) -> Option<ShortQueryAction> {
+        (query.trim().chars().count() < self.min_query_chars).then_some(self.short_query)
+    }
+
+    /// Text sent to the embedder for an indexed chunk.
+    #[must_use]
+    pub fn passage_text(&self, text: &str) -> String {
+        format!("{}{text}", self.passage_prefix)
     }
 
     /// Check if a file extension is supported for indexing.
     /// Uses `file_extensions` allowlist (defaults to all supported extensions).
     #[must_use]
     pub fn is_file_extension_supported(&self, ext: &str) -> bool {
-        self.file_extensions.iter().any(|e| e == ext)
+        self.file_extensions.iter().any(|e| e == ext) && self.is_language_enabled(ext)
+    }
+
+    /// Whether code with this extension is in `enabled_languages`.
+    /// Extensions without a tree-sitter grammar are always enabled.
+    #[must_use]
+    pub fn is_language_enabled(&self, ext: &str) -> bool {
+        let Some(names) = &self.enabled_languages else {
+            return true;
+        };
+        LanguageConfig::get_by_extension(ext).is_none_or(|c| names.iter().any(|n| n == c.name))
     }
 
-    /// Load configuration from a JSON file.
+    /// Whether a file with this extension and size in bytes is over
+    /// `max_file_size_bytes` and should be skipped.
+    #[must_use]
+    pub fn exceeds_max_file_size(&self, ext: &str, len: u64) -> bool {
+        self.max_file_size_bytes.is_some_and(|max| len > max)
+            && !BINARY_DOCUMENT_EXTENSIONS.contains(&ext)
+    }
+
+    /// Whether files with this extension are indexed as prose documents.
+    #[must_use]
+    pub fn is_document_extension(&self, ext: &str) -> bool {
+        self.document_extensions.iter().any(|e| e == ext)
+    }
+
+    /// Load configuration from a JSON, YAML or TOML file, chosen by extension
+    /// (see [`ConfigFormat::from_path`]).
     ///
     /// If `config_path` is empty, defaults to `"config.json"`.
     /// If the file does not exist, returns a default config and optionally
@@ -330,11 +743,23 @@ impl Config {
             Err(e) => return Err(e).context(format!("failed to read config: {path}")),
         };
 
-        // Parse with defaults - use context for better error messages
-        let mut cfg: Config = serde_json::from_str(&data)
-            .with_context(|| format!("invalid JSON in config file: {path}"))?;
-
+        let cfg = Self::parse(&data, path)?;
         info!("Loaded configuration from {path}");
+        Ok(cfg)
+    }
+
+    /// Parse config file contents in the format of `path` and apply
+    /// migrations and path expansion.
+    fn parse(data: &str, path: &str) -> Result<Self> {
+        // Parse with defaults - use context for better error messages
+        let format = ConfigFormat::from_path(path);
+        let parsed = match format {
+            ConfigFormat::Json => serde_json::from_str(data).map_err(anyhow::Error::from),
+            ConfigFormat::Yaml => serde_yaml::from_str(data).map_err(anyhow::Error::from),
+            ConfigFormat::Toml => toml::from_str(data).map_err(anyhow::Error::from),
+        };
+        let mut cfg: Config =
+            parsed.with_context(|| format!("invalid {} in config file: {path}", format.name()))?;
 
         // Migrate old `documents_dir` → `document_patterns`
         if let Some(ref old_dir) = cfg.documents_dir {
@@ -357,9 +782,74 @@ impl Config {
         Ok(cfg)
     }
 
-    /// Save configuration to a JSON file.
+    /// Load and fully check a config file for `rustrag validate`.
+    ///
+    /// Unlike [`Config::load`], a missing file is an error rather than a
+    /// generated template. Besides [`Config::validate`], every pattern's base
+    /// directory must exist. Returns the effective config as JSON, with the
+    /// API key resolved from the environment and masked.
+    pub fn check_file(path: &str, offline: bool) -> Result<serde_json::Value> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config: {path}"))?;
+        let mut cfg = Self::parse(&data, path)?;
+        if offline {
+            cfg.offline = true;
+        }
+        cfg.validate()?;
+
+        let mut missing: Vec<String> = cfg
+            .get_base_directories()
+            .into_iter()
+            .filter(|dir| !dir.is_dir())
+            .map(|dir| dir.display().to_string())
+            .collect();
+        missing.sort();
+        anyhow::ensure!(
+            missing.is_empty(),
+            "document base directories do not exist: {}",
+            missing.join(", ")
+        );
+
+        serde_json::to_value(cfg.redacted()).context("failed to marshal config")
+    }
+
+    /// Copy of the config with secrets masked, for display and logging.
+    ///
+    /// `embedding.api_key` becomes `***` whenever a key is in effect, whether
+    /// it comes from the file or an environment variable.
+    #[must_use]
+    pub fn redacted(&self) -> Self {
+        let mut cfg = self.clone();
+        if !cfg.embedding.resolve_api_key().is_empty() {
+            cfg.embedding.api_key = "***".to_string();
+        }
+        cfg
+    }
+
+    /// The effective config as single-line JSON with secrets masked.
+    #[must_use]
+    pub fn redacted_json(&self) -> String {
+        serde_json::to_string(&self.redacted()).unwrap_or_default()
+    }
+
+    /// Save configuration to a file, as JSON, YAML or TOML depending on its
+    /// extension (see [`ConfigFormat::from_path`]). Fails for TOML when
+    /// `max_file_size_bytes` is `None`, which TOML can't represent.
     pub fn save(&self, path: &str) -> Result<()> {
-        let data = serde_json::to_string_pretty(self).context("failed to marshal config")?;
+        let data = match ConfigFormat::from_path(path) {
+            ConfigFormat::Json => serde_json::to_string_pretty(self).map_err(anyhow::Error::from),
+            ConfigFormat::Yaml => serde_yaml::to_string(self).map_err(anyhow::Error::from),
+            ConfigFormat::Toml => {
+                // TOML has no null: an omitted limit would reload as the default
+                anyhow::ensure!(
+                    self.max_file_size_bytes.is_some(),
+                    "TOML cannot express max_file_size_bytes = null (no size limit); \
+                     use a JSON or YAML config file instead"
+                );
+                toml::to_string_pretty(self).map_err(anyhow::Error::from)
+            }
+        }
+        .context("failed to marshal config")?;
         std::fs::write(path, data).with_context(|| format!("failed to write config: {path}"))?;
         Ok(())
     }
@@ -367,7 +857,43 @@ impl Config {
     /// Validate configuration values.
     pub fn validate(&self) -> Result<()> {
         anyhow::ensure!(self.chunk_size > 0, "chunk_size must be positive");
+        anyhow::ensure!(
+            self.chunk_overlap < self.chunk_size,
+            "chunk_overlap must be smaller than chunk_size"
+        );
         anyhow::ensure!(self.search_top_k > 0, "search_top_k must be positive");
+        for name in self.enabled_languages.iter().flatten() {
+            anyhow::ensure!(
+                LanguageConfig::get_by_name(name).is_some(),
+                "enabled_languages: unknown language '{name}'"
+            );
+        }
+        for (name, limit) in &self.max_code_chunk_tokens {
+            anyhow::ensure!(
+                LanguageConfig::get_by_name(name).is_some(),
+                "max_code_chunk_tokens: unknown language '{name}'"
+            );
+            anyhow::ensure!(*limit > 0, "max_code_chunk_tokens.{name} must be positive");
+        }
+        anyhow::ensure!(
+            self.recency_boost.is_finite() && self.recency_boost >= 0.0,
+            "recency_boost must be a non-negative number"
+        );
+        for (doc_type, boost) in &self.boost_doc_types {
+            anyhow::ensure!(
+                boost.is_finite() && *boost >= 0.0,
+                "boost_doc_types.{doc_type} must be a non-negative number"
+            );
+        }
+        anyhow::ensure!(
+            (0.0..=1.0).contains(&self.dictionary_min_confidence),
+            "dictionary_min_confidence must be between 0 and 1"
+        );
+        anyhow::ensure!(
+            self.similarity_precision <= 16,
+            "similarity_precision must be at most 16, got {}",
+            self.similarity_precision
+        );
         anyhow::ensure!(
             self.embedding.dimensions > 0,
             "embedding.dimensions must be positive"
@@ -398,6 +924,12 @@ impl Config {
             !self.document_patterns.is_empty(),
             "at least one document pattern must be specified"
         );
+        anyhow::ensure!(
+            !self.offline || self.embedding.is_local_endpoint(),
+            "offline mode requires a local embedding endpoint (localhost or loopback IP), \
+             but embedding.api_url is {}",
+            self.embedding.api_url
+        );
         Ok(())
     }
 
@@ -406,7 +938,9 @@ impl Config {
         let mut files = HashSet::new();
 
         for pattern in &self.document_patterns {
-            match expand_pattern(pattern) {
+            match expand_pattern(pattern, &|ext| {
+                is_known_extension(ext) || self.is_file_extension_supported(ext)
+            }) {
                 Ok(matches) => {
                     for m in matches {
                         files.insert(m);
@@ -441,22 +975,25 @@ impl Config {
 
 /// Check if a file extension is in the static supported list (no Config needed).
 fn is_known_extension(ext: &str) -> bool {
-    SUPPORTED_EXTENSIONS.contains(&ext)
+    SUPPORTED_EXTENSIONS.contains(&ext) || NOTEBOOK_EXTENSIONS.contains(&ext)
 }
 
-/// Expand a single pattern to matching supported files.
-fn expand_pattern(pattern: &str) -> Result<Vec<PathBuf>> {
+/// Expand a single pattern to matching files whose extension passes `supported`.
+fn expand_pattern(pattern: &str, supported: &dyn Fn(&str) -> bool) -> Result<Vec<PathBuf>> {
     // If pattern contains no wildcards, treat as a directory
     if !pattern.contains('*') && !pattern.contains('?') {
-        return walk_dir_for_supported_files(Path::new(pattern));
+        return walk_dir_for_supported_files(Path::new(pattern), supported);
     }
 
     // Handle ** (recursive glob) using `ignore` crate which respects gitignore
-    double_star_glob(pattern)
+    double_star_glob(pattern, supported)
 }
 
 /// Walk a directory recursively for all supported file types using the `ignore` crate.
-fn walk_dir_for_supported_files(dir: &Path) -> Result<Vec<PathBuf>> {
+fn walk_dir_for_supported_files(
+    dir: &Path,
+    supported: &dyn Fn(&str) -> bool,
+) -> Result<Vec<PathBuf>> {
     use ignore::WalkBuilder;
     let mut files = Vec::new();
     if !dir.exists() {
@@ -464,19 +1001,18 @@ fn walk_dir_for_supported_files(dir: &Path) -> Result<Vec<PathBuf>> {
     }
     for e in WalkBuilder::new(dir).hidden(true).build().flatten() {
         let path = e.path();
-        if e.file_type().is_some_and(|ft| ft.is_file()) {
-            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                if is_known_extension(ext) {
-                    files.push(path.to_path_buf());
-                }
-            }
+        if e.file_type().is_some_and(|ft| ft.is_file())
+            && let Some(ext) = path.extension().and_then(|e| e.to_str())
+            && supported(ext)
+        {
+            files.push(path.to_path_buf());
         }
     }
     Ok(files)
 }
 
 /// Expand patterns containing `**` using the `ignore` crate.
-fn double_star_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+fn double_star_glob(pattern: &str, supported: &dyn Fn(&str) -> bool) -> Result<Vec<PathBuf>> {
     let parts: Vec<&str> = pattern.splitn(2, "**").collect();
     if parts.len() != 2 {
         anyhow::bail!("invalid ** pattern: {pattern}");
@@ -499,19 +1035,18 @@ fn double_star_glob(pattern: &str) -> Result<Vec<PathBuf>> {
             continue;
         }
         let ext = path.extension().and_then(|e| e.to_str());
-        let matches_ext = ext.is_some_and(is_known_extension);
+        let matches_ext = ext.is_some_and(supported);
         if suffix.is_empty() {
             if matches_ext {
                 files.push(path.to_path_buf());
             }
-        } else if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-            if glob::Pattern::new(suffix)
+        } else if let Some(name) = path.file_name().and_then(|n| n.to_str())
+            && glob::Pattern::new(suffix)
                 .map(|p| p.matches(name))
                 .unwrap_or(false)
-                && matches_ext
-            {
-                files.push(path.to_path_buf());
-            }
+            && matches_ext
+        {
+            files.push(path.to_path_buf());
         }
     }
     Ok(files)
@@ -579,6 +1114,10 @@ mod tests {
         // Other fields should have defaults
         assert_eq!(config.search_top_k, 5);
         assert_eq!(config.embedding.dimensions, 1024);
+        assert_eq!(config.chunk_strategy, ChunkStrategy::Size);
+
+        let config: Config = serde_json::from_str(r#"{"chunk_strategy": "heading"}"#).unwrap();
+        assert_eq!(config.chunk_strategy, ChunkStrategy::Heading);
     }
 
     #[test]
@@ -596,6 +1135,23 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_validate_chunk_overlap() {
+        let config = Config {
+            chunk_size: 100,
+            chunk_overlap: 100,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = Config {
+            chunk_size: 100,
+            chunk_overlap: 20,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
     #[test]
     fn test_validate_empty_patterns() {
         let config = Config {
@@ -612,6 +1168,113 @@ mod tests {
         assert!(!config.is_update_check_enabled());
     }
 
+    #[test]
+    fn test_offline_mode() {
+        // Remote endpoint is rejected with a clear error
+        let config = Config {
+            offline: true,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("offline mode requires a local embedding endpoint"));
+        assert!(err.contains("dashscope"));
+
+        // Local endpoint is accepted and the update check never runs
+        let mut config = Config {
+            offline: true,
+            update_check: Some(true),
+            ..Default::default()
+        };
+        config.embedding.api_url = "http://127.0.0.1:11434/v1/embeddings".to_string();
+        assert!(config.validate().is_ok());
+        assert!(!config.is_update_check_enabled());
+
+        config.embedding.api_url = "http://localhost:8080/v1/embeddings".to_string();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_formats_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config {
+            chunk_size: 900,
+            enabled_languages: Some(vec!["rust".to_string()]),
+            short_query: ShortQueryAction::Error,
+            ..Default::default()
+        };
+        config.boost_doc_types.insert("api".to_string(), 1.5);
+        config.embedding.api_model = "custom-model".to_string();
+
+        for name in ["config.json", "config.yaml", "config.yml", "config.toml"] {
+            let path = dir.path().join(name).to_string_lossy().to_string();
+            config.save(&path).unwrap();
+            let data = std::fs::read_to_string(&path).unwrap();
+            match ConfigFormat::from_path(&path) {
+                ConfigFormat::Json => assert!(data.starts_with('{')),
+                ConfigFormat::Yaml => assert!(data.contains("chunk_size: 900")),
+                ConfigFormat::Toml => assert!(data.contains("chunk_size = 900")),
+            }
+            let loaded = Config::load(&path).unwrap();
+            assert_eq!(
+                serde_json::to_value(&loaded).unwrap(),
+                serde_json::to_value(&config).unwrap(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_formats_round_trip_unset_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            max_file_size_bytes: None,
+            enabled_languages: None,
+            ..Default::default()
+        };
+
+        for name in ["config.json", "config.yaml"] {
+            let path = dir.path().join(name).to_string_lossy().to_string();
+            config.save(&path).unwrap();
+            let loaded = Config::load(&path).unwrap();
+            assert_eq!(loaded.max_file_size_bytes, None, "{name}");
+            assert_eq!(loaded.enabled_languages, None, "{name}");
+        }
+
+        // TOML can't say "no limit", so it refuses rather than reload 1 MiB
+        let path = dir.path().join("config.toml").to_string_lossy().to_string();
+        let err = config.save(&path).unwrap_err();
+        assert!(format!("{err:#}").contains("max_file_size_bytes"));
+        assert!(!std::path::Path::new(&path).exists());
+
+        let config = Config {
+            enabled_languages: None,
+            ..Default::default()
+        };
+        config.save(&path).unwrap();
+        assert_eq!(Config::load(&path).unwrap().enabled_languages, None);
+    }
+
+    #[test]
+    fn test_partial_yaml_and_toml_get_defaults_and_migrations() {
+        let yaml = "chunk_size: 700\ndocuments_dir: ./old_docs\nembedding:\n  dimensions: 768\n";
+        let config = Config::parse(yaml, "config.yaml").unwrap();
+        assert_eq!(config.chunk_size, 700);
+        assert_eq!(config.embedding.dimensions, 768);
+        assert_eq!(config.search_top_k, 5);
+        assert_eq!(config.document_patterns, vec!["./old_docs"]);
+        assert!(config.documents_dir.is_none());
+
+        let toml =
+            "chunk_size = 700\ndocuments_dir = \"./old_docs\"\n\n[embedding]\ndimensions = 768\n";
+        let config = Config::parse(toml, "config.TOML").unwrap();
+        assert_eq!(config.chunk_size, 700);
+        assert_eq!(config.embedding.dimensions, 768);
+        assert_eq!(config.document_patterns, vec!["./old_docs"]);
+
+        let err = Config::parse("chunk_size = ", "config.toml").unwrap_err();
+        assert!(err.to_string().contains("invalid TOML"), "{err}");
+    }
+
     #[test]
     fn test_migration_documents_dir() {
         let json = r#"{"documents_dir": "./old_docs"}"#;
@@ -654,6 +1317,112 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_short_query_action() {
+        let config = Config::default();
+        assert_eq!(config.short_query_action("x"), None);
+
+        let mut config: Config = serde_json::from_str(r#"{"min_query_chars": 3}"#).unwrap();
+        assert_eq!(
+            config.short_query_action("x"),
+            Some(ShortQueryAction::Keyword)
+        );
+        assert_eq!(
+            config.short_query_action(" 世界 "),
+            Some(ShortQueryAction::Keyword)
+        );
+        assert_eq!(config.short_query_action("api"), None);
+
+        config.short_query = ShortQueryAction::Error;
+        assert_eq!(
+            config.short_query_action("x"),
+            Some(ShortQueryAction::Error)
+        );
+
+        let config: Config =
+            serde_json::from_str(r#"{"min_query_chars": 2, "short_query": "error"}"#).unwrap();
+        assert_eq!(config.short_query, ShortQueryAction::Error);
+    }
+
+    #[test]
+    fn test_enabled_languages() {
+        let config = Config {
+            enabled_languages: Some(vec!["rust".to_string()]),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert!(config.is_file_extension_supported("rs"));
+        assert!(!config.is_file_extension_supported("py"));
+        // Prose and data formats have no grammar to disable
+        assert!(config.is_file_extension_supported("md"));
+        assert!(config.is_file_extension_supported("json"));
+
+        let config = Config {
+            enabled_languages: Some(vec!["cobol".to_string()]),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_exceeds_max_file_size() {
+        let config = Config::default();
+        assert!(!config.exceeds_max_file_size("rs", 1024 * 1024));
+        assert!(config.exceeds_max_file_size("js", 1024 * 1024 + 1));
+        // Binary documents are exempt
+        assert!(!config.exceeds_max_file_size("pdf", 50 * 1024 * 1024));
+
+        let unlimited = Config {
+            max_file_size_bytes: None,
+            ..Default::default()
+        };
+        assert!(!unlimited.exceeds_max_file_size("js", u64::MAX));
+    }
+
+    #[test]
+    fn test_document_extensions() {
+        let config = Config::default();
+        for ext in ["md", "rst", "adoc"] {
+            assert!(config.is_document_extension(ext));
+            assert!(config.is_file_extension_supported(ext));
+        }
+        assert!(!config.is_document_extension("txt"));
+        assert!(!config.is_document_extension("rs"));
+
+        // file_extensions stays the allowlist for document extensions too
+        let no_md = Config {
+            file_extensions: vec!["rs".to_string()],
+            ..Default::default()
+        };
+        assert!(!no_md.is_file_extension_supported("md"));
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["guide.rst", "manual.adoc", "notes.org"] {
+            std::fs::write(dir.path().join(name), "Text").unwrap();
+        }
+        let pattern = dir.path().to_string_lossy().to_string();
+        let names = |config: &Config| {
+            let mut names: Vec<String> = config
+                .get_document_files()
+                .unwrap()
+                .iter()
+                .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+
+        let mut config = Config {
+            document_patterns: vec![pattern],
+            ..Default::default()
+        };
+        assert_eq!(names(&config), ["guide.rst", "manual.adoc"]);
+        config.document_extensions.push("org".to_string());
+        assert!(!config.is_file_extension_supported("org"));
+        config.file_extensions.push("org".to_string());
+        assert_eq!(names(&config), ["guide.rst", "manual.adoc", "notes.org"]);
+    }
+
     #[test]
     fn test_get_document_files_deduplicates() {
         // When two patterns match the same file, it should appear only once
@@ -691,7 +1460,9 @@ mod tests {
         assert!(config.is_file_extension_supported("cjs"));
         assert!(config.is_file_extension_supported("mts"));
         assert!(config.is_file_extension_supported("cts"));
-        assert!(!config.is_file_extension_supported("java"));
+        assert!(config.is_file_extension_supported("java"));
+        assert!(config.is_file_extension_supported("hpp"));
+        assert!(!config.is_file_extension_supported("kt"));
         assert!(!config.is_file_extension_supported(""));
     }
 
@@ -836,4 +1607,44 @@ mod tests {
         assert_eq!(config.embedding.api_model, "nomic-embed-text");
         assert_eq!(config.embedding.dimensions, 768);
     }
+
+    #[test]
+    fn test_check_file_requires_base_directories() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("config.json");
+        let missing = temp.path().join("missing");
+        let json = serde_json::json!({ "document_patterns": [missing] });
+        std::fs::write(&path, json.to_string()).unwrap();
+
+        let err = Config::check_file(path.to_str().unwrap(), false).unwrap_err();
+        assert!(err.to_string().contains("do not exist"), "{err}");
+
+        std::fs::create_dir(&missing).unwrap();
+        let effective = Config::check_file(path.to_str().unwrap(), false).unwrap();
+        assert_eq!(effective["chunk_size"], default_chunk_size());
+    }
+
+    #[test]
+    fn test_check_file_missing_config_is_error() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("absent.json");
+        assert!(Config::check_file(path.to_str().unwrap(), false).is_err());
+        // Unlike `load`, no template is generated
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_redacted_json_masks_api_key() {
+        let mut config = Config::default();
+        config.embedding.api_key = "sk-secret-value".to_string();
+        config.chunk_size = 321;
+
+        let json = config.redacted_json();
+        assert!(!json.contains("sk-secret-value"));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["embedding"]["api_key"], "***");
+        assert_eq!(value["chunk_size"], 321);
+        // The original config is untouched
+        assert_eq!(config.embedding.api_key, "sk-secret-value");
+    }
 }
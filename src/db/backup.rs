@@ -0,0 +1,326 @@
+//! Database backup and restore helpers.
+use super::Db;
+use anyhow::{Context, bail};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Result};
+use std::fs::{self, File, TryLockError};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Tables every RustRAG database must contain.
+const REQUIRED_TABLES: &[&str] = &[
+    "documents",
+    "chunks",
+    "vec_chunks",
+    "code_metadata",
+    "code_relations",
+    "system_metadata",
+];
+
+impl Db {
+    /// Copy the database to `<db>.bak-<timestamp>` and return the backup path.
+    /// Only the newest `keep` backups are kept (all when `keep` is 0).
+    ///
+    /// Uses `VACUUM INTO` so the copy is consistent even with pending WAL
+    /// frames. No-op (returns `None`) for in-memory databases.
+    pub fn backup(&self, keep: usize) -> Result<Option<PathBuf>> {
+        let Some(path) = self.path() else {
+            return Ok(None);
+        };
+        let stamp = chrono::Utc::now().format("%Y%m%d%H%M%S%3f");
+        let backup = with_suffix(path, &format!(".bak-{stamp}"));
+
+        let conn = self.get_conn()?;
+        conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+        info!("Database backed up to {}", backup.display());
+        if keep > 0
+            && let Err(e) = prune_backups(path, keep)
+        {
+            warn!("Failed to prune old backups of {}: {e}", path.display());
+        }
+        Ok(Some(backup))
+    }
+}
+
+/// Delete all but the newest `keep` `<db>.bak-<timestamp>` files of `db_path`.
+/// The timestamps sort chronologically, so names are compared as strings.
+fn prune_backups(db_path: &Path, keep: usize) -> std::io::Result<()> {
+    let (Some(dir), Some(name)) = (db_path.parent(), db_path.file_name()) else {
+        return Ok(());
+    };
+    let prefix = format!("{}.bak-", name.to_string_lossy());
+    let mut backups: Vec<PathBuf> = fs::read_dir(if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    })?
+    .filter_map(|entry| entry.ok().map(|e| e.path()))
+    .filter(|p| {
+        p.file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with(&prefix))
+    })
+    .collect();
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        fs::remove_file(old)?;
+        info!("Removed old backup {}", old.display());
+    }
+    Ok(())
+}
+
+/// Exclusive OS advisory lock on `<db>.lock`, held by a running server so a
+/// second server or `restore` can't use the same DB.
+///
+/// The lock is released when the process exits, even on a crash. The file
+/// itself (holding the last owner's PID, for diagnostics) is left in place:
+/// deleting it could drop a lock another process has just taken.
+pub struct ServerLock {
+    _file: File,
+}
+
+impl ServerLock {
+    /// Take the lock for `db_path`, failing if another process holds it.
+    pub fn acquire<P: AsRef<Path>>(db_path: P) -> anyhow::Result<Self> {
+        let path = lock_path(db_path.as_ref());
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("cannot open lock file {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut owner = String::new();
+                let _ = file.read_to_string(&mut owner);
+                bail!(
+                    "database {} is in use by another process (pid {}, lock file {})",
+                    db_path.as_ref().display(),
+                    owner.trim(),
+                    path.display()
+                );
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("cannot lock {}", path.display()));
+            }
+        }
+        file.set_len(0)?;
+        file.write_all(std::process::id().to_string().as_bytes())?;
+        Ok(Self { _file: file })
+    }
+}
+
+fn lock_path(db_path: &Path) -> PathBuf {
+    with_suffix(db_path, ".lock")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(suffix);
+    PathBuf::from(s)
+}
+
+/// Check that `path` is a readable SQLite file carrying the RustRAG schema.
+pub fn validate_backup(path: &Path) -> anyhow::Result<()> {
+    if !path.is_file() {
+        bail!("backup not found: {}", path.display());
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("cannot open backup {}", path.display()))?;
+
+    let check: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .context("backup is not a valid SQLite database")?;
+    if check != "ok" {
+        bail!("backup failed integrity check: {check}");
+    }
+
+    for table in REQUIRED_TABLES {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = ?1)",
+            [table],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            bail!("backup is not a RustRAG database: missing table '{table}'");
+        }
+    }
+    Ok(())
+}
+
+/// Embedding dimension recorded in the backup at `path`, if any.
+fn backup_embedding_dimensions(path: &Path) -> anyhow::Result<Option<usize>> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM system_metadata WHERE key = ?1",
+            [super::EMBEDDING_DIMENSIONS_KEY],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(value.and_then(|v| v.parse().ok()))
+}
+
+/// Replace the DB at `db_path` with `backup`, which must have been built
+/// with `dimensions`-dimension embeddings.
+///
+/// The backup is validated, copied next to the target and then renamed over
+/// it, so the target is never left half-written. Refuses while a server holds
+/// the DB's [`ServerLock`], and holds it itself until done.
+pub fn restore_backup(backup: &Path, db_path: &Path, dimensions: usize) -> anyhow::Result<()> {
+    let _lock = ServerLock::acquire(db_path).context("stop the server before restoring")?;
+    validate_backup(backup)?;
+    if let Some(stored) = backup_embedding_dimensions(backup)?
+        && stored != dimensions
+    {
+        bail!(
+            "backup was built with {stored}-dimension embeddings but embedding.dimensions is \
+             {dimensions}"
+        );
+    }
+
+    let staging = with_suffix(db_path, ".restore-tmp");
+    fs::copy(backup, &staging)
+        .with_context(|| format!("failed to copy backup to {}", staging.display()))?;
+
+    // WAL/SHM files of the old DB must not be applied to the restored one
+    for suffix in ["-wal", "-shm"] {
+        let side = with_suffix(db_path, suffix);
+        if side.exists() {
+            fs::remove_file(&side)
+                .with_context(|| format!("failed to remove {}", side.display()))?;
+        }
+    }
+
+    fs::rename(&staging, db_path)
+        .with_context(|| format!("failed to replace {}", db_path.display()))?;
+    info!("Restored {} from {}", db_path.display(), backup.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::VEC_DIMENSIONS;
+
+    #[test]
+    fn test_backup_file_backed_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Db::open(dir.path().join("rag.db")).unwrap();
+        db.set_metadata("marker", "before-clear").unwrap();
+
+        let backup = db
+            .backup(0)
+            .unwrap()
+            .expect("file-backed DB should be backed up");
+        assert!(backup.exists());
+        assert!(
+            backup
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("rag.db.bak-")
+        );
+
+        // Destructive change after the backup doesn't affect the copy
+        db.set_metadata("marker", "after-clear").unwrap();
+        let restored = Db::open(&backup).unwrap();
+        assert_eq!(
+            restored.get_metadata("marker").unwrap().as_deref(),
+            Some("before-clear")
+        );
+    }
+
+    #[test]
+    fn test_backup_in_memory_is_noop() {
+        let db = Db::open_in_memory().unwrap();
+        assert!(db.backup(0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_backup_keeps_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Db::open(dir.path().join("rag.db")).unwrap();
+        let mut backups = Vec::new();
+        for _ in 0..3 {
+            backups.push(db.backup(2).unwrap().unwrap());
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let exists: Vec<bool> = backups.iter().map(|b| b.exists()).collect();
+        assert_eq!(exists, [false, true, true]);
+    }
+
+    #[test]
+    fn test_restore_backup_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("rag.db");
+        let backup = {
+            let db = Db::open(&db_path).unwrap();
+            db.set_metadata("marker", "original").unwrap();
+            let backup = db.backup(0).unwrap().unwrap();
+            db.set_metadata("marker", "changed").unwrap();
+            backup
+        };
+
+        restore_backup(&backup, &db_path, VEC_DIMENSIONS).unwrap();
+
+        let db = Db::open(&db_path).unwrap();
+        assert_eq!(
+            db.get_metadata("marker").unwrap().as_deref(),
+            Some("original")
+        );
+    }
+
+    #[test]
+    fn test_restore_refuses_locked_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("rag.db");
+        let db = Db::open(&db_path).unwrap();
+        let backup = db.backup(0).unwrap().unwrap();
+
+        let lock = ServerLock::acquire(&db_path).unwrap();
+        assert!(restore_backup(&backup, &db_path, VEC_DIMENSIONS).is_err());
+        drop(lock);
+        drop(db);
+        assert!(restore_backup(&backup, &db_path, VEC_DIMENSIONS).is_ok());
+    }
+
+    #[test]
+    fn test_server_lock_is_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("rag.db");
+
+        let lock = ServerLock::acquire(&db_path).unwrap();
+        let err = ServerLock::acquire(&db_path).err().unwrap();
+        assert!(err.to_string().contains(&std::process::id().to_string()));
+
+        // Released on drop; a stale lock file doesn't block the next owner
+        drop(lock);
+        assert!(lock_path(&db_path).exists());
+        assert!(ServerLock::acquire(&db_path).is_ok());
+    }
+
+    #[test]
+    fn test_restore_refuses_dimension_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("rag.db");
+        let backup = Db::open(&db_path).unwrap().backup(0).unwrap().unwrap();
+
+        let err = restore_backup(&backup, &db_path, 384).unwrap_err();
+        assert!(err.to_string().contains("384"));
+        assert!(restore_backup(&backup, &db_path, VEC_DIMENSIONS).is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_foreign_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let other = dir.path().join("other.db");
+        Connection::open(&other)
+            .unwrap()
+            .execute_batch("CREATE TABLE t (x INTEGER);")
+            .unwrap();
+        assert!(validate_backup(&other).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use super::search::{LIKE_ESCAPE, glob_to_like};
 use super::{Db, models::*, serialize_vector_f32};
 use chrono::{DateTime, Utc};
 use rusqlite::{OptionalExtension, Result, ffi, params};
@@ -23,23 +24,72 @@ impl Db {
         Ok(docs)
     }
 
+    /// One page of indexed documents, ordered by filename so consecutive
+    /// pages neither overlap nor skip entries.
+    pub fn list_documents_page(&self, offset: usize, limit: usize) -> Result<Vec<DocumentEntry>> {
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT d.filename, d.modified_at, d.title, o.filename FROM documents d
+             LEFT JOIN documents o ON d.alias_of = o.id
+             ORDER BY d.filename LIMIT ? OFFSET ?",
+        )?;
+        let rows = stmt.query_map(params![limit as i64, offset as i64], |row| {
+            Ok(DocumentEntry {
+                filename: row.get(0)?,
+                modified_at: row.get(1)?,
+                title: row.get(2)?,
+                alias_of: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Number of indexed documents.
+    pub fn count_documents(&self) -> Result<usize> {
+        let conn = self.get_conn()?;
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Filenames of the indexed documents matching a glob `pattern` (`*`,
+    /// `?`), sorted. Like the search `file_pattern` filter, a pattern without
+    /// a directory matches the file name in any directory.
+    pub fn list_documents_matching(&self, pattern: &str) -> Result<Vec<String>> {
+        let conn = self.get_conn()?;
+        let like = glob_to_like(&pattern.replace('\\', "/"));
+        let mut stmt = conn.prepare(&format!(
+            "SELECT filename FROM documents
+             WHERE filename LIKE ?1{e} OR filename LIKE '%/' || ?1{e}
+             ORDER BY filename",
+            e = LIKE_ESCAPE
+        ))?;
+        let rows = stmt.query_map([like], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Deletes every document whose filename starts with `prefix`, returning
+    /// how many were removed. The prefix is matched literally, so pass a
+    /// trailing `/` to stay within a directory. Aliases outside the prefix
+    /// survive (see [`Db::delete_document`]).
     pub fn delete_documents_by_prefix(&self, prefix: &str) -> Result<usize> {
         let mut conn = self.get_conn()?;
-        let like_pattern = format!("{}%", prefix.replace("\\", "/"));
+        let prefix = prefix.replace('\\', "/");
         let tx = conn.transaction()?;
-        tx.execute(
-            "DELETE FROM vec_chunks WHERE rowid IN (SELECT c.id FROM chunks c JOIN documents d ON c.document_id = d.id WHERE d.filename LIKE ?)",
-            params![like_pattern],
-        )?;
-        let rows = tx.execute(
-            "DELETE FROM documents WHERE filename LIKE ?",
-            params![like_pattern],
-        )?;
+        let doc_ids: Vec<i64> = tx
+            .prepare(
+                "SELECT id FROM documents WHERE substr(filename, 1, length(?1)) = ?1 ORDER BY id",
+            )?
+            .query_map(params![prefix], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+        for &doc_id in &doc_ids {
+            delete_document_by_id(&tx, doc_id)?;
+        }
         tx.commit()?;
-        Ok(rows)
+        Ok(doc_ids.len())
     }
 
-    /// Deletes a batch of documents in a single transaction.
+    /// Deletes a batch of documents in a single transaction. Aliases outside
+    /// the batch survive (see [`Db::delete_document`]).
     pub fn delete_documents_batch(&self, filenames: &[&str]) -> Result<usize> {
         let conn = self.get_conn()?;
         if filenames.is_empty() {
@@ -57,12 +107,7 @@ impl Db {
                 )
                 .optional()?
             {
-                tx.execute(
-                    "DELETE FROM vec_chunks WHERE rowid IN (SELECT id FROM chunks WHERE document_id = ?)",
-                    params![doc_id],
-                )?;
-                tx.execute("DELETE FROM chunks WHERE document_id = ?", params![doc_id])?;
-                tx.execute("DELETE FROM documents WHERE id = ?", params![doc_id])?;
+                delete_document_by_id(&tx, doc_id)?;
                 removed += 1;
             }
         }
@@ -70,7 +115,168 @@ impl Db {
         Ok(removed)
     }
 
-    /// Deletes a document and its associated chunks from the database
+    /// Records the frontmatter `docType` of an indexed document (`None` clears it).
+    pub fn set_document_doc_type(&self, filename: &str, doc_type: Option<&str>) -> Result<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "UPDATE documents SET doc_type = ? WHERE filename = ?",
+            params![doc_type, filename],
+        )?;
+        Ok(())
+    }
+
+    /// Sets (or clears) the display title of a document.
+    pub fn set_document_title(&self, filename: &str, title: Option<&str>) -> Result<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "UPDATE documents SET title = ? WHERE filename = ?",
+            params![title, filename],
+        )?;
+        Ok(())
+    }
+
+    /// Records the SHA-256 of the file a document was indexed from. The
+    /// insert methods replace it along with the document's chunks.
+    ///
+    /// Aliases of the document (see [`Db::set_document_alias`]) whose content
+    /// no longer matches are removed, so the next sync indexes them on their own.
+    pub fn set_document_content_hash(&self, filename: &str, content_hash: &str) -> Result<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "UPDATE documents SET content_hash = ? WHERE filename = ?",
+            params![content_hash, filename],
+        )?;
+        delete_stale_aliases(&conn, filename, content_hash)
+    }
+
+    /// An embedded (non-alias) document other than `exclude` whose recorded
+    /// content hash is `content_hash`, as (id, filename).
+    pub fn find_document_by_content_hash(
+        &self,
+        content_hash: &str,
+        exclude: &str,
+    ) -> Result<Option<(i64, String)>> {
+        let conn = self.get_conn()?;
+        conn.query_row(
+            "SELECT id, filename FROM documents
+             WHERE content_hash = ? AND filename != ? AND alias_of IS NULL
+             ORDER BY id LIMIT 1",
+            params![content_hash, exclude],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+    }
+
+    /// Store `filename` as an alias of the document `alias_of` with the same
+    /// content: a listed document without chunks or vectors of its own.
+    /// Deleting the original promotes an alias in its place (see
+    /// [`Db::delete_document`]). Documents aliased to `filename` itself move
+    /// over to `alias_of`.
+    pub fn set_document_alias(
+        &self,
+        filename: &str,
+        modified_at: DateTime<Utc>,
+        content_hash: &str,
+        alias_of: i64,
+    ) -> Result<()> {
+        let mut conn = self.get_conn()?;
+        let tx = conn.transaction()?;
+        let doc_id: i64 = tx.query_row(
+            r#"
+            INSERT INTO documents (filename, modified_at, indexed_at, content_hash, alias_of)
+            VALUES (?1, ?2, CURRENT_TIMESTAMP, ?3, ?4)
+            ON CONFLICT(filename) DO UPDATE SET
+                modified_at = excluded.modified_at,
+                indexed_at = CURRENT_TIMESTAMP,
+                content_hash = excluded.content_hash,
+                alias_of = excluded.alias_of,
+                title = NULL
+            RETURNING id
+            "#,
+            params![filename, modified_at, content_hash, alias_of],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "DELETE FROM vec_chunks WHERE rowid IN (SELECT id FROM chunks WHERE document_id = ?)",
+            params![doc_id],
+        )?;
+        tx.execute("DELETE FROM chunks WHERE document_id = ?", params![doc_id])?;
+        tx.execute(
+            "UPDATE documents SET alias_of = ? WHERE alias_of = ?",
+            params![alias_of, doc_id],
+        )?;
+        tx.commit()
+    }
+
+    /// The content hash recorded for a document, if it's indexed and has one.
+    pub fn get_document_content_hash(&self, filename: &str) -> Result<Option<String>> {
+        let conn = self.get_conn()?;
+        conn.query_row(
+            "SELECT content_hash FROM documents WHERE filename = ?",
+            [filename],
+            |row| row.get(0),
+        )
+        .optional()
+        .map(Option::flatten)
+    }
+
+    /// Whether a document is indexed under `filename`.
+    pub fn document_exists(&self, filename: &str) -> Result<bool> {
+        let conn = self.get_conn()?;
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM documents WHERE filename = ?)",
+            [filename],
+            |row| row.get(0),
+        )
+    }
+
+    /// Overwrites the stored `modified_at` of a document without touching its
+    /// chunks, so differential sync treats it as unchanged. Returns `false`
+    /// if the document isn't indexed.
+    pub fn touch_document(&self, filename: &str, modified_at: DateTime<Utc>) -> Result<bool> {
+        let conn = self.get_conn()?;
+        let rows = conn.execute(
+            "UPDATE documents SET modified_at = ? WHERE filename = ?",
+            params![modified_at, filename],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Renames an indexed document, keeping its chunks, vectors and metadata,
+    /// the word mappings recorded against it and the relations resolved to
+    /// it (`code_relations.target_file`). Returns false when `old`
+    /// isn't indexed; fails with a constraint violation when `new` already is.
+    pub fn rename_document(&self, old: &str, new: &str) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+        let tx = conn.transaction()?;
+        let rows = tx.execute(
+            "UPDATE documents SET filename = ? WHERE filename = ?",
+            params![new, old],
+        )?;
+        if rows == 0 {
+            return Ok(false);
+        }
+        tx.execute(
+            "UPDATE OR REPLACE word_mapping_source SET document = ? WHERE document = ?",
+            params![new, old],
+        )?;
+        tx.execute(
+            "UPDATE word_mapping SET source_document = ? WHERE source_document = ?",
+            params![new, old],
+        )?;
+        tx.execute(
+            "UPDATE code_relations SET target_file = ? WHERE target_file = ?",
+            params![new, old],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Deletes a document and its associated chunks from the database.
+    ///
+    /// If the document has aliases (see [`Db::set_document_alias`]), the
+    /// oldest one takes over its chunks and vectors, which hold the same
+    /// content, and becomes the original of the others.
     pub fn delete_document(&self, filename: &str) -> Result<bool> {
         let conn = self.get_conn()?;
         let doc_id: Option<i64> = conn
@@ -82,25 +288,22 @@ impl Db {
             .optional()?;
 
         if let Some(doc_id) = doc_id {
-            // Virtual table cascade deletion workaround
-            conn.execute(
-                "DELETE FROM vec_chunks WHERE rowid IN (SELECT id FROM chunks WHERE document_id = ?)",
-                params![doc_id],
-            )?;
-
-            // Cascade deletes chunks, code_metadata, code_relations
-            let rows = conn.execute("DELETE FROM documents WHERE id = ?", params![doc_id])?;
-            Ok(rows > 0)
+            let tx = conn.unchecked_transaction()?;
+            delete_document_by_id(&tx, doc_id)?;
+            tx.commit()?;
+            Ok(true)
         } else {
             Ok(false)
         }
     }
 
-    /// Inserts or updates a markdown document with its chunks and embeddings
+    /// Inserts or updates a markdown document with its chunks and embeddings.
+    /// `content_hash` is the SHA-256 of the bytes the chunks came from, if known.
     pub fn insert_document(
         &self,
         filename: &str,
         modified_at: DateTime<Utc>,
+        content_hash: Option<&str>,
         chunks: &[Chunk<'_>],
         embeddings: &[Vec<f32>],
     ) -> Result<()> {
@@ -117,12 +320,20 @@ impl Db {
         }
 
         let tx = conn.transaction()?;
-        upsert_document_and_insert_chunks(&tx, filename, modified_at, chunks, embeddings)?;
+        upsert_document_and_insert_chunks(
+            &tx,
+            filename,
+            modified_at,
+            content_hash,
+            chunks,
+            embeddings,
+        )?;
         tx.commit()?;
         Ok(())
     }
 
-    /// Inserts word mappings into the dictionary table (UPSERT).
+    /// Inserts word mappings into the dictionary table (UPSERT), recording
+    /// each mapping's source document in `word_mapping_source`.
     pub fn insert_word_mappings(
         &self,
         mappings: &[(String, String, String, f64, String)],
@@ -133,20 +344,86 @@ impl Db {
         }
         let tx = conn.transaction()?;
         for (source_word, target_word, source_lang, confidence, source_doc) in mappings {
-            tx.execute(
+            let mapping_id: i64 = tx.query_row(
                 r#"
                 INSERT INTO word_mapping (source_word, target_word, source_lang, confidence, source_document)
                 VALUES (?, ?, ?, ?, ?)
                 ON CONFLICT(source_word, target_word, source_lang) DO UPDATE SET
                     confidence = MAX(word_mapping.confidence, excluded.confidence),
                     source_document = excluded.source_document
+                RETURNING id
                 "#,
                 params![source_word, target_word, source_lang, confidence, source_doc],
+                |row| row.get(0),
+            )?;
+            if source_doc.is_empty() {
+                continue;
+            }
+            tx.execute(
+                r#"
+                INSERT INTO word_mapping_source (mapping_id, document, confidence)
+                VALUES (?, ?, ?)
+                ON CONFLICT(mapping_id, document) DO UPDATE SET
+                    confidence = MAX(word_mapping_source.confidence, excluded.confidence)
+                "#,
+                params![mapping_id, source_doc, confidence],
             )?;
         }
         tx.commit()
     }
 
+    /// Returns all word mappings, highest confidence first, optionally
+    /// restricted to one source language.
+    pub fn list_word_mappings(&self, source_lang: Option<&str>) -> Result<Vec<WordMapping>> {
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, source_word, target_word, source_lang, confidence, source_document
+            FROM word_mapping
+            WHERE ?1 IS NULL OR source_lang = ?1
+            ORDER BY confidence DESC, source_word, target_word
+            "#,
+        )?;
+        let rows = stmt.query_map([source_lang], |row| {
+            Ok(WordMapping {
+                id: row.get(0)?,
+                source_word: row.get(1)?,
+                target_word: row.get(2)?,
+                source_lang: row.get(3)?,
+                confidence: row.get(4)?,
+                source_document: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Returns the documents a mapping was extracted from, in any source
+    /// language, highest confidence first.
+    pub fn get_word_mapping_sources(
+        &self,
+        source_word: &str,
+        target_word: &str,
+    ) -> Result<Vec<WordMappingSource>> {
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT s.document, w.source_lang, s.confidence
+            FROM word_mapping_source s
+            JOIN word_mapping w ON s.mapping_id = w.id
+            WHERE w.source_word = ? AND w.target_word = ?
+            ORDER BY s.confidence DESC, s.document
+            "#,
+        )?;
+        let rows = stmt.query_map(params![source_word, target_word], |row| {
+            Ok(WordMappingSource {
+                document: row.get(0)?,
+                source_lang: row.get(1)?,
+                confidence: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+
     /// Returns the total number of word mappings in the dictionary.
     pub fn get_word_mapping_count(&self) -> Result<i64> {
         let conn = self.get_conn()?;
@@ -154,63 +431,213 @@ impl Db {
     }
 
     /// Inserts or updates a code document with its chunks, vectors, and metadata
+    ///
+    /// Returns the chunk IDs of `chunks`, in order.
     pub fn insert_code_document(
         &self,
         filename: &str,
         modified_at: DateTime<Utc>,
         chunks: &[CodeChunk<'_>],
         embeddings: &[Vec<f32>],
-    ) -> Result<()> {
+    ) -> Result<Vec<i64>> {
+        self.insert_code_document_with_docs(filename, modified_at, None, chunks, &[], embeddings)
+    }
+
+    /// Like [`Self::insert_code_document`], also storing doc comments as
+    /// prose chunks after the code chunks, each linked to the chunk it
+    /// documents. `embeddings` covers `chunks` followed by `docs`.
+    ///
+    /// Returns the chunk IDs of `chunks`, in order.
+    pub fn insert_code_document_with_docs(
+        &self,
+        filename: &str,
+        modified_at: DateTime<Utc>,
+        content_hash: Option<&str>,
+        chunks: &[CodeChunk<'_>],
+        docs: &[DocChunk<'_>],
+        embeddings: &[Vec<f32>],
+    ) -> Result<Vec<i64>> {
         let mut conn = self.get_conn()?;
-        if chunks.len() != embeddings.len() {
+        if chunks.len() + docs.len() != embeddings.len() {
             return Err(rusqlite::Error::SqliteFailure(
                 ffi::Error::new(ffi::SQLITE_MISUSE),
                 Some(format!(
                     "chunks ({}) and embeddings ({}) length mismatch",
-                    chunks.len(),
+                    chunks.len() + docs.len(),
                     embeddings.len()
                 )),
             ));
         }
+        if let Some(doc) = docs.iter().find(|d| d.owner >= chunks.len()) {
+            return Err(rusqlite::Error::SqliteFailure(
+                ffi::Error::new(ffi::SQLITE_MISUSE),
+                Some(format!(
+                    "doc chunk owner {} out of range ({} chunks)",
+                    doc.owner,
+                    chunks.len()
+                )),
+            ));
+        }
 
         // Convert CodeChunks to plain Chunks for the shared base
         let plain_chunks: Vec<Chunk<'_>> = chunks
             .iter()
-            .map(|cc| Chunk {
-                position: cc.chunk.position,
-                content: cc.chunk.content,
-            })
+            .map(|cc| cc.chunk.clone())
+            .chain(docs.iter().map(|d| d.chunk.clone()))
             .collect();
 
         let tx = conn.transaction()?;
-        let chunk_ids = upsert_document_and_insert_chunks(
+        let all_ids = upsert_document_and_insert_chunks(
             &tx,
             filename,
             modified_at,
+            content_hash,
             &plain_chunks,
             embeddings,
         )?;
+        let (chunk_ids, doc_ids) = all_ids.split_at(chunks.len());
 
-        // Insert code-specific metadata
-        for (i, code_chunk) in chunks.iter().enumerate() {
+        insert_code_metadata(&tx, chunk_ids, chunks)?;
+        for (doc_id, doc) in doc_ids.iter().zip(docs) {
             tx.execute(
-                "INSERT INTO code_metadata (chunk_id, symbol_name, symbol_type, language, start_line, end_line, parent_symbol, signature) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
-                params![
-                    chunk_ids[i],
-                    code_chunk.symbol_name,
-                    code_chunk.symbol_type,
-                    code_chunk.language,
-                    code_chunk.start_line.map(|x| x as i64),
-                    code_chunk.end_line.map(|x| x as i64),
-                    code_chunk.parent_symbol,
-                    code_chunk.signature,
-                ],
+                "UPDATE chunks SET parent_chunk_id = ? WHERE id = ?",
+                params![chunk_ids[doc.owner], doc_id],
             )?;
         }
 
         tx.commit()?;
-        Ok(())
+        Ok(chunk_ids.to_vec())
+    }
+
+    /// Inserts or updates a markdown document together with fenced code
+    /// blocks stored as code chunks after its prose chunks. `embeddings`
+    /// covers `chunks` followed by `code_blocks`.
+    ///
+    /// Returns the chunk IDs of `code_blocks`, in order.
+    pub fn insert_markdown_document(
+        &self,
+        filename: &str,
+        modified_at: DateTime<Utc>,
+        content_hash: Option<&str>,
+        chunks: &[Chunk<'_>],
+        code_blocks: &[CodeChunk<'_>],
+        embeddings: &[Vec<f32>],
+    ) -> Result<Vec<i64>> {
+        let mut conn = self.get_conn()?;
+        if chunks.len() + code_blocks.len() != embeddings.len() {
+            return Err(rusqlite::Error::SqliteFailure(
+                ffi::Error::new(ffi::SQLITE_MISUSE),
+                Some(format!(
+                    "chunks ({}) and embeddings ({}) length mismatch",
+                    chunks.len() + code_blocks.len(),
+                    embeddings.len()
+                )),
+            ));
+        }
+
+        let all_chunks: Vec<Chunk<'_>> = chunks
+            .iter()
+            .cloned()
+            .chain(code_blocks.iter().map(|cc| cc.chunk.clone()))
+            .collect();
+
+        let tx = conn.transaction()?;
+        let chunk_ids = upsert_document_and_insert_chunks(
+            &tx,
+            filename,
+            modified_at,
+            content_hash,
+            &all_chunks,
+            embeddings,
+        )?;
+        let block_ids = chunk_ids[chunks.len()..].to_vec();
+        insert_code_metadata(&tx, &block_ids, code_blocks)?;
+
+        tx.commit()?;
+        Ok(block_ids)
+    }
+}
+
+/// Insert the `code_metadata` row of each chunk in `chunks`, stored under
+/// the matching ID in `chunk_ids`.
+fn insert_code_metadata(
+    tx: &rusqlite::Transaction,
+    chunk_ids: &[i64],
+    chunks: &[CodeChunk<'_>],
+) -> Result<()> {
+    for (chunk_id, code_chunk) in chunk_ids.iter().zip(chunks) {
+        tx.execute(
+            "INSERT INTO code_metadata (chunk_id, symbol_name, symbol_name_lower, symbol_type, language, start_line, end_line, parent_symbol, signature, doc_comment, part) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                chunk_id,
+                code_chunk.symbol_name,
+                code_chunk.symbol_name.map(str::to_lowercase),
+                code_chunk.symbol_type,
+                code_chunk.language,
+                code_chunk.start_line.map(|x| x as i64),
+                code_chunk.end_line.map(|x| x as i64),
+                code_chunk.parent_symbol,
+                code_chunk.signature,
+                code_chunk.doc_comment,
+                code_chunk.part as i64,
+            ],
+        )?;
     }
+    Ok(())
+}
+
+/// Delete document `doc_id` with its chunks and vectors, first handing them
+/// over to its oldest alias, if any, so the aliases stay searchable.
+fn delete_document_by_id(tx: &rusqlite::Transaction, doc_id: i64) -> Result<()> {
+    let heir: Option<i64> = tx
+        .query_row(
+            "SELECT id FROM documents WHERE alias_of = ? ORDER BY id LIMIT 1",
+            params![doc_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(heir) = heir {
+        tx.execute(
+            "UPDATE documents SET alias_of = NULL,
+                 doc_type = (SELECT doc_type FROM documents WHERE id = ?1),
+                 title = (SELECT title FROM documents WHERE id = ?1)
+             WHERE id = ?2",
+            params![doc_id, heir],
+        )?;
+        tx.execute(
+            "UPDATE documents SET alias_of = ? WHERE alias_of = ?",
+            params![heir, doc_id],
+        )?;
+        tx.execute(
+            "UPDATE chunks SET document_id = ? WHERE document_id = ?",
+            params![heir, doc_id],
+        )?;
+    } else {
+        // Virtual table cascade deletion workaround
+        tx.execute(
+            "DELETE FROM vec_chunks WHERE rowid IN (SELECT id FROM chunks WHERE document_id = ?)",
+            params![doc_id],
+        )?;
+    }
+    // Cascade deletes chunks, code_metadata, code_relations
+    tx.execute("DELETE FROM documents WHERE id = ?", params![doc_id])?;
+    Ok(())
+}
+
+/// Delete the aliases of document `filename` whose content hash is no
+/// longer `content_hash`.
+fn delete_stale_aliases(
+    conn: &rusqlite::Connection,
+    filename: &str,
+    content_hash: &str,
+) -> Result<()> {
+    conn.execute(
+        "DELETE FROM documents
+         WHERE alias_of = (SELECT id FROM documents WHERE filename = ?)
+           AND content_hash IS NOT ?",
+        params![filename, content_hash],
+    )?;
+    Ok(())
 }
 
 /// Shared logic: UPSERT document, delete old chunks/vectors, insert new ones.
@@ -219,22 +646,28 @@ fn upsert_document_and_insert_chunks(
     tx: &rusqlite::Transaction,
     filename: &str,
     modified_at: DateTime<Utc>,
+    content_hash: Option<&str>,
     chunks: &[Chunk<'_>],
     embeddings: &[Vec<f32>],
 ) -> Result<Vec<i64>> {
     // UPSERT document
     let doc_id: i64 = tx.query_row(
         r#"
-        INSERT INTO documents (filename, modified_at, indexed_at)
-        VALUES (?, ?, CURRENT_TIMESTAMP)
+        INSERT INTO documents (filename, modified_at, indexed_at, content_hash)
+        VALUES (?, ?, CURRENT_TIMESTAMP, ?)
         ON CONFLICT(filename) DO UPDATE SET
             modified_at = excluded.modified_at,
-            indexed_at = CURRENT_TIMESTAMP
+            indexed_at = CURRENT_TIMESTAMP,
+            content_hash = excluded.content_hash,
+            alias_of = NULL
         RETURNING id
         "#,
-        params![filename, modified_at],
+        params![filename, modified_at, content_hash],
         |row| row.get(0),
     )?;
+    if let Some(hash) = content_hash {
+        delete_stale_aliases(tx, filename, hash)?;
+    }
 
     // Clean up old contents
     tx.execute(
@@ -247,8 +680,14 @@ fn upsert_document_and_insert_chunks(
     let mut chunk_ids = Vec::with_capacity(chunks.len());
     for (i, chunk) in chunks.iter().enumerate() {
         tx.execute(
-            "INSERT INTO chunks (document_id, position, content) VALUES (?, ?, ?)",
-            params![doc_id, chunk.position as i64, chunk.content],
+            "INSERT INTO chunks (document_id, position, content, start_line, end_line) VALUES (?, ?, ?, ?, ?)",
+            params![
+                doc_id,
+                chunk.position as i64,
+                chunk.content,
+                chunk.start_line.map(|l| l as i64),
+                chunk.end_line.map(|l| l as i64)
+            ],
         )?;
         let chunk_id = tx.last_insert_rowid();
         chunk_ids.push(chunk_id);
@@ -267,6 +706,122 @@ fn upsert_document_and_insert_chunks(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_list_word_mappings() {
+        let db = Db::open_in_memory().unwrap();
+        let mapping = |source: &str, target: &str, lang: &str, confidence: f64| {
+            (
+                source.to_string(),
+                target.to_string(),
+                lang.to_string(),
+                confidence,
+                "doc.md".to_string(),
+            )
+        };
+        db.insert_word_mappings(&[
+            mapping("認証", "authentication", "ja", 0.6),
+            mapping("設定", "config", "ja", 0.9),
+            mapping("配置", "config", "zh", 0.8),
+        ])
+        .unwrap();
+
+        let all = db.list_word_mappings(None).unwrap();
+        let confidences: Vec<f64> = all.iter().map(|m| m.confidence).collect();
+        assert_eq!(confidences, [0.9, 0.8, 0.6]);
+
+        let ja = db.list_word_mappings(Some("ja")).unwrap();
+        assert_eq!(ja.len(), 2);
+        assert_eq!(ja[0].source_word, "設定");
+        assert_eq!(ja[0].source_document.as_deref(), Some("doc.md"));
+    }
+
+    #[test]
+    fn test_get_word_mapping_sources() {
+        let db = Db::open_in_memory().unwrap();
+        let mapping = |document: &str, confidence: f64| {
+            (
+                "認証".to_string(),
+                "authentication".to_string(),
+                "ja".to_string(),
+                confidence,
+                document.to_string(),
+            )
+        };
+        db.insert_word_mappings(&[mapping("docs/a.md", 0.8), mapping("docs/b.md", 1.0)])
+            .unwrap();
+        // Re-extracting keeps the best confidence per document
+        db.insert_word_mappings(&[mapping("docs/a.md", 0.6)])
+            .unwrap();
+
+        let sources = db
+            .get_word_mapping_sources("認証", "authentication")
+            .unwrap();
+        assert_eq!(
+            sources,
+            [
+                WordMappingSource {
+                    document: "docs/b.md".to_string(),
+                    source_lang: "ja".to_string(),
+                    confidence: 1.0,
+                },
+                WordMappingSource {
+                    document: "docs/a.md".to_string(),
+                    source_lang: "ja".to_string(),
+                    confidence: 0.8,
+                },
+            ]
+        );
+        assert!(
+            db.get_word_mapping_sources("認証", "auth")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_modified_at_keeps_subsecond_precision() {
+        let db = Db::open_in_memory().unwrap();
+        let modified_at = DateTime::from_timestamp(1_700_000_000, 123_456_789).unwrap();
+        db.insert_document(
+            "a.md",
+            modified_at,
+            None,
+            &[Chunk {
+                position: 0,
+                content: "a",
+                start_line: None,
+                end_line: None,
+            }],
+            &[vec![0.1; 1024]],
+        )
+        .unwrap();
+
+        let docs = db.list_documents().unwrap();
+        assert_eq!(docs["a.md"], modified_at);
+    }
+
+    #[test]
+    fn test_list_documents_page() {
+        let db = Db::open_in_memory().unwrap();
+        let now = Utc::now();
+        for name in ["c.md", "a.md", "e.md", "b.md", "d.md"] {
+            db.insert_document(name, now, None, &[], &[]).unwrap();
+        }
+
+        assert_eq!(db.count_documents().unwrap(), 5);
+        let page = |offset, limit| -> Vec<String> {
+            db.list_documents_page(offset, limit)
+                .unwrap()
+                .into_iter()
+                .map(|d| d.filename)
+                .collect()
+        };
+        assert_eq!(page(0, 2), ["a.md", "b.md"]);
+        assert_eq!(page(2, 2), ["c.md", "d.md"]);
+        assert_eq!(page(4, 2), ["e.md"]);
+        assert!(page(5, 2).is_empty());
+    }
+
     #[test]
     fn test_documents_crud() {
         let db = Db::open_in_memory().unwrap();
@@ -278,21 +833,26 @@ mod tests {
             Chunk {
                 position: 0,
                 content: "Hello",
+                start_line: None,
+                end_line: None,
             },
             Chunk {
                 position: 1,
                 content: "World",
+                start_line: None,
+                end_line: None,
             },
         ];
         let embeddings = vec![vec![0.1; 1024], vec![0.2; 1024]];
 
-        db.insert_document(filename, now, &chunks, &embeddings)
+        db.insert_document(filename, now, None, &chunks, &embeddings)
             .unwrap();
 
         // 2. List documents
         let docs = db.list_documents().unwrap();
         assert_eq!(docs.len(), 1);
         assert!(docs.contains_key(filename));
+        assert!(db.document_exists(filename).unwrap());
 
         // 3. Count rows
         let chunks_count: i64 = db
@@ -313,9 +873,11 @@ mod tests {
         let new_chunks = vec![Chunk {
             position: 0,
             content: "Replaced",
+            start_line: None,
+            end_line: None,
         }];
         let new_embeddings = vec![vec![0.5; 1024]];
-        db.insert_document(filename, Utc::now(), &new_chunks, &new_embeddings)
+        db.insert_document(filename, Utc::now(), None, &new_chunks, &new_embeddings)
             .unwrap();
 
         // Count rows again - old chunks should be deleted
@@ -336,6 +898,7 @@ mod tests {
         // 5. Delete document
         let deleted = db.delete_document(filename).unwrap();
         assert!(deleted);
+        assert!(!db.document_exists(filename).unwrap());
 
         // Verify cascading deletes
         let chunks_count: i64 = db
@@ -353,6 +916,60 @@ mod tests {
         assert_eq!(vec_chunks_count, 0);
     }
 
+    #[test]
+    fn test_doc_chunks_linked_to_owner() {
+        let db = Db::open_in_memory().unwrap();
+        let code = CodeChunk {
+            chunk: Chunk {
+                position: 0,
+                content: "fn parse() {}",
+                start_line: None,
+                end_line: None,
+            },
+            symbol_name: Some("parse"),
+            symbol_type: "function",
+            language: "rust",
+            start_line: Some(3),
+            end_line: Some(3),
+            parent_symbol: None,
+            signature: None,
+            doc_comment: Some("Parses input."),
+            part: 0,
+        };
+        let doc = DocChunk {
+            owner: 0,
+            chunk: Chunk {
+                position: 1,
+                content: "Parses input.",
+                start_line: Some(3),
+                end_line: Some(3),
+            },
+        };
+        let ids = db
+            .insert_code_document_with_docs(
+                "lib.rs",
+                Utc::now(),
+                None,
+                &[code],
+                &[doc],
+                &[vec![0.1; 1024], vec![0.2; 1024]],
+            )
+            .unwrap();
+        assert_eq!(ids.len(), 1);
+
+        let conn = db.get_conn().unwrap();
+        let (parent, symbols): (Option<i64>, i64) = conn
+            .query_row(
+                "SELECT c.parent_chunk_id, (SELECT COUNT(*) FROM code_metadata WHERE chunk_id = c.id)
+                 FROM chunks c WHERE c.content = 'Parses input.'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(parent, Some(ids[0]));
+        assert_eq!(symbols, 0);
+    }
+
     #[test]
     fn test_delete_documents_batch() {
         let db = Db::open_in_memory().unwrap();
@@ -361,9 +978,12 @@ mod tests {
         db.insert_document(
             "a.md",
             now,
+            None,
             &[Chunk {
                 position: 0,
                 content: "A",
+                start_line: None,
+                end_line: None,
             }],
             &[vec![0.1; 1024]],
         )
@@ -371,9 +991,12 @@ mod tests {
         db.insert_document(
             "b.md",
             now,
+            None,
             &[Chunk {
                 position: 0,
                 content: "B",
+                start_line: None,
+                end_line: None,
             }],
             &[vec![0.2; 1024]],
         )
@@ -381,9 +1004,12 @@ mod tests {
         db.insert_document(
             "c.md",
             now,
+            None,
             &[Chunk {
                 position: 0,
                 content: "C",
+                start_line: None,
+                end_line: None,
             }],
             &[vec![0.3; 1024]],
         )
@@ -403,6 +1029,129 @@ mod tests {
         assert_eq!(removed, 0);
     }
 
+    #[test]
+    fn test_delete_documents_by_prefix() {
+        let db = Db::open_in_memory().unwrap();
+        let now = Utc::now();
+        for name in [
+            "/p/docs/a.md",
+            "/p/docs/sub/b.md",
+            "/p/docs_old/c.md",
+            "/p/d_cs/d.md",
+        ] {
+            let chunk = Chunk {
+                position: 0,
+                content: name,
+                start_line: None,
+                end_line: None,
+            };
+            db.insert_document(name, now, None, &[chunk], &[vec![0.1; 1024]])
+                .unwrap();
+        }
+
+        assert_eq!(db.delete_documents_by_prefix("/p/docs/").unwrap(), 2);
+        // `_` is not a wildcard
+        assert_eq!(db.delete_documents_by_prefix("/p/dXcs/").unwrap(), 0);
+
+        let mut docs: Vec<String> = db.list_documents().unwrap().into_keys().collect();
+        docs.sort();
+        assert_eq!(docs, ["/p/d_cs/d.md", "/p/docs_old/c.md"]);
+        // Vectors of the removed chunks are gone too
+        assert_eq!(db.search(&vec![0.1; 1024], 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_delete_original_promotes_alias() {
+        let db = Db::open_in_memory().unwrap();
+        let now = Utc::now();
+        let chunk = Chunk {
+            position: 0,
+            content: "shared",
+            start_line: None,
+            end_line: None,
+        };
+        db.insert_document("/p/docs/a.md", now, Some("h"), &[chunk], &[vec![0.1; 1024]])
+            .unwrap();
+        let (original, _) = db.find_document_by_content_hash("h", "").unwrap().unwrap();
+        db.set_document_alias("/p/docs/b.md", now, "h", original)
+            .unwrap();
+        db.set_document_alias("/p/vendor/a.md", now, "h", original)
+            .unwrap();
+
+        // Both aliases inside the prefix go, the one outside it keeps the vectors
+        assert_eq!(db.delete_documents_by_prefix("/p/docs/").unwrap(), 2);
+        let docs = db.list_documents_page(0, 10).unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].filename, "/p/vendor/a.md");
+        assert_eq!(docs[0].alias_of, None);
+        let hits = db.search(&vec![0.1; 1024], 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].document_name, "/p/vendor/a.md");
+    }
+
+    #[test]
+    fn test_rename_document() {
+        let db = Db::open_in_memory().unwrap();
+        let now = Utc::now();
+        for name in ["/p/old.md", "/p/taken.md"] {
+            let chunk = Chunk {
+                position: 0,
+                content: name,
+                start_line: None,
+                end_line: None,
+            };
+            db.insert_document(name, now, None, &[chunk], &[vec![0.1; 1024]])
+                .unwrap();
+        }
+        db.set_document_content_hash("/p/old.md", "abc").unwrap();
+        // A relation from taken.md resolved to a symbol in old.md
+        let hits = db.search(&vec![0.1; 1024], 10).unwrap();
+        let taken_chunk = hits
+            .iter()
+            .find(|h| h.document_name == "/p/taken.md")
+            .unwrap()
+            .chunk_id;
+        db.insert_relations(&[CodeRelation {
+            id: 0,
+            source_chunk_id: taken_chunk,
+            target_chunk_id: None,
+            relation_type: "calls".to_string(),
+            target_name: "helper".to_string(),
+            target_file: Some("/p/old.md".to_string()),
+            confidence: 1.0,
+            source_name: None,
+            source_file: None,
+        }])
+        .unwrap();
+
+        assert!(db.rename_document("/p/old.md", "/p/new.md").unwrap());
+        let relations = db.get_relations_from(taken_chunk, None).unwrap();
+        assert_eq!(relations[0].target_file.as_deref(), Some("/p/new.md"));
+        let docs = db.list_documents().unwrap();
+        assert!(!docs.contains_key("/p/old.md"));
+        assert_eq!(docs["/p/new.md"], now);
+        assert_eq!(
+            db.get_document_content_hash("/p/new.md")
+                .unwrap()
+                .as_deref(),
+            Some("abc")
+        );
+        // The chunk and its vector moved along without re-embedding
+        let hits = db.search(&vec![0.1; 1024], 10).unwrap();
+        assert!(
+            hits.iter()
+                .any(|h| h.document_name == "/p/new.md" && h.chunk_content == "/p/old.md")
+        );
+
+        assert!(!db.rename_document("/p/missing.md", "/p/other.md").unwrap());
+        let err = db.rename_document("/p/new.md", "/p/taken.md").unwrap_err();
+        assert_eq!(
+            err.sqlite_error_code(),
+            Some(rusqlite::ErrorCode::ConstraintViolation)
+        );
+        assert!(db.list_documents().unwrap().contains_key("/p/new.md"));
+    }
+
     #[test]
     fn test_delete_nonexistent_document() {
         let db = Db::open_in_memory().unwrap();
@@ -1,21 +1,27 @@
 //! Vector Database module using SQLite and sqlite-vec
 use rusqlite::{Connection, Result};
 use sqlite_vec::sqlite3_vec_init;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Once;
-use tracing::info;
+use tracing::{info, warn};
 
+pub mod backup;
 pub mod documents;
 pub mod models;
 pub mod relations;
 pub mod search;
+pub mod stats;
 
 const SCHEMA_SQL: &str = r#"
 CREATE TABLE IF NOT EXISTS documents (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     filename TEXT NOT NULL UNIQUE,
     indexed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-    modified_at DATETIME NOT NULL
+    modified_at DATETIME NOT NULL,
+    doc_type TEXT,
+    content_hash TEXT,
+    title TEXT,
+    alias_of INTEGER REFERENCES documents(id) ON DELETE CASCADE
 );
 
 CREATE INDEX IF NOT EXISTS idx_filename ON documents(filename);
@@ -25,6 +31,9 @@ CREATE TABLE IF NOT EXISTS chunks (
     document_id INTEGER NOT NULL,
     position INTEGER NOT NULL,
     content TEXT NOT NULL,
+    start_line INTEGER,
+    end_line INTEGER,
+    parent_chunk_id INTEGER REFERENCES chunks(id) ON DELETE CASCADE,
     FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
 );
 
@@ -34,16 +43,38 @@ CREATE VIRTUAL TABLE IF NOT EXISTS vec_chunks USING vec0(
     embedding float32[1024]
 );
 
+CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(
+    content,
+    content='chunks',
+    content_rowid='id'
+);
+
+CREATE TRIGGER IF NOT EXISTS chunks_fts_insert AFTER INSERT ON chunks BEGIN
+    INSERT INTO chunks_fts(rowid, content) VALUES (new.id, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS chunks_fts_delete AFTER DELETE ON chunks BEGIN
+    INSERT INTO chunks_fts(chunks_fts, rowid, content) VALUES ('delete', old.id, old.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS chunks_fts_update AFTER UPDATE ON chunks BEGIN
+    INSERT INTO chunks_fts(chunks_fts, rowid, content) VALUES ('delete', old.id, old.content);
+    INSERT INTO chunks_fts(rowid, content) VALUES (new.id, new.content);
+END;
+
 CREATE TABLE IF NOT EXISTS code_metadata (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     chunk_id INTEGER NOT NULL UNIQUE,
     symbol_name TEXT,
+    symbol_name_lower TEXT,
     symbol_type TEXT NOT NULL,
     language TEXT NOT NULL,
     start_line INTEGER,
     end_line INTEGER,
     parent_symbol TEXT,
     signature TEXT,
+    doc_comment TEXT,
+    part INTEGER NOT NULL DEFAULT 0,
     FOREIGN KEY (chunk_id) REFERENCES chunks(id) ON DELETE CASCADE
 );
 
@@ -88,8 +119,252 @@ CREATE TABLE IF NOT EXISTS word_mapping (
 CREATE INDEX IF NOT EXISTS idx_word_source ON word_mapping(source_word);
 CREATE INDEX IF NOT EXISTS idx_word_target ON word_mapping(target_word);
 CREATE INDEX IF NOT EXISTS idx_word_lang ON word_mapping(source_lang);
+
+-- Every document a mapping was extracted from, with the confidence it gave
+CREATE TABLE IF NOT EXISTS word_mapping_source (
+    mapping_id INTEGER NOT NULL,
+    document TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    PRIMARY KEY (mapping_id, document),
+    FOREIGN KEY (mapping_id) REFERENCES word_mapping(id) ON DELETE CASCADE
+);
 "#;
 
+/// Dimension of `vec_chunks.embedding` in `SCHEMA_SQL`.
+pub const VEC_DIMENSIONS: usize = 1024;
+
+/// Key in `system_metadata` holding the embedding dimension the index was built with.
+pub(crate) const EMBEDDING_DIMENSIONS_KEY: &str = "embedding_dimensions";
+
+/// Schema version this build expects. Bump it when appending to `MIGRATIONS`.
+pub const SCHEMA_VERSION: u32 = 13;
+
+/// Key in `system_metadata` holding the applied schema version.
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// How long a connection waits on a lock held by another one (e.g. a
+/// background sync write) before failing with "database is locked".
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// A schema upgrade from `version - 1` to `version`.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    up: fn(&Connection) -> Result<()>,
+}
+
+/// Ordered list of migrations. `SCHEMA_SQL` always creates the latest layout
+/// for new tables, so migrations only need to upgrade tables that already
+/// existed in older databases:
+///
+/// - New columns go through [`add_column_if_missing`], since a fresh DB
+///   already has them from `SCHEMA_SQL` when the migration runs.
+/// - Indexes on new columns belong in the migration, not `SCHEMA_SQL`, which
+///   runs first and would fail against an old table.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "start tracking schema_version",
+        up: |_| Ok(()),
+    },
+    Migration {
+        version: 2,
+        description: "add documents.doc_type",
+        up: |conn| add_column_if_missing(conn, "documents", "doc_type", "TEXT"),
+    },
+    Migration {
+        version: 3,
+        description: "add code_metadata.symbol_name_lower",
+        up: migrate_symbol_name_lower,
+    },
+    Migration {
+        version: 4,
+        description: "add chunks_fts keyword index",
+        // The table and its sync triggers come from SCHEMA_SQL; fill it
+        // from the chunks indexed before it existed.
+        up: |conn| conn.execute_batch("INSERT INTO chunks_fts(chunks_fts) VALUES ('rebuild')"),
+    },
+    Migration {
+        version: 5,
+        description: "add chunks.start_line",
+        up: |conn| add_column_if_missing(conn, "chunks", "start_line", "INTEGER"),
+    },
+    Migration {
+        version: 6,
+        description: "add word_mapping_source",
+        // Older mappings only remember their most recent document
+        up: |conn| {
+            conn.execute_batch(
+                "INSERT OR IGNORE INTO word_mapping_source (mapping_id, document, confidence)
+                 SELECT id, source_document, confidence FROM word_mapping
+                 WHERE source_document IS NOT NULL AND source_document != ''",
+            )
+        },
+    },
+    Migration {
+        version: 7,
+        description: "add chunks.end_line",
+        up: |conn| add_column_if_missing(conn, "chunks", "end_line", "INTEGER"),
+    },
+    Migration {
+        version: 8,
+        description: "add documents.content_hash",
+        up: |conn| add_column_if_missing(conn, "documents", "content_hash", "TEXT"),
+    },
+    Migration {
+        version: 9,
+        description: "add code_metadata.doc_comment",
+        up: |conn| add_column_if_missing(conn, "code_metadata", "doc_comment", "TEXT"),
+    },
+    Migration {
+        version: 10,
+        description: "add documents.title",
+        up: |conn| add_column_if_missing(conn, "documents", "title", "TEXT"),
+    },
+    Migration {
+        version: 11,
+        description: "add documents.alias_of",
+        up: |conn| {
+            add_column_if_missing(
+                conn,
+                "documents",
+                "alias_of",
+                "INTEGER REFERENCES documents(id) ON DELETE CASCADE",
+            )
+        },
+    },
+    Migration {
+        version: 12,
+        description: "link doc comment chunks through chunks.parent_chunk_id",
+        up: migrate_doc_comment_chunks,
+    },
+    Migration {
+        version: 13,
+        description: "add code_metadata.part",
+        up: |conn| {
+            add_column_if_missing(conn, "code_metadata", "part", "INTEGER NOT NULL DEFAULT 0")
+        },
+    },
+];
+
+/// Doc comments used to be stored as `doc_comment` symbols sharing the name
+/// and start line of the symbol they document. Link each one to that
+/// symbol's chunk instead and drop the pseudo-symbol rows.
+fn migrate_doc_comment_chunks(conn: &Connection) -> Result<()> {
+    add_column_if_missing(
+        conn,
+        "chunks",
+        "parent_chunk_id",
+        "INTEGER REFERENCES chunks(id) ON DELETE CASCADE",
+    )?;
+    conn.execute_batch(
+        "UPDATE chunks SET parent_chunk_id = (
+             SELECT owner.id FROM code_metadata doc
+             JOIN chunks owner ON owner.document_id = chunks.document_id
+             JOIN code_metadata om ON om.chunk_id = owner.id
+             WHERE doc.chunk_id = chunks.id
+               AND om.symbol_type != 'doc_comment'
+               AND om.symbol_name = doc.symbol_name
+               AND om.start_line = doc.start_line
+             LIMIT 1
+         )
+         WHERE id IN (SELECT chunk_id FROM code_metadata WHERE symbol_type = 'doc_comment');
+         DELETE FROM code_metadata WHERE symbol_type = 'doc_comment';",
+    )
+}
+
+/// Add and backfill the case-folded symbol name used for lookups. The
+/// backfill runs in Rust so it folds exactly like inserts do (SQLite's
+/// `LOWER` only handles ASCII).
+fn migrate_symbol_name_lower(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "code_metadata", "symbol_name_lower", "TEXT")?;
+    let names: Vec<(i64, String)> = conn
+        .prepare(
+            "SELECT id, symbol_name FROM code_metadata
+             WHERE symbol_name IS NOT NULL AND symbol_name_lower IS NULL",
+        )?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_>>()?;
+    let mut update = conn.prepare("UPDATE code_metadata SET symbol_name_lower = ? WHERE id = ?")?;
+    for (id, name) in names {
+        update.execute(rusqlite::params![name.to_lowercase(), id])?;
+    }
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_code_symbol_lower ON code_metadata(symbol_name_lower)",
+    )
+}
+
+fn read_schema_version(conn: &Connection) -> Result<u32> {
+    let res = conn.query_row(
+        "SELECT value FROM system_metadata WHERE key = ?",
+        [SCHEMA_VERSION_KEY],
+        |row| row.get::<_, String>(0),
+    );
+    match res {
+        Ok(v) => Ok(v.parse().unwrap_or(0)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if row.get::<_, String>(1)? == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// `ALTER TABLE <table> ADD COLUMN <column> <decl>` unless the column exists.
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    if !column_exists(conn, table, column)? {
+        conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl}"))?;
+    }
+    Ok(())
+}
+
+fn run_migrations(conn: &mut Connection) -> Result<()> {
+    apply_migrations(conn, MIGRATIONS)
+}
+
+/// Apply every migration newer than the recorded schema version, in order.
+/// Each step runs in its own transaction together with the version bump.
+fn apply_migrations(conn: &mut Connection, migrations: &[Migration]) -> Result<()> {
+    let current = read_schema_version(conn)?;
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if current > latest {
+        warn!(
+            "Database schema version {} is newer than this build supports ({}); \
+             some features may not work",
+            current, latest
+        );
+    }
+    for m in migrations.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()?;
+        (m.up)(&tx)?;
+        tx.execute(
+            "INSERT INTO system_metadata (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = CURRENT_TIMESTAMP",
+            [SCHEMA_VERSION_KEY, &m.version.to_string()],
+        )?;
+        tx.commit()?;
+        info!("Applied schema migration {} ({})", m.version, m.description);
+    }
+    Ok(())
+}
+
+/// Stamp the embedding dimension on a newly created index. Existing values are
+/// kept so a DB built for another dimension is detected rather than relabeled.
+fn record_embedding_dimensions(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO system_metadata (key, value) VALUES (?1, ?2)",
+        [EMBEDDING_DIMENSIONS_KEY, &VEC_DIMENSIONS.to_string()],
+    )?;
+    Ok(())
+}
+
 static INIT_VEC: Once = Once::new();
 
 /// Register sqlite-vec as a SQLite auto-extension. Must be called BEFORE any
@@ -132,6 +407,18 @@ impl ManageConnection for SqliteManager {
              PRAGMA journal_mode = WAL;
              PRAGMA synchronous = NORMAL;",
         )?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+
+        // In-memory databases can't use WAL; files can fail to on some filesystems
+        if let Some(p) = &self.path {
+            let mode: String = conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))?;
+            if !mode.eq_ignore_ascii_case("wal") {
+                warn!(
+                    "{} uses journal_mode={mode} instead of WAL; concurrent sync and search may block",
+                    p.display()
+                );
+            }
+        }
 
         // Verification
         let vec_version: String = conn.query_row("SELECT vec_version()", [], |row| row.get(0))?;
@@ -148,10 +435,28 @@ impl ManageConnection for SqliteManager {
     }
 }
 
+/// Create the parent directory of `path` if needed and resolve symlinks in it.
+fn resolve_db_path(path: &Path) -> std::io::Result<PathBuf> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    if path.exists() {
+        return std::fs::canonicalize(path);
+    }
+    // Not created yet: resolve the directory it will be created in
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => {
+            Ok(std::fs::canonicalize(parent)?.join(name))
+        }
+        _ => Ok(path.to_path_buf()),
+    }
+}
+
 /// A wrapper around a SQLite connection pool initialized with sqlite-vec and the application schema.
 #[derive(Clone)]
 pub struct Db {
     pub pool: Pool<SqliteManager>,
+    path: Option<PathBuf>,
 }
 
 impl Db {
@@ -162,8 +467,19 @@ impl Db {
     }
 
     /// Open a database connection pool at the given path and initialize the schema.
+    ///
+    /// Missing parent directories are created, and a symlinked path is
+    /// resolved so the `-wal`/`-shm` files sit next to the real database.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref();
+        let path = &resolve_db_path(path.as_ref()).map_err(|e| {
+            rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CANTOPEN),
+                Some(format!(
+                    "cannot prepare database path {}: {e}",
+                    path.as_ref().display()
+                )),
+            )
+        })?;
         info!("Initializing database: {}", path.display());
 
         let manager = SqliteManager {
@@ -177,14 +493,56 @@ impl Db {
             })?;
 
         // Initialize schema using the first connection
-        let conn = pool.get().map_err(|e| {
+        let mut conn = pool.get().map_err(|e| {
             rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(1), Some(e.to_string()))
         })?;
         conn.execute_batch(SCHEMA_SQL)?;
+        run_migrations(&mut conn)?;
+        record_embedding_dimensions(&conn)?;
 
         info!("Database initialized successfully");
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    /// Path of the database file, or `None` for an in-memory database.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Schema version recorded in `system_metadata` (0 if never set).
+    pub fn schema_version(&self) -> Result<u32> {
+        read_schema_version(&*self.get_conn()?)
+    }
+
+    /// Version string reported by the loaded sqlite-vec extension.
+    pub fn sqlite_vec_version(&self) -> Result<String> {
+        self.get_conn()?
+            .query_row("SELECT vec_version()", [], |row| row.get(0))
+    }
+
+    /// Embedding dimension the index was built with.
+    pub fn embedding_dimensions(&self) -> Result<Option<usize>> {
+        Ok(self
+            .get_metadata(EMBEDDING_DIMENSIONS_KEY)?
+            .and_then(|v| v.parse().ok()))
+    }
+
+    /// Fail if the index was built with a different embedding dimension than
+    /// `configured`, instead of letting inserts and searches fail cryptically.
+    pub fn check_embedding_dimensions(&self, configured: usize) -> anyhow::Result<()> {
+        if let Some(stored) = self.embedding_dimensions()? {
+            anyhow::ensure!(
+                stored == configured,
+                "index was built with {stored}-dimension embeddings but embedding.dimensions is \
+                 {configured}; use a model with matching dimensions or point db_path at a new \
+                 database and reindex"
+            );
+        }
+        Ok(())
     }
 
     pub fn get_metadata(&self, key: &str) -> Result<Option<String>> {
@@ -219,11 +577,13 @@ impl Db {
                 rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(1), Some(e.to_string()))
             })?;
 
-        let conn = pool.get().map_err(|e| {
+        let mut conn = pool.get().map_err(|e| {
             rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(1), Some(e.to_string()))
         })?;
         conn.execute_batch(SCHEMA_SQL)?;
-        Ok(Self { pool })
+        run_migrations(&mut conn)?;
+        record_embedding_dimensions(&conn)?;
+        Ok(Self { pool, path: None })
     }
 }
 
@@ -236,6 +596,15 @@ pub fn serialize_vector_f32(vec: &[f32]) -> Vec<u8> {
     bytes
 }
 
+/// Inverse of [`serialize_vector_f32`]: read a raw little-endian float32
+/// blob as stored by sqlite-vec. Trailing bytes short of a float are ignored.
+pub fn deserialize_vector_f32(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -256,6 +625,230 @@ mod tests {
         assert_eq!(tables, 6);
     }
 
+    #[test]
+    fn test_schema_version_recorded() {
+        let db = Db::open_in_memory().unwrap();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+        assert!(db.sqlite_vec_version().unwrap().starts_with('v'));
+    }
+
+    #[test]
+    fn test_embedding_dimensions_persisted_and_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rag.db");
+        {
+            let db = Db::open(&path).unwrap();
+            assert_eq!(db.embedding_dimensions().unwrap(), Some(VEC_DIMENSIONS));
+            assert!(db.check_embedding_dimensions(VEC_DIMENSIONS).is_ok());
+
+            let err = db.check_embedding_dimensions(768).unwrap_err();
+            assert!(err.to_string().contains("1024-dimension"));
+
+            // Simulate an index built by a different model
+            db.set_metadata(EMBEDDING_DIMENSIONS_KEY, "768").unwrap();
+        }
+
+        // Reopening keeps the recorded value rather than overwriting it
+        let db = Db::open(&path).unwrap();
+        assert_eq!(db.embedding_dimensions().unwrap(), Some(768));
+        assert!(db.check_embedding_dimensions(VEC_DIMENSIONS).is_err());
+    }
+
+    #[test]
+    fn test_open_creates_missing_parent_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data/nested/vectors.db");
+        let db = Db::open(&path).unwrap();
+
+        assert!(path.exists());
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_open_resolves_symlinked_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().join("real.db");
+        drop(Db::open(&real).unwrap());
+        let link = dir.path().join("link.db");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let db = Db::open(&link).unwrap();
+        assert_eq!(db.path(), Some(real.canonicalize().unwrap().as_path()));
+    }
+
+    #[test]
+    fn test_file_db_uses_wal_and_busy_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Db::open(dir.path().join("rag.db")).unwrap();
+        let conn = db.get_conn().unwrap();
+
+        let mode: String = conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+        let timeout: i64 = conn
+            .query_row("PRAGMA busy_timeout", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(timeout, BUSY_TIMEOUT.as_millis() as i64);
+        let synchronous: i64 = conn
+            .query_row("PRAGMA synchronous", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(synchronous, 1); // NORMAL
+    }
+
+    #[test]
+    fn test_migration_from_unversioned_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("old.db");
+
+        // Fixture: a pre-versioning DB with data but no schema_version key
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE documents (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    filename TEXT NOT NULL UNIQUE,
+                    indexed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    modified_at DATETIME NOT NULL
+                );
+                INSERT INTO documents (filename, modified_at) VALUES ('old.md', '2024-01-01T00:00:00Z');",
+            )
+            .unwrap();
+        }
+
+        let db = Db::open(&path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+        assert!(db.list_documents().unwrap().contains_key("old.md"));
+
+        // Reopening doesn't re-run migrations
+        drop(db);
+        let db = Db::open(&path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_symbol_name_lower_backfilled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("old.db");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE code_metadata (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    chunk_id INTEGER NOT NULL UNIQUE,
+                    symbol_name TEXT,
+                    symbol_type TEXT NOT NULL,
+                    language TEXT NOT NULL,
+                    start_line INTEGER,
+                    end_line INTEGER,
+                    parent_symbol TEXT,
+                    signature TEXT
+                );
+                INSERT INTO code_metadata (chunk_id, symbol_name, symbol_type, language)
+                VALUES (1, 'ÜberParse', 'function', 'rust'), (2, NULL, 'block', 'rust');",
+            )
+            .unwrap();
+        }
+
+        let db = Db::open(&path).unwrap();
+        let conn = db.get_conn().unwrap();
+        let lowered: Vec<Option<String>> = conn
+            .prepare("SELECT symbol_name_lower FROM code_metadata ORDER BY chunk_id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(lowered, [Some("überparse".to_string()), None]);
+    }
+
+    #[test]
+    fn test_doc_comment_symbols_migrated_to_linked_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("old.db");
+        {
+            let db = Db::open(&path).unwrap();
+            let conn = db.get_conn().unwrap();
+            conn.execute_batch(
+                "INSERT INTO documents (id, filename, modified_at) VALUES (1, 'a.rs', 0);
+                 INSERT INTO chunks (id, document_id, position, content)
+                 VALUES (1, 1, 0, 'fn parse() {}'), (2, 1, 1, 'Parses input.');
+                 INSERT INTO code_metadata (chunk_id, symbol_name, symbol_type, language, start_line)
+                 VALUES (1, 'parse', 'function', 'rust', 3), (2, 'parse', 'doc_comment', 'rust', 3);
+                 UPDATE system_metadata SET value = '11' WHERE key = 'schema_version';",
+            )
+            .unwrap();
+        }
+
+        let db = Db::open(&path).unwrap();
+        let conn = db.get_conn().unwrap();
+        let parent: Option<i64> = conn
+            .query_row(
+                "SELECT parent_chunk_id FROM chunks WHERE id = 2",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(parent, Some(1));
+        let types: Vec<String> = conn
+            .prepare("SELECT symbol_type FROM code_metadata")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(types, ["function"]);
+    }
+
+    #[test]
+    fn test_column_migration_preserves_data() {
+        const TEST_MIGRATIONS: &[Migration] = &[
+            Migration {
+                version: 1,
+                description: "start tracking schema_version",
+                up: |_| Ok(()),
+            },
+            Migration {
+                version: 2,
+                description: "add documents.title",
+                up: |conn| add_column_if_missing(conn, "documents", "title", "TEXT"),
+            },
+        ];
+
+        // Fixture at schema version 1: documents table without `title`
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE documents (id INTEGER PRIMARY KEY, filename TEXT NOT NULL);
+             INSERT INTO documents (filename) VALUES ('a.md'), ('b.md');
+             CREATE TABLE system_metadata (
+                 key TEXT PRIMARY KEY,
+                 value TEXT NOT NULL,
+                 updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+             );
+             INSERT INTO system_metadata (key, value) VALUES ('schema_version', '1');",
+        )
+        .unwrap();
+        assert!(!column_exists(&conn, "documents", "title").unwrap());
+
+        apply_migrations(&mut conn, TEST_MIGRATIONS).unwrap();
+
+        assert!(column_exists(&conn, "documents", "title").unwrap());
+        assert_eq!(read_schema_version(&conn).unwrap(), 2);
+        let filenames: Vec<String> = conn
+            .prepare("SELECT filename FROM documents ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(filenames, ["a.md", "b.md"]);
+
+        // Re-running is a no-op, and the ALTER is idempotent on a fresh layout
+        apply_migrations(&mut conn, TEST_MIGRATIONS).unwrap();
+        add_column_if_missing(&conn, "documents", "title", "TEXT").unwrap();
+    }
+
     #[test]
     fn test_serialize_vector_f32() {
         let vec = vec![1.0f32, 0.0, -1.0];
@@ -268,5 +861,7 @@ mod tests {
         assert_eq!(&bytes[4..8], &0.0f32.to_le_bytes());
         // Verify third float (-1.0)
         assert_eq!(&bytes[8..12], &(-1.0f32).to_le_bytes());
+
+        assert_eq!(deserialize_vector_f32(&bytes), vec);
     }
 }
@@ -1,7 +1,15 @@
+use chrono::{DateTime, Utc};
+
 #[derive(Debug, Clone)]
 pub struct Chunk<'a> {
     pub position: usize,
     pub content: &'a str,
+    /// 1-based line in the source file where the chunk starts, for prose
+    /// chunks that track it (code chunks keep lines in `code_metadata`).
+    pub start_line: Option<usize>,
+    /// 1-based line in the source file where the chunk ends, alongside
+    /// `start_line`.
+    pub end_line: Option<usize>,
 }
 
 #[derive(Debug, Clone)]
@@ -14,6 +22,19 @@ pub struct CodeChunk<'a> {
     pub end_line: Option<usize>,
     pub parent_symbol: Option<&'a str>,
     pub signature: Option<&'a str>,
+    pub doc_comment: Option<&'a str>,
+    /// Piece index of a symbol split into several chunks; 0 for a whole
+    /// symbol or its first piece.
+    pub part: usize,
+}
+
+/// A symbol's doc comment, stored as a prose chunk without `code_metadata`
+/// and linked to the code chunk it documents through `chunks.parent_chunk_id`.
+#[derive(Debug, Clone)]
+pub struct DocChunk<'a> {
+    /// Index of the documented chunk in the accompanying code chunks.
+    pub owner: usize,
+    pub chunk: Chunk<'a>,
 }
 
 #[derive(Debug)]
@@ -52,3 +73,30 @@ pub struct WordMapping {
     pub confidence: f64,
     pub source_document: Option<String>,
 }
+
+/// A document that contributed a word mapping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordMappingSource {
+    pub document: String,
+    pub source_lang: String,
+    pub confidence: f64,
+}
+
+/// A symbol and how many code relations point at it (incoming) or leave it
+/// (outgoing), as ranked by `top_symbols`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolCount {
+    pub name: String,
+    pub count: i64,
+}
+
+/// An indexed document as listed by `list_documents`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentEntry {
+    pub filename: String,
+    pub modified_at: DateTime<Utc>,
+    pub title: Option<String>,
+    /// Filename of the document this one duplicates, when it's stored as an
+    /// alias instead of being embedded itself.
+    pub alias_of: Option<String>,
+}
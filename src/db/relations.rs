@@ -1,6 +1,86 @@
 use super::{Db, models::*};
 use rusqlite::types::Value;
 use rusqlite::{OptionalExtension, Result, Row, params};
+use std::collections::BTreeSet;
+
+/// Optional scope for [`Db::export_relations_dot`].
+#[derive(Debug, Default)]
+pub struct GraphFilter<'a> {
+    /// Only include relations whose source symbol lives in this file.
+    pub file: Option<&'a str>,
+    /// Only include relations touching this symbol (as source or target).
+    pub symbol: Option<&'a str>,
+}
+
+/// Confidence recorded for a relation linked to the only matching symbol in
+/// its own file.
+const LINK_SAME_FILE_CONFIDENCE: f64 = 1.0;
+/// ... to the only matching symbol in the same language.
+const LINK_SAME_LANGUAGE_CONFIDENCE: f64 = 0.8;
+/// ... to the only matching symbol anywhere, in another language.
+const LINK_OTHER_LANGUAGE_CONFIDENCE: f64 = 0.5;
+
+/// Outcome of [`Db::resolve_relation_targets`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LinkResult {
+    /// Relations that now point at a concrete chunk.
+    pub linked: usize,
+    /// Relations left unresolved because several symbols match equally well.
+    pub ambiguous: usize,
+    /// Relations whose target name matches no indexed symbol.
+    pub unmatched: usize,
+}
+
+/// A symbol a relation target could resolve to (the first piece of a split
+/// symbol).
+struct LinkCandidate {
+    chunk_id: i64,
+    language: String,
+    filename: String,
+}
+
+/// Pick the target for a relation among `candidates`, with its confidence.
+///
+/// Prefers a unique match in the source's own file, then a unique match in
+/// the source's language, then a unique match overall (only when nothing in
+/// the source's language matches). Anything else is ambiguous.
+fn pick_link_target<'a>(
+    candidates: &'a [LinkCandidate],
+    language: Option<&str>,
+    source_file: &str,
+) -> Option<(&'a LinkCandidate, f64)> {
+    let same_language: Vec<&LinkCandidate> = candidates
+        .iter()
+        .filter(|c| Some(c.language.as_str()) == language)
+        .collect();
+    let same_file: Vec<&LinkCandidate> = same_language
+        .iter()
+        .copied()
+        .filter(|c| c.filename == source_file)
+        .collect();
+
+    match (same_file.as_slice(), same_language.as_slice(), candidates) {
+        ([only], _, _) => Some((only, LINK_SAME_FILE_CONFIDENCE)),
+        ([], [only], _) => Some((only, LINK_SAME_LANGUAGE_CONFIDENCE)),
+        ([], [], [only]) => Some((only, LINK_OTHER_LANGUAGE_CONFIDENCE)),
+        _ => None,
+    }
+}
+
+/// Edge color per relation type in DOT output.
+fn relation_color(relation_type: &str) -> &'static str {
+    match relation_type {
+        "calls" => "blue",
+        "imports" => "darkgreen",
+        "inherits" => "red",
+        _ => "black",
+    }
+}
+
+/// Quote a string as a DOT identifier.
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
 
 fn map_relation_with_source(row: &Row<'_>) -> Result<CodeRelation> {
     Ok(CodeRelation {
@@ -87,6 +167,85 @@ impl Db {
         tx.commit()
     }
 
+    /// Link relations without a `target_chunk_id` to the chunk defining
+    /// their `target_name`, filling in `target_chunk_id`, `target_file` and
+    /// `confidence`.
+    ///
+    /// Names are matched exactly; see `pick_link_target` for how several
+    /// candidates are narrowed down. Ambiguous relations stay unresolved.
+    pub fn resolve_relation_targets(&self) -> Result<LinkResult> {
+        let mut conn = self.get_conn()?;
+        let pending: Vec<(i64, String, Option<String>, String)> = {
+            let mut stmt = conn.prepare(
+                r#"
+                SELECT r.id, r.target_name, cm.language, d.filename
+                FROM code_relations r
+                JOIN chunks c ON r.source_chunk_id = c.id
+                JOIN documents d ON c.document_id = d.id
+                LEFT JOIN code_metadata cm ON cm.chunk_id = r.source_chunk_id
+                WHERE r.target_chunk_id IS NULL
+                "#,
+            )?;
+            stmt.query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<Result<_>>()?
+        };
+
+        let tx = conn.transaction()?;
+        let mut result = LinkResult::default();
+        {
+            let mut candidates_stmt = tx.prepare(
+                r#"
+                SELECT cm.chunk_id, cm.language, d.filename
+                FROM code_metadata cm
+                JOIN chunks c ON cm.chunk_id = c.id
+                JOIN documents d ON c.document_id = d.id
+                WHERE cm.symbol_name = ?1 AND cm.part = 0
+                "#,
+            )?;
+            let mut update_stmt = tx.prepare(
+                "UPDATE code_relations SET target_chunk_id = ?1, target_file = ?2, confidence = ?3 WHERE id = ?4",
+            )?;
+            let mut cache: std::collections::HashMap<String, Vec<LinkCandidate>> =
+                std::collections::HashMap::new();
+
+            for (id, target_name, language, source_file) in &pending {
+                if !cache.contains_key(target_name) {
+                    let found = candidates_stmt
+                        .query_map(params![target_name], |row| {
+                            Ok(LinkCandidate {
+                                chunk_id: row.get(0)?,
+                                language: row.get(1)?,
+                                filename: row.get(2)?,
+                            })
+                        })?
+                        .collect::<Result<Vec<_>>>()?;
+                    cache.insert(target_name.clone(), found);
+                }
+                let candidates = &cache[target_name];
+                if candidates.is_empty() {
+                    result.unmatched += 1;
+                    continue;
+                }
+                match pick_link_target(candidates, language.as_deref(), source_file) {
+                    Some((target, confidence)) => {
+                        update_stmt.execute(params![
+                            target.chunk_id,
+                            target.filename,
+                            confidence,
+                            id
+                        ])?;
+                        result.linked += 1;
+                    }
+                    None => result.ambiguous += 1,
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(result)
+    }
+
     /// Returns the chunk ID for a symbol in a given file
     pub fn get_chunk_id_by_symbol(&self, filename: &str, symbol_name: &str) -> Result<Option<i64>> {
         let conn = self.get_conn()?;
@@ -96,7 +255,7 @@ impl Db {
                 FROM code_metadata cm
                 JOIN chunks c ON cm.chunk_id = c.id
                 JOIN documents d ON c.document_id = d.id
-                WHERE d.filename = ? AND cm.symbol_name = ?
+                WHERE d.filename = ? AND cm.symbol_name = ? AND cm.part = 0
                 LIMIT 1
                 "#,
             params![filename, symbol_name],
@@ -178,22 +337,24 @@ impl Db {
             "#,
         );
 
+        // Names match case-insensitively; the stored names are returned as-is
+        let name = symbol_name.to_lowercase();
         let mut params: Vec<Value> = Vec::new();
 
         match direction {
             "incoming" => {
-                query.push_str(" WHERE cr.target_name = ?");
-                params.push(Value::Text(symbol_name.to_string()));
+                query.push_str(" WHERE LOWER(cr.target_name) = ?");
+                params.push(Value::Text(name));
             }
             "outgoing" => {
-                query.push_str(" WHERE cm.symbol_name = ?");
-                params.push(Value::Text(symbol_name.to_string()));
+                query.push_str(" WHERE cm.symbol_name_lower = ?");
+                params.push(Value::Text(name));
             }
             _ => {
                 // "both" or default
-                query.push_str(" WHERE (cr.target_name = ? OR cm.symbol_name = ?)");
-                params.push(Value::Text(symbol_name.to_string()));
-                params.push(Value::Text(symbol_name.to_string()));
+                query.push_str(" WHERE (LOWER(cr.target_name) = ? OR cm.symbol_name_lower = ?)");
+                params.push(Value::Text(name.clone()));
+                params.push(Value::Text(name));
             }
         }
 
@@ -216,6 +377,115 @@ impl Db {
         Ok(results)
     }
 
+    /// The `limit` most connected symbols: relation targets ranked by how
+    /// many relations point at them (`direction` "incoming") or source
+    /// symbols ranked by how many relations leave them ("outgoing"),
+    /// optionally of one `rel_type` only. Ties are ordered by name.
+    pub fn top_symbols(
+        &self,
+        direction: &str,
+        rel_type: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SymbolCount>> {
+        let conn = self.get_conn()?;
+        let mut query = match direction {
+            "outgoing" => String::from(
+                "SELECT cm.symbol_name AS name, COUNT(*) AS n FROM code_relations cr \
+                 JOIN code_metadata cm ON cr.source_chunk_id = cm.chunk_id \
+                 WHERE cm.symbol_name IS NOT NULL",
+            ),
+            _ => String::from(
+                "SELECT cr.target_name AS name, COUNT(*) AS n FROM code_relations cr WHERE 1 = 1",
+            ),
+        };
+        let mut params: Vec<Value> = Vec::new();
+        if let Some(rt) = rel_type {
+            query.push_str(" AND cr.relation_type = ?");
+            params.push(Value::Text(rt.to_string()));
+        }
+        query.push_str(" GROUP BY name ORDER BY n DESC, name LIMIT ?");
+        params.push(Value::Integer(limit as i64));
+
+        let mut stmt = conn.prepare_cached(&query)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+            Ok(SymbolCount {
+                name: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Exports the code relation graph as a GraphViz DOT string.
+    ///
+    /// Symbols become nodes and relations become edges labeled and colored by
+    /// relation type. Output is sorted so it is stable across calls.
+    pub fn export_relations_dot(&self, filter: Option<&GraphFilter<'_>>) -> Result<String> {
+        let conn = self.get_conn()?;
+        let mut query = String::from(
+            r#"
+            SELECT cm.symbol_name, cr.target_name, cr.relation_type
+            FROM code_relations cr
+            JOIN code_metadata cm ON cr.source_chunk_id = cm.chunk_id
+            JOIN chunks c ON cm.chunk_id = c.id
+            JOIN documents d ON c.document_id = d.id
+            WHERE cm.symbol_name IS NOT NULL
+            "#,
+        );
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(f) = filter {
+            if let Some(file) = f.file {
+                query.push_str(" AND d.filename = ?");
+                params.push(Value::Text(file.replace('\\', "/")));
+            }
+            if let Some(symbol) = f.symbol {
+                let name = symbol.to_lowercase();
+                query.push_str(" AND (cm.symbol_name_lower = ? OR LOWER(cr.target_name) = ?)");
+                params.push(Value::Text(name.clone()));
+                params.push(Value::Text(name));
+            }
+        }
+
+        let param_refs: Vec<&dyn rusqlite::ToSql> =
+            params.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
+
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(param_refs.as_slice(), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut nodes = BTreeSet::new();
+        let mut edges = BTreeSet::new();
+        for row in rows {
+            let (source, target, relation_type) = row?;
+            nodes.insert(source.clone());
+            nodes.insert(target.clone());
+            edges.insert((source, target, relation_type));
+        }
+
+        let mut dot = String::from("digraph relations {\n    node [shape=box];\n");
+        for node in &nodes {
+            dot.push_str(&format!("    {};\n", dot_quote(node)));
+        }
+        for (source, target, relation_type) in &edges {
+            dot.push_str(&format!(
+                "    {} -> {} [label={}, color={}];\n",
+                dot_quote(source),
+                dot_quote(target),
+                dot_quote(relation_type),
+                relation_color(relation_type)
+            ));
+        }
+        dot.push_str("}\n");
+
+        Ok(dot)
+    }
+
     /// Looks up target words for a source word (Word Mapping dictionary)
     pub fn lookup_word_mappings(
         &self,
@@ -262,6 +532,8 @@ mod tests {
             chunk: Chunk {
                 position: 0,
                 content: "fn main() { hello() }",
+                start_line: None,
+                end_line: None,
             },
             symbol_name: Some("main"),
             symbol_type: "function",
@@ -270,6 +542,8 @@ mod tests {
             end_line: Some(2),
             parent_symbol: None,
             signature: Some("fn main()"),
+            doc_comment: None,
+            part: 0,
         }];
         let embeddings = vec![vec![0.1f32; 1024]];
         db.insert_code_document("main.rs", Utc::now(), &code_chunks, &embeddings)
@@ -304,4 +578,348 @@ mod tests {
         let from_rels = db.get_relations_from(chunk_id, Some("calls")).unwrap();
         assert_eq!(from_rels.len(), 1);
     }
+
+    #[test]
+    fn test_symbol_lookup_is_case_insensitive() {
+        let db = Db::open_in_memory().unwrap();
+        let code_chunks = vec![CodeChunk {
+            chunk: Chunk {
+                position: 0,
+                content: "func MyFunc() { Helper() }",
+                start_line: None,
+                end_line: None,
+            },
+            symbol_name: Some("MyFunc"),
+            symbol_type: "function",
+            language: "go",
+            start_line: Some(1),
+            end_line: Some(1),
+            parent_symbol: None,
+            signature: None,
+            doc_comment: None,
+            part: 0,
+        }];
+        db.insert_code_document("main.go", Utc::now(), &code_chunks, &[vec![0.1f32; 1024]])
+            .unwrap();
+        let chunk_id = db
+            .get_chunk_id_by_symbol("main.go", "MyFunc")
+            .unwrap()
+            .unwrap();
+        db.insert_relations(&[CodeRelation {
+            id: 0,
+            source_chunk_id: chunk_id,
+            target_chunk_id: None,
+            relation_type: "calls".to_string(),
+            target_name: "Helper".to_string(),
+            target_file: None,
+            confidence: 1.0,
+            source_name: None,
+            source_file: None,
+        }])
+        .unwrap();
+
+        let outgoing = db
+            .find_symbol_relations("myfunc", "outgoing", None)
+            .unwrap();
+        assert_eq!(outgoing.len(), 1);
+        // The original spelling is kept for display
+        assert_eq!(outgoing[0].source_name.as_deref(), Some("MyFunc"));
+
+        let incoming = db
+            .find_symbol_relations("HELPER", "incoming", None)
+            .unwrap();
+        assert_eq!(incoming.len(), 1);
+
+        let defs = db.find_definitions("myfunc", None, true).unwrap();
+        assert_eq!(defs.len(), 1);
+        let found = db
+            .search_symbols_by_keywords(&["myfunc"], Some(&[0.1; 1024]), 5)
+            .unwrap();
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_relation_targets() {
+        let db = Db::open_in_memory().unwrap();
+        let insert = |file: &str, language: &'static str, symbols: &[&'static str]| {
+            let chunks: Vec<CodeChunk> = symbols
+                .iter()
+                .enumerate()
+                .map(|(i, name)| CodeChunk {
+                    chunk: Chunk {
+                        position: i,
+                        content: name,
+                        start_line: None,
+                        end_line: None,
+                    },
+                    symbol_name: Some(name),
+                    symbol_type: "function",
+                    language,
+                    start_line: None,
+                    end_line: None,
+                    parent_symbol: None,
+                    signature: None,
+                    doc_comment: None,
+                    part: 0,
+                })
+                .collect();
+            let embeddings = vec![vec![0.1f32; 1024]; chunks.len()];
+            db.insert_code_document(file, Utc::now(), &chunks, &embeddings)
+                .unwrap();
+        };
+        insert("main.rs", "rust", &["main", "helper"]);
+        insert("util.rs", "rust", &["helper", "shared", "format_name"]);
+        insert("other.rs", "rust", &["shared"]);
+        insert("lib.py", "python", &["parse", "format_name"]);
+
+        let main_id = db
+            .get_chunk_id_by_symbol("main.rs", "main")
+            .unwrap()
+            .unwrap();
+        let calls = |target: &str| CodeRelation {
+            id: 0,
+            source_chunk_id: main_id,
+            target_chunk_id: None,
+            relation_type: "calls".to_string(),
+            target_name: target.to_string(),
+            target_file: None,
+            confidence: 1.0,
+            source_name: None,
+            source_file: None,
+        };
+        db.insert_relations(&[
+            calls("helper"),
+            calls("format_name"),
+            calls("parse"),
+            calls("shared"),
+            calls("missing"),
+        ])
+        .unwrap();
+
+        let result = db.resolve_relation_targets().unwrap();
+        assert_eq!(
+            result,
+            LinkResult {
+                linked: 3,
+                ambiguous: 1,
+                unmatched: 1,
+            }
+        );
+
+        let rels = db.get_relations_from(main_id, None).unwrap();
+        let target = |name: &str| {
+            let r = rels.iter().find(|r| r.target_name == name).unwrap();
+            (
+                r.target_file.clone(),
+                r.target_chunk_id.is_some(),
+                r.confidence,
+            )
+        };
+        // Same file wins over another rust file
+        assert_eq!(target("helper"), (Some("main.rs".to_string()), true, 1.0));
+        // Same language wins over python
+        assert_eq!(
+            target("format_name"),
+            (Some("util.rs".to_string()), true, 0.8)
+        );
+        // Only match is in another language
+        assert_eq!(target("parse"), (Some("lib.py".to_string()), true, 0.5));
+        assert_eq!(target("shared"), (None, false, 1.0));
+        assert_eq!(target("missing"), (None, false, 1.0));
+
+        // Linked relations are not revisited
+        let again = db.resolve_relation_targets().unwrap();
+        assert_eq!(again.linked, 0);
+    }
+
+    #[test]
+    fn test_export_relations_dot() {
+        let db = Db::open_in_memory().unwrap();
+
+        let code_chunks = vec![
+            CodeChunk {
+                chunk: Chunk {
+                    position: 0,
+                    content: "fn main() { hello() }",
+                    start_line: None,
+                    end_line: None,
+                },
+                symbol_name: Some("main"),
+                symbol_type: "function",
+                language: "rust",
+                start_line: Some(1),
+                end_line: Some(1),
+                parent_symbol: None,
+                signature: Some("fn main()"),
+                doc_comment: None,
+                part: 0,
+            },
+            CodeChunk {
+                chunk: Chunk {
+                    position: 1,
+                    content: "fn hello() { world() }",
+                    start_line: None,
+                    end_line: None,
+                },
+                symbol_name: Some("hello"),
+                symbol_type: "function",
+                language: "rust",
+                start_line: Some(2),
+                end_line: Some(2),
+                parent_symbol: None,
+                signature: Some("fn hello()"),
+                doc_comment: None,
+                part: 0,
+            },
+        ];
+        let embeddings = vec![vec![0.1f32; 1024], vec![0.2f32; 1024]];
+        db.insert_code_document("main.rs", Utc::now(), &code_chunks, &embeddings)
+            .unwrap();
+
+        let main_id = db
+            .get_chunk_id_by_symbol("main.rs", "main")
+            .unwrap()
+            .unwrap();
+        let hello_id = db
+            .get_chunk_id_by_symbol("main.rs", "hello")
+            .unwrap()
+            .unwrap();
+
+        let rel = |source_chunk_id: i64, target_name: &str| CodeRelation {
+            id: 0,
+            source_chunk_id,
+            target_chunk_id: None,
+            relation_type: "calls".to_string(),
+            target_name: target_name.to_string(),
+            target_file: None,
+            confidence: 1.0,
+            source_name: None,
+            source_file: None,
+        };
+        db.insert_relations(&[rel(main_id, "hello"), rel(hello_id, "world")])
+            .unwrap();
+
+        let dot = db.export_relations_dot(None).unwrap();
+        assert!(dot.starts_with("digraph relations {"));
+        assert!(dot.contains("    \"main\";\n"));
+        assert!(dot.contains("    \"hello\";\n"));
+        assert!(dot.contains("    \"world\";\n"));
+        assert!(dot.contains("\"main\" -> \"hello\" [label=\"calls\", color=blue];"));
+        assert!(dot.contains("\"hello\" -> \"world\" [label=\"calls\", color=blue];"));
+
+        // Neighborhood of "world" only includes the hello -> world edge
+        let filter = GraphFilter {
+            file: None,
+            symbol: Some("world"),
+        };
+        let scoped = db.export_relations_dot(Some(&filter)).unwrap();
+        assert!(scoped.contains("\"hello\" -> \"world\""));
+        assert!(!scoped.contains("\"main\""));
+    }
+
+    #[test]
+    fn test_top_symbols() {
+        let db = Db::open_in_memory().unwrap();
+
+        let chunk = |position, content, symbol_name| CodeChunk {
+            chunk: Chunk {
+                position,
+                content,
+                start_line: None,
+                end_line: None,
+            },
+            symbol_name: Some(symbol_name),
+            symbol_type: "function",
+            language: "rust",
+            start_line: None,
+            end_line: None,
+            parent_symbol: None,
+            signature: None,
+            doc_comment: None,
+            part: 0,
+        };
+        let code_chunks = vec![
+            chunk(0, "fn a() { log(); parse(); }", "a"),
+            chunk(1, "fn b() { log(); }", "b"),
+            chunk(2, "fn c() { log(); parse(); }", "c"),
+        ];
+        let ids = db
+            .insert_code_document(
+                "lib.rs",
+                Utc::now(),
+                &code_chunks,
+                &vec![vec![0.1; 1024]; 3],
+            )
+            .unwrap();
+
+        let rel = |source_chunk_id: i64, relation_type: &str, target_name: &str| CodeRelation {
+            id: 0,
+            source_chunk_id,
+            target_chunk_id: None,
+            relation_type: relation_type.to_string(),
+            target_name: target_name.to_string(),
+            target_file: None,
+            confidence: 1.0,
+            source_name: None,
+            source_file: None,
+        };
+        db.insert_relations(&[
+            rel(ids[0], "calls", "log"),
+            rel(ids[0], "calls", "parse"),
+            rel(ids[1], "calls", "log"),
+            rel(ids[2], "calls", "log"),
+            rel(ids[2], "calls", "parse"),
+            rel(ids[2], "imports", "std::fmt"),
+        ])
+        .unwrap();
+
+        let count = |name: &str, count| SymbolCount {
+            name: name.to_string(),
+            count,
+        };
+        assert_eq!(
+            db.top_symbols("incoming", Some("calls"), 10).unwrap(),
+            [count("log", 3), count("parse", 2)]
+        );
+        assert_eq!(
+            db.top_symbols("incoming", None, 1).unwrap(),
+            [count("log", 3)]
+        );
+        assert_eq!(
+            db.top_symbols("outgoing", None, 10).unwrap(),
+            [count("c", 3), count("a", 2), count("b", 1)]
+        );
+        assert!(
+            db.top_symbols("incoming", Some("inherits"), 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_lookup_word_mappings() {
+        let db = Db::open_in_memory().unwrap();
+        let mapping = |target: &str, lang: &str, confidence: f64| {
+            (
+                "認証".to_string(),
+                target.to_string(),
+                lang.to_string(),
+                confidence,
+                "doc.md".to_string(),
+            )
+        };
+        db.insert_word_mappings(&[
+            mapping("auth", "ja", 0.5),
+            mapping("authentication", "ja", 0.9),
+            mapping("verification", "zh", 0.7),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            db.lookup_word_mappings("認証", Some("ja")).unwrap(),
+            ["authentication", "auth"]
+        );
+        assert_eq!(db.lookup_word_mappings("認証", None).unwrap().len(), 3);
+        assert!(db.lookup_word_mappings("設定", None).unwrap().is_empty());
+    }
 }
@@ -1,23 +1,389 @@
-use super::{Db, serialize_vector_f32};
+use super::{Db, models::CodeMetadata, serialize_vector_f32};
+use chrono::{DateTime, Utc};
 use rusqlite::Result;
 use rusqlite::types::Value;
+use std::collections::HashMap;
+
+/// Keywords shorter than this (in bytes) are ignored by keyword search.
+pub const MIN_KEYWORD_LEN: usize = 3;
+
+/// Half-life (in days) of the recency factor used by [`rerank`].
+pub const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// How many times `top_k` candidates to fetch before re-ranking, so boosted
+/// documents just outside the top-k can still be promoted.
+pub const RERANK_CANDIDATE_FACTOR: usize = 3;
+
+/// Rank offset `k` in reciprocal rank fusion (`1 / (k + rank)`); 60 is the
+/// value from the original RRF paper and damps the weight of top ranks.
+pub const RRF_K: f64 = 60.0;
+
+/// Score adjustments applied to vector search results by [`rerank`].
+#[derive(Debug, Default, Clone)]
+pub struct RankOptions {
+    /// Weight of the recency factor added to the score (0 disables it).
+    pub recency_boost: f64,
+    /// Score multiplier per frontmatter `docType`; other documents keep 1.0.
+    pub doc_type_boosts: HashMap<String, f64>,
+}
+
+impl RankOptions {
+    /// Whether ranking would differ from plain similarity order.
+    pub fn is_active(&self) -> bool {
+        self.recency_boost > 0.0 || !self.doc_type_boosts.is_empty()
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct SearchFilter<'a> {
     pub directory: Option<&'a str>,
     pub file_pattern: Option<&'a str>,
+    /// Only code chunks in this language. Excludes prose chunks, which have
+    /// no code metadata.
+    pub language: Option<&'a str>,
+    /// Only code chunks of this symbol type (`function`, `struct`, ...).
+    /// Excludes prose chunks, which have no code metadata.
+    pub symbol_type: Option<&'a str>,
+}
+
+impl SearchFilter<'_> {
+    /// Whether `document_name` lies under the `directory` filter (always true
+    /// when no directory is set). Mirrors the SQL filter for results that
+    /// don't come from [`Db::search_with_filter`].
+    #[must_use]
+    pub fn matches_directory(&self, document_name: &str) -> bool {
+        let Some(dir) = self.directory else {
+            return true;
+        };
+        let d = dir
+            .trim_end_matches('/')
+            .trim_end_matches(std::path::MAIN_SEPARATOR);
+        document_name
+            .strip_prefix(d)
+            .is_some_and(|rest| rest.starts_with('/') || rest.starts_with('\\'))
+    }
+
+    /// Whether a chunk with `metadata` passes the `language` and
+    /// `symbol_type` filters. Mirrors the SQL filter like
+    /// [`Self::matches_directory`].
+    #[must_use]
+    pub fn matches_code(&self, metadata: Option<&CodeMetadataResult>) -> bool {
+        if self.language.is_none() && self.symbol_type.is_none() {
+            return true;
+        }
+        metadata.is_some_and(|m| {
+            self.language.is_none_or(|l| m.language == l)
+                && self.symbol_type.is_none_or(|t| m.symbol_type == t)
+        })
+    }
 }
 
 #[derive(Debug)]
 pub struct SearchResult {
     pub document_name: String,
     pub chunk_content: String,
+    /// Cosine similarity mapped to [0, 1] (`1 - distance / 2`).
     pub similarity: f64,
+    /// Raw cosine distance from sqlite-vec's `vec_distance_cosine`, in [0, 2].
+    pub distance: f64,
     pub position: usize,
     pub chunk_id: i64,
+    pub document_id: i64,
+    /// `modified_at` recorded for the document when it was indexed.
+    pub modified_at: DateTime<Utc>,
+    /// Frontmatter `docType` of the document, if any.
+    pub doc_type: Option<String>,
+    /// Title of the document (frontmatter `title:` or first H1), if any.
+    pub title: Option<String>,
+    /// 1-based line where a prose chunk starts in its file, when tracked.
+    pub start_line: Option<usize>,
+    /// 1-based line where a prose chunk ends in its file, when tracked.
+    pub end_line: Option<usize>,
     pub metadata: Option<CodeMetadataResult>,
 }
 
+impl SearchResult {
+    /// Whether the document's file changed on disk since it was indexed.
+    ///
+    /// Compares at millisecond granularity, like differential sync. Files
+    /// that are missing or unreadable are not reported as stale.
+    pub fn is_stale(&self) -> bool {
+        std::fs::metadata(&self.document_name)
+            .and_then(|m| m.modified())
+            .map(|t| {
+                DateTime::<Utc>::from(t).timestamp_millis() > self.modified_at.timestamp_millis()
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// Re-order `results` by their score under `opts`.
+///
+/// Each result is ranked by
+/// `similarity * type_boost + recency_boost * 0.5^(age_days / RECENCY_HALF_LIFE_DAYS)`:
+///
+/// - `type_boost` is the multiplier configured for the document's `docType`
+///   (1.0 when it has none or it isn't listed).
+/// - `age_days` is the time since the document's `modified_at`, so a document
+///   modified just now gains the full `recency_boost`, one modified 30 days
+///   ago half of it.
+///
+/// The reported `similarity` is left unchanged. Ties keep their input order.
+pub fn rerank(results: &mut [SearchResult], opts: &RankOptions, now: DateTime<Utc>) {
+    if !opts.is_active() {
+        return;
+    }
+    let score = |r: &SearchResult| {
+        let type_boost = r
+            .doc_type
+            .as_ref()
+            .and_then(|t| opts.doc_type_boosts.get(t))
+            .copied()
+            .unwrap_or(1.0);
+        let age_days = (now - r.modified_at).num_seconds().max(0) as f64 / 86_400.0;
+        r.similarity * type_boost
+            + opts.recency_boost * 0.5f64.powf(age_days / RECENCY_HALF_LIFE_DAYS)
+    };
+    results.sort_by(|a, b| score(b).total_cmp(&score(a)));
+}
+
+/// Drop results whose similarity is zero or below.
+///
+/// These point away from the query (e.g. with the mock embedder or an
+/// out-of-domain query) and are noise rather than weak matches.
+pub fn drop_nonpositive_similarity(results: &mut Vec<SearchResult>) {
+    results.retain(|r| r.similarity > 0.0);
+}
+
+/// Drop results whose similarity is below `min_similarity`, returning the
+/// chunk IDs of the dropped ones.
+pub fn drop_below_similarity(results: &mut Vec<SearchResult>, min_similarity: f64) -> Vec<i64> {
+    let mut dropped = Vec::new();
+    results.retain(|r| {
+        let keep = r.similarity >= min_similarity;
+        if !keep {
+            dropped.push(r.chunk_id);
+        }
+        keep
+    });
+    dropped
+}
+
+/// Drop results whose content repeats a higher-ranked one, keeping the first.
+///
+/// Contents are compared by a hash of their lowercased text with runs of
+/// whitespace collapsed, so copies differing only in case or indentation
+/// count as duplicates.
+pub fn dedup_near_identical(results: &mut Vec<SearchResult>) {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    let mut seen = std::collections::HashSet::new();
+    results.retain(|r| {
+        let mut hasher = DefaultHasher::new();
+        for word in r.chunk_content.split_whitespace() {
+            word.to_lowercase().hash(&mut hasher);
+        }
+        seen.insert(hasher.finish())
+    });
+}
+
+/// Merge ranked result lists with reciprocal rank fusion.
+///
+/// Each chunk scores `sum(1 / (RRF_K + rank))` over the lists it appears in
+/// (rank starting at 1), so chunks found by several searches rise to the top.
+/// Duplicates are merged by `chunk_id`, keeping the first occurrence; ties keep
+/// their first-appearance order. At most `top_k` results are returned.
+pub fn reciprocal_rank_fusion(lists: Vec<Vec<SearchResult>>, top_k: usize) -> Vec<SearchResult> {
+    let mut fused: Vec<(f64, SearchResult)> = Vec::new();
+    let mut index: HashMap<i64, usize> = HashMap::new();
+    for list in lists {
+        for (rank, result) in list.into_iter().enumerate() {
+            let score = 1.0 / (RRF_K + (rank + 1) as f64);
+            match index.get(&result.chunk_id) {
+                Some(&i) => fused[i].0 += score,
+                None => {
+                    index.insert(result.chunk_id, fused.len());
+                    fused.push((score, result));
+                }
+            }
+        }
+    }
+    fused.sort_by(|a, b| b.0.total_cmp(&a.0));
+    fused.into_iter().take(top_k).map(|(_, r)| r).collect()
+}
+
+/// Build an FTS5 `MATCH` expression from free text.
+///
+/// The text is split into alphanumeric tokens, each quoted so FTS5 operators
+/// and punctuation in the query are taken literally, and joined with `OR` so
+/// BM25 ranks chunks by how many tokens they contain. Returns `None` when the
+/// text has no tokens.
+fn fts_match_query(text: &str) -> Option<String> {
+    let tokens: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| format!("\"{t}\""))
+        .collect();
+    (!tokens.is_empty()).then(|| tokens.join(" OR "))
+}
+
+/// Append the SQL conditions for `filter` to `where_clauses` and `params`.
+fn push_filter_clauses(
+    filter: Option<&SearchFilter<'_>>,
+    where_clauses: &mut Vec<String>,
+    params: &mut Vec<Value>,
+) {
+    let Some(f) = filter else {
+        return;
+    };
+    if let Some(dir) = f.directory {
+        let d = dir
+            .trim_end_matches('/')
+            .trim_end_matches(std::path::MAIN_SEPARATOR);
+        // Exact, case-sensitive prefix test: LIKE would treat `_`/`%` in the
+        // directory as wildcards and fold ASCII case
+        where_clauses.push(
+            "(substr(d.filename, 1, length(?)) = ? OR substr(d.filename, 1, length(?)) = ?)"
+                .to_string(),
+        );
+        for sep in ['/', '\\'] {
+            let prefix = format!("{d}{sep}");
+            params.push(Value::Text(prefix.clone()));
+            params.push(Value::Text(prefix));
+        }
+    }
+    if let Some(pat) = f.file_pattern {
+        let like_pat = glob_to_like(pat);
+        where_clauses.push(format!(
+            "(d.filename LIKE ?{e} OR d.filename LIKE ?{e})",
+            e = LIKE_ESCAPE
+        ));
+        params.push(Value::Text(format!("%/{}", like_pat)));
+        params.push(Value::Text(like_pat));
+    }
+    // Prose chunks have NULL code metadata and never compare equal
+    if let Some(language) = f.language {
+        where_clauses.push("cm.language = ?".to_string());
+        params.push(Value::Text(language.to_string()));
+    }
+    if let Some(symbol_type) = f.symbol_type {
+        where_clauses.push("cm.symbol_type = ?".to_string());
+        params.push(Value::Text(symbol_type.to_string()));
+    }
+}
+
+/// A matched document with the similarity of its best-matching chunk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentHit {
+    pub filename: String,
+    pub best_similarity: f64,
+}
+
+/// Collapse chunk-level results to one entry per document.
+///
+/// Documents keep the order of their first (highest-ranked) chunk; the
+/// similarity is the best one seen for that document.
+pub fn document_hits<'a>(results: impl IntoIterator<Item = &'a SearchResult>) -> Vec<DocumentHit> {
+    let mut hits: Vec<DocumentHit> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for r in results {
+        match index.get(r.document_name.as_str()) {
+            Some(&i) => {
+                let hit = &mut hits[i];
+                hit.best_similarity = hit.best_similarity.max(r.similarity);
+            }
+            None => {
+                index.insert(&r.document_name, hits.len());
+                hits.push(DocumentHit {
+                    filename: r.document_name.clone(),
+                    best_similarity: r.similarity,
+                });
+            }
+        }
+    }
+    hits
+}
+
+/// A code symbol with the symbols nested inside it.
+#[derive(Debug)]
+pub struct SymbolNode {
+    pub symbol: CodeMetadata,
+    pub children: Vec<SymbolNode>,
+}
+
+/// Nest `symbols` (one file's, ordered by line) by their `parent_symbol`.
+///
+/// A symbol goes under the first symbol carrying its parent's name, so
+/// methods of a Rust `impl` land under the struct itself. Symbols whose
+/// parent isn't in the list stay at the top level; order is preserved.
+pub fn symbol_outline(symbols: Vec<CodeMetadata>) -> Vec<SymbolNode> {
+    let mut owner: HashMap<&str, usize> = HashMap::new();
+    for (i, s) in symbols.iter().enumerate() {
+        if let Some(name) = &s.symbol_name {
+            owner.entry(name).or_insert(i);
+        }
+    }
+    let mut parent: Vec<Option<usize>> = symbols
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let p = *owner.get(s.parent_symbol.as_deref()?)?;
+            (p != i).then_some(p)
+        })
+        .collect();
+    // Symbols caught in a parent cycle (e.g. same-named nested items) become roots
+    for i in 0..parent.len() {
+        let mut at = parent[i];
+        for _ in 0..parent.len() {
+            match at {
+                Some(p) if p == i => {
+                    parent[i] = None;
+                    break;
+                }
+                Some(p) => at = parent[p],
+                None => break,
+            }
+        }
+    }
+
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); symbols.len()];
+    let mut roots = Vec::new();
+    for (i, p) in parent.iter().enumerate() {
+        match p {
+            Some(p) => children[*p].push(i),
+            None => roots.push(i),
+        }
+    }
+
+    fn build(i: usize, slots: &mut [Option<CodeMetadata>], children: &[Vec<usize>]) -> SymbolNode {
+        SymbolNode {
+            symbol: slots[i].take().expect("each symbol has one parent"),
+            children: children[i]
+                .iter()
+                .map(|&c| build(c, slots, children))
+                .collect(),
+        }
+    }
+    let mut slots: Vec<Option<CodeMetadata>> = symbols.into_iter().map(Some).collect();
+    roots
+        .into_iter()
+        .map(|i| build(i, &mut slots, &children))
+        .collect()
+}
+
+/// A neighbouring chunk returned as context around a search hit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextChunk {
+    pub position: usize,
+    pub content: String,
+}
+
+/// Chunks surrounding a search hit within the same document.
+#[derive(Debug, Default)]
+pub struct AdjacentChunks {
+    pub before: Vec<ContextChunk>,
+    pub after: Vec<ContextChunk>,
+}
+
 #[derive(Debug)]
 pub struct CodeMetadataResult {
     pub symbol_name: Option<String>,
@@ -27,9 +393,10 @@ pub struct CodeMetadataResult {
     pub end_line: Option<usize>,
     pub parent_symbol: Option<String>,
     pub signature: Option<String>,
+    pub doc_comment: Option<String>,
 }
 
-fn glob_to_like(pattern: &str) -> String {
+pub(super) fn glob_to_like(pattern: &str) -> String {
     // Escape the LIKE escape character itself first, then existing SQL wildcards
     let mut result = pattern.replace('\\', "\\\\");
     result = result.replace('%', "\\%");
@@ -41,7 +408,53 @@ fn glob_to_like(pattern: &str) -> String {
 }
 
 /// The ESCAPE clause to append to all LIKE expressions that use glob_to_like.
-const LIKE_ESCAPE: &str = " ESCAPE '\\'";
+pub(super) const LIKE_ESCAPE: &str = " ESCAPE '\\'";
+
+/// Selects whole code symbols with the columns expected by
+/// [`map_search_row`]. Continuation pieces of a split symbol are skipped, and
+/// the first piece reports the end line of the last one (piece `n` is stored
+/// `n` positions after the first).
+const SYMBOL_QUERY: &str = r#"
+    SELECT
+        d.filename,
+        c.content,
+        c.position,
+        c.id as chunk_id,
+        0.0 as distance,
+        cm.symbol_name,
+        cm.symbol_type,
+        cm.language,
+        cm.start_line,
+        COALESCE((
+            SELECT MAX(pm.end_line) FROM code_metadata pm
+            JOIN chunks pc ON pm.chunk_id = pc.id
+            WHERE pc.document_id = c.document_id AND pm.part > 0
+              AND pc.position = c.position + pm.part
+        ), cm.end_line),
+        cm.parent_symbol,
+        cm.signature,
+        c.document_id,
+        d.modified_at,
+        d.doc_type,
+        c.start_line,
+        c.end_line,
+        cm.doc_comment,
+        d.title
+    FROM code_metadata cm
+    JOIN chunks c ON cm.chunk_id = c.id
+    JOIN documents d ON c.document_id = d.id
+    WHERE cm.part = 0
+"#;
+
+/// SQL for the cosine distance of `v.embedding` to the query vector, bound
+/// as the next parameter; the maximum distance (similarity 0) without one.
+fn distance_expr(query_vector: Option<&[f32]>) -> &'static str {
+    if query_vector.is_some() {
+        "vec_distance_cosine(v.embedding, ?)"
+    } else {
+        "2.0"
+    }
+}
 
 fn map_search_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SearchResult> {
     let distance: f64 = row.get(4)?;
@@ -59,6 +472,7 @@ fn map_search_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SearchResult> {
             end_line: row.get::<_, Option<i64>>(9)?.map(|v| v as usize),
             parent_symbol: row.get(10)?,
             signature: row.get(11)?,
+            doc_comment: row.get(17)?,
         })
     } else {
         None
@@ -69,7 +483,14 @@ fn map_search_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SearchResult> {
         chunk_content: row.get(1)?,
         position: row.get::<_, i64>(2)? as usize,
         chunk_id: row.get(3)?,
+        document_id: row.get(12)?,
+        modified_at: row.get(13)?,
+        doc_type: row.get(14)?,
+        title: row.get(18)?,
+        start_line: row.get::<_, Option<i64>>(15)?.map(|v| v as usize),
+        end_line: row.get::<_, Option<i64>>(16)?.map(|v| v as usize),
         similarity,
+        distance,
         metadata,
     })
 }
@@ -80,6 +501,14 @@ impl Db {
         self.search_with_filter(query_vector, top_k, None)
     }
 
+    /// The `top_k` documents closest to `vector`, ranked by their best chunk.
+    pub fn nearest_documents(&self, vector: &[f32], top_k: usize) -> Result<Vec<DocumentHit>> {
+        let results = self.search(vector, top_k * RERANK_CANDIDATE_FACTOR)?;
+        let mut hits = document_hits(&results);
+        hits.truncate(top_k);
+        Ok(hits)
+    }
+
     /// Perform vector similarity search with optional filtering
     pub fn search_with_filter(
         &self,
@@ -102,7 +531,14 @@ impl Db {
                 cm.start_line,
                 cm.end_line,
                 cm.parent_symbol,
-                cm.signature
+                cm.signature,
+                c.document_id,
+                d.modified_at,
+                d.doc_type,
+                c.start_line,
+                c.end_line,
+                cm.doc_comment,
+                d.title
             FROM vec_chunks v
             JOIN chunks c ON v.rowid = c.id
             JOIN documents d ON c.document_id = d.id
@@ -112,26 +548,7 @@ impl Db {
 
         let mut where_clauses = Vec::new();
         let mut params: Vec<Value> = vec![Value::Blob(serialize_vector_f32(query_vector))];
-
-        if let Some(f) = filter {
-            if let Some(dir) = f.directory {
-                let d = dir
-                    .trim_end_matches('/')
-                    .trim_end_matches(std::path::MAIN_SEPARATOR);
-                where_clauses.push("(d.filename LIKE ? OR d.filename LIKE ?)".to_string());
-                params.push(Value::Text(format!("{}/%", d)));
-                params.push(Value::Text(format!("{}\\%", d)));
-            }
-            if let Some(pat) = f.file_pattern {
-                let like_pat = glob_to_like(pat);
-                where_clauses.push(format!(
-                    "(d.filename LIKE ?{e} OR d.filename LIKE ?{e})",
-                    e = LIKE_ESCAPE
-                ));
-                params.push(Value::Text(format!("%/{}", like_pat)));
-                params.push(Value::Text(like_pat));
-            }
-        }
+        push_filter_clauses(filter, &mut where_clauses, &mut params);
 
         if !where_clauses.is_empty() {
             query.push_str(" WHERE ");
@@ -155,10 +572,126 @@ impl Db {
         Ok(results)
     }
 
-    /// Search code_metadata for symbols matching keywords
+    /// Full-text search over chunk content, ranked by BM25.
+    ///
+    /// Catches exact tokens such as error codes or identifiers that vector
+    /// search blurs. Any query token may match (see `fts_match_query`).
+    /// The reported `similarity` is still the cosine similarity to
+    /// `query_vector`, so results are comparable with [`Db::search_with_filter`].
+    /// Without a query vector it is 0 (distance 2).
+    pub fn search_keyword(
+        &self,
+        query_text: &str,
+        query_vector: Option<&[f32]>,
+        top_k: usize,
+        filter: Option<&SearchFilter<'_>>,
+    ) -> Result<Vec<SearchResult>> {
+        let Some(match_query) = fts_match_query(query_text) else {
+            return Ok(Vec::new());
+        };
+        let conn = self.get_conn()?;
+        let mut params: Vec<Value> = query_vector
+            .map(|v| Value::Blob(serialize_vector_f32(v)))
+            .into_iter()
+            .collect();
+        let mut query = format!(
+            r#"
+            SELECT
+                d.filename,
+                c.content,
+                c.position,
+                c.id as chunk_id,
+                {} as distance,
+                cm.symbol_name,
+                cm.symbol_type,
+                cm.language,
+                cm.start_line,
+                cm.end_line,
+                cm.parent_symbol,
+                cm.signature,
+                c.document_id,
+                d.modified_at,
+                d.doc_type,
+                c.start_line,
+                c.end_line,
+                cm.doc_comment,
+                d.title
+            FROM chunks_fts f
+            JOIN chunks c ON f.rowid = c.id
+            JOIN vec_chunks v ON v.rowid = c.id
+            JOIN documents d ON c.document_id = d.id
+            LEFT JOIN code_metadata cm ON c.id = cm.chunk_id
+            WHERE chunks_fts MATCH ?
+            "#,
+            distance_expr(query_vector)
+        );
+
+        let mut where_clauses = Vec::new();
+        params.push(Value::Text(match_query));
+        push_filter_clauses(filter, &mut where_clauses, &mut params);
+        for clause in &where_clauses {
+            query.push_str(" AND ");
+            query.push_str(clause);
+        }
+
+        query.push_str(" ORDER BY bm25(chunks_fts) LIMIT ?");
+        params.push(Value::Integer(top_k as i64));
+
+        let mut stmt = conn.prepare_cached(&query)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), map_search_row)?;
+        rows.collect()
+    }
+
+    /// Fetch up to `window` chunks before and after `position` in the same document.
+    ///
+    /// The window clamps at the document boundaries; the chunk at `position`
+    /// itself is not included.
+    pub fn get_adjacent_chunks(
+        &self,
+        document_id: i64,
+        position: usize,
+        window: usize,
+    ) -> Result<AdjacentChunks> {
+        let mut adjacent = AdjacentChunks::default();
+        if window == 0 {
+            return Ok(adjacent);
+        }
+
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT position, content FROM chunks
+             WHERE document_id = ?1 AND position BETWEEN ?2 AND ?3 AND position != ?4
+             ORDER BY position",
+        )?;
+        let low = position.saturating_sub(window) as i64;
+        let high = position.saturating_add(window) as i64;
+        let rows = stmt.query_map(
+            rusqlite::params![document_id, low, high, position as i64],
+            |row| {
+                Ok(ContextChunk {
+                    position: row.get::<_, i64>(0)? as usize,
+                    content: row.get(1)?,
+                })
+            },
+        )?;
+
+        for row in rows {
+            let chunk = row?;
+            if chunk.position < position {
+                adjacent.before.push(chunk);
+            } else {
+                adjacent.after.push(chunk);
+            }
+        }
+        Ok(adjacent)
+    }
+
+    /// Search code_metadata for symbols matching keywords, scored by the
+    /// cosine similarity of their chunk to `query_vector` (0 without one).
     pub fn search_symbols_by_keywords(
         &self,
         keywords: &[&str],
+        query_vector: Option<&[f32]>,
         limit: usize,
     ) -> Result<Vec<SearchResult>> {
         let conn = self.get_conn()?;
@@ -166,36 +699,47 @@ impl Db {
             return Ok(Vec::new());
         }
 
-        let mut query = String::from(
+        let mut query = format!(
             r#"
             SELECT
                 d.filename,
                 c.content,
                 c.position,
                 c.id as chunk_id,
-                0.0 as distance,
+                {} as distance,
                 cm.symbol_name,
                 cm.symbol_type,
                 cm.language,
                 cm.start_line,
                 cm.end_line,
                 cm.parent_symbol,
-                cm.signature
+                cm.signature,
+                c.document_id,
+                d.modified_at,
+                d.doc_type,
+                c.start_line,
+                c.end_line,
+                cm.doc_comment,
+                d.title
             FROM code_metadata cm
             JOIN chunks c ON cm.chunk_id = c.id
+            JOIN vec_chunks v ON v.rowid = c.id
             JOIN documents d ON c.document_id = d.id
-            WHERE 
-            "#,
+            WHERE "#,
+            distance_expr(query_vector)
         );
 
         let mut conditions = Vec::new();
-        let mut params: Vec<Value> = Vec::new();
+        let mut params: Vec<Value> = query_vector
+            .map(|v| Value::Blob(serialize_vector_f32(v)))
+            .into_iter()
+            .collect();
 
         for kw in keywords {
-            if kw.len() < 3 {
+            if kw.len() < MIN_KEYWORD_LEN {
                 continue;
             }
-            conditions.push("LOWER(cm.symbol_name) LIKE ?".to_string());
+            conditions.push("cm.symbol_name_lower LIKE ?".to_string());
             params.push(Value::Text(format!("%{}%", kw.to_lowercase())));
         }
 
@@ -219,23 +763,111 @@ impl Db {
 
         Ok(results)
     }
+
+    /// Find the definitions of `symbol`, ordered by file and line.
+    ///
+    /// Matches `symbol_name` case-insensitively: exactly when `exact`, as a
+    /// substring otherwise (where `*` and `?` act as glob wildcards). All
+    /// matches are returned, e.g. one per overloaded file.
+    pub fn find_definitions(
+        &self,
+        symbol: &str,
+        language: Option<&str>,
+        exact: bool,
+    ) -> Result<Vec<SearchResult>> {
+        let conn = self.get_conn()?;
+        let mut query = SYMBOL_QUERY.to_string();
+        let mut params: Vec<Value> = Vec::new();
+
+        if exact {
+            query.push_str(" AND cm.symbol_name_lower = ?");
+            params.push(Value::Text(symbol.to_lowercase()));
+        } else {
+            query.push_str(&format!(" AND cm.symbol_name_lower LIKE ?{LIKE_ESCAPE}"));
+            params.push(Value::Text(format!(
+                "%{}%",
+                glob_to_like(&symbol.to_lowercase())
+            )));
+        }
+        if let Some(lang) = language {
+            query.push_str(" AND LOWER(cm.language) = ?");
+            params.push(Value::Text(lang.to_lowercase()));
+        }
+        query.push_str(" ORDER BY d.filename, cm.start_line");
+
+        let mut stmt = conn.prepare_cached(&query)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), map_search_row)?;
+        rows.collect()
+    }
+
+    /// All code symbols indexed from `filename`, ordered by line. A symbol
+    /// split into several chunks is listed once, as in [`Db::find_definitions`].
+    pub fn list_symbols_for_file(&self, filename: &str) -> Result<Vec<CodeMetadata>> {
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare_cached(
+            r#"
+            SELECT cm.id, cm.chunk_id, cm.symbol_name, cm.symbol_type, cm.language,
+                   cm.start_line,
+                   COALESCE((
+                       SELECT MAX(pm.end_line) FROM code_metadata pm
+                       JOIN chunks pc ON pm.chunk_id = pc.id
+                       WHERE pc.document_id = c.document_id AND pm.part > 0
+                         AND pc.position = c.position + pm.part
+                   ), cm.end_line),
+                   cm.parent_symbol, cm.signature
+            FROM code_metadata cm
+            JOIN chunks c ON cm.chunk_id = c.id
+            JOIN documents d ON c.document_id = d.id
+            WHERE d.filename = ? AND cm.part = 0
+            ORDER BY cm.start_line, c.position
+            "#,
+        )?;
+        let rows = stmt.query_map(rusqlite::params![filename], |row| {
+            Ok(CodeMetadata {
+                id: row.get(0)?,
+                chunk_id: row.get(1)?,
+                symbol_name: row.get(2)?,
+                symbol_type: row.get(3)?,
+                language: row.get(4)?,
+                start_line: row.get::<_, Option<i64>>(5)?.map(|v| v as usize),
+                end_line: row.get::<_, Option<i64>>(6)?.map(|v| v as usize),
+                parent_symbol: row.get(7)?,
+                signature: row.get(8)?,
+            })
+        })?;
+        rows.collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::db::models::{Chunk, CodeChunk};
+    use crate::db::models::{Chunk, CodeChunk, DocChunk};
     use chrono::Utc;
 
+    /// Index `content` as the only chunk of document `name`, stored with `embedding`.
+    fn insert_single_chunk(
+        db: &Db,
+        name: &str,
+        modified_at: DateTime<Utc>,
+        content: &str,
+        embedding: &[f32],
+    ) {
+        let chunk = Chunk {
+            position: 0,
+            content,
+            start_line: None,
+            end_line: None,
+        };
+        db.insert_document(name, modified_at, None, &[chunk], &[embedding.to_vec()])
+            .unwrap();
+    }
+
     #[test]
     fn test_search() {
         let db = Db::open_in_memory().unwrap();
 
         // Let's insert some mock documents
-        let chunks = vec![Chunk {
-            position: 0,
-            content: "Rust programming language",
-        }];
         let padded_embedding = {
             let mut v = vec![0.0f32; 1024];
             v[0] = 0.1;
@@ -243,18 +875,20 @@ mod tests {
             v[2] = 0.3;
             v
         };
-        db.insert_document(
+        insert_single_chunk(
+            &db,
             "rust.md",
             Utc::now(),
-            &chunks,
-            std::slice::from_ref(&padded_embedding),
-        )
-        .unwrap();
+            "Rust programming language",
+            &padded_embedding,
+        );
 
         let code_chunks = vec![CodeChunk {
             chunk: Chunk {
                 position: 0,
                 content: "fn hello() {}",
+                start_line: None,
+                end_line: None,
             },
             symbol_name: Some("hello"),
             symbol_type: "function",
@@ -263,6 +897,8 @@ mod tests {
             end_line: Some(1),
             parent_symbol: None,
             signature: Some("fn hello()"),
+            doc_comment: None,
+            part: 0,
         }];
         let code_padded_embedding = {
             let mut v = vec![0.0f32; 1024];
@@ -297,61 +933,597 @@ mod tests {
     }
 
     #[test]
-    fn test_search_with_filter() {
+    fn test_recency_boost_prefers_newer_document() {
         let db = Db::open_in_memory().unwrap();
+        let embedding = vec![0.1f32; 1024];
+        let now = Utc::now();
+        let old = now - chrono::Duration::days(90);
+        insert_single_chunk(&db, "old.md", old, "release notes", &embedding);
+        insert_single_chunk(&db, "new.md", now, "release notes", &embedding);
 
-        let padded_embedding = vec![0.1f32; 1024];
+        let mut results = db.search(&embedding, 2).unwrap();
+        assert_eq!(results[0].similarity, results[1].similarity);
 
-        let chunks = vec![Chunk {
-            position: 0,
-            content: "Doc A",
-        }];
-        db.insert_document(
-            "docs/a.md",
+        let opts = RankOptions {
+            recency_boost: 0.1,
+            ..Default::default()
+        };
+        rerank(&mut results, &opts, now);
+        assert_eq!(results[0].document_name, "new.md");
+        assert_eq!(results[1].document_name, "old.md");
+
+        // Reversed input order gives the same ranking
+        results.reverse();
+        rerank(&mut results, &opts, now);
+        assert_eq!(results[0].document_name, "new.md");
+    }
+
+    #[test]
+    fn test_doc_type_boost_prefers_boosted_type() {
+        let db = Db::open_in_memory().unwrap();
+        let embedding = vec![0.1f32; 1024];
+        for (name, doc_type) in [("api.md", "api"), ("note.md", "note")] {
+            insert_single_chunk(&db, name, Utc::now(), "authentication", &embedding);
+            db.set_document_doc_type(name, Some(doc_type)).unwrap();
+        }
+
+        let opts = RankOptions {
+            doc_type_boosts: HashMap::from([("api".to_string(), 1.5)]),
+            ..Default::default()
+        };
+        for first in ["api.md", "note.md"] {
+            // Start with either document on top; equal similarity must not matter
+            let mut results = db.search(&embedding, 2).unwrap();
+            assert_eq!(results[0].similarity, results[1].similarity);
+            if results[0].document_name != first {
+                results.reverse();
+            }
+            rerank(&mut results, &opts, Utc::now());
+            assert_eq!(results[0].document_name, "api.md");
+            assert_eq!(results[0].doc_type.as_deref(), Some("api"));
+        }
+    }
+
+    #[test]
+    fn test_drop_nonpositive_similarity() {
+        let db = Db::open_in_memory().unwrap();
+        let mut toward = vec![0.0f32; 1024];
+        toward[0] = 1.0;
+        let away: Vec<f32> = toward.iter().map(|x| -x).collect();
+        for (name, embedding) in [("toward.md", &toward), ("away.md", &away)] {
+            insert_single_chunk(&db, name, Utc::now(), name, embedding);
+        }
+
+        // Toggle off: the opposite document is returned with similarity 0
+        let unfiltered = db.search(&toward, 5).unwrap();
+        assert_eq!(unfiltered.len(), 2);
+        assert!(unfiltered[1].similarity <= 0.0);
+
+        // Default on: it is omitted
+        assert!(crate::config::Config::default().suppress_nonpositive_similarity);
+        let mut results = db.search(&toward, 5).unwrap();
+        drop_nonpositive_similarity(&mut results);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].document_name, "toward.md");
+    }
+
+    #[test]
+    fn test_search_reports_raw_distance() {
+        let db = Db::open_in_memory().unwrap();
+        let mut stored = vec![0.0f32; 1024];
+        stored[0] = 1.0;
+        stored[1] = 1.0;
+        insert_single_chunk(&db, "doc.md", Utc::now(), "Doc", &stored);
+        let mut query = vec![0.0f32; 1024];
+        query[0] = 1.0;
+
+        let hit = &db.search(&query, 1).unwrap()[0];
+        let expected: f64 = db
+            .get_conn()
+            .unwrap()
+            .query_row(
+                "SELECT vec_distance_cosine(?, ?)",
+                [serialize_vector_f32(&query), serialize_vector_f32(&stored)],
+                |row| row.get(0),
+            )
+            .unwrap();
+        // 45 degrees apart: distance 1 - cos(45°)
+        assert!((hit.distance - expected).abs() < 1e-6);
+        assert!((hit.distance - (1.0 - std::f64::consts::FRAC_1_SQRT_2)).abs() < 1e-4);
+        assert!((hit.similarity - (1.0 - hit.distance / 2.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_dedup_near_identical() {
+        let db = Db::open_in_memory().unwrap();
+        let mut query = vec![0.0f32; 1024];
+        query[0] = 1.0;
+        let mut other = query.clone();
+        other[1] = 0.5;
+        let docs = [
+            ("a.rs", "fn copied() {\n    run();\n}", &query),
+            ("b.rs", "fn copied() {\n\trun();\n}", &other),
+            ("c.rs", "fn distinct() {}", &other),
+        ];
+        for (name, content, embedding) in docs {
+            insert_single_chunk(&db, name, Utc::now(), content, embedding);
+        }
+
+        let mut results = db.search(&query, 5).unwrap();
+        assert_eq!(results.len(), 3);
+        dedup_near_identical(&mut results);
+        let names: Vec<&str> = results.iter().map(|r| r.document_name.as_str()).collect();
+        assert_eq!(names, ["a.rs", "c.rs"]);
+    }
+
+    #[test]
+    fn test_search_keyword_matches_exact_tokens() {
+        let db = Db::open_in_memory().unwrap();
+        let embedding = vec![0.1f32; 1024];
+        let docs = [
+            ("docs/errors.md", "error[E0308]: mismatched types"),
+            ("docs/types.md", "Type errors are reported by the compiler."),
+            ("src/notes.md", "Unrelated notes about deployment."),
+        ];
+        for (name, content) in docs {
+            insert_single_chunk(&db, name, Utc::now(), content, &embedding);
+        }
+
+        let results = db
+            .search_keyword("what is E0308?", Some(&embedding), 5, None)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].document_name, "docs/errors.md");
+        assert!(results[0].similarity > 0.99);
+
+        // Operators and quotes in the query are taken literally
+        assert!(
+            db.search_keyword("\"NOT\" OR (", Some(&embedding), 5, None)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            db.search_keyword("?!", Some(&embedding), 5, None)
+                .unwrap()
+                .is_empty()
+        );
+
+        let filter = SearchFilter {
+            directory: Some("src"),
+            file_pattern: None,
+            language: None,
+            symbol_type: None,
+        };
+        assert!(
+            db.search_keyword("E0308", Some(&embedding), 5, Some(&filter))
+                .unwrap()
+                .is_empty()
+        );
+
+        // The full-text index follows reindexing and deletion
+        insert_single_chunk(
+            &db,
+            "docs/errors.md",
             Utc::now(),
-            &chunks,
-            std::slice::from_ref(&padded_embedding),
+            "error[E0599]: no method found",
+            &embedding,
+        );
+        assert!(
+            db.search_keyword("E0308", Some(&embedding), 5, None)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            db.search_keyword("E0599", Some(&embedding), 5, None)
+                .unwrap()
+                .len(),
+            1
+        );
+        db.delete_document("docs/errors.md").unwrap();
+        assert!(
+            db.search_keyword("E0599", Some(&embedding), 5, None)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let db = Db::open_in_memory().unwrap();
+        let embedding = vec![0.1f32; 1024];
+        for name in ["a.md", "b.md", "c.md"] {
+            insert_single_chunk(&db, name, Utc::now(), name, &embedding);
+        }
+        let ordered = |names: &[&str]| {
+            let mut results = db.search(&embedding, 3).unwrap();
+            results.retain(|r| names.contains(&r.document_name.as_str()));
+            results.sort_by_key(|r| names.iter().position(|n| *n == r.document_name));
+            results
+        };
+
+        let vector = ordered(&["a.md", "b.md", "c.md"]);
+        let keyword = ordered(&["c.md", "a.md"]);
+        let fused = reciprocal_rank_fusion(vec![vector, keyword], 10);
+        let names: Vec<&str> = fused.iter().map(|r| r.document_name.as_str()).collect();
+        // a: 1/61 + 1/62, c: 1/63 + 1/61, b: 1/62
+        assert_eq!(names, ["a.md", "c.md", "b.md"]);
+
+        let fused = reciprocal_rank_fusion(vec![ordered(&["b.md", "a.md"])], 1);
+        assert_eq!(fused.len(), 1);
+        assert_eq!(fused[0].document_name, "b.md");
+    }
+
+    #[test]
+    fn test_document_hits_dedupes_per_document() {
+        let db = Db::open_in_memory().unwrap();
+        let embedding = vec![0.1f32; 1024];
+        let chunks = [
+            Chunk {
+                position: 0,
+                content: "first",
+                start_line: None,
+                end_line: None,
+            },
+            Chunk {
+                position: 1,
+                content: "second",
+                start_line: None,
+                end_line: None,
+            },
+        ];
+        let embeddings = [embedding.clone(), embedding.clone()];
+        for name in ["a.md", "b.md"] {
+            db.insert_document(name, Utc::now(), None, &chunks, &embeddings)
+                .unwrap();
+        }
+
+        let mut results = db.search(&embedding, 4).unwrap();
+        assert_eq!(results.len(), 4);
+        results[1].similarity = 0.99;
+        let hits = document_hits(&results);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].filename, results[0].document_name);
+        assert_ne!(hits[0].filename, hits[1].filename);
+        let best = |name: &str| {
+            results
+                .iter()
+                .filter(|r| r.document_name == name)
+                .map(|r| r.similarity)
+                .fold(f64::MIN, f64::max)
+        };
+        for hit in &hits {
+            assert_eq!(hit.best_similarity, best(&hit.filename));
+        }
+    }
+
+    #[test]
+    fn test_find_definitions() {
+        let db = Db::open_in_memory().unwrap();
+        let embedding = vec![0.1f32; 1024];
+        let symbol = |name, symbol_type, language, line| CodeChunk {
+            chunk: Chunk {
+                position: line,
+                content: "body",
+                start_line: None,
+                end_line: None,
+            },
+            symbol_name: Some(name),
+            symbol_type,
+            language,
+            start_line: Some(line),
+            end_line: Some(line + 2),
+            parent_symbol: None,
+            signature: None,
+            doc_comment: None,
+            part: 0,
+        };
+        let insert = |file: &str, chunks: &[CodeChunk]| {
+            db.insert_code_document(
+                file,
+                Utc::now(),
+                chunks,
+                &vec![embedding.clone(); chunks.len()],
+            )
+            .unwrap();
+        };
+        // The doc comment is a prose chunk with no symbol of its own
+        let doc = DocChunk {
+            owner: 0,
+            chunk: Chunk {
+                position: 1,
+                content: "Parses the config.",
+                start_line: Some(10),
+                end_line: Some(12),
+            },
+        };
+        db.insert_code_document_with_docs(
+            "a.rs",
+            Utc::now(),
+            None,
+            &[symbol("parse_config", "function", "rust", 10)],
+            &[doc],
+            &vec![embedding.clone(); 2],
         )
         .unwrap();
+        insert("b.py", &[symbol("Parse_Config", "function", "python", 1)]);
+        insert(
+            "c.rs",
+            &[symbol("parse_config_file", "function", "rust", 5)],
+        );
+        insert("d.rs", &[symbol("parseXconfig", "function", "rust", 1)]);
 
-        let chunks_b = vec![Chunk {
-            position: 0,
-            content: "Doc B",
-        }];
+        let exact = db.find_definitions("PARSE_CONFIG", None, true).unwrap();
+        let files: Vec<&str> = exact.iter().map(|r| r.document_name.as_str()).collect();
+        assert_eq!(files, ["a.rs", "b.py"]);
+        assert_eq!(exact[0].metadata.as_ref().unwrap().start_line, Some(10));
+
+        let rust_only = db
+            .find_definitions("parse_config", Some("Rust"), true)
+            .unwrap();
+        assert_eq!(rust_only.len(), 1);
+
+        // Substring match; `_` is literal, so parseXconfig doesn't match
+        let fuzzy = db.find_definitions("parse_config", None, false).unwrap();
+        let files: Vec<&str> = fuzzy.iter().map(|r| r.document_name.as_str()).collect();
+        assert_eq!(files, ["a.rs", "b.py", "c.rs"]);
+
+        assert!(
+            db.find_definitions("missing", None, true)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_list_symbols_for_file() {
+        let db = Db::open_in_memory().unwrap();
+        let symbol = |name, symbol_type, line, parent| CodeChunk {
+            chunk: Chunk {
+                position: line,
+                content: "body",
+                start_line: None,
+                end_line: None,
+            },
+            symbol_name: Some(name),
+            symbol_type,
+            language: "rust",
+            start_line: Some(line),
+            end_line: Some(line + 1),
+            parent_symbol: parent,
+            signature: Some("fn x()"),
+            doc_comment: None,
+            part: 0,
+        };
+        let chunks = [
+            symbol("run", "method", 12, Some("Server")),
+            symbol("Server", "struct", 3, None),
+        ];
+        db.insert_code_document("a.rs", Utc::now(), &chunks, &vec![vec![0.1; 1024]; 2])
+            .unwrap();
+
+        let symbols = db.list_symbols_for_file("a.rs").unwrap();
+        let outline: Vec<(Option<&str>, &str, Option<usize>)> = symbols
+            .iter()
+            .map(|s| {
+                (
+                    s.symbol_name.as_deref(),
+                    s.symbol_type.as_str(),
+                    s.start_line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            outline,
+            [
+                (Some("Server"), "struct", Some(3)),
+                (Some("run"), "method", Some(12)),
+            ]
+        );
+        assert_eq!(symbols[1].parent_symbol.as_deref(), Some("Server"));
+        assert!(db.list_symbols_for_file("b.rs").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_symbol_listed_once() {
+        let db = Db::open_in_memory().unwrap();
+        let piece = |position, part, start, end| CodeChunk {
+            chunk: Chunk {
+                position,
+                content: "body",
+                start_line: None,
+                end_line: None,
+            },
+            symbol_name: Some("generated"),
+            symbol_type: "function",
+            language: "rust",
+            start_line: Some(start),
+            end_line: Some(end),
+            parent_symbol: None,
+            signature: None,
+            doc_comment: None,
+            part,
+        };
+        let chunks = [
+            piece(0, 0, 1, 40),
+            piece(1, 1, 41, 80),
+            piece(2, 2, 81, 95),
+            piece(3, 0, 97, 99),
+        ];
+        db.insert_code_document("a.rs", Utc::now(), &chunks, &vec![vec![0.1; 1024]; 4])
+            .unwrap();
+
+        let defs = db.find_definitions("generated", None, true).unwrap();
+        let spans: Vec<_> = defs
+            .iter()
+            .map(|d| {
+                let meta = d.metadata.as_ref().unwrap();
+                (meta.start_line, meta.end_line)
+            })
+            .collect();
+        assert_eq!(spans, [(Some(1), Some(95)), (Some(97), Some(99))]);
+
+        let symbols = db.list_symbols_for_file("a.rs").unwrap();
+        let spans: Vec<_> = symbols.iter().map(|s| (s.start_line, s.end_line)).collect();
+        assert_eq!(spans, [(Some(1), Some(95)), (Some(97), Some(99))]);
+    }
+
+    #[test]
+    fn test_nearest_documents_to_centroid() {
+        use crate::embedder::{Embedder, centroid, mock::MockEmbedder};
+
+        let embedder = MockEmbedder::default();
+        let topic = embedder.embed("rate limiting").unwrap();
+        // The centroid of identical examples is their own embedding
+        let center = centroid(&[topic.clone(), topic.clone(), topic.clone()]).unwrap();
+        for (c, t) in center.iter().zip(&topic) {
+            assert!((c - t).abs() < 1e-6);
+        }
+        assert!(centroid(&[]).is_none());
+
+        let db = Db::open_in_memory().unwrap();
+        for (name, text) in [("limits.md", "rate limiting"), ("other.md", "unrelated")] {
+            insert_single_chunk(&db, name, Utc::now(), text, &embedder.embed(text).unwrap());
+        }
+
+        let hits = db.nearest_documents(&center, 1).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].filename, "limits.md");
+        assert!(hits[0].best_similarity > 0.999);
+    }
+
+    #[test]
+    fn test_get_adjacent_chunks() {
+        let db = Db::open_in_memory().unwrap();
+        let embedding = vec![0.1f32; 1024];
+        let contents = ["zero", "one", "two", "three", "four"];
+        let chunks: Vec<Chunk> = contents
+            .iter()
+            .enumerate()
+            .map(|(position, content)| Chunk {
+                position,
+                content,
+                start_line: Some(position * 2 + 1),
+                end_line: Some(position * 2 + 2),
+            })
+            .collect();
         db.insert_document(
-            "src/b.rs",
+            "a.md",
             Utc::now(),
-            &chunks_b,
-            std::slice::from_ref(&padded_embedding),
+            None,
+            &chunks,
+            &vec![embedding.clone(); 5],
         )
         .unwrap();
-
-        let chunks_c = vec![Chunk {
-            position: 0,
-            content: "Doc C",
-        }];
+        // Another document that must never leak into the window
         db.insert_document(
-            "docs/nested/c.md",
+            "b.md",
             Utc::now(),
-            &chunks_c,
-            std::slice::from_ref(&padded_embedding),
+            None,
+            &[Chunk {
+                position: 1,
+                content: "other doc",
+                start_line: None,
+                end_line: None,
+            }],
+            std::slice::from_ref(&embedding),
         )
         .unwrap();
 
+        let hit = db
+            .search(&embedding, 10)
+            .unwrap()
+            .into_iter()
+            .find(|r| r.document_name == "a.md" && r.position == 2)
+            .unwrap();
+        assert_eq!((hit.start_line, hit.end_line), (Some(5), Some(6)));
+
+        let adj = db.get_adjacent_chunks(hit.document_id, 2, 1).unwrap();
+        let before: Vec<&str> = adj.before.iter().map(|c| c.content.as_str()).collect();
+        let after: Vec<&str> = adj.after.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(before, ["one"]);
+        assert_eq!(after, ["three"]);
+
+        // Clamps at the start of the document
+        let adj = db.get_adjacent_chunks(hit.document_id, 0, 3).unwrap();
+        assert!(adj.before.is_empty());
+        assert_eq!(adj.after.len(), 3);
+
+        // Clamps at the end of the document
+        let adj = db.get_adjacent_chunks(hit.document_id, 4, 10).unwrap();
+        assert_eq!(adj.before.len(), 4);
+        assert!(adj.after.is_empty());
+    }
+
+    #[test]
+    fn test_drop_below_similarity() {
+        let db = Db::open_in_memory().unwrap();
+        let mut query = vec![0.0f32; 1024];
+        query[0] = 1.0;
+        let mut close = vec![0.0f32; 1024];
+        close[0] = 0.9;
+        close[1] = 0.1;
+        let mut orthogonal = vec![0.0f32; 1024];
+        orthogonal[1] = 1.0;
+        for (name, embedding) in [("close.md", &close), ("orthogonal.md", &orthogonal)] {
+            insert_single_chunk(&db, name, Utc::now(), name, embedding);
+        }
+
+        // Orthogonal vectors have cosine distance 1, i.e. similarity 0.5
+        let mut results = db.search(&query, 5).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!((results[1].similarity - 0.5).abs() < 1e-6);
+        let orthogonal_id = results[1].chunk_id;
+        assert_eq!(drop_below_similarity(&mut results, 0.8), [orthogonal_id]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].document_name, "close.md");
+        assert!(drop_below_similarity(&mut results, 0.0).is_empty());
+    }
+
+    #[test]
+    fn test_search_with_filter() {
+        let db = Db::open_in_memory().unwrap();
+
+        let padded_embedding = vec![0.1f32; 1024];
+        for (name, content) in [
+            ("docs/a.md", "Doc A"),
+            ("src/b.rs", "Doc B"),
+            ("docs/nested/c.md", "Doc C"),
+        ] {
+            insert_single_chunk(&db, name, Utc::now(), content, &padded_embedding);
+        }
+
         // 1. Filter by directory "docs"
         let filter_dir = SearchFilter {
             directory: Some("docs"),
             file_pattern: None,
+            language: None,
+            symbol_type: None,
         };
         let res1 = db
             .search_with_filter(&padded_embedding, 10, Some(&filter_dir))
             .unwrap();
         assert_eq!(res1.len(), 2); // docs/a.md, docs/nested/c.md
 
+        // The directory is a literal, case-sensitive prefix: no wildcards
+        for dir in ["d_cs", "d%", "DOCS"] {
+            let filter = SearchFilter {
+                directory: Some(dir),
+                file_pattern: None,
+                language: None,
+                symbol_type: None,
+            };
+            let res = db
+                .search_with_filter(&padded_embedding, 10, Some(&filter))
+                .unwrap();
+            assert!(res.is_empty(), "{dir} matched {res:?}");
+        }
+
         // 2. Filter by file_pattern "*.md"
         let filter_pat = SearchFilter {
             directory: None,
             file_pattern: Some("*.md"),
+            language: None,
+            symbol_type: None,
         };
         let res2 = db
             .search_with_filter(&padded_embedding, 10, Some(&filter_pat))
@@ -362,10 +1534,82 @@ mod tests {
         let filter_rs = SearchFilter {
             directory: None,
             file_pattern: Some("*.rs"),
+            language: None,
+            symbol_type: None,
         };
         let res3 = db
             .search_with_filter(&padded_embedding, 10, Some(&filter_rs))
             .unwrap();
         assert_eq!(res3.len(), 1); // b.rs
     }
+
+    #[test]
+    fn test_search_filter_by_language_and_symbol_type() {
+        let db = Db::open_in_memory().unwrap();
+        let embedding = vec![0.1f32; 1024];
+        insert_single_chunk(&db, "guide.md", Utc::now(), "Parsing guide", &embedding);
+        let symbol = |name, symbol_type, language, position| CodeChunk {
+            chunk: Chunk {
+                position,
+                content: name,
+                start_line: None,
+                end_line: None,
+            },
+            symbol_name: Some(name),
+            symbol_type,
+            language,
+            start_line: Some(position + 1),
+            end_line: Some(position + 1),
+            parent_symbol: None,
+            signature: None,
+            doc_comment: None,
+            part: 0,
+        };
+        let insert = |file: &str, chunks: &[CodeChunk]| {
+            db.insert_code_document(
+                file,
+                Utc::now(),
+                chunks,
+                &vec![embedding.clone(); chunks.len()],
+            )
+            .unwrap();
+        };
+        insert(
+            "parse.rs",
+            &[
+                symbol("parse", "function", "rust", 0),
+                symbol("Parser", "struct", "rust", 1),
+            ],
+        );
+        insert("parse.py", &[symbol("parse_py", "function", "python", 0)]);
+
+        let names = |language, symbol_type| {
+            let filter = SearchFilter {
+                language,
+                symbol_type,
+                ..Default::default()
+            };
+            let mut names: Vec<String> = db
+                .search_with_filter(&embedding, 10, Some(&filter))
+                .unwrap()
+                .into_iter()
+                .map(|r| r.chunk_content)
+                .collect();
+            names.sort();
+            names
+        };
+
+        // Either filter leaves out the markdown chunk, which has no code metadata
+        assert_eq!(names(Some("rust"), None), ["Parser", "parse"]);
+        assert_eq!(names(None, Some("function")), ["parse", "parse_py"]);
+        assert_eq!(names(Some("rust"), Some("function")), ["parse"]);
+        assert_eq!(names(None, None).len(), 4);
+
+        let filter = SearchFilter {
+            language: Some("rust"),
+            ..Default::default()
+        };
+        assert!(!filter.matches_code(None));
+        assert!(SearchFilter::default().matches_code(None));
+    }
 }
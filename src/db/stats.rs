@@ -0,0 +1,406 @@
+use super::{Db, deserialize_vector_f32};
+use rusqlite::Result;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Aggregate counts describing the contents of the index.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct IndexStats {
+    pub documents: i64,
+    pub chunks: i64,
+    /// Code symbols, counting a symbol split into several chunks once.
+    pub code_symbols: i64,
+    pub relations: i64,
+    pub word_mappings: i64,
+    /// Number of code symbols per language.
+    pub symbols_by_language: BTreeMap<String, i64>,
+}
+
+/// Aggregates for the documents directly inside one directory.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DirectorySummary {
+    /// Path relative to the summarized root (`"."` for the root itself).
+    pub directory: String,
+    pub documents: usize,
+    pub chunks: usize,
+    /// Languages of the code symbols indexed from these documents.
+    pub languages: BTreeSet<String>,
+}
+
+/// Vectors with an L2 norm below this are reported as degenerate, e.g. the
+/// all-zero output of a failed model.
+pub const DEGENERATE_NORM: f64 = 1e-6;
+
+/// Norms within this distance of 1.0 count as normalized.
+pub const UNIT_NORM_TOLERANCE: f64 = 0.01;
+
+/// L2 norm distribution over a sample of the stored embeddings, from
+/// [`Db::embedding_norm_stats`]. The norm fields are 0 when nothing was sampled.
+#[derive(Debug, Default, PartialEq)]
+pub struct EmbeddingNormStats {
+    /// Vectors stored in the index.
+    pub total: usize,
+    /// Vectors the statistics were computed over.
+    pub sampled: usize,
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Sampled vectors with a norm below [`DEGENERATE_NORM`].
+    pub degenerate: usize,
+    /// Chunk IDs of the degenerate vectors, ascending.
+    pub degenerate_chunk_ids: Vec<i64>,
+    /// Sampled vectors with a norm within [`UNIT_NORM_TOLERANCE`] of 1.0.
+    pub unit_norm: usize,
+}
+
+impl Db {
+    /// Norm statistics over up to `sample_size` stored embeddings, chosen at
+    /// random when the index holds more. A healthy index of normalized
+    /// embeddings has norms of about 1.0 and no degenerate vectors.
+    pub fn embedding_norm_stats(&self, sample_size: usize) -> Result<EmbeddingNormStats> {
+        let conn = self.get_conn()?;
+        let total: i64 = conn.query_row("SELECT COUNT(*) FROM vec_chunks", [], |row| row.get(0))?;
+        let mut stmt = conn.prepare(
+            "SELECT rowid, embedding FROM vec_chunks
+             WHERE rowid IN (SELECT rowid FROM vec_chunks ORDER BY random() LIMIT ?)",
+        )?;
+        let rows = stmt.query_map([sample_size as i64], |row| {
+            let embedding: Vec<u8> = row.get(1)?;
+            let norm = deserialize_vector_f32(&embedding)
+                .iter()
+                .map(|&v| f64::from(v) * f64::from(v))
+                .sum::<f64>()
+                .sqrt();
+            Ok((row.get::<_, i64>(0)?, norm))
+        })?;
+
+        let mut stats = EmbeddingNormStats {
+            total: total as usize,
+            ..Default::default()
+        };
+        let mut norms = Vec::new();
+        for row in rows {
+            let (chunk_id, norm) = row?;
+            if norm < DEGENERATE_NORM {
+                stats.degenerate_chunk_ids.push(chunk_id);
+            }
+            if (norm - 1.0).abs() <= UNIT_NORM_TOLERANCE {
+                stats.unit_norm += 1;
+            }
+            norms.push(norm);
+        }
+        stats.degenerate_chunk_ids.sort_unstable();
+        stats.degenerate = stats.degenerate_chunk_ids.len();
+        stats.sampled = norms.len();
+        if norms.is_empty() {
+            return Ok(stats);
+        }
+        norms.sort_by(f64::total_cmp);
+        stats.min = norms[0];
+        stats.max = norms[norms.len() - 1];
+        stats.median = norms[norms.len() / 2];
+        stats.mean = norms.iter().sum::<f64>() / norms.len() as f64;
+        Ok(stats)
+    }
+
+    /// Collect aggregate counts over all index tables.
+    pub fn get_index_stats(&self) -> Result<IndexStats> {
+        let conn = self.get_conn()?;
+        let count = |table: &str| -> Result<i64> {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+        };
+
+        let mut stats = IndexStats {
+            documents: count("documents")?,
+            chunks: count("chunks")?,
+            code_symbols: conn.query_row(
+                "SELECT COUNT(*) FROM code_metadata WHERE part = 0",
+                [],
+                |row| row.get(0),
+            )?,
+            relations: count("code_relations")?,
+            word_mappings: count("word_mapping")?,
+            symbols_by_language: BTreeMap::new(),
+        };
+
+        let mut stmt = conn.prepare(
+            "SELECT language, COUNT(*) FROM code_metadata WHERE part = 0 GROUP BY language",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for row in rows {
+            let (language, n): (String, i64) = row?;
+            stats.symbols_by_language.insert(language, n);
+        }
+
+        Ok(stats)
+    }
+
+    /// Per-directory document, chunk and language counts for every indexed
+    /// document under `dir`, sorted by directory. Counts are not recursive:
+    /// each document is attributed to the directory that contains it.
+    pub fn directory_summary(&self, dir: &str) -> Result<Vec<DirectorySummary>> {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        let conn = self.get_conn()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                d.filename,
+                (SELECT COUNT(*) FROM chunks c WHERE c.document_id = d.id),
+                (SELECT GROUP_CONCAT(DISTINCT cm.language)
+                 FROM chunks c JOIN code_metadata cm ON cm.chunk_id = c.id
+                 WHERE c.document_id = d.id)
+            FROM documents d
+            WHERE substr(d.filename, 1, length(?1)) = ?1
+            "#,
+        )?;
+        let rows = stmt.query_map([&prefix], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?;
+
+        let mut by_dir: BTreeMap<String, DirectorySummary> = BTreeMap::new();
+        for row in rows {
+            let (filename, chunks, languages) = row?;
+            let relative = &filename[prefix.len()..];
+            let directory = match relative.rsplit_once('/') {
+                Some((parent, _)) => parent.to_string(),
+                None => ".".to_string(),
+            };
+            let summary = by_dir
+                .entry(directory.clone())
+                .or_insert_with(|| DirectorySummary {
+                    directory,
+                    ..Default::default()
+                });
+            summary.documents += 1;
+            summary.chunks += chunks as usize;
+            if let Some(languages) = languages {
+                summary
+                    .languages
+                    .extend(languages.split(',').map(str::to_string));
+            }
+        }
+
+        Ok(by_dir.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::{Chunk, CodeChunk, DocChunk};
+    use chrono::Utc;
+
+    #[test]
+    fn test_get_index_stats() {
+        let db = Db::open_in_memory().unwrap();
+        assert_eq!(db.get_index_stats().unwrap(), IndexStats::default());
+
+        let embedding = vec![0.1f32; 1024];
+        db.insert_document(
+            "a.md",
+            Utc::now(),
+            None,
+            &[
+                Chunk {
+                    position: 0,
+                    content: "one",
+                    start_line: None,
+                    end_line: None,
+                },
+                Chunk {
+                    position: 1,
+                    content: "two",
+                    start_line: None,
+                    end_line: None,
+                },
+            ],
+            &[embedding.clone(), embedding.clone()],
+        )
+        .unwrap();
+
+        let code = |name, language, part| CodeChunk {
+            chunk: Chunk {
+                position: 0,
+                content: "fn x() {}",
+                start_line: None,
+                end_line: None,
+            },
+            symbol_name: Some(name),
+            symbol_type: "function",
+            language,
+            start_line: Some(1),
+            end_line: Some(1),
+            parent_symbol: None,
+            signature: None,
+            doc_comment: None,
+            part,
+        };
+        // A symbol split in two, with its doc comment as a linked prose chunk
+        let doc = DocChunk {
+            owner: 0,
+            chunk: Chunk {
+                position: 2,
+                content: "Does x.",
+                start_line: Some(1),
+                end_line: Some(1),
+            },
+        };
+        db.insert_code_document_with_docs(
+            "main.rs",
+            Utc::now(),
+            None,
+            &[code("x", "rust", 0), code("x", "rust", 1)],
+            &[doc],
+            &[embedding.clone(), embedding.clone(), embedding.clone()],
+        )
+        .unwrap();
+        db.insert_code_document(
+            "main.py",
+            Utc::now(),
+            &[code("y", "python", 0)],
+            std::slice::from_ref(&embedding),
+        )
+        .unwrap();
+
+        let stats = db.get_index_stats().unwrap();
+        assert_eq!(stats.documents, 3);
+        assert_eq!(stats.chunks, 6);
+        assert_eq!(stats.code_symbols, 2);
+        assert_eq!(stats.relations, 0);
+        assert_eq!(stats.word_mappings, 0);
+        assert_eq!(stats.symbols_by_language.get("rust"), Some(&1));
+        assert_eq!(stats.symbols_by_language.get("python"), Some(&1));
+    }
+
+    #[test]
+    fn test_embedding_norm_stats_flags_degenerate_vectors() {
+        let db = Db::open_in_memory().unwrap();
+        assert_eq!(
+            db.embedding_norm_stats(100).unwrap(),
+            EmbeddingNormStats::default()
+        );
+
+        let mut unit = vec![0.0f32; 1024];
+        unit[0] = 0.6;
+        unit[1] = 0.8;
+        let zero = vec![0.0f32; 1024];
+        let chunk = |position| Chunk {
+            position,
+            content: "text",
+            start_line: None,
+            end_line: None,
+        };
+        let chunks: Vec<Chunk> = (0..5).map(chunk).collect();
+        let ids = {
+            db.insert_document(
+                "a.md",
+                Utc::now(),
+                None,
+                &chunks,
+                &[
+                    unit.clone(),
+                    zero.clone(),
+                    unit.clone(),
+                    zero.clone(),
+                    unit.clone(),
+                ],
+            )
+            .unwrap();
+            let conn = db.get_conn().unwrap();
+            let mut stmt = conn
+                .prepare("SELECT id FROM chunks ORDER BY position")
+                .unwrap();
+            stmt.query_map([], |row| row.get::<_, i64>(0))
+                .unwrap()
+                .collect::<Result<Vec<_>>>()
+                .unwrap()
+        };
+
+        let stats = db.embedding_norm_stats(100).unwrap();
+        assert_eq!(stats.total, 5);
+        assert_eq!(stats.sampled, 5);
+        assert_eq!(stats.degenerate, 2);
+        assert_eq!(stats.degenerate_chunk_ids, [ids[1], ids[3]]);
+        assert_eq!(stats.unit_norm, 3);
+        assert_eq!(stats.min, 0.0);
+        assert!((stats.max - 1.0).abs() < 1e-6);
+        assert!((stats.median - 1.0).abs() < 1e-6);
+        assert!((stats.mean - 0.6).abs() < 1e-6);
+
+        let sample = db.embedding_norm_stats(2).unwrap();
+        assert_eq!(sample.total, 5);
+        assert_eq!(sample.sampled, 2);
+    }
+
+    #[test]
+    fn test_directory_summary_nested() {
+        let db = Db::open_in_memory().unwrap();
+        let embedding = vec![0.1f32; 1024];
+        let doc = |name: &str, n: usize| {
+            let chunks: Vec<Chunk> = (0..n)
+                .map(|position| Chunk {
+                    position,
+                    content: "text",
+                    start_line: None,
+                    end_line: None,
+                })
+                .collect();
+            db.insert_document(name, Utc::now(), None, &chunks, &vec![embedding.clone(); n])
+                .unwrap();
+        };
+        let code = |name: &str, language: &'static str, n: usize| {
+            let chunks: Vec<CodeChunk> = (0..n)
+                .map(|position| CodeChunk {
+                    chunk: Chunk {
+                        position,
+                        content: "fn x() {}",
+                        start_line: None,
+                        end_line: None,
+                    },
+                    symbol_name: Some("x"),
+                    symbol_type: "function",
+                    language,
+                    start_line: None,
+                    end_line: None,
+                    parent_symbol: None,
+                    signature: None,
+                    doc_comment: None,
+                    part: 0,
+                })
+                .collect();
+            db.insert_code_document(name, Utc::now(), &chunks, &vec![embedding.clone(); n])
+                .unwrap();
+        };
+
+        doc("/p/README.md", 1);
+        doc("/p/docs/guide.md", 2);
+        doc("/p/docs/api.md", 3);
+        code("/p/src/main.rs", "rust", 2);
+        code("/p/src/gen.py", "python", 1);
+        code("/p/src/util/io.rs", "rust", 1);
+        // Outside the summarized root, including a sibling sharing its prefix
+        doc("/p2/other.md", 1);
+        doc("/q/else.md", 1);
+
+        let summary = db.directory_summary("/p/").unwrap();
+        let dirs: Vec<&str> = summary.iter().map(|s| s.directory.as_str()).collect();
+        assert_eq!(dirs, [".", "docs", "src", "src/util"]);
+
+        let get = |d: &str| summary.iter().find(|s| s.directory == d).unwrap();
+        assert_eq!((get(".").documents, get(".").chunks), (1, 1));
+        assert_eq!((get("docs").documents, get("docs").chunks), (2, 5));
+        assert!(get("docs").languages.is_empty());
+        assert_eq!((get("src").documents, get("src").chunks), (2, 3));
+        assert_eq!(
+            get("src").languages.iter().collect::<Vec<_>>(),
+            ["python", "rust"]
+        );
+        assert_eq!(get("src/util").documents, 1);
+    }
+}
@@ -86,11 +86,14 @@ impl ApiEmbedder {
             ));
         }
 
-        let client = Client::builder()
+        let client = crate::http::blocking_client_builder()
             .timeout(Duration::from_secs(config.timeout_secs))
             .build()
             .map_err(|e| {
-                EmbedderError::ModelLoadFailed(format!("Failed to build HTTP client: {e}"))
+                EmbedderError::ModelLoadFailed(format!(
+                    "Failed to build HTTP client ({} TLS backend): {e}",
+                    crate::http::TLS_BACKEND
+                ))
             })?;
 
         Ok(Self {
@@ -115,7 +118,7 @@ impl ApiEmbedder {
             .map_err(|e| {
                 let retryable = e.is_timeout() || e.is_connect() || e.is_request();
                 ApiError {
-                    message: format!("Network error: {e}"),
+                    message: format!("Network error: {}", crate::http::describe_error(&e)),
                     retryable,
                 }
             })?;
@@ -302,6 +305,10 @@ impl super::Embedder for ApiEmbedder {
     fn dimensions(&self) -> usize {
         self.dimensions
     }
+
+    fn name(&self) -> &str {
+        "api"
+    }
 }
 
 /// Estimate token count from text length (~4 chars per token for English/mixed text).
@@ -376,6 +383,15 @@ mod tests {
         assert_eq!(batches[1].len(), 2);
     }
 
+    #[test]
+    fn test_create_smart_batches_splits_ten_by_four() {
+        let embedder = test_embedder_with_batch_size(4);
+        let texts: Vec<&str> = vec!["t"; 10];
+        let batches = embedder.create_smart_batches(&texts);
+        let sizes: Vec<usize> = batches.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [4, 4, 2]);
+    }
+
     #[test]
     fn test_create_smart_batches_long_text_standalone() {
         let embedder = test_embedder_with_batch_size(32);
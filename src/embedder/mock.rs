@@ -4,7 +4,7 @@
 /// mirroring the Go version's `MockEmbedder`.
 use std::hash::{DefaultHasher, Hash, Hasher};
 
-use super::{Embedder, EmbedderError};
+use super::{Embedder, EmbedderError, l2_normalize};
 
 /// A mock embedder that produces deterministic vectors from text hashes.
 ///
@@ -27,29 +27,31 @@ impl Default for MockEmbedder {
     }
 }
 
+/// Next output of the SplitMix64 generator, advancing `state`.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 impl Embedder for MockEmbedder {
     fn embed(&self, text: &str) -> Result<Vec<f32>, EmbedderError> {
-        // Generate a deterministic embedding based on text hash
+        // Seed a PRNG from the text hash so every dimension is independent
         let mut hasher = DefaultHasher::new();
         text.hash(&mut hasher);
-        let hash = hasher.finish();
+        let mut state = hasher.finish();
 
-        // Use the hash bytes to seed deterministic float values
-        let bytes = hash.to_le_bytes();
+        // Values in [-1, 1]: unrelated texts land near cosine 0, like real
+        // embeddings, instead of sharing one positive, periodic pattern
         let mut embedding = Vec::with_capacity(self.dimensions);
-        for i in 0..self.dimensions {
-            embedding.push(bytes[i % 8] as f32 / 255.0);
-        }
-
-        // L2 normalize
-        let norm_sq: f32 = embedding.iter().map(|v| v * v).sum();
-        if norm_sq > 0.0 {
-            let inv = 1.0 / norm_sq.sqrt();
-            for v in &mut embedding {
-                *v *= inv;
-            }
+        for _ in 0..self.dimensions {
+            let bits = splitmix64(&mut state) >> 40; // top 24 bits
+            embedding.push(bits as f32 / (1u64 << 23) as f32 - 1.0);
         }
 
+        l2_normalize(&mut embedding);
         Ok(embedding)
     }
 
@@ -60,12 +62,31 @@ impl Embedder for MockEmbedder {
     fn dimensions(&self) -> usize {
         self.dimensions
     }
+
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn is_mock(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_mock_embedder_identifies_itself() {
+        let embedder = MockEmbedder::default();
+        assert_eq!(embedder.name(), "mock");
+        assert!(embedder.is_mock());
+
+        let cached = crate::embedder::CachingEmbedder::new(embedder, 4);
+        assert_eq!(cached.name(), "mock");
+        assert!(cached.is_mock());
+    }
+
     #[test]
     fn test_mock_embed_dimensions() {
         let embedder = MockEmbedder::new(1024);
@@ -110,6 +131,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mock_embed_spreads_entropy_across_dimensions() {
+        let embedder = MockEmbedder::new(1024);
+        let cosine = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+        // The former scheme: 8 hash bytes repeated across all dimensions
+        let periodic = |text: &str| {
+            let mut hasher = DefaultHasher::new();
+            text.hash(&mut hasher);
+            let bytes = hasher.finish().to_le_bytes();
+            let mut v: Vec<f32> = (0..1024).map(|i| bytes[i % 8] as f32 / 255.0).collect();
+            l2_normalize(&mut v);
+            v
+        };
+
+        let texts = ["config file", "config files", "Config file", "config_file"];
+        let (mut mock_total, mut periodic_total) = (0.0, 0.0);
+        for (i, a) in texts.iter().enumerate() {
+            for b in &texts[i + 1..] {
+                let sim = cosine(&embedder.embed(a).unwrap(), &embedder.embed(b).unwrap());
+                assert!(sim.abs() < 0.2, "{a:?} vs {b:?}: {sim}");
+                mock_total += sim.abs();
+                periodic_total += cosine(&periodic(a), &periodic(b)).abs();
+            }
+        }
+        assert!(mock_total < periodic_total);
+
+        let v = embedder.embed("config file").unwrap();
+        assert!((0..1016).any(|i| v[i] != v[i + 8]), "vector is periodic");
+    }
+
     #[test]
     fn test_mock_default_dimensions() {
         let embedder = MockEmbedder::default();
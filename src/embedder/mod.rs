@@ -2,6 +2,11 @@
 pub mod api;
 pub mod mock;
 
+use crate::indexer::content_hash::sha256_hex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 use thiserror::Error;
 
 /// Errors that can occur during embedding operations.
@@ -27,4 +32,354 @@ pub trait Embedder: Send + Sync {
 
     /// Return the dimensionality of the embedding vectors.
     fn dimensions(&self) -> usize;
+
+    /// Short name of the backend, e.g. `"api"` or `"mock"`.
+    fn name(&self) -> &str;
+
+    /// Whether vectors are synthetic rather than semantic; true only for
+    /// the mock fallback.
+    fn is_mock(&self) -> bool {
+        false
+    }
+}
+
+/// Scale `vector` to unit length in place. Zero vectors are left as they are.
+pub fn l2_normalize(vector: &mut [f32]) {
+    let norm_sq: f32 = vector.iter().map(|v| v * v).sum();
+    if norm_sq > 0.0 {
+        let inv = 1.0 / norm_sq.sqrt();
+        for v in vector {
+            *v *= inv;
+        }
+    }
+}
+
+/// Unit-length mean of `vectors`, or `None` when there are none.
+pub fn centroid(vectors: &[Vec<f32>]) -> Option<Vec<f32>> {
+    let first = vectors.first()?;
+    let mut mean = vec![0.0; first.len()];
+    for vector in vectors {
+        for (m, v) in mean.iter_mut().zip(vector) {
+            *m += v;
+        }
+    }
+    l2_normalize(&mut mean);
+    Some(mean)
+}
+
+/// Wraps an [`Embedder`], keeping the vectors of the most recently used texts.
+///
+/// Texts are looked up by the SHA-256 digest of their content, so the cache
+/// doesn't hold the texts themselves; only misses reach the inner embedder, and repeated texts within one batch are embedded once.
+pub struct CachingEmbedder<E> {
+    inner: E,
+    capacity: usize,
+    cache: Mutex<LruCache>,
+}
+
+impl<E: Embedder> CachingEmbedder<E> {
+    /// Cache up to `capacity` vectors in front of `inner`.
+    #[must_use]
+    pub fn new(inner: E, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity,
+            cache: Mutex::new(LruCache::default()),
+        }
+    }
+
+    fn cache(&self) -> std::sync::MutexGuard<'_, LruCache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<E: Embedder> Embedder for CachingEmbedder<E> {
+    fn embed(&self, text: &str) -> Result<Vec<f32>, EmbedderError> {
+        if let Some(vector) = self.cache().get(text) {
+            return Ok(vector);
+        }
+        let vector = self.inner.embed(text)?;
+        self.cache().insert(text, vector.clone(), self.capacity);
+        Ok(vector)
+    }
+
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, EmbedderError> {
+        let mut results: Vec<Option<Vec<f32>>> = Vec::with_capacity(texts.len());
+        let mut misses: Vec<&str> = Vec::new();
+        {
+            let mut cache = self.cache();
+            for text in texts {
+                let hit = cache.get(text);
+                if hit.is_none() && !misses.contains(text) {
+                    misses.push(text);
+                }
+                results.push(hit);
+            }
+        }
+        if misses.is_empty() {
+            return Ok(results.into_iter().flatten().collect());
+        }
+
+        let vectors = self.inner.embed_batch(&misses)?;
+        if vectors.len() != misses.len() {
+            return Err(EmbedderError::InferenceFailed(format!(
+                "expected {} embeddings, got {}",
+                misses.len(),
+                vectors.len()
+            )));
+        }
+        let embedded: HashMap<&str, Vec<f32>> = misses.into_iter().zip(vectors).collect();
+        let mut cache = self.cache();
+        for (text, vector) in &embedded {
+            cache.insert(text, vector.clone(), self.capacity);
+        }
+        Ok(texts
+            .iter()
+            .zip(results)
+            .map(|(text, hit)| hit.unwrap_or_else(|| embedded[text].clone()))
+            .collect())
+    }
+
+    fn dimensions(&self) -> usize {
+        self.inner.dimensions()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn is_mock(&self) -> bool {
+        self.inner.is_mock()
+    }
+}
+
+/// Wraps an [`Embedder`], failing calls that take longer than a timeout.
+///
+/// Each call runs on its own thread. Inference can't be interrupted, so a
+/// call that times out keeps running in the background and its result is
+/// discarded; the caller gets `InferenceFailed` and can move on.
+pub struct TimeoutEmbedder {
+    inner: Arc<dyn Embedder>,
+    timeout: Duration,
+}
+
+impl TimeoutEmbedder {
+    /// Limit every call to `inner` to `timeout`.
+    #[must_use]
+    pub fn new(inner: Arc<dyn Embedder>, timeout: Duration) -> Self {
+        Self { inner, timeout }
+    }
+
+    fn run<T: Send + 'static>(
+        &self,
+        call: impl FnOnce(&dyn Embedder) -> Result<T, EmbedderError> + Send + 'static,
+    ) -> Result<T, EmbedderError> {
+        let inner = Arc::clone(&self.inner);
+        let (tx, rx) = mpsc::sync_channel(1);
+        std::thread::Builder::new()
+            .name("embed".to_string())
+            .spawn(move || {
+                // The receiver is gone if the call already timed out
+                let _ = tx.send(call(inner.as_ref()));
+            })
+            .map_err(|e| EmbedderError::InferenceFailed(format!("spawn failed: {e}")))?;
+        match rx.recv_timeout(self.timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(EmbedderError::InferenceFailed(format!(
+                "timeout after {}s",
+                self.timeout.as_secs_f64()
+            ))),
+            Err(RecvTimeoutError::Disconnected) => Err(EmbedderError::InferenceFailed(
+                "embedding thread panicked".to_string(),
+            )),
+        }
+    }
+}
+
+impl Embedder for TimeoutEmbedder {
+    fn embed(&self, text: &str) -> Result<Vec<f32>, EmbedderError> {
+        let text = text.to_string();
+        self.run(move |e| e.embed(&text))
+    }
+
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, EmbedderError> {
+        let texts: Vec<String> = texts.iter().map(|t| (*t).to_string()).collect();
+        self.run(move |e| {
+            let refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+            e.embed_batch(&refs)
+        })
+    }
+
+    fn dimensions(&self) -> usize {
+        self.inner.dimensions()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn is_mock(&self) -> bool {
+        self.inner.is_mock()
+    }
+}
+
+/// Least-recently-used map from text to vector, keyed by the text's digest.
+#[derive(Default)]
+struct LruCache {
+    /// Vector and last-use tick per text digest.
+    entries: HashMap<String, (Vec<f32>, u64)>,
+    /// Text digests by last-use tick, oldest first.
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl LruCache {
+    fn get(&mut self, text: &str) -> Option<Vec<f32>> {
+        self.tick += 1;
+        let (vector, used) = self.entries.get_mut(&sha256_hex(text.as_bytes()))?;
+        let key = self.order.remove(used)?;
+        *used = self.tick;
+        self.order.insert(self.tick, key);
+        Some(vector.clone())
+    }
+
+    fn insert(&mut self, text: &str, vector: Vec<f32>, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        let key = sha256_hex(text.as_bytes());
+        if let Some((_, used)) = self.entries.insert(key.clone(), (vector, self.tick)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(self.tick, key);
+        while self.entries.len() > capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Mock embedder counting the texts it is asked to embed.
+    #[derive(Default)]
+    struct CountingEmbedder {
+        embedded: AtomicUsize,
+    }
+
+    impl Embedder for CountingEmbedder {
+        fn embed(&self, text: &str) -> Result<Vec<f32>, EmbedderError> {
+            self.embedded.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![text.len() as f32])
+        }
+
+        fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, EmbedderError> {
+            texts.iter().map(|t| self.embed(t)).collect()
+        }
+
+        fn dimensions(&self) -> usize {
+            1
+        }
+
+        fn name(&self) -> &str {
+            "counting"
+        }
+    }
+
+    #[test]
+    fn test_caching_embedder_only_embeds_misses() {
+        let embedder = CachingEmbedder::new(CountingEmbedder::default(), 2);
+        let count = |e: &CachingEmbedder<CountingEmbedder>| e.inner.embedded.load(Ordering::SeqCst);
+
+        let vectors = embedder.embed_batch(&["a", "bb", "a"]).unwrap();
+        assert_eq!(vectors, [vec![1.0], vec![2.0], vec![1.0]]);
+        assert_eq!(count(&embedder), 2);
+
+        // Hits keep their position among misses
+        let vectors = embedder.embed_batch(&["ccc", "bb"]).unwrap();
+        assert_eq!(vectors, [vec![3.0], vec![2.0]]);
+        assert_eq!(count(&embedder), 3);
+
+        // Capacity 2: "a" was least recently used and got evicted
+        embedder.embed("bb").unwrap();
+        assert_eq!(count(&embedder), 3);
+        embedder.embed("a").unwrap();
+        assert_eq!(count(&embedder), 4);
+    }
+
+    /// Embedder whose batches silently drop the first text.
+    struct ShortBatchEmbedder;
+
+    impl Embedder for ShortBatchEmbedder {
+        fn embed(&self, text: &str) -> Result<Vec<f32>, EmbedderError> {
+            Ok(vec![text.len() as f32])
+        }
+
+        fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, EmbedderError> {
+            texts[1..].iter().map(|t| self.embed(t)).collect()
+        }
+
+        fn dimensions(&self) -> usize {
+            1
+        }
+
+        fn name(&self) -> &str {
+            "short"
+        }
+    }
+
+    #[test]
+    fn test_caching_embedder_rejects_short_batch() {
+        let embedder = CachingEmbedder::new(ShortBatchEmbedder, 4);
+        let err = embedder.embed_batch(&["a", "bb"]).unwrap_err();
+        assert!(matches!(err, EmbedderError::InferenceFailed(_)), "{err}");
+    }
+
+    /// Embedder that sleeps before answering.
+    struct SlowEmbedder(Duration);
+
+    impl Embedder for SlowEmbedder {
+        fn embed(&self, text: &str) -> Result<Vec<f32>, EmbedderError> {
+            std::thread::sleep(self.0);
+            Ok(vec![text.len() as f32])
+        }
+
+        fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, EmbedderError> {
+            texts.iter().map(|t| self.embed(t)).collect()
+        }
+
+        fn dimensions(&self) -> usize {
+            1
+        }
+
+        fn name(&self) -> &str {
+            "slow"
+        }
+    }
+
+    #[test]
+    fn test_timeout_embedder() {
+        let fast = TimeoutEmbedder::new(
+            Arc::new(SlowEmbedder(Duration::ZERO)),
+            Duration::from_secs(5),
+        );
+        assert_eq!(
+            fast.embed_batch(&["a", "bb"]).unwrap(),
+            [vec![1.0], vec![2.0]]
+        );
+        assert_eq!(fast.name(), "slow");
+
+        let slow = TimeoutEmbedder::new(
+            Arc::new(SlowEmbedder(Duration::from_secs(2))),
+            Duration::from_millis(50),
+        );
+        let err = slow.embed("a").unwrap_err();
+        assert!(err.to_string().contains("timeout"), "{err}");
+    }
 }
@@ -5,19 +5,103 @@ use std::fs;
 use std::path::Path;
 
 use anyhow::{Context, Result, bail};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_yaml::{Mapping, Value};
+
+use crate::indexer::markdown::strip_bom;
 
 /// Metadata stored in YAML frontmatter.
-#[derive(Debug, Clone, Default)]
+///
+/// Keys other than the typed ones are kept in `extra` so rewriting the
+/// frontmatter doesn't drop them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Metadata {
+    #[serde(
+        default,
+        deserialize_with = "scalar_string",
+        skip_serializing_if = "String::is_empty"
+    )]
     pub domain: String,
+    #[serde(
+        rename = "docType",
+        default,
+        deserialize_with = "scalar_string",
+        skip_serializing_if = "String::is_empty"
+    )]
     pub doc_type: String,
+    #[serde(
+        default,
+        deserialize_with = "scalar_string",
+        skip_serializing_if = "String::is_empty"
+    )]
     pub language: String,
+    #[serde(
+        default,
+        deserialize_with = "tag_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub tags: Vec<String>,
+    #[serde(
+        default,
+        deserialize_with = "scalar_string",
+        skip_serializing_if = "String::is_empty"
+    )]
     pub project: String,
+    /// Any other frontmatter keys, in their original order.
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+impl Metadata {
+    /// The `title` key, if set to a non-empty scalar.
+    #[must_use]
+    pub fn title(&self) -> Option<String> {
+        let title = scalar_to_string(self.extra.get("title")?.clone())?;
+        let title = title.trim();
+        (!title.is_empty()).then(|| title.to_string())
+    }
+}
+
+/// Render a scalar (string, number or bool) as a string; null becomes empty.
+fn scalar_to_string(value: Value) -> Option<String> {
+    match value {
+        Value::Null => Some(String::new()),
+        Value::String(s) => Some(s),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn scalar_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    scalar_to_string(Value::deserialize(deserializer)?)
+        .ok_or_else(|| D::Error::custom("expected a scalar value"))
+}
+
+/// Accept tags as a YAML list or a comma-separated string.
+fn tag_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    let items = match Value::deserialize(deserializer)? {
+        Value::Sequence(seq) => seq
+            .into_iter()
+            .map(|v| scalar_to_string(v).ok_or_else(|| D::Error::custom("tags must be scalars")))
+            .collect::<Result<Vec<_>, _>>()?,
+        other => scalar_to_string(other)
+            .ok_or_else(|| D::Error::custom("expected a list of tags"))?
+            .split(',')
+            .map(str::to_string)
+            .collect(),
+    };
+    Ok(items
+        .into_iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect())
 }
 
 /// Parse frontmatter from markdown content. Returns `(Option<Metadata>, body)`.
 pub fn parse(content: &str) -> Result<(Option<Metadata>, String)> {
+    let content = strip_bom(content);
     let lines: Vec<&str> = content.lines().collect();
 
     if lines.len() < 3 || lines[0].trim() != "---" {
@@ -35,86 +119,49 @@ pub fn parse(content: &str) -> Result<(Option<Metadata>, String)> {
         None => bail!("frontmatter not closed"),
     };
 
-    let frontmatter_lines = &lines[1..end_idx];
-    let body_lines = &lines[end_idx + 1..];
-
-    let mut metadata = Metadata::default();
-
-    for line in frontmatter_lines {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-
-        if let Some((key, value)) = line.split_once(':') {
-            let key = key.trim();
-            let value = value.trim();
-
-            match key {
-                "domain" => metadata.domain = value.to_string(),
-                "docType" => metadata.doc_type = value.to_string(),
-                "language" => metadata.language = value.to_string(),
-                "project" => metadata.project = value.to_string(),
-                "tags" => {
-                    let value = value.trim_matches(|c| c == '[' || c == ']');
-                    metadata.tags = value
-                        .split(',')
-                        .map(|t| t.trim().to_string())
-                        .filter(|t| !t.is_empty())
-                        .collect();
-                }
-                _ => {}
-            }
-        }
-    }
+    let yaml = lines[1..end_idx].join("\n");
+    let metadata = if yaml.trim().is_empty() {
+        Metadata::default()
+    } else {
+        serde_yaml::from_str(&yaml).context("invalid YAML frontmatter")?
+    };
 
-    let body = body_lines.join("\n");
+    let body = lines[end_idx + 1..].join("\n");
     Ok((Some(metadata), body))
 }
 
-/// Quote a YAML scalar value if it contains special characters.
-fn yaml_quote(value: &str) -> String {
-    if value.is_empty()
-        || value.contains(':')
-        || value.contains('#')
-        || value.contains('[')
-        || value.contains(']')
-        || value.contains('{')
-        || value.contains('}')
-        || value.contains('"')
-        || value.contains('\'')
-        || value.starts_with(' ')
-        || value.ends_with(' ')
-    {
-        format!("\"{}\"", value.replace('"', "\\\""))
-    } else {
-        value.to_string()
+/// Byte offset where the body starts, just past the closing `---` line of
+/// a leading frontmatter block. Returns 0 when there is no closed block.
+///
+/// Unlike [`parse`] this doesn't look at the YAML, so chunking can skip a
+/// block even when it fails to parse.
+#[must_use]
+pub fn body_offset(content: &str) -> usize {
+    let mut lines = content.split_inclusive('\n');
+    let Some(first) = lines.next() else {
+        return 0;
+    };
+    if first.trim() != "---" {
+        return 0;
+    }
+    let mut offset = first.len();
+    for line in lines {
+        offset += line.len();
+        if line.trim() == "---" {
+            return offset;
+        }
     }
+    0
 }
 
 /// Generate YAML frontmatter string from metadata.
 pub fn generate(metadata: &Metadata) -> String {
-    let mut builder = String::from("---\n");
-
-    if !metadata.domain.is_empty() {
-        builder.push_str(&format!("domain: {}\n", yaml_quote(&metadata.domain)));
-    }
-    if !metadata.doc_type.is_empty() {
-        builder.push_str(&format!("docType: {}\n", yaml_quote(&metadata.doc_type)));
-    }
-    if !metadata.language.is_empty() {
-        builder.push_str(&format!("language: {}\n", yaml_quote(&metadata.language)));
-    }
-    if !metadata.tags.is_empty() {
-        let quoted_tags: Vec<String> = metadata.tags.iter().map(|t| yaml_quote(t)).collect();
-        builder.push_str(&format!("tags: [{}]\n", quoted_tags.join(", ")));
-    }
-    if !metadata.project.is_empty() {
-        builder.push_str(&format!("project: {}\n", yaml_quote(&metadata.project)));
-    }
-
-    builder.push_str("---\n");
-    builder
+    let yaml = match serde_yaml::to_value(metadata) {
+        Ok(Value::Mapping(map)) if map.is_empty() => String::new(),
+        Ok(value) => serde_yaml::to_string(&value).unwrap_or_default(),
+        Err(_) => String::new(),
+    };
+    format!("---\n{yaml}---\n")
 }
 
 /// Add frontmatter to a file (errors if frontmatter already exists).
@@ -128,7 +175,7 @@ pub fn add_frontmatter(file_path: &Path, metadata: &Metadata) -> Result<()> {
     }
 
     let fm = generate(metadata);
-    let new_content = format!("{}\n{}", fm, content);
+    let new_content = format!("{}\n{}", fm, strip_bom(&content));
 
     fs::write(file_path, new_content)
         .with_context(|| format!("failed to write {}", file_path.display()))?;
@@ -175,11 +222,66 @@ pub fn update_frontmatter(file_path: &Path, metadata: &Metadata) -> Result<()> {
     Ok(())
 }
 
+/// What [`apply_frontmatter`] did to a file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApplyOutcome {
+    /// The file had no frontmatter; it was added.
+    Added,
+    /// Existing frontmatter was merged with the metadata.
+    Updated,
+    /// The file already has frontmatter and overwriting wasn't requested.
+    Skipped,
+    /// Reading, parsing or writing the file failed.
+    Failed(String),
+}
+
+impl ApplyOutcome {
+    /// Lowercase name used in reports: `added`, `updated`, `skipped` or `failed`.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Updated => "updated",
+            Self::Skipped => "skipped",
+            Self::Failed(_) => "failed",
+        }
+    }
+}
+
+/// Add `metadata` as frontmatter to a file without any. A file that already
+/// has frontmatter is merged into with `overwrite` (see
+/// [`update_frontmatter`]) and left alone otherwise.
+pub fn apply_frontmatter(file_path: &Path, metadata: &Metadata, overwrite: bool) -> ApplyOutcome {
+    let has_frontmatter = fs::read_to_string(file_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| parse(&content))
+        .map(|(existing, _)| existing.is_some());
+    let result = match has_frontmatter {
+        Ok(false) => add_frontmatter(file_path, metadata).map(|()| ApplyOutcome::Added),
+        Ok(true) if overwrite => {
+            update_frontmatter(file_path, metadata).map(|()| ApplyOutcome::Updated)
+        }
+        Ok(true) => Ok(ApplyOutcome::Skipped),
+        Err(e) => Err(e),
+    };
+    result.unwrap_or_else(|e| ApplyOutcome::Failed(format!("{e:#}")))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Write;
 
+    #[test]
+    fn test_body_offset() {
+        let content = "---\ndomain: backend\n---\n# Title\n";
+        assert_eq!(&content[body_offset(content)..], "# Title\n");
+        assert_eq!(body_offset("---\nnot: [closed\n---\r\nBody"), 22);
+        assert_eq!(body_offset("# Title\n---\n"), 0);
+        assert_eq!(body_offset("---\nunclosed: true\n"), 0);
+        assert_eq!(body_offset(""), 0);
+    }
+
     #[test]
     fn test_parse_no_frontmatter() {
         let (meta, body) = parse("# Hello\n\nWorld").unwrap();
@@ -206,12 +308,14 @@ mod tests {
             language: "typescript".into(),
             tags: vec!["ui".into(), "react".into()],
             project: "myapp".into(),
+            ..Default::default()
         };
         let fm = generate(&meta);
         assert!(fm.starts_with("---\n"));
         assert!(fm.ends_with("---\n"));
         assert!(fm.contains("domain: frontend"));
-        assert!(fm.contains("tags: [ui, react]"));
+        assert!(fm.contains("docType: spec"));
+        assert!(fm.contains("tags:\n- ui\n- react\n"));
     }
 
     #[test]
@@ -264,7 +368,7 @@ mod tests {
     #[test]
     fn test_update_adds_when_none_exists() {
         let mut temp = tempfile::NamedTempFile::new().unwrap();
-        write!(temp, "# Doc\n").unwrap();
+        writeln!(temp, "# Doc").unwrap();
 
         let meta = Metadata {
             domain: "new".into(),
@@ -276,4 +380,87 @@ mod tests {
         assert!(result.contains("domain: new"));
         assert!(result.contains("# Doc"));
     }
+
+    #[test]
+    fn test_parse_real_yaml() {
+        let content = "---\ndomain: \"auth: tokens\"\ntags:\n  - auth\n  - db\nowner:\n  team: platform\n  oncall: true\nversion: 2\n---\n# Doc\n";
+        let (meta, body) = parse(content).unwrap();
+        let meta = meta.unwrap();
+        assert_eq!(meta.domain, "auth: tokens");
+        assert_eq!(meta.tags, vec!["auth", "db"]);
+        assert_eq!(meta.extra.len(), 2);
+        assert_eq!(meta.extra["owner"]["team"].as_str(), Some("platform"));
+        assert_eq!(body, "# Doc");
+
+        // Comma-separated tags and non-string scalars are still accepted
+        let (meta, _) = parse("---\ntags: a, b\nproject: 42\n---\n").unwrap();
+        let meta = meta.unwrap();
+        assert_eq!(meta.tags, vec!["a", "b"]);
+        assert_eq!(meta.project, "42");
+    }
+
+    #[test]
+    fn test_roundtrip_preserves_unknown_keys() {
+        let content = "---\ndomain: backend\nowner:\n  team: platform\nreviewed: 2024-01-01\n---\n";
+        let (meta, _) = parse(content).unwrap();
+        let meta = meta.unwrap();
+        let (reparsed, _) = parse(&generate(&meta)).unwrap();
+        assert_eq!(reparsed.unwrap(), meta);
+    }
+
+    #[test]
+    fn test_update_preserves_unknown_keys() {
+        let mut temp = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            temp,
+            "---\ndomain: old\nowner:\n  team: platform\nstatus: draft\n---\n# Doc\n"
+        )
+        .unwrap();
+
+        let meta = Metadata {
+            domain: "new".into(),
+            ..Default::default()
+        };
+        update_frontmatter(temp.path(), &meta).unwrap();
+
+        let (meta, body) = parse(&fs::read_to_string(temp.path()).unwrap()).unwrap();
+        let meta = meta.unwrap();
+        assert_eq!(meta.domain, "new");
+        assert_eq!(meta.extra["status"].as_str(), Some("draft"));
+        assert_eq!(meta.extra["owner"]["team"].as_str(), Some("platform"));
+        assert!(body.contains("# Doc"));
+    }
+
+    #[test]
+    fn test_parse_with_bom() {
+        let content = "\u{FEFF}---\ndocType: api\n---\n# Doc\n";
+        let (meta, body) = parse(content).unwrap();
+        assert_eq!(meta.unwrap().doc_type, "api");
+        assert_eq!(body, "# Doc");
+
+        let (meta, body) = parse("\u{FEFF}# No frontmatter").unwrap();
+        assert!(meta.is_none());
+        assert_eq!(body, "# No frontmatter");
+    }
+
+    #[test]
+    fn test_add_frontmatter_with_bom() {
+        let mut temp = tempfile::NamedTempFile::new().unwrap();
+        write!(temp, "\u{FEFF}# Hello").unwrap();
+
+        let meta = Metadata {
+            domain: "backend".into(),
+            ..Default::default()
+        };
+        add_frontmatter(temp.path(), &meta).unwrap();
+
+        let result = fs::read_to_string(temp.path()).unwrap();
+        assert!(result.starts_with("---\n"));
+        assert!(!result.contains('\u{FEFF}'));
+    }
+
+    #[test]
+    fn test_parse_invalid_yaml() {
+        assert!(parse("---\ndomain: [unclosed\n---\n").is_err());
+    }
 }
@@ -0,0 +1,120 @@
+//! Shared HTTP client construction.
+//!
+//! The TLS backend is picked at compile time with the `rustls-tls` (default)
+//! or `native-tls` cargo feature; `native-tls` wins when both are enabled.
+use std::error::Error as _;
+
+#[cfg(not(any(feature = "rustls-tls", feature = "native-tls")))]
+compile_error!("enable the `rustls-tls` or `native-tls` feature");
+
+/// Name of the TLS backend compiled into this binary.
+pub const TLS_BACKEND: &str = if cfg!(feature = "native-tls") {
+    "native-tls"
+} else {
+    "rustls"
+};
+
+/// Phrases in an error chain that indicate a TLS/certificate failure.
+const TLS_ERROR_MARKERS: &[&str] = &["certificate", "tls", "ssl", "handshake", "corrupt message"];
+
+macro_rules! select_tls {
+    ($builder:expr) => {{
+        #[cfg(feature = "native-tls")]
+        let builder = $builder.use_native_tls();
+        #[cfg(not(feature = "native-tls"))]
+        let builder = $builder.use_rustls_tls();
+        builder
+    }};
+}
+
+/// Async client builder using the configured TLS backend.
+pub fn client_builder() -> reqwest::ClientBuilder {
+    select_tls!(reqwest::Client::builder())
+}
+
+/// Blocking client builder using the configured TLS backend.
+pub fn blocking_client_builder() -> reqwest::blocking::ClientBuilder {
+    select_tls!(reqwest::blocking::Client::builder())
+}
+
+/// Render a request error with its full source chain.
+///
+/// reqwest's top-level message ("error sending request for url ...") hides
+/// the cause, so the chain is joined with `: `. TLS failures get a hint
+/// naming the compiled backend and the alternative feature.
+#[must_use]
+pub fn describe_error(err: &reqwest::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+
+    let lower = message.to_lowercase();
+    if TLS_ERROR_MARKERS.iter().any(|m| lower.contains(m)) {
+        let alternative = if cfg!(feature = "native-tls") {
+            "rustls-tls"
+        } else {
+            "native-tls"
+        };
+        format!(
+            "TLS error ({TLS_BACKEND} backend): {message}. Check that the server presents a \
+             valid certificate for this host; if it does, try a build with the \
+             `{alternative}` feature"
+        )
+    } else {
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_describe_error_reports_tls_failure() {
+        // A plain-HTTP server answering an https:// request forces a handshake failure
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+            }
+        });
+
+        let client = blocking_client_builder().build().unwrap();
+        let err = client
+            .get(format!("https://127.0.0.1:{port}/"))
+            .send()
+            .unwrap_err();
+        server.join().unwrap();
+
+        let message = describe_error(&err);
+        assert!(message.starts_with("TLS error"), "{message}");
+        assert!(message.contains(TLS_BACKEND), "{message}");
+    }
+
+    #[test]
+    fn test_describe_error_keeps_plain_errors() {
+        // Nothing listens on a freshly released port
+        let port = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let client = blocking_client_builder().build().unwrap();
+        let err = client
+            .get(format!("http://127.0.0.1:{port}/"))
+            .send()
+            .unwrap_err();
+
+        let message = describe_error(&err);
+        assert!(!message.starts_with("TLS error"), "{message}");
+        assert!(message.contains("127.0.0.1"), "{message}");
+    }
+}
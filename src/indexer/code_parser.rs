@@ -1,6 +1,9 @@
 use super::languages::LanguageConfig;
+use crate::config::Config;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
+use std::ops::Range;
 use std::path::Path;
 use tree_sitter::{Node, Parser, Query, QueryCursor, StreamingIterator};
 
@@ -15,26 +18,246 @@ pub struct CodeChunk {
     pub end_line: usize,
     pub parent_symbol: Option<String>,
     pub signature: String,
+    /// Byte ranges of comment nodes within `content`.
+    pub comment_ranges: Vec<Range<usize>>,
+    /// Leading doc comment or docstring, with comment markers removed.
+    pub doc_comment: Option<String>,
+    /// Byte range of the docstring statement within `content`, for
+    /// languages whose docstrings sit inside the body (Python).
+    pub doc_range: Option<Range<usize>>,
+    /// Index of this piece of a symbol split by `max_code_chunk_tokens`: 0
+    /// for a whole symbol or its first piece, then 1, 2, ...
+    pub part: usize,
 }
 
 impl CodeChunk {
+    /// `"{language} {symbol_name}: {content}"`, with the doc comment put
+    /// before the content when it sits outside the symbol's source (Python
+    /// docstrings are already part of the body).
     pub fn get_embedding_text(&self) -> String {
-        format!("{} {}: {}", self.language, self.symbol_name, self.content)
+        self.embedding_text_with(&self.content)
     }
+
+    /// [`Self::get_embedding_text`] with `content` in place of the chunk's.
+    fn embedding_text_with(&self, content: &str) -> String {
+        let name = self.embedded_symbol_name();
+        match &self.doc_comment {
+            Some(doc) if self.language != "python" => {
+                format!("{} {}: {}\n{}", self.language, name, doc, content)
+            }
+            _ => format!("{} {}: {}", self.language, name, content),
+        }
+    }
+
+    /// Build the embedding text, honoring the code-related config toggles.
+    ///
+    /// With `index_doc_comments` the doc comment gets a chunk of its own, so
+    /// it is left out here, docstring included.
+    pub fn embedding_text_for(&self, config: &Config) -> String {
+        let prose = is_prose_symbol_type(&self.symbol_type);
+        let strip = config.strip_comments_from_code_embedding && !prose;
+        let skip_doc = config.index_doc_comments && self.doc_comment.is_some();
+        let mut removed: Vec<Range<usize>> = Vec::new();
+        if strip {
+            removed.extend(self.comment_ranges.iter().cloned());
+        }
+        if skip_doc {
+            removed.extend(self.doc_range.clone());
+        }
+        let mut content = if removed.is_empty() {
+            Cow::Borrowed(self.content.as_str())
+        } else {
+            removed.sort_by_key(|r| r.start);
+            Cow::Owned(self.content_without(&removed))
+        };
+        if let Some(head) = truncate_at_whitespace(&content, config.max_embed_chars) {
+            tracing::info!(
+                symbol = %self.symbol_name,
+                chars = content.chars().count(),
+                embedded = head.chars().count(),
+                "Truncated chunk content for embedding"
+            );
+            content = Cow::Owned(head.to_string());
+        }
+
+        if prose {
+            // Doc comments and markdown cells are prose; embed them as-is.
+            content.into_owned()
+        } else if strip || skip_doc {
+            format!(
+                "{} {}: {}",
+                self.language,
+                self.embedded_symbol_name(),
+                content
+            )
+        } else {
+            self.embedding_text_with(&content)
+        }
+    }
+
+    /// `symbol_name`, cut to [`MAX_EMBED_SYMBOL_NAME_CHARS`].
+    fn embedded_symbol_name(&self) -> &str {
+        match self
+            .symbol_name
+            .char_indices()
+            .nth(MAX_EMBED_SYMBOL_NAME_CHARS)
+        {
+            Some((idx, _)) => &self.symbol_name[..idx],
+            None => &self.symbol_name,
+        }
+    }
+
+    /// Return `content` with all comment nodes removed.
+    ///
+    /// Lines left empty by the removal are dropped so that long comment blocks
+    /// don't turn into runs of blank lines.
+    pub fn content_without_comments(&self) -> String {
+        self.content_without(&self.comment_ranges)
+    }
+
+    /// `content` with the ascending byte `ranges` removed, dropping lines the
+    /// removal leaves empty.
+    fn content_without(&self, ranges: &[Range<usize>]) -> String {
+        let mut stripped = String::with_capacity(self.content.len());
+        let mut last = 0;
+        for range in ranges {
+            if range.start < last || range.end > self.content.len() {
+                continue;
+            }
+            stripped.push_str(&self.content[last..range.start]);
+            last = range.end;
+        }
+        stripped.push_str(&self.content[last..]);
+
+        stripped
+            .lines()
+            .map(str::trim_end)
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// `symbol_type` used for notebook markdown cells.
+pub const MARKDOWN_CELL_SYMBOL_TYPE: &str = "markdown_cell";
+
+/// Symbol names in embedding text are cut to this many characters.
+pub const MAX_EMBED_SYMBOL_NAME_CHARS: usize = 200;
+
+/// The head of `text` up to `max_chars` characters, ending at the last
+/// whitespace before the limit (or exactly at it when there is none).
+/// `None` when `text` fits or `max_chars` is 0.
+pub fn truncate_at_whitespace(text: &str, max_chars: usize) -> Option<&str> {
+    if max_chars == 0 {
+        return None;
+    }
+    let (limit, _) = text.char_indices().nth(max_chars)?;
+    let head = &text[..limit];
+    let cut = head
+        .rfind(char::is_whitespace)
+        .filter(|&i| !head[..i].trim_end().is_empty())
+        .unwrap_or(limit);
+    Some(head[..cut].trim_end())
+}
+
+/// Whether chunks of `symbol_type` hold prose rather than source code.
+pub fn is_prose_symbol_type(symbol_type: &str) -> bool {
+    symbol_type == MARKDOWN_CELL_SYMBOL_TYPE
+}
+
+/// Whether `path` is a test file by its language's naming conventions:
+/// `*_test.go`, `test_*.py` / `*_test.py`, `*.test.*` / `*.spec.*` for
+/// JS/TS, `*Test.java` / `*Tests.java`, and Rust files under a `tests/`
+/// directory below `root`. Only the part of `path` inside `root` is checked,
+/// so a `tests` directory above the indexed tree doesn't count.
+pub fn is_test_file(path: &Path, root: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let Some((stem, ext)) = name.rsplit_once('.') else {
+        return false;
+    };
+    match ext {
+        "go" => stem.ends_with("_test"),
+        "py" => stem.starts_with("test_") || stem.ends_with("_test"),
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => {
+            stem.ends_with(".test") || stem.ends_with(".spec")
+        }
+        "java" => stem.ends_with("Test") || stem.ends_with("Tests"),
+        "rs" => path
+            .strip_prefix(root)
+            .ok()
+            .and_then(Path::parent)
+            .is_some_and(|dir| dir.components().any(|c| c.as_os_str() == "tests")),
+        _ => false,
+    }
+}
+
+/// Whether a Rust item is test-only: marked `#[test]` (or a runner's
+/// `#[tokio::test]`) or `#[cfg(test)]`, or nested inside such an item.
+fn is_rust_test_item(node: Node, source: &[u8]) -> bool {
+    let mut item = Some(node);
+    while let Some(current) = item {
+        let mut prev = current.prev_sibling();
+        while let Some(sib) = prev {
+            if sib.kind() == "attribute_item" {
+                let attr: String = sib
+                    .utf8_text(source)
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .collect();
+                let inner = attr.trim_start_matches("#[").trim_end_matches(']');
+                let path = inner.split('(').next().unwrap_or_default();
+                if inner == "cfg(test)" || path == "test" || path.ends_with("::test") {
+                    return true;
+                }
+            } else if !sib.kind().contains("comment") {
+                break;
+            }
+            prev = sib.prev_sibling();
+        }
+        item = current.parent();
+    }
+    false
 }
 
 pub struct CodeParser {
     queries: HashMap<String, Query>,
+    exclude_tests: bool,
+    max_chunk_tokens: HashMap<String, usize>,
 }
 
 impl CodeParser {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_languages(None)
+    }
+
+    /// Parser for the languages named in `enabled` only (all when `None`);
+    /// files in other languages are rejected.
+    pub fn with_languages(enabled: Option<&[String]>) -> Result<Self, Box<dyn std::error::Error>> {
         let mut queries = HashMap::new();
-        for config in super::languages::LanguageConfig::get_all() {
+        for config in LanguageConfig::get_enabled(enabled) {
             let query = Query::new(&config.language, config.query)?;
             queries.insert(config.name.to_string(), query);
         }
-        Ok(Self { queries })
+        Ok(Self {
+            queries,
+            exclude_tests: false,
+            max_chunk_tokens: HashMap::new(),
+        })
+    }
+
+    /// Skip test-only symbols (currently Rust `#[test]` functions and
+    /// `#[cfg(test)]` modules) when extracting.
+    pub fn exclude_tests(mut self, exclude: bool) -> Self {
+        self.exclude_tests = exclude;
+        self
+    }
+
+    /// Split symbols whose estimated token count exceeds their language's
+    /// limit into consecutive chunks at statement boundaries. Languages
+    /// without an entry are never split.
+    pub fn max_chunk_tokens(mut self, limits: HashMap<String, usize>) -> Self {
+        self.max_chunk_tokens = limits;
+        self
     }
 
     pub fn parse_file<P: AsRef<Path>>(
@@ -42,9 +265,21 @@ impl CodeParser {
         filepath: P,
     ) -> Result<Vec<CodeChunk>, Box<dyn std::error::Error>> {
         let filepath = filepath.as_ref();
-        let content = fs::read(filepath)?;
-
         let ext = filepath.extension().and_then(|e| e.to_str()).unwrap_or("");
+        self.parse_source(&fs::read(filepath)?, ext)
+    }
+
+    /// Parse the contents of a file with extension `ext`.
+    pub fn parse_source(
+        &mut self,
+        source: &[u8],
+        ext: &str,
+    ) -> Result<Vec<CodeChunk>, Box<dyn std::error::Error>> {
+        #[cfg(feature = "notebook")]
+        if ext == "ipynb" {
+            let notebook = super::notebook::parse_notebook(std::str::from_utf8(source)?)?;
+            return super::notebook::notebook_chunks(self, &notebook);
+        }
 
         let config = match LanguageConfig::get_by_extension(ext) {
             Some(c) => c,
@@ -54,7 +289,7 @@ impl CodeParser {
             }
         };
 
-        self.parse_code(&content, config.name)
+        self.parse_code(source, config.name)
     }
 
     pub fn parse_code(
@@ -63,6 +298,9 @@ impl CodeParser {
         lang_name: &str,
     ) -> Result<Vec<CodeChunk>, Box<dyn std::error::Error>> {
         let config = LanguageConfig::get_by_name(lang_name).ok_or("unsupported language")?;
+        if !self.queries.contains_key(lang_name) {
+            return Err(format!("language not enabled: {lang_name}").into());
+        }
 
         let mut parser = Parser::new();
         parser.set_language(&config.language)?;
@@ -109,29 +347,61 @@ impl CodeParser {
             }
 
             if let Some(node) = main_node {
+                if self.exclude_tests && lang == "rust" && is_rust_test_item(node, source) {
+                    continue;
+                }
                 let start_byte = node.start_byte();
                 let end_byte = node.end_byte();
                 let key = format!("{}-{}-{}", start_byte, end_byte, symbol_type);
                 if seen.insert(key) {
-                    let content = node.utf8_text(source)?.to_string();
-                    let start_line = node.start_position().row + 1;
-                    let end_line = node.end_position().row + 1;
-
-                    let signature = extract_signature(&content, lang);
+                    let content = node.utf8_text(source)?;
+                    let signature = extract_signature(content, lang);
                     let parent_symbol = find_parent_symbol(node, source, lang);
+                    let mut comment_ranges = Vec::new();
+                    collect_comment_ranges(node, start_byte, &mut comment_ranges);
+                    let mut doc_comment = extract_doc_comment(node, source, lang);
+                    let mut doc_range = doc_comment
+                        .as_ref()
+                        .filter(|_| lang == "python")
+                        .and_then(|_| python_docstring(node))
+                        .map(|d| (d.start_byte() - start_byte)..(d.end_byte() - start_byte));
 
-                    chunks.push(CodeChunk {
-                        content,
-                        position,
-                        symbol_name,
-                        symbol_type: symbol_type.clone(),
-                        language: lang.to_string(),
-                        start_line,
-                        end_line,
-                        parent_symbol,
-                        signature,
-                    });
-                    position += 1;
+                    let pieces = match self.max_chunk_tokens.get(lang) {
+                        Some(&max_tokens) => statement_pieces(node, source, max_tokens),
+                        None => vec![node.byte_range()],
+                    };
+                    let mut start_line = node.start_position().row + 1;
+                    for (part, piece) in pieces.into_iter().enumerate() {
+                        let text = std::str::from_utf8(&source[piece.clone()])?;
+                        let content = text.trim_end().to_string();
+                        let end_line = start_line + content.matches('\n').count();
+                        let next_start_line = start_line + text.matches('\n').count();
+                        let rebased = (piece.start - start_byte)..(piece.end - start_byte);
+                        let comment_ranges = comment_ranges
+                            .iter()
+                            .filter(|r| rebased.start <= r.start && r.end <= rebased.end)
+                            .map(|r| (r.start - rebased.start)..(r.end - rebased.start))
+                            .collect();
+
+                        chunks.push(CodeChunk {
+                            content,
+                            position,
+                            symbol_name: symbol_name.clone(),
+                            symbol_type: symbol_type.clone(),
+                            language: lang.to_string(),
+                            start_line,
+                            end_line,
+                            parent_symbol: parent_symbol.clone(),
+                            signature: signature.clone(),
+                            comment_ranges,
+                            // Only the first piece carries the doc comment.
+                            doc_comment: doc_comment.take(),
+                            doc_range: doc_range.take(),
+                            part,
+                        });
+                        position += 1;
+                        start_line = next_start_line;
+                    }
                 }
             }
         }
@@ -140,6 +410,134 @@ impl CodeParser {
     }
 }
 
+/// Rough token count of `text`, erring high (~3 bytes per token).
+fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(3)
+}
+
+/// Byte ranges covering `node` in consecutive pieces of at most `max_tokens`
+/// estimated tokens, cut at the start of the line of a statement in the
+/// node's `body`. A single statement over the limit stays whole, and nodes
+/// without a body are never split.
+fn statement_pieces(node: Node, source: &[u8], max_tokens: usize) -> Vec<Range<usize>> {
+    let tokens = |range: Range<usize>| estimate_tokens(&String::from_utf8_lossy(&source[range]));
+    let whole = node.byte_range();
+    let Some(body) = node.child_by_field_name("body") else {
+        return vec![whole];
+    };
+    if tokens(whole.clone()) <= max_tokens {
+        return vec![whole];
+    }
+
+    let mut pieces = Vec::new();
+    let mut piece_start = whole.start;
+    let mut cursor = body.walk();
+    // Never cut before the first statement, which would leave the
+    // signature on its own.
+    for statement in body.named_children(&mut cursor).skip(1) {
+        let line_start = source[piece_start..statement.start_byte()]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(piece_start, |i| piece_start + i + 1);
+        if line_start > piece_start && tokens(piece_start..statement.end_byte()) > max_tokens {
+            pieces.push(piece_start..line_start);
+            piece_start = line_start;
+        }
+    }
+    pieces.push(piece_start..whole.end);
+    pieces
+}
+
+/// Collect byte ranges (relative to `base`) of all comment nodes under `node`.
+///
+/// Grammars name these `comment` (Go, Python, JS/TS) or `line_comment` /
+/// `block_comment` (Rust).
+fn collect_comment_ranges(node: Node, base: usize, out: &mut Vec<Range<usize>>) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if child.kind().contains("comment") {
+            out.push(child.start_byte() - base..child.end_byte() - base);
+        } else {
+            collect_comment_ranges(child, base, out);
+        }
+    }
+}
+
+/// The statement holding the docstring of a Python function or class: a
+/// string literal as the first statement of its body.
+fn python_docstring(node: Node) -> Option<Node> {
+    let first = node.child_by_field_name("body")?.named_child(0)?;
+    let is_string = first.kind() == "expression_statement"
+        && first.named_child(0).is_some_and(|s| s.kind() == "string");
+    is_string.then_some(first)
+}
+
+/// Extract the doc comment attached to a symbol node.
+///
+/// Python uses the docstring (first string statement of the body); other
+/// languages use the run of comments directly preceding the declaration.
+fn extract_doc_comment(node: Node, source: &[u8], lang: &str) -> Option<String> {
+    let raw = if lang == "python" {
+        python_docstring(node)?
+            .named_child(0)?
+            .utf8_text(source)
+            .ok()?
+            .trim_matches(|c| c == '"' || c == '\'')
+            .to_string()
+    } else {
+        // Exported JS/TS declarations carry their comments on the export node
+        let anchor = match node.parent() {
+            Some(p) if p.kind() == "export_statement" => p,
+            _ => node,
+        };
+        let mut comments = Vec::new();
+        let mut row = anchor.start_position().row;
+        let mut prev = anchor.prev_sibling();
+        while let Some(sib) = prev {
+            // Rust attributes sit between an item and its doc comment
+            let is_attribute = matches!(sib.kind(), "attribute_item" | "inner_attribute_item");
+            if !(is_attribute || sib.kind().contains("comment")) || sib.end_position().row + 1 < row
+            {
+                break;
+            }
+            if !is_attribute {
+                comments.push(sib.utf8_text(source).ok()?);
+            }
+            row = sib.start_position().row;
+            prev = sib.prev_sibling();
+        }
+        if comments.is_empty() {
+            return None;
+        }
+        comments.reverse();
+        comments
+            .iter()
+            .flat_map(|c| c.lines())
+            .map(strip_comment_markers)
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let doc = raw
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string();
+    if doc.is_empty() { None } else { Some(doc) }
+}
+
+fn strip_comment_markers(line: &str) -> &str {
+    let line = line.trim();
+    let line = line.strip_suffix("*/").unwrap_or(line);
+    ["///", "//!", "//", "/**", "/*", "*", "#"]
+        .iter()
+        .find_map(|m| line.strip_prefix(m))
+        .unwrap_or(line)
+        .trim()
+}
+
 fn extract_signature(content: &str, lang: &str) -> String {
     let content = content.trim();
     match lang {
@@ -210,27 +608,35 @@ fn find_parent_symbol(node: Node, source: &[u8], lang: &str) -> Option<String> {
             "typescript" | "javascript" => kind == "class_declaration",
             "php" => false, // PHP not supported; kept for future extension
             "rust" => kind == "impl_item" || kind == "struct_item" || kind == "trait_item",
+            "java" => matches!(
+                kind,
+                "class_declaration"
+                    | "interface_declaration"
+                    | "enum_declaration"
+                    | "record_declaration"
+            ),
+            "c" => kind == "struct_specifier",
+            "cpp" => kind == "class_specifier" || kind == "struct_specifier",
             _ => false,
         };
 
         if is_class_like {
             if lang == "rust" && kind == "impl_item" {
-                if let Some(type_node) = p.child_by_field_name("type") {
-                    if let Ok(name) = type_node.utf8_text(source) {
-                        return Some(name.to_string());
-                    }
+                if let Some(type_node) = p.child_by_field_name("type")
+                    && let Ok(name) = type_node.utf8_text(source)
+                {
+                    return Some(name.to_string());
                 }
             } else {
                 let mut cursor = p.walk();
                 for child in p.children(&mut cursor) {
                     let child_kind = child.kind();
-                    if child_kind.contains("identifier")
+                    if (child_kind.contains("identifier")
                         || child_kind == "type_identifier"
-                        || child_kind == "name"
+                        || child_kind == "name")
+                        && let Ok(name) = child.utf8_text(source)
                     {
-                        if let Ok(name) = child.utf8_text(source) {
-                            return Some(name.to_string());
-                        }
+                        return Some(name.to_string());
                     }
                 }
             }
@@ -244,6 +650,20 @@ fn find_parent_symbol(node: Node, source: &[u8], lang: &str) -> Option<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parser_rejects_languages_not_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let rs = dir.path().join("lib.rs");
+        let py = dir.path().join("app.py");
+        fs::write(&rs, "fn main() {}\n").unwrap();
+        fs::write(&py, "def main():\n    pass\n").unwrap();
+
+        let mut parser = CodeParser::with_languages(Some(&["rust".to_string()])).unwrap();
+        assert_eq!(parser.parse_file(&rs).unwrap().len(), 1);
+        let err = parser.parse_file(&py).unwrap_err();
+        assert!(err.to_string().contains("not enabled"));
+    }
+
     #[test]
     fn test_parse_rust_code() {
         let mut parser = CodeParser::new().expect("Failed to initialize CodeParser");
@@ -289,6 +709,268 @@ mod tests {
         assert!(found_function, "Should find my_function");
     }
 
+    #[test]
+    fn test_embedding_text_strips_comments() {
+        let mut parser = CodeParser::new().expect("Failed to initialize CodeParser");
+        let source_code = r#"
+fn heavily_commented(a: i32) -> i32 {
+    // Copyright notice that should not dominate the embedding
+    // Licensed under some very long license text
+    let b = a + 1; // trailing remark
+    /* block comment
+       spanning lines */
+    b * 2
+}
+"#;
+
+        let chunks = parser
+            .parse_code(source_code.as_bytes(), "rust")
+            .expect("Failed to parse Rust code");
+        let chunk = chunks
+            .iter()
+            .find(|c| c.symbol_name == "heavily_commented")
+            .expect("Should find heavily_commented");
+
+        let config = Config {
+            strip_comments_from_code_embedding: true,
+            ..Default::default()
+        };
+        let text = chunk.embedding_text_for(&config);
+        assert!(!text.contains("Copyright"));
+        assert!(!text.contains("trailing remark"));
+        assert!(!text.contains("block comment"));
+        assert!(text.contains("let b = a + 1;"));
+        assert!(text.contains("b * 2"));
+
+        // Stored content keeps the comments
+        assert!(chunk.content.contains("Copyright"));
+        // Default config embeds the full content
+        assert!(
+            chunk
+                .embedding_text_for(&Config::default())
+                .contains("Copyright")
+        );
+    }
+
+    #[test]
+    fn test_max_embed_chars_truncates_embedding_text_only() {
+        let mut parser = CodeParser::new().expect("Failed to initialize CodeParser");
+        let body: String = (0..500).map(|i| format!("    let v{i} = {i};\n")).collect();
+        let source_code = format!("fn generated() {{\n{body}    tail_marker();\n}}\n");
+
+        let chunks = parser
+            .parse_code(source_code.as_bytes(), "rust")
+            .expect("Failed to parse Rust code");
+        let chunk = chunks
+            .iter()
+            .find(|c| c.symbol_name == "generated")
+            .expect("Should find generated");
+
+        let config = Config {
+            max_embed_chars: 100,
+            ..Default::default()
+        };
+        let text = chunk.embedding_text_for(&config);
+        assert!(text.starts_with("rust generated: fn generated() {"));
+        assert!(!text.contains("tail_marker"));
+        let embedded = text.strip_prefix("rust generated: ").unwrap();
+        assert!(embedded.chars().count() <= 100);
+        // Cut at a whitespace boundary of the content
+        let rest = chunk.content.strip_prefix(embedded).unwrap();
+        assert!(rest.starts_with(char::is_whitespace));
+
+        // The stored content and the default embedding text stay complete
+        assert!(chunk.content.contains("tail_marker"));
+        assert!(
+            chunk
+                .embedding_text_for(&Config::default())
+                .contains("tail_marker")
+        );
+    }
+
+    #[test]
+    fn test_truncate_at_whitespace() {
+        assert_eq!(truncate_at_whitespace("short", 10), None);
+        assert_eq!(truncate_at_whitespace("short", 0), None);
+        assert_eq!(truncate_at_whitespace("one two three", 9), Some("one two"));
+        assert_eq!(truncate_at_whitespace("onetwothree", 6), Some("onetwo"));
+        assert_eq!(truncate_at_whitespace("数据 库连接", 4), Some("数据"));
+    }
+
+    #[test]
+    fn test_oversized_symbol_split_at_statements() {
+        let body: String = (0..300).map(|i| format!("    let v{i} = {i};\n")).collect();
+        let source_code = format!("/// Huge.\nfn generated() {{\n{body}}}\n\nfn small() {{}}\n");
+        let limits = HashMap::from([("rust".to_string(), 200)]);
+        let mut parser = CodeParser::new().unwrap().max_chunk_tokens(limits);
+
+        let chunks = parser.parse_code(source_code.as_bytes(), "rust").unwrap();
+        let (pieces, rest): (Vec<_>, Vec<_>) =
+            chunks.iter().partition(|c| c.symbol_name == "generated");
+        assert!(pieces.len() > 1);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].position, pieces.len());
+        assert_eq!(rest[0].part, 0);
+        assert!(
+            pieces[0]
+                .content
+                .starts_with("fn generated() {\n    let v0 = 0;")
+        );
+        assert!(pieces.last().unwrap().content.ends_with('}'));
+        assert_eq!(pieces[0].doc_comment.as_deref(), Some("Huge."));
+        assert_eq!(pieces[0].start_line, 2);
+        for (i, pair) in pieces.windows(2).enumerate() {
+            assert_eq!(pair[0].position, i);
+            assert_eq!(pair[1].part, i + 1);
+            assert_eq!(pair[1].start_line, pair[0].end_line + 1);
+            assert!(pair[1].content.starts_with("    let v"));
+            assert!(pair[1].doc_comment.is_none());
+        }
+        assert_eq!(pieces.last().unwrap().end_line, 303);
+        let rejoined: String = pieces.iter().map(|c| format!("{}\n", c.content)).collect();
+        assert!(source_code.contains(&rejoined));
+
+        // Without a limit the symbol stays whole
+        let mut parser = CodeParser::new().unwrap();
+        let chunks = parser.parse_code(source_code.as_bytes(), "rust").unwrap();
+        assert_eq!(chunks.len(), 2);
+    }
+
+    #[test]
+    fn test_python_docstring_embedded_once() {
+        let mut parser = CodeParser::new().expect("Failed to initialize CodeParser");
+        let source_code = r#"
+def fetch_rates(currency):
+    """Fetch the latest exchange rates for a currency.
+
+    Results are cached for one hour.
+    """
+    return client.get(currency)
+"#;
+
+        let chunks = parser
+            .parse_code(source_code.as_bytes(), "python")
+            .expect("Failed to parse Python code");
+        let func = chunks
+            .iter()
+            .find(|c| c.symbol_name == "fetch_rates")
+            .expect("Should find fetch_rates");
+        assert_eq!(
+            func.doc_comment.as_deref(),
+            Some(
+                "Fetch the latest exchange rates for a currency.\n\nResults are cached for one hour."
+            )
+        );
+
+        let inline = func.embedding_text_for(&Config::default());
+        assert!(inline.contains("Fetch the latest exchange rates"));
+
+        // With its own chunk, the docstring is cut from the symbol's text
+        let config = Config {
+            index_doc_comments: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            func.embedding_text_for(&config),
+            "python fetch_rates: def fetch_rates(currency):\n    return client.get(currency)"
+        );
+    }
+
+    #[test]
+    fn test_rust_doc_comment_extracted() {
+        let mut parser = CodeParser::new().expect("Failed to initialize CodeParser");
+        let source_code = "/// Adds one.\n/// Never overflows.\nfn add_one(x: u8) -> u8 { x.saturating_add(1) }\n";
+
+        let chunks = parser
+            .parse_code(source_code.as_bytes(), "rust")
+            .expect("Failed to parse Rust code");
+        let func = chunks
+            .iter()
+            .find(|c| c.symbol_name == "add_one")
+            .expect("Should find add_one");
+        assert_eq!(
+            func.doc_comment.as_deref(),
+            Some("Adds one.\nNever overflows.")
+        );
+        assert!(
+            func.get_embedding_text()
+                .starts_with("rust add_one: Adds one.\nNever overflows.\nfn add_one")
+        );
+    }
+
+    #[test]
+    fn test_rust_doc_comment_before_attributes() {
+        let mut parser = CodeParser::new().expect("Failed to initialize CodeParser");
+        let source_code = "/// A point.\n#[derive(Debug)]\nstruct Point { x: i32 }\n\n/** Doubles it. */\n#[must_use]\n#[inline]\nfn double(x: i32) -> i32 { x * 2 }\n";
+
+        let chunks = parser
+            .parse_code(source_code.as_bytes(), "rust")
+            .expect("Failed to parse Rust code");
+        let doc = |name: &str| {
+            chunks
+                .iter()
+                .find(|c| c.symbol_name == name)
+                .and_then(|c| c.doc_comment.clone())
+        };
+        assert_eq!(doc("Point").as_deref(), Some("A point."));
+        assert_eq!(doc("double").as_deref(), Some("Doubles it."));
+    }
+
+    #[test]
+    fn test_is_test_file() {
+        for name in [
+            "pkg/rates_test.go",
+            "app/test_rates.py",
+            "app/rates_test.py",
+            "web/rates.spec.ts",
+            "web/rates.test.js",
+            "src/RatesTest.java",
+            "crate/tests/rates.rs",
+        ] {
+            assert!(is_test_file(Path::new(name), Path::new("")), "{name}");
+        }
+        for name in [
+            "pkg/rates.go",
+            "app/testing.py",
+            "web/rates.ts",
+            "src/Testable.java",
+            "crate/src/rates.rs",
+            "docs/test_plan.md",
+        ] {
+            assert!(!is_test_file(Path::new(name), Path::new("")), "{name}");
+        }
+        // Only the path below the indexed root counts
+        let root = Path::new("/home/me/tests/project");
+        assert!(!is_test_file(&root.join("src/rates.rs"), root));
+        assert!(is_test_file(&root.join("tests/rates.rs"), root));
+    }
+
+    #[test]
+    fn test_exclude_rust_test_items() {
+        let source_code = r#"
+fn rate() -> u32 { 1 }
+
+#[test]
+fn rate_is_one() { assert_eq!(rate(), 1); }
+
+#[cfg(test)]
+mod tests {
+    fn helper() {}
+}
+"#;
+        let names = |exclude| {
+            let mut parser = CodeParser::new().unwrap().exclude_tests(exclude);
+            parser
+                .parse_code(source_code.as_bytes(), "rust")
+                .unwrap()
+                .into_iter()
+                .map(|c| c.symbol_name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(false), ["rate", "rate_is_one", "tests", "helper"]);
+        assert_eq!(names(true), ["rate"]);
+    }
+
     #[test]
     fn test_parse_python_code() {
         let mut parser = CodeParser::new().expect("Failed to initialize CodeParser");
@@ -328,4 +1010,108 @@ def my_function():
         assert!(found_method, "Should find my_method under MyClass");
         assert!(found_function, "Should find my_function");
     }
+
+    fn symbols(chunks: &[CodeChunk]) -> Vec<(&str, &str, Option<&str>)> {
+        chunks
+            .iter()
+            .map(|c| {
+                (
+                    c.symbol_name.as_str(),
+                    c.symbol_type.as_str(),
+                    c.parent_symbol.as_deref(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_java_code() {
+        let mut parser = CodeParser::new().expect("Failed to initialize CodeParser");
+        let source_code = r#"
+public class Greeter implements Runnable {
+    public Greeter() {}
+
+    public void run() {
+        System.out.println("Hello");
+    }
+}
+
+interface Shape {
+    double area();
+}
+        "#;
+
+        let chunks = parser
+            .parse_code(source_code.as_bytes(), "java")
+            .expect("Failed to parse Java code");
+        let found = symbols(&chunks);
+        assert!(found.contains(&("Greeter", "class", None)), "{found:?}");
+        assert!(
+            found.contains(&("run", "method", Some("Greeter"))),
+            "{found:?}"
+        );
+        assert!(found.contains(&("Shape", "interface", None)), "{found:?}");
+    }
+
+    #[test]
+    fn test_parse_c_code() {
+        let mut parser = CodeParser::new().expect("Failed to initialize CodeParser");
+        let source_code = r#"
+#include <stdio.h>
+
+struct point {
+    int x;
+    int y;
+};
+
+int add(int a, int b) {
+    return a + b;
+}
+
+char *name(void) {
+    return "c";
+}
+        "#;
+
+        let chunks = parser
+            .parse_code(source_code.as_bytes(), "c")
+            .expect("Failed to parse C code");
+        let found = symbols(&chunks);
+        assert!(found.contains(&("point", "struct", None)), "{found:?}");
+        assert!(found.contains(&("add", "function", None)), "{found:?}");
+        assert!(found.contains(&("name", "function", None)), "{found:?}");
+        let add = chunks.iter().find(|c| c.symbol_name == "add").unwrap();
+        assert_eq!(add.signature, "int add(int a, int b)");
+    }
+
+    #[test]
+    fn test_parse_cpp_code() {
+        let mut parser = CodeParser::new().expect("Failed to initialize CodeParser");
+        let source_code = r#"
+class Widget : public Base {
+public:
+    void draw() {
+        render();
+    }
+};
+
+void Widget::resize(int w) {}
+
+int main() {
+    return 0;
+}
+        "#;
+
+        let chunks = parser
+            .parse_code(source_code.as_bytes(), "cpp")
+            .expect("Failed to parse C++ code");
+        let found = symbols(&chunks);
+        assert!(found.contains(&("Widget", "class", None)), "{found:?}");
+        assert!(
+            found.contains(&("draw", "method", Some("Widget"))),
+            "{found:?}"
+        );
+        assert!(found.contains(&("resize", "method", None)), "{found:?}");
+        assert!(found.contains(&("main", "function", None)), "{found:?}");
+    }
 }
@@ -1,12 +1,44 @@
-use crate::config::Config;
+use crate::config::{ChunkStrategy, Config};
 use crate::db::Db;
 use crate::embedder::Embedder;
+use crate::indexer::code_parser::{CodeChunk, is_test_file, truncate_at_whitespace};
+use crate::indexer::content_hash::{file_content_hash, sha256_hex};
 use crate::indexer::markdown;
+use crate::indexer::relations::RelationExtractor;
 use chrono::{DateTime, Utc};
 use ignore::WalkBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use ignore::overrides::OverrideBuilder;
-use std::path::Path;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Weak};
+
+/// Locks of the documents currently being indexed, keyed by stored filename.
+/// Entries are dropped once no one holds or waits for them.
+static FILE_LOCKS: LazyLock<std::sync::Mutex<HashMap<String, Weak<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(Default::default);
+
+/// Wait for exclusive access to index `filename` (a normalized document path).
+///
+/// Background sync and explicit `index` calls both go through this, so two
+/// writers never race on the same document's upsert.
+pub async fn lock_file(filename: &str) -> tokio::sync::OwnedMutexGuard<()> {
+    let lock = {
+        let mut locks = FILE_LOCKS
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        locks.retain(|_, lock| lock.strong_count() > 0);
+        match locks.get(filename).and_then(Weak::upgrade) {
+            Some(lock) => lock,
+            None => {
+                let lock = Arc::new(tokio::sync::Mutex::new(()));
+                locks.insert(filename.to_string(), Arc::downgrade(&lock));
+                lock
+            }
+        }
+    };
+    lock.lock_owned().await
+}
 
 /// Normalizes a path to absolute format, stripping Windows UNC prefixes.
 pub fn normalize_system_path(path: &Path) -> String {
@@ -20,6 +52,35 @@ pub fn normalize_system_path(path: &Path) -> String {
     s.replace('\\', "/")
 }
 
+/// Prefix matching every document under the normalized directory `dir_str`.
+///
+/// The trailing slash keeps sibling roots apart (`/p/a` must not match `/p/ab`).
+fn dir_prefix(dir_str: &str) -> String {
+    if dir_str.ends_with('/') {
+        dir_str.to_string()
+    } else {
+        format!("{dir_str}/")
+    }
+}
+
+/// Average line length above which a file is treated as minified.
+const MINIFIED_AVG_LINE_LEN: usize = 300;
+
+/// Lines at the start of a file searched for a generated-code marker.
+const GENERATED_MARKER_LINES: usize = 5;
+
+/// Whether `content` looks generated or minified: it carries a `@generated`
+/// or Go-style "Code generated ... DO NOT EDIT." marker near the top, or its
+/// non-blank lines are very long on average.
+pub fn is_generated_content(content: &str) -> bool {
+    let marked = content.lines().take(GENERATED_MARKER_LINES).any(|line| {
+        line.contains("@generated")
+            || (line.contains("Code generated") && line.contains("DO NOT EDIT"))
+    });
+    let lines = content.lines().filter(|l| !l.trim().is_empty()).count();
+    marked || (lines > 0 && content.len() / lines > MINIFIED_AVG_LINE_LEN)
+}
+
 /// File type classification for routing to the appropriate indexer.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FileType {
@@ -33,23 +94,415 @@ pub enum FileType {
 pub fn classify_extension(ext: &str) -> Option<FileType> {
     match ext {
         "md" => Some(FileType::Markdown),
-        "rs" | "go" | "py" | "js" | "ts" | "jsx" | "tsx" => Some(FileType::Code),
+        "rs" | "go" | "py" | "js" | "ts" | "jsx" | "tsx" | "java" | "c" | "h" | "cpp" | "cc"
+        | "cxx" | "hpp" | "hh" | "hxx" => Some(FileType::Code),
+        // Notebooks are split into prose and code chunks by the code parser
+        #[cfg(feature = "notebook")]
+        "ipynb" => Some(FileType::Code),
         "txt" | "log" | "json" | "yaml" | "yml" | "toml" | "csv" | "html" | "htm" | "pdf"
         | "docx" | "xls" | "xlsx" | "xlsb" | "ods" => Some(FileType::Text),
         _ => None,
     }
 }
 
+/// Classify a file extension under `config`: extensions listed in
+/// `document_extensions` are chunked like markdown, the rest fall back to
+/// [`classify_extension`].
+pub fn classify_file(ext: &str, config: &Config) -> Option<FileType> {
+    if config.is_document_extension(ext) {
+        Some(FileType::Markdown)
+    } else {
+        classify_extension(ext)
+    }
+}
+
+/// With `config.dedup_documents_by_hash`, store `db_path` as an alias of an
+/// indexed document with the same content hash instead of embedding it
+/// again (see [`Db::set_document_alias`]). Returns the original's filename
+/// when it did; on any error the file is left to be indexed normally.
+pub fn alias_duplicate(
+    db: &Db,
+    config: &Config,
+    content_hash: &str,
+    db_path: &str,
+    mod_time: DateTime<Utc>,
+) -> Option<String> {
+    if !config.dedup_documents_by_hash {
+        return None;
+    }
+    let (original_id, original) = db
+        .find_document_by_content_hash(content_hash, db_path)
+        .ok()
+        .flatten()?;
+    match db.set_document_alias(db_path, mod_time, content_hash, original_id) {
+        Ok(()) => {
+            tracing::info!("{db_path} has the same content as {original}; stored as an alias");
+            Some(original)
+        }
+        Err(e) => {
+            tracing::warn!("Failed to store {db_path} as an alias of {original}: {e}");
+            None
+        }
+    }
+}
+
+/// Frontmatter `docType` of a markdown file's `content`, if it declares one.
+pub fn markdown_doc_type(content: &str) -> Option<String> {
+    let (metadata, _) = crate::frontmatter::parse(content).ok()?;
+    metadata.map(|m| m.doc_type).filter(|t| !t.is_empty())
+}
+
+/// Title of a markdown file's `content`: its frontmatter `title:`, else
+/// (with `title_from_h1`) its first `# Heading`.
+pub fn markdown_title(content: &str, config: &Config) -> Option<String> {
+    let content = markdown::strip_bom(content);
+    let from_frontmatter = crate::frontmatter::parse(content)
+        .ok()
+        .and_then(|(metadata, _)| metadata?.title());
+    from_frontmatter.or_else(|| {
+        config
+            .title_from_h1
+            .then(|| markdown::first_h1(content))
+            .flatten()
+    })
+}
+
+/// Store a markdown document's prose `chunks` followed by its fenced
+/// `code_blocks`; `vectors` covers both, in that order. `content_hash` is
+/// the SHA-256 of the file content they were parsed from. With
+/// `config.fence_relations`, relations found in each block are recorded with the
+/// block's chunk as their source.
+#[allow(clippy::too_many_arguments)]
+pub fn store_markdown_document(
+    db: &Db,
+    db_path: &str,
+    mod_time: DateTime<Utc>,
+    content_hash: Option<&str>,
+    chunks: &[markdown::Chunk],
+    code_blocks: &[CodeChunk],
+    vectors: &[Vec<f32>],
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db_chunks: Vec<crate::db::models::Chunk> = chunks
+        .iter()
+        .map(|c| crate::db::models::Chunk {
+            position: c.position,
+            content: c.content.as_str(),
+            start_line: c.start_line,
+            end_line: c.end_line,
+        })
+        .collect();
+    if code_blocks.is_empty() {
+        db.insert_document(db_path, mod_time, content_hash, &db_chunks, vectors)?;
+        return Ok(());
+    }
+
+    let db_blocks: Vec<crate::db::models::CodeChunk> = code_blocks
+        .iter()
+        .enumerate()
+        .map(|(i, c)| crate::db::models::CodeChunk {
+            chunk: crate::db::models::Chunk {
+                position: chunks.len() + i,
+                content: &c.content,
+                start_line: Some(c.start_line),
+                end_line: Some(c.end_line),
+            },
+            symbol_name: Some(c.symbol_name.as_str()),
+            symbol_type: &c.symbol_type,
+            language: &c.language,
+            start_line: Some(c.start_line),
+            end_line: Some(c.end_line),
+            parent_symbol: None,
+            signature: None,
+            doc_comment: None,
+            part: c.part,
+        })
+        .collect();
+    let block_ids = db.insert_markdown_document(
+        db_path,
+        mod_time,
+        content_hash,
+        &db_chunks,
+        &db_blocks,
+        vectors,
+    )?;
+
+    if config.fence_relations {
+        let extractor = RelationExtractor::with_languages(config.enabled_languages.as_deref())?;
+        let mut relations = Vec::new();
+        for (block, chunk_id) in code_blocks.iter().zip(block_ids) {
+            let found = extractor.extract_relations(
+                block.content.as_bytes(),
+                &block.language,
+                db_path,
+                &block.symbol_name,
+            )?;
+            relations.extend(found.into_iter().map(|r| db_relation(r, chunk_id)));
+        }
+        db.insert_relations(&relations)?;
+    }
+    Ok(())
+}
+
+/// With `index_doc_comments`, the doc comments of `chunks` as prose chunks
+/// positioned after them, each linked to the symbol it documents.
+pub fn doc_chunks<'a>(
+    config: &Config,
+    chunks: &'a [CodeChunk],
+) -> Vec<crate::db::models::DocChunk<'a>> {
+    if !config.index_doc_comments {
+        return Vec::new();
+    }
+    chunks
+        .iter()
+        .enumerate()
+        .filter_map(|(owner, c)| Some((owner, c, c.doc_comment.as_deref()?)))
+        .enumerate()
+        .map(|(i, (owner, c, doc))| crate::db::models::DocChunk {
+            owner,
+            chunk: crate::db::models::Chunk {
+                position: chunks.len() + i,
+                content: doc,
+                start_line: Some(c.start_line),
+                end_line: Some(c.end_line),
+            },
+        })
+        .collect()
+}
+
+/// Embedding text of a doc comment chunk from [`doc_chunks`]: the prose as-is,
+/// cut to `max_embed_chars`.
+pub fn doc_chunk_embedding_text(config: &Config, doc: &str) -> String {
+    let doc = truncate_at_whitespace(doc, config.max_embed_chars).unwrap_or(doc);
+    config.passage_text(doc)
+}
+
+/// Relations of the parsed symbols `chunks` of a code file, each stored
+/// against its chunk in `chunk_ids` (same order).
+///
+/// Calls, inheritance and imports inside a symbol are read from the symbol's
+/// own source. Imports outside every symbol (the usual top-of-file `use` or
+/// `import` lines) are read from the whole file `source` and recorded
+/// against the file's first chunk, since every relation needs a source chunk.
+pub fn code_relations(
+    config: &Config,
+    source: &[u8],
+    db_path: &str,
+    chunks: &[CodeChunk],
+    chunk_ids: &[i64],
+) -> Result<Vec<crate::db::models::CodeRelation>, Box<dyn std::error::Error>> {
+    use crate::indexer::relations::RelationType;
+
+    let extractor = RelationExtractor::with_languages(config.enabled_languages.as_deref())?;
+    let mut relations = Vec::new();
+    let symbols = chunks.iter().zip(chunk_ids);
+    for (chunk, &chunk_id) in symbols.clone() {
+        let found = extractor.extract_relations(
+            chunk.content.as_bytes(),
+            &chunk.language,
+            db_path,
+            &chunk.symbol_name,
+        )?;
+        relations.extend(found.into_iter().map(|r| db_relation(r, chunk_id)));
+    }
+
+    let Some((first, &first_id)) = symbols.clone().next() else {
+        return Ok(relations);
+    };
+    let in_symbol = |line: usize| {
+        symbols
+            .clone()
+            .any(|(c, _)| (c.start_line..=c.end_line).contains(&line))
+    };
+    let file_imports = extractor
+        .extract_relations(source, &first.language, db_path, &first.symbol_name)?
+        .into_iter()
+        .filter(|r| r.relation_type == RelationType::Imports && !in_symbol(r.source_line));
+    relations.extend(file_imports.map(|r| db_relation(r, first_id)));
+    Ok(relations)
+}
+
+/// A relation found by [`RelationExtractor`], as stored for `source_chunk_id`.
+fn db_relation(
+    relation: crate::indexer::relations::CodeRelation,
+    source_chunk_id: i64,
+) -> crate::db::models::CodeRelation {
+    crate::db::models::CodeRelation {
+        id: 0,
+        source_chunk_id,
+        target_chunk_id: None,
+        relation_type: relation.relation_type.as_str().to_string(),
+        target_name: relation.target_name,
+        target_file: None,
+        confidence: 1.0,
+        source_name: None,
+        source_file: None,
+    }
+}
+
+/// Build the directory walker used for indexing. A path is skipped when any
+/// of these exclude it:
+///
+/// 1. `exclude_patterns` (globs, checked first and never re-included)
+/// 2. `.gitignore` files and the configured `ignore_patterns`
+/// 3. a leading dot in its name, unless `include_hidden` is set
+/// 4. a test file name (see [`is_test_file`]), if `exclude_test_code` is set
+fn build_walker(config: &Config, dir: &Path) -> ignore::Walk {
+    let mut overrides = OverrideBuilder::new(dir);
+    for pattern in &config.exclude_patterns {
+        let _ = overrides.add(&format!("!{}", pattern));
+    }
+    let override_matcher = overrides
+        .build()
+        .unwrap_or_else(|_| OverrideBuilder::new(dir).build().unwrap());
+
+    let custom = custom_ignore(config, dir);
+    let exclude_tests = config.exclude_test_code;
+    let root = dir.to_path_buf();
+    // Walk builder respects .gitignore by default
+    WalkBuilder::new(dir)
+        .hidden(!config.include_hidden)
+        .overrides(override_matcher)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            if exclude_tests && !is_dir && is_test_file(entry.path(), &root) {
+                return false;
+            }
+            !custom
+                .matched_path_or_any_parents(entry.path(), is_dir)
+                .is_ignore()
+        })
+        .build()
+}
+
+/// Matcher for the configured `ignore_patterns`, relative to `root`.
+///
+/// Patterns use `.gitignore` syntax; a `!pattern` re-includes paths excluded
+/// by an earlier ignore pattern but not by a `.gitignore` file. Invalid
+/// patterns are logged and skipped.
+fn custom_ignore(config: &Config, root: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in &config.ignore_patterns {
+        if let Err(e) = builder.add_line(None, pattern) {
+            tracing::warn!("Ignoring invalid ignore pattern {:?}: {}", pattern, e);
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        tracing::warn!("Failed to build ignore patterns: {}", e);
+        Gitignore::empty()
+    })
+}
+
+/// Whether any component of `path` below `root` starts with a dot.
+#[cfg(feature = "git")]
+fn is_hidden_below(root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root).is_ok_and(|rel| {
+        rel.components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+    })
+}
+
+/// Markdown files (`.md`, `.markdown`) under `dir`, sorted, skipping what
+/// indexing would skip (see `build_walker`).
+pub fn markdown_files(config: &Config, dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = build_walker(config, dir)
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        .map(ignore::DirEntry::into_path)
+        .filter(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e == "md" || e == "markdown")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// How many of a directory's indexable files are present in the index.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    pub total_supported: usize,
+    pub indexed: usize,
+    /// Supported files with no matching document, sorted.
+    pub missing: Vec<String>,
+}
+
+/// Compare the indexable files under `dir` against the indexed documents.
+pub fn directory_coverage(
+    db: &Db,
+    config: &Config,
+    dir: &Path,
+) -> Result<CoverageReport, Box<dyn std::error::Error>> {
+    let indexed_docs = db.list_documents()?;
+    let mut report = CoverageReport::default();
+
+    for entry in build_walker(config, dir).filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            continue;
+        }
+        let ext = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        if !config.is_file_extension_supported(ext) {
+            continue;
+        }
+
+        report.total_supported += 1;
+        let path_str = normalize_system_path(path);
+        if indexed_docs.contains_key(&path_str) {
+            report.indexed += 1;
+        } else {
+            report.missing.push(path_str);
+        }
+    }
+
+    report.missing.sort();
+    Ok(report)
+}
+
 #[derive(Debug, Default, PartialEq, Eq)]
 pub struct CodeSyncResult {
     pub indexed: usize,
     pub skipped: usize,
+    /// Files left out because they look generated or minified.
+    pub generated: usize,
+    /// Files left out for exceeding `max_file_size_bytes`.
+    pub skipped_large: usize,
     pub failed: usize,
     pub added: usize,
     pub updated: usize,
     pub removed: usize,
 }
 
+/// Running counts reported by [`Indexer::index_directory_with_progress`]
+/// after each file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IndexProgress {
+    /// Normalized path of the file just processed.
+    pub path: String,
+    pub processed: usize,
+    /// Supported files found in the directory walk.
+    pub total: usize,
+    pub added: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// Outcome of [`Indexer::reindex_all`] and [`Indexer::reindex_matching`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReindexAllResult {
+    /// Documents reindexed from disk.
+    pub succeeded: Vec<String>,
+    /// Documents that exist on disk but could not be reindexed.
+    pub failed: Vec<String>,
+    /// Documents whose file no longer exists; removed from the index.
+    pub missing: Vec<String>,
+}
+
 pub struct Indexer<'a, E: Embedder + ?Sized> {
     pub db: Arc<Db>,
     pub embedder: &'a E,
@@ -72,11 +525,33 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
         self.config.is_file_extension_supported(ext)
     }
 
-    /// Indexes all supported files in a directory with differential sync
+    /// Indexes all supported files in a directory with differential sync.
+    ///
+    /// With `prune`, documents under `dir` whose file wasn't found by the walk
+    /// (deleted, or now excluded) are removed and counted in `removed`.
+    ///
+    /// With `dry_run`, nothing is embedded or written: the result counts what
+    /// a real sync would add, update, skip and remove.
     pub async fn index_directory<P: AsRef<Path>>(
         &mut self,
         dir: P,
         force: bool,
+        prune: bool,
+        dry_run: bool,
+    ) -> Result<CodeSyncResult, Box<dyn std::error::Error>> {
+        self.index_directory_with_progress(dir, force, prune, dry_run, None)
+            .await
+    }
+
+    /// [`Indexer::index_directory`], calling `on_progress` after each file
+    /// with the file's path and the running counts.
+    pub async fn index_directory_with_progress<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        force: bool,
+        prune: bool,
+        dry_run: bool,
+        on_progress: Option<&(dyn Fn(&IndexProgress) + Sync)>,
     ) -> Result<CodeSyncResult, Box<dyn std::error::Error>> {
         let dir = dir.as_ref();
         let dir_str = normalize_system_path(dir);
@@ -85,20 +560,32 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
         std::hash::Hash::hash(&self.config.exclude_patterns, &mut hasher);
         std::hash::Hash::hash(&self.config.file_extensions, &mut hasher);
+        // Only hashed when changed from the defaults, so existing indexes
+        // aren't purged by an upgrade
+        if !self.config.ignore_patterns.is_empty() || !self.config.include_hidden {
+            std::hash::Hash::hash(&self.config.ignore_patterns, &mut hasher);
+            std::hash::Hash::hash(&self.config.include_hidden, &mut hasher);
+        }
+        if self.config.exclude_test_code {
+            std::hash::Hash::hash(&self.config.exclude_test_code, &mut hasher);
+        }
+        // Switching chunkers would leave old and new chunks mixed
+        if self.config.chunk_strategy != ChunkStrategy::Size {
+            std::hash::Hash::hash(&self.config.chunk_strategy, &mut hasher);
+        }
         let config_hash = std::hash::Hasher::finish(&hasher).to_string();
         let meta_key = format!("dir_hash:{}", dir_str);
 
-        {
+        let stored = self.db.get_metadata(&meta_key).unwrap_or(None);
+        let should_rebuild = stored.as_deref() != Some(config_hash.as_str());
+        if !dry_run {
             let db_guard = self.db.clone();
-            let stored = db_guard.get_metadata(&meta_key).unwrap_or(None);
-            let should_rebuild = stored.as_deref() != Some(config_hash.as_str());
-
             if should_rebuild && !force {
                 tracing::info!(
                     "Configuration filter change detected for {}. Purging previous index.",
                     dir.display()
                 );
-                if let Ok(removed) = db_guard.delete_documents_by_prefix(&dir_str) {
+                if let Ok(removed) = db_guard.delete_documents_by_prefix(&dir_prefix(&dir_str)) {
                     tracing::info!("Purged {} stale documents due to config change.", removed);
                 }
                 let _ = db_guard.set_metadata(&meta_key, &config_hash);
@@ -108,43 +595,40 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
         }
 
         // Get existing documents from DB map(filename -> modified_at)
-        let existing_docs = {
+        let mut existing_docs = {
             let db_guard = self.db.clone();
             db_guard.list_documents()?
         };
+        if dry_run && should_rebuild && !force {
+            // A real sync would have purged these above
+            let prefix = dir_prefix(&dir_str);
+            existing_docs.retain(|path, _| !path.starts_with(&prefix));
+        }
 
         let mut visited_paths = std::collections::HashSet::new();
 
         let mut result = CodeSyncResult::default();
 
-        // Build overrides from config
-        let mut overrides = OverrideBuilder::new(dir);
-        for pattern in &self.config.exclude_patterns {
-            let _ = overrides.add(&format!("!{}", pattern));
-        }
-        let override_matcher = overrides
-            .build()
-            .unwrap_or_else(|_| OverrideBuilder::new(dir).build().unwrap());
-
-        // Walk builder respects .gitignore by default
-        let walker = WalkBuilder::new(dir)
-            .hidden(false)
-            .overrides(override_matcher)
-            .build();
+        // Collect candidates up front so progress can report a total
+        let entries: Vec<_> = build_walker(&self.config, dir)
+            .filter_map(|e| e.ok())
+            .filter(|entry| {
+                let path = entry.path();
+                let ext = path
+                    .extension()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or_default();
+                !path.is_dir() && self.is_supported_extension(ext)
+            })
+            .collect();
+        let total = entries.len();
 
-        for entry in walker.into_iter().filter_map(|e| e.ok()) {
+        for (i, entry) in entries.iter().enumerate() {
             let path = entry.path();
-            if path.is_dir() {
-                continue;
-            }
-
             let ext = path
                 .extension()
                 .and_then(|s| s.to_str())
                 .unwrap_or_default();
-            if !self.is_supported_extension(ext) {
-                continue;
-            }
 
             // Enforce consistent absolute system paths for all documents
             let path_str = normalize_system_path(path);
@@ -157,7 +641,8 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
             let mut was_update = false;
 
             if let Some(existing_time) = existing_docs.get(&path_str) {
-                if !force && mod_time.timestamp() == existing_time.timestamp() {
+                // Millisecond precision so edits within the same second are seen
+                if !force && mod_time.timestamp_millis() == existing_time.timestamp_millis() {
                     result.skipped += 1;
                     needs_indexing = false;
                 } else {
@@ -165,23 +650,21 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
                 }
             }
 
-            if needs_indexing {
-                let success = match classify_extension(ext) {
-                    Some(FileType::Markdown) => {
-                        self.index_markdown(path, &path_str, mod_time).await.is_ok()
-                    }
-                    Some(FileType::Code) => self
-                        .index_code_file(path, &path_str, mod_time)
-                        .await
-                        .is_ok(),
-                    Some(FileType::Text) => self
-                        .index_text_file(path, &path_str, mod_time)
-                        .await
-                        .is_ok(),
-                    None => false,
-                };
-
-                if success {
+            // A touched but unchanged file only needs its new mtime recorded
+            if was_update && !force && self.content_unchanged(path, &path_str) {
+                if !dry_run {
+                    self.db.touch_document(&path_str, mod_time)?;
+                }
+                result.skipped += 1;
+                needs_indexing = false;
+            }
+
+            if needs_indexing && self.config.exceeds_max_file_size(ext, metadata.len()) {
+                result.skipped_large += 1;
+            } else if needs_indexing && self.skips_generated(path, ext) {
+                result.generated += 1;
+            } else if needs_indexing {
+                if dry_run || self.index_path(path, &path_str, ext, mod_time).await {
                     result.indexed += 1;
                     if was_update {
                         result.updated += 1;
@@ -192,17 +675,31 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
                     result.failed += 1;
                 }
             }
+
+            if let Some(report) = on_progress {
+                report(&IndexProgress {
+                    path: path_str,
+                    processed: i + 1,
+                    total,
+                    added: result.added,
+                    updated: result.updated,
+                    skipped: result.skipped,
+                    failed: result.failed,
+                });
+            }
         }
 
         // Phase 2: Stale Cleanup — collect stale paths, then delete in a single batch
-        let dir_str = normalize_system_path(dir);
+        let prefix = dir_prefix(&normalize_system_path(dir));
         let stale_paths: Vec<&str> = existing_docs
             .keys()
-            .filter(|p| p.starts_with(&dir_str) && !visited_paths.contains(p.as_str()))
+            .filter(|p| p.starts_with(&prefix) && !visited_paths.contains(p.as_str()))
             .map(|p| p.as_str())
             .collect();
 
-        if !stale_paths.is_empty() {
+        if prune && dry_run {
+            result.removed += stale_paths.len();
+        } else if prune && !stale_paths.is_empty() {
             let db_guard = self.db.clone();
             if let Ok(removed) = db_guard.delete_documents_batch(&stale_paths) {
                 result.removed += removed;
@@ -212,6 +709,112 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
         Ok(result)
     }
 
+    /// Reindex only files under `dir` changed since `since_ref` in the git
+    /// repo containing it, including untracked files.
+    ///
+    /// Changed files are reindexed, files deleted since the ref are removed
+    /// from the index, and everything else is left untouched (no full walk).
+    #[cfg(feature = "git")]
+    pub async fn index_git_changed<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        since_ref: &str,
+    ) -> Result<CodeSyncResult, Box<dyn std::error::Error>> {
+        let dir = dir.as_ref();
+        let changed = crate::indexer::git::changed_files(dir, since_ref)?;
+        let root = crate::indexer::git::repo_root(dir)?;
+
+        let mut overrides = OverrideBuilder::new(&root);
+        for pattern in &self.config.exclude_patterns {
+            let _ = overrides.add(&format!("!{}", pattern));
+        }
+        let override_matcher = overrides
+            .build()
+            .unwrap_or_else(|_| OverrideBuilder::new(&root).build().unwrap());
+
+        let custom = custom_ignore(&self.config, &root);
+
+        let existing_docs = self.db.list_documents()?;
+        let mut result = CodeSyncResult::default();
+
+        for path in changed {
+            if override_matcher.matched(&path, false).is_ignore()
+                || custom.matched_path_or_any_parents(&path, false).is_ignore()
+                || (!self.config.include_hidden && is_hidden_below(&root, &path))
+                || (self.config.exclude_test_code && is_test_file(&path, &root))
+            {
+                result.skipped += 1;
+                continue;
+            }
+
+            if !path.exists() {
+                let path_str = normalize_system_path(&path);
+                if self.db.delete_document(&path_str)? {
+                    result.removed += 1;
+                }
+                continue;
+            }
+
+            let path_str = normalize_system_path(&path);
+            if self.index_file(&path).await? {
+                result.indexed += 1;
+                if existing_docs.contains_key(&path_str) {
+                    result.updated += 1;
+                } else {
+                    result.added += 1;
+                }
+            } else {
+                result.skipped += 1;
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Re-read, re-chunk and re-embed every indexed document from disk.
+    ///
+    /// Like a forced [`index_directory`](Self::index_directory), but driven by
+    /// the documents already in the index instead of a directory walk. Files
+    /// that no longer exist are removed.
+    pub async fn reindex_all(&self) -> Result<ReindexAllResult, Box<dyn std::error::Error>> {
+        let mut filenames: Vec<String> = self.db.list_documents()?.into_keys().collect();
+        filenames.sort();
+        self.reindex_documents(filenames).await
+    }
+
+    /// Like [`reindex_all`](Self::reindex_all), limited to the indexed
+    /// documents matching a glob `pattern` (see [`Db::list_documents_matching`]).
+    pub async fn reindex_matching(
+        &self,
+        pattern: &str,
+    ) -> Result<ReindexAllResult, Box<dyn std::error::Error>> {
+        let filenames = self.db.list_documents_matching(pattern)?;
+        self.reindex_documents(filenames).await
+    }
+
+    async fn reindex_documents(
+        &self,
+        filenames: Vec<String>,
+    ) -> Result<ReindexAllResult, Box<dyn std::error::Error>> {
+        let mut result = ReindexAllResult::default();
+        for filename in filenames {
+            let path = Path::new(&filename);
+            if !path.exists() {
+                self.db.delete_document(&filename)?;
+                result.missing.push(filename);
+                continue;
+            }
+
+            if self.index_file(path).await? {
+                result.succeeded.push(filename);
+            } else {
+                result.failed.push(filename);
+            }
+        }
+
+        Ok(result)
+    }
+
     pub async fn index_file(&self, path: &Path) -> Result<bool, Box<dyn std::error::Error>> {
         let ext = path
             .extension()
@@ -229,56 +832,127 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
             Ok(m) => m.into(),
             Err(_) => return Ok(false),
         };
+        if self.config.exceeds_max_file_size(ext, metadata.len()) || self.skips_generated(path, ext)
+        {
+            return Ok(false);
+        }
         let path_str = normalize_system_path(path);
 
-        let success = match classify_extension(ext) {
-            Some(FileType::Markdown) => {
-                self.index_markdown(path, &path_str, mod_time).await.is_ok()
-            }
+        Ok(self.index_path(path, &path_str, ext, mod_time).await)
+    }
+
+    /// Whether the file's bytes hash to the content hash stored for its
+    /// document. False when no hash is stored.
+    fn content_unchanged(&self, path: &Path, path_str: &str) -> bool {
+        self.db
+            .get_document_content_hash(path_str)
+            .ok()
+            .flatten()
+            .is_some_and(|stored| file_content_hash(path).is_ok_and(|hash| hash == stored))
+    }
+
+    /// Whether `path` is a code or markdown file to leave out because it
+    /// looks generated (see [`is_generated_content`]) and `index_generated`
+    /// is off.
+    fn skips_generated(&self, path: &Path, ext: &str) -> bool {
+        !self.config.index_generated
+            && matches!(
+                classify_file(ext, &self.config),
+                Some(FileType::Code | FileType::Markdown)
+            )
+            && std::fs::read_to_string(path).is_ok_and(|c| is_generated_content(&c))
+    }
+
+    /// Index one file under its stored name, holding the file's lock.
+    /// Returns whether it was indexed successfully.
+    async fn index_path(
+        &self,
+        path: &Path,
+        path_str: &str,
+        ext: &str,
+        mod_time: DateTime<Utc>,
+    ) -> bool {
+        let _guard = lock_file(path_str).await;
+        // Read once, so the stored content hash covers exactly what is chunked
+        let Ok(content) = std::fs::read(path) else {
+            return false;
+        };
+        let hash = sha256_hex(&content);
+        if alias_duplicate(&self.db, &self.config, &hash, path_str, mod_time).is_some() {
+            return true;
+        }
+        match classify_file(ext, &self.config) {
+            Some(FileType::Markdown) => self
+                .index_markdown(&content, path_str, mod_time, &hash)
+                .await
+                .is_ok(),
             Some(FileType::Code) => self
-                .index_code_file(path, &path_str, mod_time)
+                .index_code_file(&content, ext, path_str, mod_time, &hash)
                 .await
                 .is_ok(),
             Some(FileType::Text) => self
-                .index_text_file(path, &path_str, mod_time)
+                .index_text_file(&content, ext, path_str, mod_time, &hash)
                 .await
                 .is_ok(),
             None => false,
-        };
-
-        Ok(success)
+        }
     }
 
     async fn index_markdown(
         &self,
-        real_path: &Path,
+        content: &[u8],
         db_path: &str,
         mod_time: DateTime<Utc>,
+        content_hash: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let chunks = markdown::parse_markdown(real_path, self.chunk_size)?;
+        let content = std::str::from_utf8(content)?;
+        let chunks = markdown::parse_markdown_content(
+            content,
+            self.chunk_size,
+            self.config.chunk_overlap,
+            self.config.chunk_strategy,
+            self.config.normalize_whitespace,
+        );
         if chunks.is_empty() {
             return Ok(());
         }
+        let mut code_blocks = if self.config.extract_markdown_code_blocks {
+            markdown::code_block_chunks(markdown::strip_bom(content))
+        } else {
+            Vec::new()
+        };
+        if self.config.index_frontmatter {
+            code_blocks.extend(markdown::frontmatter_chunk(markdown::strip_bom(content)));
+        }
 
-        let text_refs: Vec<&str> = chunks.iter().map(|c| c.content.as_str()).collect();
+        let texts: Vec<String> = chunks
+            .iter()
+            .map(|c| self.config.passage_text(&c.content))
+            .chain(code_blocks.iter().map(|c| {
+                self.config
+                    .passage_text(&c.embedding_text_for(&self.config))
+            }))
+            .collect();
+        let text_refs: Vec<&str> = texts.iter().map(|s| s.as_str()).collect();
 
         // Vectorize chunks
         let vectors = self.embedder.embed_batch(&text_refs)?;
 
-        // Map to models::Chunk for DB insertion
-        let db_chunks: Vec<crate::db::models::Chunk> = chunks
-            .iter()
-            .map(|c| crate::db::models::Chunk {
-                position: c.position,
-                content: c.content.as_str(),
-            })
-            .collect();
-
         // Write to DB
-        {
-            let db_guard = self.db.clone();
-            db_guard.insert_document(db_path, mod_time, &db_chunks, &vectors)?;
-        }
+        store_markdown_document(
+            &self.db,
+            db_path,
+            mod_time,
+            Some(content_hash),
+            &chunks,
+            &code_blocks,
+            &vectors,
+            &self.config,
+        )?;
+        self.db
+            .set_document_doc_type(db_path, markdown_doc_type(content).as_deref())?;
+        self.db
+            .set_document_title(db_path, markdown_title(content, &self.config).as_deref())?;
 
         Ok(())
     }
@@ -290,20 +964,35 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
     /// and stores them with full code metadata.
     async fn index_code_file(
         &self,
-        real_path: &Path,
+        source: &[u8],
+        ext: &str,
         db_path: &str,
         mod_time: DateTime<Utc>,
+        content_hash: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
         use crate::indexer::code_parser::CodeParser;
 
-        let mut parser = CodeParser::new()?;
-        let code_chunks = parser.parse_file(real_path)?;
+        let mut parser = CodeParser::with_languages(self.config.enabled_languages.as_deref())?
+            .exclude_tests(self.config.exclude_test_code)
+            .max_chunk_tokens(self.config.max_code_chunk_tokens.clone());
+        let code_chunks = parser.parse_source(source, ext)?;
         if code_chunks.is_empty() {
             return Ok(());
         }
+        let docs = doc_chunks(&self.config, &code_chunks);
 
         // Generate embedding text enriched with language + symbol context
-        let text_refs: Vec<String> = code_chunks.iter().map(|c| c.get_embedding_text()).collect();
+        let text_refs: Vec<String> = code_chunks
+            .iter()
+            .map(|c| {
+                self.config
+                    .passage_text(&c.embedding_text_for(&self.config))
+            })
+            .chain(
+                docs.iter()
+                    .map(|d| doc_chunk_embedding_text(&self.config, d.chunk.content)),
+            )
+            .collect();
         let text_str_refs: Vec<&str> = text_refs.iter().map(|s| s.as_str()).collect();
 
         // Vectorize
@@ -317,6 +1006,8 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
                 chunk: crate::db::models::Chunk {
                     position: i,
                     content: &c.content,
+                    start_line: None,
+                    end_line: None,
                 },
                 symbol_name: Some(c.symbol_name.as_str()),
                 symbol_type: &c.symbol_type,
@@ -325,14 +1016,22 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
                 end_line: Some(c.end_line),
                 parent_symbol: c.parent_symbol.as_deref(),
                 signature: Some(c.signature.as_str()),
+                doc_comment: c.doc_comment.as_deref(),
+                part: c.part,
             })
             .collect();
 
-        // Write to DB with code metadata
-        {
-            let db_guard = self.db.clone();
-            db_guard.insert_code_document(db_path, mod_time, &db_chunks, &vectors)?;
-        }
+        // Write to DB with code metadata, then the relations of each symbol
+        let chunk_ids = self.db.insert_code_document_with_docs(
+            db_path,
+            mod_time,
+            Some(content_hash),
+            &db_chunks,
+            &docs,
+            &vectors,
+        )?;
+        let relations = code_relations(&self.config, source, db_path, &code_chunks, &chunk_ids)?;
+        self.db.insert_relations(&relations)?;
 
         Ok(())
     }
@@ -341,16 +1040,27 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
     /// Extracts text using format-specific logic, chunks it, embeds, and stores.
     async fn index_text_file(
         &self,
-        real_path: &Path,
+        data: &[u8],
+        ext: &str,
         db_path: &str,
         mod_time: DateTime<Utc>,
+        content_hash: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let chunks = crate::indexer::text_parser::extract_and_chunk(real_path, self.chunk_size)?;
+        let chunks = crate::indexer::text_parser::extract_and_chunk_bytes(
+            data,
+            ext,
+            self.chunk_size,
+            self.config.chunk_overlap,
+        )?;
         if chunks.is_empty() {
             return Ok(());
         }
 
-        let text_refs: Vec<&str> = chunks.iter().map(|c| c.content.as_str()).collect();
+        let texts: Vec<String> = chunks
+            .iter()
+            .map(|c| self.config.passage_text(&c.content))
+            .collect();
+        let text_refs: Vec<&str> = texts.iter().map(|s| s.as_str()).collect();
         let vectors = self.embedder.embed_batch(&text_refs)?;
 
         let db_chunks: Vec<crate::db::models::Chunk> = chunks
@@ -358,12 +1068,20 @@ impl<'a, E: Embedder + ?Sized> Indexer<'a, E> {
             .map(|c| crate::db::models::Chunk {
                 position: c.position,
                 content: c.content.as_str(),
+                start_line: c.start_line,
+                end_line: c.end_line,
             })
             .collect();
 
         {
             let db_guard = self.db.clone();
-            db_guard.insert_document(db_path, mod_time, &db_chunks, &vectors)?;
+            db_guard.insert_document(
+                db_path,
+                mod_time,
+                Some(content_hash),
+                &db_chunks,
+                &vectors,
+            )?;
         }
 
         Ok(())
@@ -375,6 +1093,7 @@ mod tests {
     use super::*;
     use crate::db::Db;
     use crate::embedder::mock::MockEmbedder;
+    use crate::indexer::content_hash::sha256_hex;
     use std::fs;
     use tempfile::tempdir;
 
@@ -401,20 +1120,29 @@ mod tests {
         );
 
         // First sync
-        let res1 = indexer.index_directory(dir_path, false).await.unwrap();
+        let res1 = indexer
+            .index_directory(dir_path, false, true, false)
+            .await
+            .unwrap();
         assert_eq!(res1.added, 2);
         assert_eq!(res1.indexed, 2);
         assert_eq!(res1.skipped, 0);
 
         // Second sync immediately - should skip both
-        let res2 = indexer.index_directory(dir_path, false).await.unwrap();
+        let res2 = indexer
+            .index_directory(dir_path, false, true, false)
+            .await
+            .unwrap();
         assert_eq!(res2.added, 0);
         assert_eq!(res2.updated, 0);
         assert_eq!(res2.indexed, 0);
         assert_eq!(res2.skipped, 2);
 
         // Third sync with force=true - should update both
-        let res3 = indexer.index_directory(dir_path, true).await.unwrap();
+        let res3 = indexer
+            .index_directory(dir_path, true, true, false)
+            .await
+            .unwrap();
         assert_eq!(res3.added, 0);
         assert_eq!(res3.updated, 2);
         assert_eq!(res3.indexed, 2);
@@ -423,5 +1151,1139 @@ mod tests {
         // Check DB
         let docs = db_arc.list_documents().unwrap();
         assert_eq!(docs.len(), 2);
+
+        // An edit within the same second is still picked up
+        let mtime = fs::metadata(&file1).unwrap().modified().unwrap();
+        fs::write(&file1, "Content 1, edited").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&file1)
+            .unwrap()
+            .set_modified(mtime + std::time::Duration::from_millis(1))
+            .unwrap();
+        let res_edit = indexer
+            .index_directory(dir_path, false, true, false)
+            .await
+            .unwrap();
+        assert_eq!(res_edit.updated, 1);
+        assert_eq!(res_edit.skipped, 1);
+
+        // A deleted file is kept without prune and removed with it
+        fs::remove_file(&file2).unwrap();
+        let res4 = indexer
+            .index_directory(dir_path, false, false, false)
+            .await
+            .unwrap();
+        assert_eq!(res4.removed, 0);
+        assert_eq!(db_arc.list_documents().unwrap().len(), 2);
+
+        let res5 = indexer
+            .index_directory(dir_path, false, true, false)
+            .await
+            .unwrap();
+        assert_eq!(res5.removed, 1);
+        assert_eq!(db_arc.list_documents().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_index_directory_dry_run_leaves_db_untouched() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("kept.md"), "Kept").unwrap();
+        fs::write(dir.join("edited.md"), "Before").unwrap();
+        fs::write(dir.join("gone.md"), "Gone").unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(Config::default()));
+        let plan = indexer
+            .index_directory(dir, false, true, true)
+            .await
+            .unwrap();
+        assert_eq!((plan.added, plan.indexed), (3, 3));
+        assert!(db_arc.list_documents().unwrap().is_empty());
+
+        indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+        let before = db_arc.list_documents().unwrap();
+
+        fs::write(dir.join("edited.md"), "After, longer").unwrap();
+        let mtime = fs::metadata(dir.join("edited.md"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        fs::File::options()
+            .write(true)
+            .open(dir.join("edited.md"))
+            .unwrap()
+            .set_modified(mtime + std::time::Duration::from_secs(5))
+            .unwrap();
+        fs::remove_file(dir.join("gone.md")).unwrap();
+        fs::write(dir.join("new.md"), "New").unwrap();
+
+        let plan = indexer
+            .index_directory(dir, false, true, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            (plan.added, plan.updated, plan.skipped, plan.removed),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(db_arc.list_documents().unwrap(), before);
+    }
+
+    #[cfg(feature = "git")]
+    #[tokio::test]
+    async fn test_index_git_changed_only_reindexes_changed_files() {
+        use std::process::Command;
+
+        let git = |dir: &Path, args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {args:?} failed");
+        };
+
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+        fs::write(dir.join("changed.md"), "Before").unwrap();
+        fs::write(dir.join("untouched.md"), "Same").unwrap();
+        fs::write(dir.join("deleted.md"), "Gone soon").unwrap();
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-q", "-m", "init"]);
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let mut indexer = Indexer::new(
+            db_arc.clone(),
+            &embedder,
+            500,
+            Arc::new(crate::config::Config::default()),
+        );
+        indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+
+        fs::write(dir.join("changed.md"), "After").unwrap();
+        git(dir, &["rm", "-q", "deleted.md"]);
+        git(dir, &["commit", "-q", "-am", "edit"]);
+
+        let result = indexer.index_git_changed(dir, "HEAD~1").await.unwrap();
+        assert_eq!(result.indexed, 1);
+        assert_eq!(result.updated, 1);
+        assert_eq!(result.removed, 1);
+
+        let docs = db_arc.list_documents().unwrap();
+        assert_eq!(docs.len(), 2);
+        let query = embedder.embed("After").unwrap();
+        let hit = &db_arc.search(&query, 1).unwrap()[0];
+        assert!(hit.document_name.ends_with("changed.md"));
+        assert_eq!(hit.chunk_content, "After");
+    }
+
+    #[tokio::test]
+    async fn test_directory_coverage_reports_missing() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("indexed.md"), "Indexed").unwrap();
+        fs::write(dir.join("also_indexed.md"), "Also indexed").unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = Arc::new(crate::config::Config::default());
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, config.clone());
+        indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+
+        // Added after indexing, plus an unsupported file that must be ignored
+        fs::write(dir.join("new.md"), "New").unwrap();
+        fs::write(dir.join("notes.txt"), "Notes").unwrap();
+        fs::write(dir.join("image.png"), [0u8; 4]).unwrap();
+
+        let report = directory_coverage(&db_arc, &config, dir).unwrap();
+        assert_eq!(report.total_supported, 4);
+        assert_eq!(report.indexed, 2);
+        assert_eq!(
+            report.missing,
+            vec![
+                normalize_system_path(&dir.join("new.md")),
+                normalize_system_path(&dir.join("notes.txt")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ignore_patterns_and_hidden_files() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        for file in [
+            "docs/a.md",
+            "vendor/lib.md",
+            ".github/notes.md",
+            ".cache/tmp.md",
+        ] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "Content").unwrap();
+        }
+        let indexed = |config: crate::config::Config| async {
+            let db_arc = Arc::new(Db::open_in_memory().unwrap());
+            let embedder = MockEmbedder::default();
+            let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(config));
+            indexer
+                .index_directory(dir, false, true, false)
+                .await
+                .unwrap();
+            let mut names: Vec<String> = db_arc
+                .list_documents()
+                .unwrap()
+                .into_keys()
+                .map(|name| name[normalize_system_path(dir).len() + 1..].to_string())
+                .collect();
+            names.sort();
+            names
+        };
+
+        // Hidden files are included by default
+        assert_eq!(
+            indexed(crate::config::Config::default()).await,
+            [
+                ".cache/tmp.md",
+                ".github/notes.md",
+                "docs/a.md",
+                "vendor/lib.md"
+            ]
+        );
+
+        // Custom patterns exclude a directory and all dotfiles but one
+        let config = crate::config::Config {
+            ignore_patterns: vec!["vendor/".into(), ".*".into(), "!.github/".into()],
+            ..Default::default()
+        };
+        assert_eq!(indexed(config).await, [".github/notes.md", "docs/a.md"]);
+
+        let config = crate::config::Config {
+            include_hidden: false,
+            ..Default::default()
+        };
+        assert_eq!(indexed(config).await, ["docs/a.md", "vendor/lib.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_index_directory_reports_progress() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("a.md"), "Alpha").unwrap();
+        fs::write(dir.join("b.md"), "Beta").unwrap();
+        fs::write(dir.join("c.txt"), "Gamma").unwrap();
+        fs::write(dir.join("image.png"), [0u8; 4]).unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = Arc::new(crate::config::Config::default());
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, config);
+        indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+
+        fs::write(dir.join("b.md"), "Beta, edited").unwrap();
+        let mtime = fs::metadata(dir.join("b.md")).unwrap().modified().unwrap();
+        fs::File::options()
+            .write(true)
+            .open(dir.join("b.md"))
+            .unwrap()
+            .set_modified(mtime + std::time::Duration::from_secs(5))
+            .unwrap();
+
+        let reports = std::sync::Mutex::new(Vec::new());
+        let on_progress = |p: &IndexProgress| reports.lock().unwrap().push(p.clone());
+        let result = indexer
+            .index_directory_with_progress(dir, false, true, false, Some(&on_progress))
+            .await
+            .unwrap();
+
+        let reports = reports.into_inner().unwrap();
+        let processed: Vec<usize> = reports.iter().map(|p| p.processed).collect();
+        assert_eq!(processed, [1, 2, 3]);
+        assert!(reports.iter().all(|p| p.total == 3));
+        let last = reports.last().unwrap();
+        assert_eq!(
+            (last.added, last.updated, last.skipped, last.failed),
+            (result.added, result.updated, result.skipped, result.failed)
+        );
+        assert_eq!((last.updated, last.skipped), (1, 2));
+        assert!(
+            reports
+                .iter()
+                .any(|p| p.path == normalize_system_path(&dir.join("b.md")))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_touch_document_prevents_reembedding() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        let file = dir.join("doc.md");
+        fs::write(&file, "Unchanged content").unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = Arc::new(crate::config::Config::default());
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, config);
+        indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+
+        // mtime drifts while the content stays the same
+        let drifted =
+            fs::metadata(&file).unwrap().modified().unwrap() + std::time::Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(drifted)
+            .unwrap();
+
+        let name = normalize_system_path(&file);
+        assert!(db_arc.touch_document(&name, drifted.into()).unwrap());
+        assert!(!db_arc.touch_document("missing.md", drifted.into()).unwrap());
+
+        let result = indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+        assert_eq!((result.skipped, result.updated), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_content_hash_skips_reembedding() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        let file = dir.join("doc.md");
+        fs::write(&file, "Unchanged content").unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = Arc::new(crate::config::Config::default());
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, config);
+        indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+        let name = normalize_system_path(&file);
+        let hash = db_arc.get_document_content_hash(&name).unwrap();
+        assert_eq!(hash, Some(sha256_hex(b"Unchanged content")));
+
+        // Rewriting the same bytes only moves the mtime
+        let later =
+            fs::metadata(&file).unwrap().modified().unwrap() + std::time::Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let result = indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+        assert_eq!((result.skipped, result.updated), (1, 0));
+        let stored = db_arc.list_documents().unwrap()[&name];
+        assert_eq!(
+            stored.timestamp_millis(),
+            DateTime::<Utc>::from(later).timestamp_millis()
+        );
+
+        // A forced run ignores the hash
+        let result = indexer
+            .index_directory(dir, true, true, false)
+            .await
+            .unwrap();
+        assert_eq!((result.skipped, result.updated), (0, 1));
+        assert_eq!(db_arc.get_document_content_hash(&name).unwrap(), hash);
+    }
+
+    #[tokio::test]
+    async fn test_reindex_all_rebuilds_and_drops_missing() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("kept.md"), "Original content").unwrap();
+        fs::write(dir.join("gone.md"), "Soon deleted").unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = Arc::new(crate::config::Config::default());
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, config);
+        indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+
+        fs::write(dir.join("kept.md"), "Rewritten content").unwrap();
+        fs::remove_file(dir.join("gone.md")).unwrap();
+
+        let result = indexer.reindex_all().await.unwrap();
+        assert_eq!(result.succeeded.len(), 1);
+        assert!(result.failed.is_empty());
+        assert_eq!(
+            result.missing,
+            vec![format!("{}/gone.md", normalize_system_path(dir))]
+        );
+
+        let docs = db_arc.list_documents().unwrap();
+        assert_eq!(docs.len(), 1);
+        let query = embedder.embed("Rewritten content").unwrap();
+        let results = db_arc.search(&query, 1).unwrap();
+        assert_eq!(results[0].chunk_content, "Rewritten content");
+    }
+
+    #[tokio::test]
+    async fn test_reindex_matching_only_touches_matches() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        for name in ["api-users.md", "api-orders.md", "guide.md"] {
+            fs::write(dir.join(name), format!("Old {name}")).unwrap();
+        }
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = Arc::new(crate::config::Config::default());
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, config);
+        indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+
+        for name in ["api-users.md", "api-orders.md", "guide.md"] {
+            fs::write(dir.join(name), format!("New {name}")).unwrap();
+        }
+        let result = indexer.reindex_matching("api-*.md").await.unwrap();
+        let root = normalize_system_path(dir);
+        assert_eq!(
+            result.succeeded,
+            [
+                format!("{root}/api-orders.md"),
+                format!("{root}/api-users.md")
+            ]
+        );
+        assert!(result.failed.is_empty() && result.missing.is_empty());
+
+        let content_of = |text: &str| {
+            let query = embedder.embed(text).unwrap();
+            db_arc.search(&query, 1).unwrap()[0].chunk_content.clone()
+        };
+        assert_eq!(content_of("New api-users.md"), "New api-users.md");
+        assert_eq!(content_of("New api-orders.md"), "New api-orders.md");
+        // Not matched, so still the old content
+        assert_eq!(content_of("Old guide.md"), "Old guide.md");
+    }
+
+    #[tokio::test]
+    async fn test_index_records_frontmatter_doc_type() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(
+            dir.join("api.md"),
+            "---\ndocType: api\n---\nAuth API reference",
+        )
+        .unwrap();
+        fs::write(dir.join("plain.md"), "No frontmatter here").unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = Arc::new(crate::config::Config::default());
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, config);
+        indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+
+        let results = db_arc.search(&embedder.embed("x").unwrap(), 10).unwrap();
+        let doc_type = |name: &str| {
+            results
+                .iter()
+                .find(|r| r.document_name.ends_with(name))
+                .unwrap()
+                .doc_type
+                .clone()
+        };
+        assert_eq!(doc_type("/api.md").as_deref(), Some("api"));
+        assert_eq!(doc_type("/plain.md"), None);
+    }
+
+    #[tokio::test]
+    async fn test_index_records_document_title() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(
+            dir.join("guide.md"),
+            "Intro line\n\n```bash\n# not a title\n```\n\n# Install Guide\n\n## Steps\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("named.md"),
+            "---\ntitle: From Frontmatter\n---\n# Heading Title\n",
+        )
+        .unwrap();
+        fs::write(dir.join("plain.md"), "No headings here").unwrap();
+
+        for title_from_h1 in [true, false] {
+            let db_arc = Arc::new(Db::open_in_memory().unwrap());
+            let embedder = MockEmbedder::default();
+            let config = crate::config::Config {
+                title_from_h1,
+                ..Default::default()
+            };
+            let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(config));
+            indexer
+                .index_directory(dir, false, true, false)
+                .await
+                .unwrap();
+
+            let titles: Vec<Option<String>> = db_arc
+                .list_documents_page(0, 10)
+                .unwrap()
+                .into_iter()
+                .map(|d| d.title)
+                .collect();
+            let guide = title_from_h1.then(|| "Install Guide".to_string());
+            assert_eq!(
+                titles,
+                [guide.clone(), Some("From Frontmatter".to_string()), None]
+            );
+
+            let results = db_arc.search(&embedder.embed("x").unwrap(), 10).unwrap();
+            let hit = results
+                .iter()
+                .find(|r| r.document_name.ends_with("/guide.md"))
+                .unwrap();
+            assert_eq!(hit.title, guide);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_same_filename_across_roots() {
+        let temp_dir = tempdir().unwrap();
+        let root_a = temp_dir.path().join("a");
+        let root_ab = temp_dir.path().join("ab");
+        fs::create_dir_all(&root_a).unwrap();
+        fs::create_dir_all(&root_ab).unwrap();
+        fs::write(root_a.join("README.md"), "Project A").unwrap();
+        fs::write(root_ab.join("README.md"), "Project AB").unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let mut indexer = Indexer::new(
+            db_arc.clone(),
+            &embedder,
+            500,
+            Arc::new(crate::config::Config::default()),
+        );
+        indexer
+            .index_directory(&root_ab, false, true, false)
+            .await
+            .unwrap();
+        // Syncing "a" must not treat "ab/README.md" as a stale file of its own
+        let res = indexer
+            .index_directory(&root_a, false, true, false)
+            .await
+            .unwrap();
+        assert_eq!(res.removed, 0);
+
+        let docs = db_arc.list_documents().unwrap();
+        assert_eq!(docs.len(), 2);
+        assert!(docs.contains_key(&normalize_system_path(&root_a.join("README.md"))));
+        assert!(docs.contains_key(&normalize_system_path(&root_ab.join("README.md"))));
+    }
+
+    #[tokio::test]
+    async fn test_search_result_flagged_stale_after_edit() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("notes.md");
+        fs::write(&file, "Original content").unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let mut indexer = Indexer::new(
+            db_arc.clone(),
+            &embedder,
+            500,
+            Arc::new(crate::config::Config::default()),
+        );
+        indexer
+            .index_directory(temp_dir.path(), false, true, false)
+            .await
+            .unwrap();
+
+        let query = embedder.embed("Original content").unwrap();
+        let results = db_arc.search(&query, 1).unwrap();
+        assert!(!results[0].is_stale());
+
+        // Rewrite the file and push its mtime forward past the indexed time
+        fs::write(&file, "Edited content").unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        let results = db_arc.search(&query, 1).unwrap();
+        assert!(results[0].is_stale());
+    }
+
+    #[tokio::test]
+    async fn test_index_doc_comments_searchable() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("rates.py"),
+            "def fetch_rates(currency):\n    \"\"\"Fetch the latest exchange rates.\"\"\"\n    return client.get(currency)\n",
+        )
+        .unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = crate::config::Config {
+            index_doc_comments: true,
+            ..Default::default()
+        };
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(config));
+        indexer
+            .index_directory(temp_dir.path(), false, true, false)
+            .await
+            .unwrap();
+
+        let query = embedder.embed("Fetch the latest exchange rates.").unwrap();
+        let results = db_arc.search(&query, 1).unwrap();
+        assert_eq!(results[0].chunk_content, "Fetch the latest exchange rates.");
+        // A prose chunk, not a second definition of the symbol
+        assert!(results[0].metadata.is_none());
+        let defs = db_arc.find_definitions("fetch_rates", None, true).unwrap();
+        assert_eq!(defs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_frontmatter_kept_out_of_prose_chunks() {
+        let temp_dir = tempdir().unwrap();
+        let doc = temp_dir.path().join("auth.md");
+        fs::write(
+            &doc,
+            "---\ndomain: backend\ntags: [auth]\n---\n# Login\n\nTokens expire hourly.\n",
+        )
+        .unwrap();
+
+        let embedder = MockEmbedder::default();
+        let query = embedder.embed("Tokens expire hourly").unwrap();
+        for index_frontmatter in [false, true] {
+            let db_arc = Arc::new(Db::open_in_memory().unwrap());
+            let config = crate::config::Config {
+                index_frontmatter,
+                ..Default::default()
+            };
+            let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(config));
+            indexer
+                .index_directory(temp_dir.path(), false, true, false)
+                .await
+                .unwrap();
+
+            let results = db_arc.search(&query, 10).unwrap();
+            let (frontmatter, prose): (Vec<_>, Vec<_>) =
+                results.iter().partition(|r| r.metadata.is_some());
+            assert!(!prose.is_empty());
+            assert!(prose.iter().all(|r| !r.chunk_content.contains("domain:")));
+            assert_eq!(frontmatter.len(), usize::from(index_frontmatter));
+
+            let symbols = db_arc
+                .list_symbols_for_file(&normalize_system_path(&doc))
+                .unwrap();
+            if index_frontmatter {
+                assert_eq!(symbols[0].symbol_type, markdown::FRONTMATTER_SYMBOL_TYPE);
+                assert_eq!(
+                    frontmatter[0].chunk_content,
+                    "domain: backend\ntags: [auth]"
+                );
+            } else {
+                assert!(symbols.is_empty());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_markdown_fence_relations() {
+        let temp_dir = tempdir().unwrap();
+        let doc = temp_dir.path().join("guide.md");
+        fs::write(
+            &doc,
+            "# Usage\n\nCall the loader:\n\n```python\nrows = load_rates(\"EUR\")\n```\n",
+        )
+        .unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = crate::config::Config {
+            extract_markdown_code_blocks: true,
+            fence_relations: true,
+            ..Default::default()
+        };
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(config));
+        indexer
+            .index_directory(temp_dir.path(), false, true, false)
+            .await
+            .unwrap();
+
+        let calls = db_arc
+            .find_symbol_relations("load_rates", "incoming", Some("calls"))
+            .unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].source_name.as_deref(), Some("code block 1"));
+        assert_eq!(
+            calls[0].source_file.as_deref(),
+            Some(normalize_system_path(&doc).as_str())
+        );
+
+        // The block is searchable as python code
+        let symbols = db_arc
+            .list_symbols_for_file(&normalize_system_path(&doc))
+            .unwrap();
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].language, "python");
+        assert_eq!(
+            (symbols[0].start_line, symbols[0].end_line),
+            (Some(6), Some(6))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prose_document_extensions_chunked_like_markdown() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("guide.rst"),
+            "Guide\n=====\n\nInstall the package first.\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("manual.adoc"),
+            "= Manual\n\nRun the server.\n",
+        )
+        .unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = Arc::new(crate::config::Config::default());
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, config);
+        let result = indexer
+            .index_directory(temp_dir.path(), false, true, false)
+            .await
+            .unwrap();
+
+        assert_eq!(result.indexed, 2);
+        let query = embedder
+            .embed("Guide\n=====\n\nInstall the package first.")
+            .unwrap();
+        let hit = &db_arc.search(&query, 1).unwrap()[0];
+        assert!(hit.document_name.ends_with("/guide.rst"));
+        // Line ranges are only recorded on the markdown path
+        assert_eq!(hit.start_line, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_exclude_test_code_skips_test_files() {
+        let temp_dir = tempdir().unwrap();
+        let files = [
+            ("rates.go", "package rates\nfunc Rate() int { return 1 }\n"),
+            ("rates_test.go", "package rates\nfunc TestRate(t *T) {}\n"),
+            ("test_rates.py", "def test_rate():\n    pass\n"),
+            ("rates.spec.ts", "function specRate() {}\n"),
+            ("rates.test.js", "function testRate() {}\n"),
+        ];
+        for (name, content) in files {
+            fs::write(temp_dir.path().join(name), content).unwrap();
+        }
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = crate::config::Config {
+            exclude_test_code: true,
+            ..Default::default()
+        };
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(config));
+        let result = indexer
+            .index_directory(temp_dir.path(), false, true, false)
+            .await
+            .unwrap();
+
+        assert_eq!(result.indexed, 1);
+        let docs = db_arc.list_documents().unwrap();
+        assert_eq!(docs.len(), 1);
+        assert!(docs.keys().all(|d| d.ends_with("/rates.go")));
+    }
+
+    #[test]
+    fn test_is_generated_content() {
+        assert!(is_generated_content("// @generated by protoc\nfn f() {}\n"));
+        assert!(is_generated_content(
+            "// Code generated by stringer. DO NOT EDIT.\npackage p\n"
+        ));
+        assert!(is_generated_content(&"var a=1;".repeat(100)));
+        assert!(!is_generated_content(
+            "fn main() {\n    println!(\"hi\");\n}\n"
+        ));
+        assert!(!is_generated_content(""));
+    }
+
+    #[tokio::test]
+    async fn test_files_over_max_size_skipped() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("small.md"), "Small file").unwrap();
+        fs::write(
+            temp_dir.path().join("package-lock.json"),
+            format!("{{\"lock\": \"{}\"}}", "x".repeat(200)),
+        )
+        .unwrap();
+
+        let embedder = MockEmbedder::default();
+        for (max_file_size_bytes, expected_docs) in [(Some(100), 1), (None, 2)] {
+            let db_arc = Arc::new(Db::open_in_memory().unwrap());
+            let config = crate::config::Config {
+                max_file_size_bytes,
+                ..Default::default()
+            };
+            let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(config));
+            let result = indexer
+                .index_directory(temp_dir.path(), false, true, false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.skipped_large, 2 - expected_docs);
+            assert_eq!(result.indexed, expected_docs);
+            assert_eq!(db_arc.list_documents().unwrap().len(), expected_docs);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_minified_file_skipped_unless_index_generated() {
+        let temp_dir = tempdir().unwrap();
+        let minified = format!("function a(b){{return b+1}}{}\n", "var c=a(2);".repeat(200));
+        fs::write(temp_dir.path().join("app.min.js"), minified).unwrap();
+        fs::write(
+            temp_dir.path().join("app.js"),
+            "function add(a, b) {\n  return a + b;\n}\n",
+        )
+        .unwrap();
+
+        let embedder = MockEmbedder::default();
+        for (index_generated, expected_docs) in [(false, 1), (true, 2)] {
+            let db_arc = Arc::new(Db::open_in_memory().unwrap());
+            let config = crate::config::Config {
+                index_generated,
+                ..Default::default()
+            };
+            let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(config));
+            let result = indexer
+                .index_directory(temp_dir.path(), false, true, false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.generated, 2 - expected_docs);
+            assert_eq!(db_arc.list_documents().unwrap().len(), expected_docs);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_passage_prefix_applied_before_embedding() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("note.md"), "Plain body text.").unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = crate::config::Config {
+            passage_prefix: "passage: ".to_string(),
+            ..Default::default()
+        };
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(config));
+        indexer
+            .index_directory(temp_dir.path(), false, true, false)
+            .await
+            .unwrap();
+
+        let query = embedder.embed("passage: Plain body text.").unwrap();
+        let results = db_arc.search(&query, 1).unwrap();
+        // The prefix is embedded but not stored
+        assert_eq!(results[0].chunk_content, "Plain body text.");
+        assert!(results[0].similarity > 0.999, "{}", results[0].similarity);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_index_of_same_file() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("shared.md");
+        fs::write(&file, "# Shared\n\nFirst part.\n\nSecond part.").unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let indexer = Indexer::new(
+            db_arc.clone(),
+            &embedder,
+            20,
+            Arc::new(crate::config::Config::default()),
+        );
+
+        let (a, b) = tokio::join!(indexer.index_file(&file), indexer.index_file(&file));
+        assert!(a.unwrap() && b.unwrap());
+
+        let stats = db_arc.get_index_stats().unwrap();
+        assert_eq!(stats.documents, 1);
+        let expected = markdown::parse_markdown(&file, 20, 0, Default::default(), false)
+            .unwrap()
+            .len();
+        assert_eq!(stats.chunks as usize, expected);
+
+        // A second writer waits until the first releases the file
+        let filename = normalize_system_path(&file);
+        let guard = lock_file(&filename).await;
+        let waiting =
+            tokio::time::timeout(std::time::Duration::from_millis(50), lock_file(&filename));
+        assert!(waiting.await.is_err());
+        drop(guard);
+        let _relocked = lock_file(&filename).await;
+    }
+
+    #[tokio::test]
+    async fn test_symbol_outline_nests_methods_under_struct() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("counter.rs");
+        fs::write(
+            &file,
+            "pub struct Counter {\n    n: u32,\n}\n\nimpl Counter {\n    pub fn new() -> Self {\n        Self { n: 0 }\n    }\n\n    pub fn bump(&mut self) {\n        self.n += 1;\n    }\n}\n\nfn helper() {}\n",
+        )
+        .unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = Arc::new(crate::config::Config::default());
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, config);
+        indexer
+            .index_directory(temp_dir.path(), false, true, false)
+            .await
+            .unwrap();
+
+        let symbols = db_arc
+            .list_symbols_for_file(&normalize_system_path(&file))
+            .unwrap();
+        let outline = crate::db::search::symbol_outline(symbols);
+        let name = |n: &crate::db::search::SymbolNode| n.symbol.symbol_name.clone().unwrap();
+
+        let roots: Vec<String> = outline.iter().map(name).collect();
+        assert_eq!(roots, ["Counter", "Counter", "helper"]);
+        assert_eq!(outline[0].symbol.start_line, Some(1));
+        let methods: Vec<String> = outline[0].children.iter().map(name).collect();
+        assert_eq!(methods, ["new", "bump"]);
+        assert!(outline[1..].iter().all(|n| n.children.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_doc_comment_stored_with_symbol() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("rates.rs"),
+            "/// Fetch the latest exchange rates.\npub fn fetch_rates() {}\n\npub fn undocumented() {}\n",
+        )
+        .unwrap();
+
+        let db_arc = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = Arc::new(crate::config::Config::default());
+        let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, config);
+        indexer
+            .index_directory(temp_dir.path(), false, true, false)
+            .await
+            .unwrap();
+
+        let doc_comment = |symbol: &str| {
+            let found = db_arc.find_definitions(symbol, None, true).unwrap();
+            found[0].metadata.as_ref().unwrap().doc_comment.clone()
+        };
+        assert_eq!(
+            doc_comment("fetch_rates").as_deref(),
+            Some("Fetch the latest exchange rates.")
+        );
+        assert_eq!(doc_comment("undocumented"), None);
+    }
+
+    #[test]
+    fn test_bulk_frontmatter_over_markdown_files() {
+        use crate::frontmatter::{ApplyOutcome, Metadata, apply_frontmatter, parse};
+
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir(dir.join("guides")).unwrap();
+        fs::write(dir.join("a.md"), "# A\n").unwrap();
+        fs::write(dir.join("guides/b.md"), "# B\n").unwrap();
+        fs::write(dir.join("c.md"), "---\ndomain: frontend\n---\n# C\n").unwrap();
+        fs::write(dir.join("broken.md"), "---\ndomain: [unclosed\n---\n").unwrap();
+        fs::write(dir.join("notes.txt"), "Not markdown").unwrap();
+
+        let config = crate::config::Config::default();
+        let files = markdown_files(&config, dir);
+        let names: Vec<String> = files
+            .iter()
+            .map(|p| {
+                p.strip_prefix(dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        assert_eq!(names, ["a.md", "broken.md", "c.md", "guides/b.md"]);
+
+        let metadata = Metadata {
+            domain: "backend".to_string(),
+            ..Default::default()
+        };
+        let outcomes = |overwrite| -> Vec<&'static str> {
+            files
+                .iter()
+                .map(|p| apply_frontmatter(p, &metadata, overwrite).as_str())
+                .collect()
+        };
+        assert_eq!(outcomes(false), ["added", "failed", "skipped", "added"]);
+        assert!(matches!(
+            apply_frontmatter(&dir.join("broken.md"), &metadata, true),
+            ApplyOutcome::Failed(_)
+        ));
+
+        let domain = |name: &str| {
+            let content = fs::read_to_string(dir.join(name)).unwrap();
+            parse(&content).unwrap().0.unwrap().domain
+        };
+        assert_eq!(domain("a.md"), "backend");
+        assert_eq!(domain("guides/b.md"), "backend");
+        assert_eq!(domain("c.md"), "frontend");
+
+        assert_eq!(outcomes(true), ["updated", "failed", "updated", "updated"]);
+        assert_eq!(domain("c.md"), "backend");
+        assert_eq!(
+            fs::read_to_string(dir.join("notes.txt")).unwrap(),
+            "Not markdown"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_switching_chunk_strategy_rebuilds_index() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(
+            dir.join("guide.md"),
+            "# Guide\n\nIntro.\n\n## Setup\n\nRun it.\n",
+        )
+        .unwrap();
+
+        let db = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let mut indexer = Indexer::new(db.clone(), &embedder, 500, Arc::new(Default::default()));
+        indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+        assert_eq!(db.get_index_stats().unwrap().chunks, 1);
+
+        // The file is unchanged, but its chunks were cut by the other strategy
+        let config = crate::config::Config {
+            chunk_strategy: ChunkStrategy::Heading,
+            ..Default::default()
+        };
+        let mut indexer = Indexer::new(db.clone(), &embedder, 500, Arc::new(config));
+        indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+        assert_eq!(db.get_index_stats().unwrap().chunks, 2);
+    }
+
+    #[tokio::test]
+    async fn test_dedup_documents_by_hash_stores_alias() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir(dir.join("vendor")).unwrap();
+        let content = "# Shared\n\nThe same guide, copied into vendor.\n";
+        fs::write(dir.join("guide.md"), content).unwrap();
+        fs::write(dir.join("vendor/guide.md"), content).unwrap();
+
+        let db = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let config = crate::config::Config {
+            dedup_documents_by_hash: true,
+            ..Default::default()
+        };
+        let mut indexer = Indexer::new(db.clone(), &embedder, 500, Arc::new(config));
+        let res = indexer
+            .index_directory(dir, false, true, false)
+            .await
+            .unwrap();
+        assert_eq!(res.indexed, 2);
+
+        // Both paths are listed, but only the first one has vectors
+        let docs = db.list_documents_page(0, 10).unwrap();
+        assert_eq!(docs.len(), 2);
+        let original = normalize_system_path(&dir.join("guide.md"));
+        let alias = normalize_system_path(&dir.join("vendor/guide.md"));
+        assert_eq!(docs[0].filename, original);
+        assert_eq!(docs[0].alias_of, None);
+        assert_eq!(docs[1].filename, alias);
+        assert_eq!(docs[1].alias_of.as_deref(), Some(original.as_str()));
+        assert_eq!(db.get_index_stats().unwrap().chunks, 1);
+        let query = embedder
+            .embed("The same guide, copied into vendor.")
+            .unwrap();
+        let hits = db.search(&query, 10).unwrap();
+        assert!(hits.iter().all(|h| h.document_name == original));
+
+        // Once the copy diverges it is indexed on its own
+        fs::write(
+            dir.join("vendor/guide.md"),
+            "# Patched\n\nA local change.\n",
+        )
+        .unwrap();
+        indexer
+            .index_directory(dir, true, true, false)
+            .await
+            .unwrap();
+        let docs = db.list_documents_page(0, 10).unwrap();
+        assert!(docs.iter().all(|d| d.alias_of.is_none()));
+        assert_eq!(db.get_index_stats().unwrap().chunks, 2);
+
+        // Removing the original hands its vectors to the alias
+        fs::write(dir.join("vendor/guide.md"), content).unwrap();
+        indexer
+            .index_directory(dir, true, true, false)
+            .await
+            .unwrap();
+        assert_eq!(db.get_index_stats().unwrap().chunks, 1);
+        fs::remove_file(dir.join("guide.md")).unwrap();
+        let res = indexer
+            .index_directory(dir, true, true, false)
+            .await
+            .unwrap();
+        assert_eq!(res.removed, 1);
+        let docs = db.list_documents_page(0, 10).unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].filename, alias);
+        assert_eq!(docs[0].alias_of, None);
+        let hits = db.search(&query, 10).unwrap();
+        assert!(!hits.is_empty());
+        assert!(hits.iter().all(|h| h.document_name == alias));
     }
 }
@@ -147,6 +147,14 @@ impl DictionaryExtractor {
     }
 }
 
+/// Drop mappings with a confidence below `min_confidence`, returning how many
+/// were removed.
+pub fn retain_confident(mappings: &mut Vec<WordMapping>, min_confidence: f32) -> usize {
+    let before = mappings.len();
+    mappings.retain(|m| m.confidence >= min_confidence);
+    before - mappings.len()
+}
+
 pub fn split_camel_case(s: &str) -> Vec<String> {
     if s.contains('_') {
         return s
@@ -174,6 +182,78 @@ pub fn split_camel_case(s: &str) -> Vec<String> {
     words
 }
 
+/// Append the split form of code identifiers in `query` to it, e.g.
+/// `"find getUserById"` becomes `"find getUserById get user by id"`.
+///
+/// Tokens are split with [`split_camel_case`] after trimming surrounding
+/// punctuation; tokens that don't split into several words add nothing, and
+/// a query without identifiers is returned unchanged.
+pub fn expand_query_identifiers(query: &str) -> String {
+    let parts: Vec<String> = query
+        .split_whitespace()
+        .map(|token| token.trim_matches(|c: char| !c.is_alphanumeric() && c != '_'))
+        .map(split_camel_case)
+        .filter(|words| words.len() > 1)
+        .flatten()
+        .collect();
+    if parts.is_empty() {
+        query.to_string()
+    } else {
+        format!("{query} {}", parts.join(" "))
+    }
+}
+
+/// A query token and the dictionary words added for it by
+/// [`expand_query_with_dictionary`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Substitution {
+    pub token: String,
+    pub targets: Vec<String>,
+}
+
+/// Append the dictionary translations of `query`'s tokens to it, e.g.
+/// `"認証 エラー"` becomes `"認証 エラー authentication"`.
+///
+/// Tokens are runs of letters, digits and `_`; each distinct token is passed
+/// to `lookup` once, and target words already in the query aren't repeated.
+/// Returns the expanded query and the substitutions made.
+pub fn expand_query_with_dictionary<E>(
+    query: &str,
+    mut lookup: impl FnMut(&str) -> Result<Vec<String>, E>,
+) -> Result<(String, Vec<Substitution>), E> {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut words: HashSet<String> = query
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .map(str::to_lowercase)
+        .collect();
+    let mut added = Vec::new();
+    let mut substitutions = Vec::new();
+
+    for token in query.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        if token.is_empty() || !seen.insert(token.to_string()) {
+            continue;
+        }
+        let targets: Vec<String> = lookup(token)?
+            .into_iter()
+            .filter(|t| words.insert(t.to_lowercase()))
+            .collect();
+        if !targets.is_empty() {
+            added.extend(targets.iter().cloned());
+            substitutions.push(Substitution {
+                token: token.to_string(),
+                targets,
+            });
+        }
+    }
+
+    let expanded = if added.is_empty() {
+        query.to_string()
+    } else {
+        format!("{query} {}", added.join(" "))
+    };
+    Ok((expanded, substitutions))
+}
+
 pub fn is_chinese(s: &str) -> bool {
     s.chars().any(|c| {
         let u = c as u32;
@@ -182,9 +262,16 @@ pub fn is_chinese(s: &str) -> bool {
     })
 }
 
+/// Minimum share of kana among CJK characters for text to count as Japanese.
+///
+/// Japanese prose mixes kanji with kana (particles, okurigana, loanwords), so
+/// even kanji-heavy technical text clears this; Chinese text has no kana.
+const JA_MIN_KANA_SHARE: f64 = 0.1;
+
 /// Detect whether content is primarily CJK or English.
 /// Returns "zh", "ja", "ko", "en", "mixed", or "unknown".
 /// CJK detection covers Han ideographs (shared by zh/ja/ko), Hiragana, Katakana, and Hangul.
+/// Han text with a significant share of kana is "ja"; Han without kana is "zh".
 pub fn detect_language(s: &str) -> &'static str {
     let mut han_count = 0;
     let mut ja_kana_count = 0;
@@ -219,7 +306,13 @@ pub fn detect_language(s: &str) -> &'static str {
     let cjk_ratio = cjk_count as f64 / total_count as f64;
     let en_ratio = en_count as f64 / total_count as f64;
 
-    if ja_kana_count > han_count && ja_kana_count > ko_hangul_count && cjk_ratio > 0.1 {
+    let kana_share = if cjk_count > 0 {
+        ja_kana_count as f64 / cjk_count as f64
+    } else {
+        0.0
+    };
+
+    if kana_share >= JA_MIN_KANA_SHARE && ja_kana_count > ko_hangul_count && cjk_ratio > 0.1 {
         "ja"
     } else if ko_hangul_count > 0 && cjk_ratio > 0.1 {
         "ko"
@@ -236,6 +329,35 @@ pub fn detect_language(s: &str) -> &'static str {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_detect_language_japanese() {
+        // Kanji-heavy Japanese still carries kana
+        assert_eq!(detect_language("認証設定を変更する"), "ja");
+        assert_eq!(detect_language("データベース接続の初期化"), "ja");
+        // Mixed Japanese/English technical prose
+        assert_eq!(
+            detect_language("この関数は config を読み込みます。API key が必要です。"),
+            "ja"
+        );
+        assert_eq!(
+            detect_language(
+                "# Setup\n\nRun the server with the default settings.\n\n設定ファイルを編集してください。"
+            ),
+            "ja"
+        );
+    }
+
+    #[test]
+    fn test_detect_language_chinese_and_others() {
+        assert_eq!(detect_language("这是一个测试文档，用于验证配置"), "zh");
+        assert_eq!(detect_language("데이터베이스 연결"), "ko");
+        assert_eq!(
+            detect_language("This document is written entirely in English."),
+            "en"
+        );
+        assert_eq!(detect_language("1234 !!"), "unknown");
+    }
+
     #[test]
     fn test_extract_from_content() {
         let extractor = DictionaryExtractor::new();
@@ -266,6 +388,81 @@ mod tests {
         assert!(found_case_two);
     }
 
+    #[test]
+    fn test_low_confidence_mappings_not_persisted() {
+        let extractor = DictionaryExtractor::new();
+        // Parenthesised terms map at 1.0, their split words at 0.8
+        let mut mappings = extractor.extract_from_content("用户认证 (UserAuth)", "doc.md", "zh");
+        assert!(mappings.iter().any(|m| m.confidence < 0.9));
+
+        let dropped = retain_confident(&mut mappings, 0.9);
+        assert!(dropped > 0);
+
+        let db = crate::db::Db::open_in_memory().unwrap();
+        let rows: Vec<_> = mappings
+            .into_iter()
+            .map(|m| {
+                (
+                    m.source_word,
+                    m.target_word,
+                    m.source_lang,
+                    m.confidence as f64,
+                    m.source_document,
+                )
+            })
+            .collect();
+        db.insert_word_mappings(&rows).unwrap();
+
+        let stored = db.list_word_mappings(None).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].target_word, "userauth");
+        assert!(stored.iter().all(|m| m.confidence >= 0.9));
+    }
+
+    #[test]
+    fn test_expand_query_with_dictionary() {
+        let mut lookups = Vec::new();
+        let (expanded, substitutions) =
+            expand_query_with_dictionary("認証 エラー, 認証 auth", |token| {
+                lookups.push(token.to_string());
+                Ok::<_, ()>(match token {
+                    "認証" => vec!["authentication".to_string(), "auth".to_string()],
+                    _ => Vec::new(),
+                })
+            })
+            .unwrap();
+
+        assert_eq!(expanded, "認証 エラー, 認証 auth authentication");
+        assert_eq!(
+            substitutions,
+            [Substitution {
+                token: "認証".to_string(),
+                targets: vec!["authentication".to_string()],
+            }]
+        );
+        // Each distinct token is looked up once
+        assert_eq!(lookups, ["認証", "エラー", "auth"]);
+
+        let (unchanged, none) =
+            expand_query_with_dictionary("no mappings", |_| Ok::<_, ()>(Vec::new())).unwrap();
+        assert_eq!(unchanged, "no mappings");
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_expand_query_identifiers() {
+        let expanded = expand_query_identifiers("where is getUserById called?");
+        assert_eq!(expanded, "where is getUserById called? get user by id");
+        assert_eq!(
+            expand_query_identifiers("`parse_config_file()` errors"),
+            "`parse_config_file()` errors parse config file"
+        );
+        assert_eq!(
+            expand_query_identifiers("plain words only"),
+            "plain words only"
+        );
+    }
+
     #[test]
     fn test_split_camel_case() {
         assert_eq!(
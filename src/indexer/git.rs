@@ -0,0 +1,122 @@
+/// Git integration for incremental indexing.
+///
+/// Shells out to the `git` CLI to list files changed since a ref, so only
+/// those files need to be reindexed or removed.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn run_git(repo: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Root directory of the work tree containing `dir`.
+pub fn repo_root(dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let root = run_git(dir, &["rev-parse", "--show-toplevel"])?;
+    Ok(PathBuf::from(root.trim()))
+}
+
+/// Absolute paths of files under `dir` that differ between `since_ref` and
+/// the work tree, plus untracked files that aren't ignored.
+///
+/// Includes deleted files (which no longer exist on disk) so callers can
+/// drop them from the index.
+pub fn changed_files(
+    dir: &Path,
+    since_ref: &str,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    if since_ref.is_empty() || since_ref.starts_with('-') {
+        return Err(format!("invalid git ref: {since_ref:?}").into());
+    }
+    let dir = dir.canonicalize()?;
+    // `--relative` limits the diff to `dir` and prints paths relative to it,
+    // as `ls-files` does; `-z` keeps unusual names unquoted.
+    let diff = run_git(
+        &dir,
+        &["diff", "--name-only", "--relative", "-z", since_ref, "--"],
+    )?;
+    let untracked = run_git(&dir, &["ls-files", "--others", "--exclude-standard", "-z"])?;
+
+    let mut files: Vec<PathBuf> = diff
+        .split('\0')
+        .chain(untracked.split('\0'))
+        .filter(|l| !l.is_empty())
+        .map(|l| dir.join(l))
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[test]
+    fn test_changed_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        git(dir, &["init", "-q"]);
+        fs::write(dir.join("a.md"), "A").unwrap();
+        fs::write(dir.join("b.md"), "B").unwrap();
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-q", "-m", "init"]);
+
+        fs::write(dir.join("a.md"), "A2").unwrap();
+        fs::write(dir.join("new file ü.md"), "N").unwrap();
+        let changed = changed_files(dir, "HEAD").unwrap();
+        let names: Vec<_> = changed
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["a.md", "new file ü.md"]);
+    }
+
+    #[test]
+    fn test_changed_files_limited_to_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        git(dir, &["init", "-q"]);
+        fs::create_dir(dir.join("docs")).unwrap();
+        fs::write(dir.join("docs/a.md"), "A").unwrap();
+        fs::write(dir.join("b.md"), "B").unwrap();
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-q", "-m", "init"]);
+
+        fs::write(dir.join("docs/a.md"), "A2").unwrap();
+        fs::write(dir.join("b.md"), "B2").unwrap();
+        fs::write(dir.join("c.md"), "C").unwrap();
+        let changed = changed_files(&dir.join("docs"), "HEAD").unwrap();
+        assert_eq!(changed, [dir.canonicalize().unwrap().join("docs/a.md")]);
+    }
+
+    #[test]
+    fn test_changed_files_rejects_option_like_ref() {
+        let temp = tempfile::tempdir().unwrap();
+        assert!(changed_files(temp.path(), "--output=/tmp/x").is_err());
+    }
+}
@@ -18,6 +18,9 @@ static ALL_CONFIGS: LazyLock<Vec<LanguageConfig>> = LazyLock::new(|| {
         typescript_config(),
         javascript_config(),
         rust_config(),
+        java_config(),
+        c_config(),
+        cpp_config(),
     ]
 });
 
@@ -26,6 +29,15 @@ impl LanguageConfig {
         &ALL_CONFIGS
     }
 
+    /// The languages named in `enabled`, or all of them when `None`.
+    pub fn get_enabled(
+        enabled: Option<&[String]>,
+    ) -> impl Iterator<Item = &'static LanguageConfig> {
+        Self::get_all()
+            .iter()
+            .filter(move |c| enabled.is_none_or(|names| names.iter().any(|n| n == c.name)))
+    }
+
     pub fn get_by_extension(ext: &str) -> Option<&'static LanguageConfig> {
         Self::get_all().iter().find(|c| c.extensions.contains(&ext))
     }
@@ -238,6 +250,147 @@ fn rust_config() -> LanguageConfig {
     }
 }
 
+fn java_config() -> LanguageConfig {
+    LanguageConfig {
+        name: "java",
+        language: tree_sitter_java::LANGUAGE.into(),
+        extensions: &["java"],
+        query: r#"
+(class_declaration
+  name: (identifier) @name) @class
+
+(record_declaration
+  name: (identifier) @name) @class
+
+(enum_declaration
+  name: (identifier) @name) @class
+
+(interface_declaration
+  name: (identifier) @name) @interface
+
+(method_declaration
+  name: (identifier) @name) @method
+
+(constructor_declaration
+  name: (identifier) @name) @method
+"#,
+        call_query: r#"
+(method_invocation
+  name: (identifier) @call)
+"#,
+        import_query: r#"
+(import_declaration
+  (scoped_identifier) @import)
+(import_declaration
+  (identifier) @import)
+"#,
+        inherit_query: r#"
+(superclass
+  (type_identifier) @inherit)
+(super_interfaces
+  (type_list
+    (type_identifier) @inherit))
+(extends_interfaces
+  (type_list
+    (type_identifier) @inherit))
+"#,
+    }
+}
+
+fn c_config() -> LanguageConfig {
+    LanguageConfig {
+        name: "c",
+        language: tree_sitter_c::LANGUAGE.into(),
+        extensions: &["c", "h"],
+        query: r#"
+(function_definition
+  declarator: (function_declarator
+    declarator: (identifier) @name)) @function
+
+(function_definition
+  declarator: (pointer_declarator
+    declarator: (function_declarator
+      declarator: (identifier) @name))) @function
+
+(struct_specifier
+  name: (type_identifier) @name
+  body: (field_declaration_list)) @struct
+"#,
+        call_query: r#"
+(call_expression
+  function: (identifier) @call)
+(call_expression
+  function: (field_expression
+    field: (field_identifier) @call))
+"#,
+        import_query: r#"
+(preproc_include
+  path: (_) @import)
+"#,
+        inherit_query: "",
+    }
+}
+
+fn cpp_config() -> LanguageConfig {
+    LanguageConfig {
+        name: "cpp",
+        language: tree_sitter_cpp::LANGUAGE.into(),
+        extensions: &["cpp", "cc", "cxx", "hpp", "hh", "hxx"],
+        query: r#"
+(function_definition
+  declarator: (function_declarator
+    declarator: (identifier) @name)) @function
+
+(function_definition
+  declarator: (function_declarator
+    declarator: (field_identifier) @name)) @method
+
+(function_definition
+  declarator: (function_declarator
+    declarator: (qualified_identifier
+      name: (identifier) @name))) @method
+
+(function_definition
+  declarator: (pointer_declarator
+    declarator: (function_declarator
+      declarator: (identifier) @name))) @function
+
+(function_definition
+  declarator: (reference_declarator
+    (function_declarator
+      declarator: (identifier) @name))) @function
+
+(class_specifier
+  name: (type_identifier) @name
+  body: (field_declaration_list)) @class
+
+(struct_specifier
+  name: (type_identifier) @name
+  body: (field_declaration_list)) @struct
+"#,
+        call_query: r#"
+(call_expression
+  function: (identifier) @call)
+(call_expression
+  function: (field_expression
+    field: (field_identifier) @call))
+(call_expression
+  function: (qualified_identifier
+    name: (identifier) @call))
+"#,
+        import_query: r#"
+(preproc_include
+  path: (_) @import)
+"#,
+        inherit_query: r#"
+(base_class_clause
+  (type_identifier) @inherit)
+(base_class_clause
+  (qualified_identifier) @inherit)
+"#,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -282,4 +435,17 @@ mod tests {
         assert!(cts_config.is_some());
         assert_eq!(cts_config.unwrap().name, "typescript");
     }
+
+    #[test]
+    fn test_c_family_extensions() {
+        assert_eq!(
+            LanguageConfig::get_by_extension("java").unwrap().name,
+            "java"
+        );
+        assert_eq!(LanguageConfig::get_by_extension("c").unwrap().name, "c");
+        assert_eq!(LanguageConfig::get_by_extension("h").unwrap().name, "c");
+        for ext in ["cpp", "cc", "hpp"] {
+            assert_eq!(LanguageConfig::get_by_extension(ext).unwrap().name, "cpp");
+        }
+    }
 }
@@ -1,3 +1,6 @@
+use super::code_parser::CodeChunk;
+use super::languages::LanguageConfig;
+use crate::config::ChunkStrategy;
 use std::fs;
 use std::path::Path;
 
@@ -5,24 +8,106 @@ use std::path::Path;
 pub struct Chunk {
     pub content: String,
     pub position: usize,
+    /// 1-based line in the source file where the chunk starts, if known.
+    pub start_line: Option<usize>,
+    /// 1-based line in the source file where the chunk ends, if known.
+    pub end_line: Option<usize>,
 }
 
-/// Parses a markdown file and splits it into chunks.
+/// Parses a markdown file and splits it into chunks (see
+/// [`parse_markdown_content`]).
 pub fn parse_markdown<P: AsRef<Path>>(
     filepath: P,
     chunk_size: usize,
+    chunk_overlap: usize,
+    strategy: ChunkStrategy,
+    normalize: bool,
 ) -> std::io::Result<Vec<Chunk>> {
     let content = fs::read_to_string(filepath)?;
-    let chunks = split_into_chunks(&content, chunk_size);
-    Ok(chunks
+    Ok(parse_markdown_content(
+        &content,
+        chunk_size,
+        chunk_overlap,
+        strategy,
+        normalize,
+    ))
+}
+
+/// Splits the `content` of a markdown file into chunks.
+///
+/// A leading YAML frontmatter block is left out: it is metadata, and
+/// embedding it would pull every annotated document toward the same
+/// `domain:`/`tags:` text. Line numbers still refer to the whole file.
+///
+/// `chunk_overlap` only applies to [`ChunkStrategy::Size`]; heading chunks
+/// already carry their context in the heading trail.
+///
+/// With `normalize`, the body goes through [`normalize_whitespace`] before it
+/// is split, so fences that span two chunks stay verbatim; line numbers are
+/// mapped back to the original file.
+pub fn parse_markdown_content(
+    content: &str,
+    chunk_size: usize,
+    chunk_overlap: usize,
+    strategy: ChunkStrategy,
+    normalize: bool,
+) -> Vec<Chunk> {
+    let content = strip_bom(content);
+    let body_start = crate::frontmatter::body_offset(content);
+    let skipped_lines = content[..body_start].matches('\n').count();
+    let normalized;
+    let (body, line_map) = if normalize {
+        let (text, lines) = normalize_whitespace_with_lines(&content[body_start..]);
+        normalized = text;
+        (normalized.as_str(), Some(lines))
+    } else {
+        (&content[body_start..], None)
+    };
+    let original_line = |line: usize| {
+        let line = line_map
+            .as_ref()
+            .and_then(|m| m.get(line - 1).copied())
+            .unwrap_or(line);
+        line + skipped_lines
+    };
+    let mut chunks = match strategy {
+        ChunkStrategy::Size => split_into_chunks_with_lines(body, chunk_size),
+        ChunkStrategy::Heading => split_by_headings_with_lines(body, chunk_size),
+    };
+    if strategy == ChunkStrategy::Size {
+        add_overlap(&mut chunks, chunk_overlap.min(chunk_size.saturating_sub(1)));
+    }
+    chunks
         .into_iter()
         .enumerate()
-        .map(|(position, content)| Chunk { content, position })
-        .collect())
+        .map(|(position, (content, start_line, end_line))| Chunk {
+            content,
+            position,
+            start_line: Some(original_line(start_line)),
+            end_line: Some(original_line(end_line)),
+        })
+        .collect()
+}
+
+/// Drop a leading UTF-8 byte order mark, as written by some Windows editors.
+pub fn strip_bom(content: &str) -> &str {
+    content.strip_prefix('\u{FEFF}').unwrap_or(content)
 }
 
 /// Splits text into chunks of approximately `chunk_size` characters (using `char` count).
 pub fn split_into_chunks(content: &str, chunk_size: usize) -> Vec<String> {
+    split_into_chunks_with_lines(content, chunk_size)
+        .into_iter()
+        .map(|(chunk, _, _)| chunk)
+        .collect()
+}
+
+/// [`split_into_chunks`], pairing each chunk with the 1-based, inclusive
+/// range of `content` lines it spans.
+pub fn split_into_chunks_with_lines(
+    content: &str,
+    chunk_size: usize,
+) -> Vec<(String, usize, usize)> {
     let char_count = content.chars().count();
 
     if char_count <= chunk_size {
@@ -30,11 +115,16 @@ pub fn split_into_chunks(content: &str, chunk_size: usize) -> Vec<String> {
         if trimmed.is_empty() {
             return Vec::new();
         }
-        return vec![trimmed.to_string()];
+        let start = offset_in(content, trimmed);
+        let end = start + trimmed.len();
+        return offsets_to_lines(content, vec![(trimmed.to_string(), start, end)]);
     }
 
+    // Chunks paired with the byte offsets they start and end at
     let mut chunks = Vec::new();
     let mut current_chunk = String::new();
+    let mut current_start = 0;
+    let mut current_end = 0;
 
     // Split by paragraphs (double newline)
     let paragraphs: Vec<&str> = content.split("\n\n").collect();
@@ -44,14 +134,18 @@ pub fn split_into_chunks(content: &str, chunk_size: usize) -> Vec<String> {
         if para.is_empty() {
             continue;
         }
+        let para_start = offset_in(content, para);
 
         let current_len = current_chunk.chars().count();
         let para_len = para.chars().count();
 
         // If adding this paragraph exceeds chunk size, start new chunk
         if current_len > 0 && current_len + para_len + 2 > chunk_size {
-            chunks.push(current_chunk.clone());
-            current_chunk.clear();
+            chunks.push((
+                std::mem::take(&mut current_chunk),
+                current_start,
+                current_end,
+            ));
         }
 
         let act_current_len = current_chunk.chars().count();
@@ -60,29 +154,395 @@ pub fn split_into_chunks(content: &str, chunk_size: usize) -> Vec<String> {
         if para_len > chunk_size {
             // Flush current chunk first
             if act_current_len > 0 {
-                chunks.push(current_chunk.clone());
-                current_chunk.clear();
+                chunks.push((
+                    std::mem::take(&mut current_chunk),
+                    current_start,
+                    current_end,
+                ));
             }
 
-            // Split by sentences or fixed size
-            let sub_chunks = split_large_paragraph(para, chunk_size);
-            chunks.extend(sub_chunks);
+            // Split by sentences or fixed size; pieces are in-order substrings
+            let mut search_from = 0;
+            for piece in split_large_paragraph(para, chunk_size) {
+                let at = para[search_from..]
+                    .find(piece.as_str())
+                    .map_or(search_from, |i| search_from + i);
+                search_from = at + piece.len();
+                chunks.push((piece, para_start + at, para_start + search_from));
+            }
         } else {
             if act_current_len > 0 {
                 current_chunk.push_str("\n\n");
+            } else {
+                current_start = para_start;
             }
             current_chunk.push_str(para);
+            current_end = para_start + para.len();
         }
     }
 
     // Add remaining chunk
     if !current_chunk.is_empty() {
-        chunks.push(current_chunk);
+        chunks.push((current_chunk, current_start, current_end));
+    }
+
+    offsets_to_lines(content, chunks)
+}
+
+/// Start every chunk after the first with the last `overlap` characters of
+/// the chunk before it. Line ranges keep describing each chunk's own text.
+pub fn add_overlap(chunks: &mut [(String, usize, usize)], overlap: usize) {
+    if overlap == 0 {
+        return;
+    }
+    // Walk backwards so each tail comes from the previous chunk's own text
+    for i in (1..chunks.len()).rev() {
+        let prev = &chunks[i - 1].0;
+        let tail_start = prev
+            .char_indices()
+            .rev()
+            .nth(overlap - 1)
+            .map_or(0, |(at, _)| at);
+        let tail = prev[tail_start..].trim_start();
+        if !tail.is_empty() {
+            chunks[i].0 = format!("{tail}\n\n{}", chunks[i].0);
+        }
+    }
+}
+
+/// Byte offset of `part` within `whole`, which it must be a subslice of.
+fn offset_in(whole: &str, part: &str) -> usize {
+    part.as_ptr() as usize - whole.as_ptr() as usize
+}
+
+/// Replace ascending `(start, end)` byte offsets into `text` with 1-based
+/// line numbers.
+fn offsets_to_lines(
+    text: &str,
+    chunks: Vec<(String, usize, usize)>,
+) -> Vec<(String, usize, usize)> {
+    let mut line = 1;
+    let mut last = 0;
+    let mut advance = |offset: usize| {
+        let offset = offset.max(last);
+        line += text[last..offset].matches('\n').count();
+        last = offset;
+        line
+    };
+    chunks
+        .into_iter()
+        .map(|(chunk, start, end)| {
+            let start = advance(start);
+            (chunk, start, advance(end))
+        })
+        .collect()
+}
+
+/// Splits markdown into one chunk per heading section.
+///
+/// A section runs from an ATX heading (`#`..`######`) to the next heading of
+/// the same or higher level. Each chunk starts with the heading trail
+/// (e.g. `"Guide > Install"`) in place of the heading line, so the embedding
+/// carries its section context. Sections longer than `chunk_size` fall back to
+/// [`split_into_chunks`], repeating the trail on every piece. Headings inside
+/// fenced code blocks are ignored.
+pub fn split_by_headings(content: &str, chunk_size: usize) -> Vec<String> {
+    split_by_headings_with_lines(content, chunk_size)
+        .into_iter()
+        .map(|(chunk, _, _)| chunk)
+        .collect()
+}
+
+/// [`split_by_headings`], pairing each chunk with the 1-based, inclusive
+/// range of `content` lines it spans. A section's first chunk starts on its
+/// heading line.
+pub fn split_by_headings_with_lines(
+    content: &str,
+    chunk_size: usize,
+) -> Vec<(String, usize, usize)> {
+    // (heading trail, body, line of the heading or 1 for the preamble)
+    let mut sections: Vec<(String, String, usize)> = Vec::new();
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    let mut trail = String::new();
+    let mut body = String::new();
+    let mut heading_line = 1;
+    let mut in_fence = false;
+
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+
+        let heading = if in_fence { None } else { parse_heading(line) };
+        let Some((level, title)) = heading else {
+            body.push_str(line);
+            body.push('\n');
+            continue;
+        };
+
+        sections.push((
+            std::mem::take(&mut trail),
+            std::mem::take(&mut body),
+            heading_line,
+        ));
+        heading_line = index + 1;
+        while stack.last().is_some_and(|(l, _)| *l >= level) {
+            stack.pop();
+        }
+        stack.push((level, title));
+        trail = stack
+            .iter()
+            .map(|(_, t)| t.as_str())
+            .collect::<Vec<_>>()
+            .join(" > ");
     }
+    sections.push((trail, body, heading_line));
 
+    let mut chunks = Vec::new();
+    for (trail, body, heading_line) in sections {
+        let trimmed = body.trim();
+        // Heading-only sections still contribute to their children's trail
+        if trimmed.is_empty() {
+            continue;
+        }
+        // Line of `content` that the trimmed body starts on
+        let body_line = if trail.is_empty() {
+            heading_line
+        } else {
+            heading_line + 1
+        } + body[..offset_in(&body, trimmed)].matches('\n').count();
+        let pieces = |size| {
+            split_into_chunks_with_lines(trimmed, size)
+                .into_iter()
+                .map(move |(piece, start, end)| (piece, body_line + start - 1, body_line + end - 1))
+        };
+
+        if trail.is_empty() {
+            chunks.extend(pieces(chunk_size));
+            continue;
+        }
+
+        let prefix_len = trail.chars().count() + 2;
+        if prefix_len + trimmed.chars().count() <= chunk_size {
+            let end_line = body_line + trimmed.matches('\n').count();
+            chunks.push((format!("{trail}\n\n{trimmed}"), heading_line, end_line));
+        } else {
+            let budget = chunk_size.saturating_sub(prefix_len).max(chunk_size / 2);
+            chunks.extend(pieces(budget).enumerate().map(|(i, (piece, start, end))| {
+                let start = if i == 0 { heading_line } else { start };
+                (format!("{trail}\n\n{piece}"), start, end)
+            }));
+        }
+    }
+
+    // A document made only of headings still deserves a chunk
+    if chunks.is_empty() {
+        return split_into_chunks_with_lines(content, chunk_size);
+    }
+    chunks
+}
+
+/// Text of the first level-1 ATX heading (`# Title`) after any frontmatter,
+/// ignoring lines inside code fences.
+pub fn first_h1(content: &str) -> Option<String> {
+    let body = &content[crate::frontmatter::body_offset(content)..];
+    let mut in_fence = false;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some((1, title)) = parse_heading(line) {
+            return Some(title);
+        }
+    }
+    None
+}
+
+/// Parse an ATX heading line into `(level, title)`.
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    // Up to three spaces of indentation are allowed
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let level = rest.len() - rest.trim_start_matches('#').len();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let text = &rest[level..];
+    if !text.is_empty() && !text.starts_with([' ', '\t']) {
+        return None;
+    }
+    let title = text.trim().trim_end_matches('#').trim_end();
+    if title.is_empty() {
+        return None;
+    }
+    Some((level, title.to_string()))
+}
+
+/// `symbol_type` of a fenced code block indexed from a markdown file.
+pub const CODE_BLOCK_SYMBOL_TYPE: &str = "code_block";
+
+/// `symbol_type` of the frontmatter block indexed from a markdown file.
+pub const FRONTMATTER_SYMBOL_TYPE: &str = "frontmatter";
+
+/// The YAML between the `---` delimiters of a leading frontmatter block, as
+/// a chunk spanning those lines. `None` if there is no block or it's empty.
+pub fn frontmatter_chunk(content: &str) -> Option<CodeChunk> {
+    let block = &content[..crate::frontmatter::body_offset(content)];
+    let yaml: Vec<&str> = block.lines().skip(1).collect();
+    let yaml = &yaml[..yaml.len().checked_sub(1)?];
+    if yaml.iter().all(|l| l.trim().is_empty()) {
+        return None;
+    }
+    Some(CodeChunk {
+        content: yaml.join("\n"),
+        position: 0,
+        symbol_name: FRONTMATTER_SYMBOL_TYPE.to_string(),
+        symbol_type: FRONTMATTER_SYMBOL_TYPE.to_string(),
+        language: "yaml".to_string(),
+        start_line: 2,
+        end_line: yaml.len() + 1,
+        parent_symbol: None,
+        signature: String::new(),
+        comment_ranges: Vec::new(),
+        doc_comment: None,
+        doc_range: None,
+        part: 0,
+    })
+}
+
+/// Fenced code blocks whose info string names a supported language, one
+/// chunk each (`code block 1`, `code block 2`, ...) with the lines of the code
+/// inside the fence. Other fences are left to the prose chunks.
+pub fn code_block_chunks(content: &str) -> Vec<CodeChunk> {
+    let mut chunks = Vec::new();
+    // (fence marker, language, first code line, code lines)
+    let mut open: Option<(String, Option<&'static str>, usize, Vec<&str>)> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        let marker = fence_marker(trimmed);
+        match open.as_mut() {
+            None => {
+                if let Some(marker) = marker {
+                    let info = trimmed[marker.len()..].split_whitespace().next();
+                    open = Some((
+                        marker.to_string(),
+                        fence_language(info.unwrap_or("")),
+                        index + 2,
+                        Vec::new(),
+                    ));
+                }
+            }
+            Some((open_marker, _, _, lines)) => {
+                // Closed by a bare fence of the same character, at least as long
+                let closes =
+                    marker.is_some_and(|m| m == trimmed && m.starts_with(open_marker.as_str()));
+                if !closes {
+                    lines.push(line);
+                    continue;
+                }
+                let (_, language, start_line, lines) = open.take().unwrap_or_default();
+                let code = lines.join("\n");
+                let Some(language) = language.filter(|_| !code.trim().is_empty()) else {
+                    continue;
+                };
+                chunks.push(CodeChunk {
+                    content: code,
+                    position: chunks.len(),
+                    symbol_name: format!("code block {}", chunks.len() + 1),
+                    symbol_type: CODE_BLOCK_SYMBOL_TYPE.to_string(),
+                    language: language.to_string(),
+                    start_line,
+                    end_line: start_line + lines.len() - 1,
+                    parent_symbol: None,
+                    signature: String::new(),
+                    comment_ranges: Vec::new(),
+                    doc_comment: None,
+                    doc_range: None,
+                    part: 0,
+                });
+            }
+        }
+    }
     chunks
 }
 
+/// The run of three or more backticks or tildes opening `line`, if any.
+fn fence_marker(line: &str) -> Option<&str> {
+    let first = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let len = line.len() - line.trim_start_matches(first).len();
+    (len >= 3).then(|| &line[..len])
+}
+
+/// Map a fence info string (`python`, `py`, `c++`, ...) to a supported language.
+fn fence_language(info: &str) -> Option<&'static str> {
+    let info = info.to_ascii_lowercase();
+    let info = match info.as_str() {
+        "golang" => "go",
+        "c++" => "cpp",
+        other => other,
+    };
+    LanguageConfig::get_by_name(info)
+        .or_else(|| LanguageConfig::get_by_extension(info))
+        .map(|config| config.name)
+}
+
+/// Collapses whitespace noise in prose while preserving fenced code blocks.
+///
+/// Outside ```` ``` ```` / `~~~` fences, runs of spaces and tabs become a single
+/// space, lines are trimmed, and consecutive blank lines collapse into one.
+/// Lines inside a fence (including the fence markers) are kept verbatim.
+pub fn normalize_whitespace(content: &str) -> String {
+    normalize_whitespace_with_lines(content).0
+}
+
+/// [`normalize_whitespace`], also returning for each output line the 1-based
+/// line of `content` it came from.
+pub fn normalize_whitespace_with_lines(content: &str) -> (String, Vec<usize>) {
+    let mut lines: Vec<String> = Vec::new();
+    let mut origins = Vec::new();
+    let mut in_fence = false;
+    let mut prev_blank = false;
+
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let is_fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+
+        if in_fence || is_fence {
+            lines.push(line.trim_end().to_string());
+            origins.push(index + 1);
+            if is_fence {
+                in_fence = !in_fence;
+            }
+            prev_blank = false;
+            continue;
+        }
+
+        let collapsed = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if collapsed.is_empty() {
+            if !prev_blank && !lines.is_empty() {
+                lines.push(String::new());
+                origins.push(index + 1);
+            }
+            prev_blank = true;
+        } else {
+            lines.push(collapsed);
+            origins.push(index + 1);
+            prev_blank = false;
+        }
+    }
+
+    (lines.join("\n").trim_end().to_string(), origins)
+}
+
 /// Splits a large paragraph into smaller chunks, preferring sentence boundaries.
 fn split_large_paragraph(para: &str, chunk_size: usize) -> Vec<String> {
     let mut chunks = Vec::new();
@@ -181,15 +641,264 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_normalize_whitespace_preserves_code_fence() {
+        let content = "Some   text\twith   gaps   \n\n\n\nNext  paragraph\n\n```rust\nfn main() {\n    let  x = 1;\n}\n```\n";
+        let normalized = normalize_whitespace(content);
+
+        assert!(normalized.contains("Some text with gaps\n\nNext paragraph"));
+        assert!(!normalized.contains("\n\n\n"));
+        // Indentation and inner spacing inside the fence are preserved
+        assert!(normalized.contains("fn main() {\n    let  x = 1;\n}"));
+    }
+
+    #[test]
+    fn test_parse_markdown_normalizes_before_splitting() {
+        let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            temp_file,
+            "Intro   text\n\n\n\n```py\ndef f():\n    x  =  1\n\n\n    return x\n```\n\nAfter   fence."
+        )
+        .unwrap();
+
+        let chunks = parse_markdown(temp_file.path(), 30, 0, ChunkStrategy::Size, true).unwrap();
+        let lines: Vec<_> = chunks
+            .iter()
+            .map(|c| (c.content.as_str(), c.start_line, c.end_line))
+            .collect();
+        // The fence is split across chunks but keeps its spacing, and the
+        // prose after it is still normalized
+        assert_eq!(
+            lines,
+            vec![
+                ("Intro text", Some(1), Some(1)),
+                ("```py\ndef f():\n    x  =  1", Some(5), Some(7)),
+                ("return x\n```\n\nAfter fence.", Some(10), Some(13)),
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_markdown_short_file() {
         let content = "# Test\n\nThis is a short file.";
         let mut temp_file = tempfile::NamedTempFile::new().unwrap();
         write!(temp_file, "{}", content).unwrap();
 
-        let chunks = parse_markdown(temp_file.path(), 500).unwrap();
+        let chunks = parse_markdown(temp_file.path(), 500, 0, ChunkStrategy::Size, false).unwrap();
         assert_eq!(chunks.len(), 1);
         assert_eq!(chunks[0].position, 0);
         assert!(chunks[0].content.contains("Test"));
     }
+
+    #[test]
+    fn test_split_by_headings_keeps_sections() {
+        let content = "Intro text.\n\n# Guide\n\n## Install\n\nRun the installer.\n\nThen restart.\n\n## Usage\n\nCall it.\n\n# FAQ\n\nAsk away.\n";
+        let chunks = split_by_headings(content, 500);
+
+        assert_eq!(
+            chunks,
+            vec![
+                "Intro text.",
+                "Guide > Install\n\nRun the installer.\n\nThen restart.",
+                "Guide > Usage\n\nCall it.",
+                "FAQ\n\nAsk away.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_by_headings_splits_oversized_section() {
+        let para = "Sentence in a long section. ".repeat(10);
+        let content = format!("# Top\n\n## Long\n\n{}", vec![para; 6].join("\n\n"));
+        let chunks = split_by_headings(&content, 500);
+
+        assert!(chunks.len() >= 2);
+        for chunk in &chunks {
+            assert!(chunk.starts_with("Top > Long\n\n"));
+            assert!(chunk.chars().count() <= 500);
+        }
+    }
+
+    #[test]
+    fn test_split_by_headings_ignores_fenced_hashes() {
+        let content = "# Setup\n\n```bash\n# not a heading\nmake\n```\n";
+        let chunks = split_by_headings(content, 500);
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].starts_with("Setup\n\n```bash\n# not a heading"));
+    }
+
+    #[test]
+    fn test_split_by_headings_heading_only_document() {
+        assert_eq!(split_by_headings("# Title\n", 500), vec!["# Title"]);
+        assert!(split_by_headings("  \n", 500).is_empty());
+    }
+
+    #[test]
+    fn test_chunk_start_lines() {
+        let content = "\n# Title\n\nFirst para.\n\nSecond para.\n";
+        assert_eq!(
+            split_into_chunks_with_lines(content, 15),
+            vec![
+                ("# Title".to_string(), 2, 2),
+                ("First para.".to_string(), 4, 4),
+                ("Second para.".to_string(), 6, 6),
+            ]
+        );
+
+        let content = "Intro.\n\n# Guide\n\nRead this.\n\n## Install\n\n\nRun it.\n";
+        assert_eq!(
+            split_by_headings_with_lines(content, 500),
+            vec![
+                ("Intro.".to_string(), 1, 1),
+                ("Guide\n\nRead this.".to_string(), 3, 5),
+                ("Guide > Install\n\nRun it.".to_string(), 7, 10),
+            ]
+        );
+
+        // Later pieces of an oversized section point into the body
+        let content = "# Long\n\nalpha alpha alpha.\n\nbeta beta beta.\n";
+        let chunks = split_by_headings_with_lines(content, 25);
+        assert_eq!(
+            chunks,
+            vec![
+                ("Long\n\nalpha alpha alpha.".to_string(), 1, 3),
+                ("Long\n\nbeta beta beta.".to_string(), 5, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_markdown_records_line_ranges() {
+        let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            temp_file,
+            "First paragraph\nspans two lines.\n\n\nSecond paragraph\nalso spans\nthree lines."
+        )
+        .unwrap();
+
+        let chunks = parse_markdown(temp_file.path(), 40, 0, ChunkStrategy::Size, false).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            (chunks[0].start_line, chunks[0].end_line),
+            (Some(1), Some(2))
+        );
+        assert_eq!(
+            (chunks[1].start_line, chunks[1].end_line),
+            (Some(5), Some(7))
+        );
+    }
+
+    #[test]
+    fn test_parse_markdown_skips_frontmatter() {
+        let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            temp_file,
+            "---\ndomain: backend\ntags: [auth]\n---\nLogin flow\ndescribed here."
+        )
+        .unwrap();
+
+        for strategy in [ChunkStrategy::Size, ChunkStrategy::Heading] {
+            let chunks = parse_markdown(temp_file.path(), 500, 0, strategy, false).unwrap();
+            assert_eq!(chunks.len(), 1);
+            assert_eq!(chunks[0].content, "Login flow\ndescribed here.");
+            assert_eq!(
+                (chunks[0].start_line, chunks[0].end_line),
+                (Some(5), Some(6))
+            );
+        }
+    }
+
+    #[test]
+    fn test_frontmatter_chunk() {
+        let content = "---\ndomain: backend\ntags: [auth]\n---\nBody text.";
+        let chunk = frontmatter_chunk(content).unwrap();
+        assert_eq!(chunk.content, "domain: backend\ntags: [auth]");
+        assert_eq!(chunk.symbol_type, FRONTMATTER_SYMBOL_TYPE);
+        assert_eq!((chunk.start_line, chunk.end_line), (2, 3));
+
+        assert!(frontmatter_chunk("---\n---\nBody").is_none());
+        assert!(frontmatter_chunk("Body only").is_none());
+    }
+
+    #[test]
+    fn test_add_overlap_repeats_previous_tail() {
+        let mut chunks = split_into_chunks_with_lines("Question here?\n\nAnswer there.", 15);
+        add_overlap(&mut chunks, 5);
+        assert_eq!(
+            chunks,
+            vec![
+                ("Question here?".to_string(), 1, 1),
+                ("here?\n\nAnswer there.".to_string(), 3, 3),
+            ]
+        );
+
+        // Counted in characters, never splitting a multi-byte character
+        let mut chunks = split_into_chunks_with_lines("数据库索引。\n\n向量搜索。", 7);
+        add_overlap(&mut chunks, 3);
+        assert_eq!(chunks[1].0, "索引。\n\n向量搜索。");
+
+        // An overlap longer than the previous chunk repeats all of it
+        let mut chunks = split_into_chunks_with_lines("Hi.\n\nSecond chunk.", 15);
+        add_overlap(&mut chunks, 50);
+        assert_eq!(chunks[1].0, "Hi.\n\nSecond chunk.");
+        assert!(chunks.iter().all(|(c, _, _)| !c.trim().is_empty()));
+    }
+
+    #[test]
+    fn test_parse_markdown_overlap_bounded_by_chunk_size() {
+        let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+        write!(temp_file, "one two three.\n\nfour five six.").unwrap();
+
+        // The overlap is capped one character short of chunk_size
+        let chunks = parse_markdown(temp_file.path(), 14, 100, ChunkStrategy::Size, false).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].content, "one two three.");
+        assert_eq!(chunks[1].content, "ne two three.\n\nfour five six.");
+
+        // Heading chunks are left as they are
+        let chunks =
+            parse_markdown(temp_file.path(), 14, 5, ChunkStrategy::Heading, false).unwrap();
+        assert_eq!(chunks[1].content, "four five six.");
+    }
+
+    #[test]
+    fn test_code_block_chunks() {
+        let content = "Intro.\n\n```py\ndef f():\n    g()\n```\n\n```text\nplain\n```\n\n~~~~rust\nfn main() {}\n~~~\n~~~~\n";
+        let blocks = code_block_chunks(content);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].language, "python");
+        assert_eq!(blocks[0].content, "def f():\n    g()");
+        assert_eq!((blocks[0].start_line, blocks[0].end_line), (4, 5));
+        // A shorter fence inside a longer one doesn't close it
+        assert_eq!(blocks[1].language, "rust");
+        assert_eq!(blocks[1].content, "fn main() {}\n~~~");
+        assert_eq!(blocks[1].symbol_name, "code block 2");
+        assert_eq!(blocks[1].symbol_type, CODE_BLOCK_SYMBOL_TYPE);
+    }
+
+    #[test]
+    fn test_parse_markdown_strips_bom() {
+        let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+        write!(temp_file, "\u{FEFF}# Title\n\nBody text.").unwrap();
+
+        for strategy in [ChunkStrategy::Size, ChunkStrategy::Heading] {
+            let chunks = parse_markdown(temp_file.path(), 500, 0, strategy, false).unwrap();
+            assert!(!chunks.is_empty());
+            assert!(chunks.iter().all(|c| !c.content.contains('\u{FEFF}')));
+        }
+        let chunks =
+            parse_markdown(temp_file.path(), 500, 0, ChunkStrategy::Heading, false).unwrap();
+        assert_eq!(chunks[0].content, "Title\n\nBody text.");
+    }
+
+    #[test]
+    fn test_parse_heading() {
+        assert_eq!(
+            parse_heading("## Install ##"),
+            Some((2, "Install".to_string()))
+        );
+        assert_eq!(parse_heading("#hashtag"), None);
+        assert_eq!(parse_heading("####### seven"), None);
+        assert_eq!(parse_heading("    # indented code"), None);
+    }
 }
@@ -1,7 +1,12 @@
 pub mod code_parser;
+pub mod content_hash;
 pub mod core;
 pub mod dictionary;
+#[cfg(feature = "git")]
+pub mod git;
 pub mod languages;
 pub mod markdown;
+#[cfg(feature = "notebook")]
+pub mod notebook;
 pub mod relations;
 pub mod text_parser;
@@ -0,0 +1,219 @@
+//! Jupyter notebook (`.ipynb`) parsing.
+//!
+//! Markdown cells become one prose chunk each; code cells are parsed with
+//! [`CodeParser`] in the notebook's kernel language, falling back to one chunk
+//! per cell when the language isn't supported or the cell defines no symbols.
+//! Chunks are named after their cell (`cell 3`), and line numbers are relative
+//! to the cell.
+use super::code_parser::{CodeChunk, CodeParser, MARKDOWN_CELL_SYMBOL_TYPE};
+use super::languages::LanguageConfig;
+use serde_json::Value;
+
+/// `symbol_type` of a code cell stored as a whole.
+pub const CODE_CELL_SYMBOL_TYPE: &str = "code_cell";
+
+/// Kernel language assumed when the notebook metadata doesn't name one.
+const DEFAULT_LANGUAGE: &str = "python";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellKind {
+    Markdown,
+    Code,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    pub kind: CellKind,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notebook {
+    /// Kernel language, lowercased (e.g. `python`).
+    pub language: String,
+    /// Non-empty markdown and code cells, in order. Raw cells are dropped.
+    pub cells: Vec<Cell>,
+}
+
+/// Parse notebook JSON (nbformat 4).
+pub fn parse_notebook(content: &str) -> Result<Notebook, Box<dyn std::error::Error>> {
+    let value: Value = serde_json::from_str(content)?;
+    let cells = value
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or("notebook has no cells array")?;
+
+    let metadata = &value["metadata"];
+    let language = metadata["kernelspec"]["language"]
+        .as_str()
+        .or_else(|| metadata["language_info"]["name"].as_str())
+        .unwrap_or(DEFAULT_LANGUAGE)
+        .to_lowercase();
+    let language = match language.as_str() {
+        "c++" => "cpp".to_string(),
+        _ => language,
+    };
+
+    let cells = cells
+        .iter()
+        .filter_map(|cell| {
+            let kind = match cell["cell_type"].as_str()? {
+                "markdown" => CellKind::Markdown,
+                "code" => CellKind::Code,
+                _ => return None,
+            };
+            // `source` is either a string or a list of lines
+            let source = match &cell["source"] {
+                Value::String(s) => s.clone(),
+                Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+                _ => return None,
+            };
+            (!source.trim().is_empty()).then_some(Cell { kind, source })
+        })
+        .collect();
+
+    Ok(Notebook { language, cells })
+}
+
+/// Build the chunks for every cell of `notebook`.
+pub fn notebook_chunks(
+    parser: &mut CodeParser,
+    notebook: &Notebook,
+) -> Result<Vec<CodeChunk>, Box<dyn std::error::Error>> {
+    let supported = LanguageConfig::get_by_name(&notebook.language).is_some();
+    let mut chunks = Vec::new();
+
+    for (i, cell) in notebook.cells.iter().enumerate() {
+        let cell_name = format!("cell {}", i + 1);
+        let whole_cell = |symbol_type: &str, language: &str| CodeChunk {
+            content: cell.source.clone(),
+            position: 0,
+            symbol_name: cell_name.clone(),
+            symbol_type: symbol_type.to_string(),
+            language: language.to_string(),
+            start_line: 1,
+            end_line: cell.source.lines().count().max(1),
+            parent_symbol: None,
+            signature: String::new(),
+            comment_ranges: Vec::new(),
+            doc_comment: None,
+            doc_range: None,
+            part: 0,
+        };
+
+        match cell.kind {
+            CellKind::Markdown => chunks.push(whole_cell(MARKDOWN_CELL_SYMBOL_TYPE, "markdown")),
+            CellKind::Code => {
+                let symbols = if supported {
+                    parser.parse_code(cell.source.as_bytes(), &notebook.language)?
+                } else {
+                    Vec::new()
+                };
+                if symbols.is_empty() {
+                    chunks.push(whole_cell(CODE_CELL_SYMBOL_TYPE, &notebook.language));
+                } else {
+                    chunks.extend(symbols.into_iter().map(|mut symbol| {
+                        symbol
+                            .parent_symbol
+                            .get_or_insert_with(|| cell_name.clone());
+                        symbol
+                    }));
+                }
+            }
+        }
+    }
+
+    for (position, chunk) in chunks.iter_mut().enumerate() {
+        chunk.position = position;
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+    use crate::embedder::mock::MockEmbedder;
+    use crate::indexer::core::{Indexer, normalize_system_path};
+    use std::sync::Arc;
+
+    const NOTEBOOK: &str = r##"{
+        "cells": [
+            {"cell_type": "markdown", "metadata": {}, "source": ["# Loading data\n", "Read the CSV export."]},
+            {"cell_type": "code", "metadata": {}, "outputs": [], "source": "def load(path):\n    return open(path).read()\n"},
+            {"cell_type": "code", "metadata": {}, "outputs": [], "source": "data = load('export.csv')"},
+            {"cell_type": "raw", "metadata": {}, "source": "ignored"}
+        ],
+        "metadata": {"kernelspec": {"language": "python", "name": "python3"}},
+        "nbformat": 4,
+        "nbformat_minor": 5
+    }"##;
+
+    #[test]
+    fn test_notebook_chunks() {
+        let notebook = parse_notebook(NOTEBOOK).unwrap();
+        assert_eq!(notebook.language, "python");
+        assert_eq!(notebook.cells.len(), 3);
+        assert_eq!(
+            notebook.cells[0].source,
+            "# Loading data\nRead the CSV export."
+        );
+
+        let mut parser = CodeParser::new().unwrap();
+        let chunks = notebook_chunks(&mut parser, &notebook).unwrap();
+        let kinds: Vec<(&str, &str, &str)> = chunks
+            .iter()
+            .map(|c| {
+                (
+                    c.symbol_name.as_str(),
+                    c.symbol_type.as_str(),
+                    c.language.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("cell 1", MARKDOWN_CELL_SYMBOL_TYPE, "markdown"),
+                ("load", "function", "python"),
+                ("cell 3", CODE_CELL_SYMBOL_TYPE, "python"),
+            ]
+        );
+        assert_eq!(chunks[1].parent_symbol.as_deref(), Some("cell 2"));
+        assert_eq!(
+            chunks.iter().map(|c| c.position).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_notebook() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("analysis.ipynb");
+        std::fs::write(&path, NOTEBOOK).unwrap();
+
+        let db = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let mut indexer = Indexer::new(
+            db.clone(),
+            &embedder,
+            500,
+            Arc::new(crate::config::Config::default()),
+        );
+        let result = indexer
+            .index_directory(temp.path(), false, true, false)
+            .await
+            .unwrap();
+        assert_eq!(result.added, 1);
+
+        let filename = normalize_system_path(&path);
+        let markdown = db.find_definitions("cell 1", None, true).unwrap();
+        assert_eq!(markdown.len(), 1);
+        assert_eq!(markdown[0].document_name, filename);
+        assert!(markdown[0].chunk_content.contains("Read the CSV export."));
+
+        let code = db.find_definitions("load", Some("python"), true).unwrap();
+        assert_eq!(code.len(), 1);
+        assert_eq!(code[0].document_name, filename);
+    }
+}
@@ -37,11 +37,17 @@ pub struct RelationExtractor {
 
 impl RelationExtractor {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_languages(None)
+    }
+
+    /// Extractor for the languages named in `enabled` only (all when
+    /// `None`); other languages yield no relations.
+    pub fn with_languages(enabled: Option<&[String]>) -> Result<Self, Box<dyn std::error::Error>> {
         let mut call_queries = HashMap::new();
         let mut import_queries = HashMap::new();
         let mut inherit_queries = HashMap::new();
 
-        for config in LanguageConfig::get_all() {
+        for config in LanguageConfig::get_enabled(enabled) {
             let name = config.name.to_string();
 
             if !config.call_query.is_empty() {
@@ -236,4 +242,41 @@ mod tests {
         assert!(found_call_helper, "Should find self.helper() call");
         assert!(found_call_external, "Should find external_function() call");
     }
+
+    #[test]
+    fn test_extract_relations_java_and_cpp() {
+        let extractor = RelationExtractor::new().expect("Failed to initialize RelationExtractor");
+        let java = r#"
+import java.util.List;
+
+class Dog extends Animal implements Pet {
+    void bark() { speak(); }
+}
+        "#;
+        let relations = extractor
+            .extract_relations(java.as_bytes(), "java", "Dog.java", "Dog")
+            .unwrap();
+        let has = |rels: &[CodeRelation], t: RelationType, name: &str| {
+            rels.iter()
+                .any(|r| r.relation_type == t && r.target_name == name)
+        };
+        assert!(has(&relations, RelationType::Imports, "java.util.List"));
+        assert!(has(&relations, RelationType::Inherits, "Animal"));
+        assert!(has(&relations, RelationType::Inherits, "Pet"));
+        assert!(has(&relations, RelationType::Calls, "speak"));
+
+        let cpp = r#"
+#include "widget.h"
+
+class Button : public Widget {
+    void click() { handler.fire(); }
+};
+        "#;
+        let relations = extractor
+            .extract_relations(cpp.as_bytes(), "cpp", "button.cpp", "Button")
+            .unwrap();
+        assert!(has(&relations, RelationType::Imports, "widget.h"));
+        assert!(has(&relations, RelationType::Inherits, "Widget"));
+        assert!(has(&relations, RelationType::Calls, "fire"));
+    }
 }
@@ -10,23 +10,34 @@ const MAX_SPREADSHEET_ROWS: usize = 10_000;
 
 /// Entry point: extract text from a file and split into chunks.
 /// Dispatches by file extension to format-specific handlers.
-pub fn extract_and_chunk(path: &Path, chunk_size: usize) -> Result<Vec<Chunk>> {
-    let ext = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+pub fn extract_and_chunk(
+    path: &Path,
+    chunk_size: usize,
+    chunk_overlap: usize,
+) -> Result<Vec<Chunk>> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    extract_and_chunk_bytes(&fs::read(path)?, ext, chunk_size, chunk_overlap)
+}
 
+/// Like [`extract_and_chunk`], for the already read `data` of a file with
+/// extension `ext`.
+pub fn extract_and_chunk_bytes(
+    data: &[u8],
+    ext: &str,
+    chunk_size: usize,
+    chunk_overlap: usize,
+) -> Result<Vec<Chunk>> {
+    let ext = ext.to_lowercase();
     let text = match ext.as_str() {
-        "txt" | "log" => fs::read_to_string(path)?,
-        "json" => extract_json(path)?,
-        "yaml" | "yml" => extract_yaml(path)?,
-        "toml" => extract_toml(path)?,
-        "csv" => extract_csv(path)?,
-        "html" | "htm" => extract_html(path)?,
-        "pdf" => extract_pdf(path)?,
-        "docx" => extract_docx(path)?,
-        "xls" | "xlsx" | "xlsb" | "ods" => extract_spreadsheet(path)?,
+        "txt" | "log" => std::str::from_utf8(data)?.to_string(),
+        "json" => extract_json(data)?,
+        "yaml" | "yml" => extract_yaml(data)?,
+        "toml" => extract_toml(data)?,
+        "csv" => extract_csv(data)?,
+        "html" | "htm" => extract_html(data)?,
+        "pdf" => extract_pdf(data)?,
+        "docx" => extract_docx(data)?,
+        "xls" | "xlsx" | "xlsb" | "ods" => extract_spreadsheet(data, &ext)?,
         other => anyhow::bail!("unsupported text format: {other}"),
     };
 
@@ -35,19 +46,27 @@ pub fn extract_and_chunk(path: &Path, chunk_size: usize) -> Result<Vec<Chunk>> {
         return Ok(Vec::new());
     }
 
-    let text_chunks = markdown::split_into_chunks(trimmed, chunk_size);
+    let mut text_chunks = markdown::split_into_chunks_with_lines(trimmed, chunk_size);
+    markdown::add_overlap(
+        &mut text_chunks,
+        chunk_overlap.min(chunk_size.saturating_sub(1)),
+    );
     Ok(text_chunks
         .into_iter()
         .enumerate()
-        .map(|(position, content)| Chunk { content, position })
+        .map(|(position, (content, _, _))| Chunk {
+            content,
+            position,
+            start_line: None,
+            end_line: None,
+        })
         .collect())
 }
 
 // ── JSON ───────────────────────────────────────────────────────────
 
-fn extract_json(path: &Path) -> Result<String> {
-    let content = fs::read_to_string(path)?;
-    let value: serde_json::Value = serde_json::from_str(&content)?;
+fn extract_json(data: &[u8]) -> Result<String> {
+    let value: serde_json::Value = serde_json::from_slice(data)?;
     let mut blocks = Vec::new();
     collect_json_blocks(&value, &mut blocks, String::new());
     Ok(blocks.join("\n\n"))
@@ -91,9 +110,8 @@ fn collect_json_blocks(value: &serde_json::Value, blocks: &mut Vec<String>, pref
 
 // ── YAML ───────────────────────────────────────────────────────────
 
-fn extract_yaml(path: &Path) -> Result<String> {
-    let content = fs::read_to_string(path)?;
-    let value: serde_yaml::Value = serde_yaml::from_str(&content)?;
+fn extract_yaml(data: &[u8]) -> Result<String> {
+    let value: serde_yaml::Value = serde_yaml::from_slice(data)?;
     let mut blocks = Vec::new();
     collect_yaml_blocks(&value, &mut blocks, String::new());
     Ok(blocks.join("\n\n"))
@@ -141,9 +159,8 @@ fn collect_yaml_blocks(value: &serde_yaml::Value, blocks: &mut Vec<String>, pref
 
 // ── TOML ───────────────────────────────────────────────────────────
 
-fn extract_toml(path: &Path) -> Result<String> {
-    let content = fs::read_to_string(path)?;
-    let value: toml::Value = toml::from_str(&content)?;
+fn extract_toml(data: &[u8]) -> Result<String> {
+    let value: toml::Value = toml::from_str(std::str::from_utf8(data)?)?;
     let mut blocks = Vec::new();
     collect_toml_blocks(&value, &mut blocks, String::new());
     Ok(blocks.join("\n\n"))
@@ -190,10 +207,10 @@ fn collect_toml_blocks(value: &toml::Value, blocks: &mut Vec<String>, prefix: St
 
 // ── CSV ────────────────────────────────────────────────────────────
 
-fn extract_csv(path: &Path) -> Result<String> {
+fn extract_csv(data: &[u8]) -> Result<String> {
     let mut reader = csv::ReaderBuilder::new()
         .has_headers(true)
-        .from_path(path)?;
+        .from_reader(data);
 
     let headers = reader
         .headers()?
@@ -216,9 +233,8 @@ fn extract_csv(path: &Path) -> Result<String> {
 
 // ── HTML ───────────────────────────────────────────────────────────
 
-fn extract_html(path: &Path) -> Result<String> {
-    let html_content = fs::read_to_string(path)?;
-    let document = scraper::Html::parse_document(&html_content);
+fn extract_html(data: &[u8]) -> Result<String> {
+    let document = scraper::Html::parse_document(std::str::from_utf8(data)?);
 
     // Remove script and style content by selecting body
     let body_sel = scraper::Selector::parse("body").unwrap();
@@ -261,8 +277,8 @@ fn extract_html(path: &Path) -> Result<String> {
 
 // ── PDF ────────────────────────────────────────────────────────────
 
-fn extract_pdf(path: &Path) -> Result<String> {
-    let doc = lopdf::Document::load(path)?;
+fn extract_pdf(data: &[u8]) -> Result<String> {
+    let doc = lopdf::Document::load_mem(data)?;
     let pages = doc.get_pages();
     let page_numbers: Vec<u32> = pages.keys().copied().collect();
 
@@ -276,9 +292,8 @@ fn extract_pdf(path: &Path) -> Result<String> {
 
 // ── DOCX ───────────────────────────────────────────────────────────
 
-fn extract_docx(path: &Path) -> Result<String> {
-    let buf = fs::read(path)?;
-    let docx = docx_rs::read_docx(&buf)?;
+fn extract_docx(data: &[u8]) -> Result<String> {
+    let docx = docx_rs::read_docx(data)?;
     let json_str = docx.json();
     let value: serde_json::Value = serde_json::from_str(&json_str)?;
 
@@ -292,10 +307,10 @@ fn collect_docx_text(value: &serde_json::Value, texts: &mut Vec<String>) {
     match value {
         serde_json::Value::Object(map) => {
             // Look for "text" field which contains paragraph text content
-            if let Some(serde_json::Value::String(s)) = map.get("text") {
-                if !s.trim().is_empty() {
-                    texts.push(s.clone());
-                }
+            if let Some(serde_json::Value::String(s)) = map.get("text")
+                && !s.trim().is_empty()
+            {
+                texts.push(s.clone());
             }
             // Recurse into all values
             for val in map.values() {
@@ -313,10 +328,16 @@ fn collect_docx_text(value: &serde_json::Value, texts: &mut Vec<String>) {
 
 // ── Spreadsheet ────────────────────────────────────────────────────
 
-fn extract_spreadsheet(path: &Path) -> Result<String> {
-    use calamine::{Data, Reader, open_workbook_auto};
+fn extract_spreadsheet(data: &[u8], ext: &str) -> Result<String> {
+    use calamine::{Data, Reader, Sheets, open_workbook_from_rs};
 
-    let mut workbook = open_workbook_auto(path)?;
+    let cursor = std::io::Cursor::new(data);
+    let mut workbook = match ext {
+        "xls" => Sheets::Xls(open_workbook_from_rs(cursor)?),
+        "xlsx" => Sheets::Xlsx(open_workbook_from_rs(cursor)?),
+        "xlsb" => Sheets::Xlsb(open_workbook_from_rs(cursor)?),
+        _ => Sheets::Ods(open_workbook_from_rs(cursor)?),
+    };
     let mut blocks = Vec::new();
 
     for (sheet_name, range) in workbook.worksheets() {
@@ -519,7 +540,7 @@ serde = "1.0"
 
         // Verify extract_pdf doesn't panic and returns a result
         // (minimal PDFs may yield empty text, which is fine)
-        let result = extract_pdf(&pdf_path);
+        let result = extract_pdf(&std::fs::read(&pdf_path).unwrap());
         assert!(result.is_ok());
     }
 
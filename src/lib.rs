@@ -10,15 +10,19 @@
 //! - **[`db`]** — SQLite + sqlite-vec vector database (CRUD, search, relations)
 //! - **[`embedder`]** — Text embedding via ONNX Runtime (multilingual-e5-small)
 //! - **[`indexer`]** — Markdown chunking, Tree-sitter code parsing, dictionary extraction
-//! - **[`mcp`]** — MCP server with 7 tool handlers (stdio + HTTP transport via rmcp)
+//! - **[`mcp`]** — MCP server with 30 tool handlers (stdio + HTTP transport via rmcp)
+//! - **[`resync`]** — Differential sync of the base directories, at startup and on an interval
 //! - **[`frontmatter`]** — YAML frontmatter read/write for markdown files
+//! - **[`http`]** — Shared HTTP client construction and TLS backend selection
 //! - **[`updater`]** — Version update checker (GitHub API + 24h cache)
 
 pub mod config;
 pub mod db;
 pub mod embedder;
 pub mod frontmatter;
+pub mod http;
 pub mod indexer;
 pub mod mcp;
+pub mod resync;
 pub mod updater;
 pub mod watcher;
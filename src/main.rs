@@ -1,19 +1,23 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use rustrag::config::Config;
 use rustrag::db::Db;
+use rustrag::db::backup::{ServerLock, restore_backup};
 use rustrag::indexer::core::Indexer;
 use rustrag::mcp::server::{McpContext, McpServer};
-use rustrag::updater;
+use rustrag::{resync, updater};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing_subscriber::EnvFilter;
 
 /// Local RAG MCP Server — Rust implementation of DevRag
 #[derive(Parser, Debug)]
 #[command(name = "rustrag", about = "Local RAG MCP Server", version)]
 struct Cli {
-    /// Path to configuration file
-    #[arg(short, long, default_value = "config.json")]
+    /// Path to configuration file (.json, .yaml/.yml or .toml)
+    #[arg(short, long, default_value = "config.json", global = true)]
     config: String,
 
     /// Log level (trace, debug, info, warn, error)
@@ -24,6 +28,10 @@ struct Cli {
     #[arg(long)]
     skip_sync: bool,
 
+    /// Print what the initial sync would add, update, skip and remove, then exit
+    #[arg(long)]
+    dry_run: bool,
+
     /// Transport mode: "stdio" or "http"
     #[arg(long, default_value = "stdio")]
     transport: String,
@@ -31,6 +39,26 @@ struct Cli {
     /// HTTP port (used if transport="http")
     #[arg(long, default_value_t = 8765)]
     port: u16,
+
+    /// Offline mode: no update check, local embedding endpoint required
+    #[arg(long)]
+    offline: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Replace the configured database with a backup file
+    Restore {
+        /// Path to the backup (e.g. rustrag.db.bak-20250101120000000)
+        backup: PathBuf,
+    },
+    /// Check the configuration and print the effective config as JSON
+    Validate,
+    /// Print every tool's name, description and parameter schema as JSON
+    Tools,
 }
 
 #[tokio::main]
@@ -46,10 +74,28 @@ async fn main() -> Result<()> {
         .with_writer(std::io::stderr)
         .init();
 
+    if let Some(Command::Tools) = &cli.command {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&McpServer::tool_manifest())?
+        );
+        return Ok(());
+    }
+
+    if let Some(Command::Validate) = &cli.command {
+        let effective =
+            Config::check_file(&cli.config, cli.offline).context("Invalid configuration")?;
+        println!("{}", serde_json::to_string_pretty(&effective)?);
+        return Ok(());
+    }
+
     tracing::info!("Starting RustRAG MCP Server...");
 
     // 3. Load and validate configuration
-    let config = Config::load(&cli.config).context("Failed to load config")?;
+    let mut config = Config::load(&cli.config).context("Failed to load config")?;
+    if cli.offline {
+        config.offline = true;
+    }
     config.validate().context("Invalid configuration")?;
     let chunk_size = config.chunk_size;
     let config = Arc::new(config);
@@ -61,12 +107,21 @@ async fn main() -> Result<()> {
         dimensions = config.embedding.dimensions,
         "Configuration loaded"
     );
+    tracing::debug!(config = %config.redacted_json(), "Effective configuration");
+
+    if let Some(Command::Restore { backup }) = &cli.command {
+        restore_backup(backup, config.db_path.as_ref(), config.embedding.dimensions)
+            .context("Restore failed")?;
+        println!("Restored {} from {}", config.db_path, backup.display());
+        return Ok(());
+    }
 
     // 3b. Check for updates (best-effort, errors silently ignored)
     if config.is_update_check_enabled() {
         let ver = updater::CURRENT_VERSION;
+        let repo = config.update_repo.clone();
         tokio::spawn(async move {
-            updater::check_for_update(ver, "").await;
+            updater::check_for_update(ver, "", repo.as_deref()).await;
         });
     }
 
@@ -82,6 +137,16 @@ async fn main() -> Result<()> {
     // 5. Initialize database
     tracing::info!(db_path = %config.db_path, "Opening database");
     let db = Db::open(&config.db_path).context("Failed to open database")?;
+    db.check_embedding_dimensions(config.embedding.dimensions)
+        .context("Embedding dimension mismatch")?;
+
+    if cli.dry_run {
+        return print_sync_plan(Arc::new(db), config, cli.config.clone()).await;
+    }
+
+    // Held for the server's lifetime so a second server or `rustrag restore`
+    // can't use the same DB
+    let _server_lock = ServerLock::acquire(&config.db_path).context("Failed to lock database")?;
 
     // 6. Wrap db in Arc so MCP and sync can share it
     let db = Arc::new(db);
@@ -94,54 +159,24 @@ async fn main() -> Result<()> {
         let sync_ctx = mcp_ctx.clone();
 
         tokio::spawn(async move {
-            let base_dirs = sync_ctx.config.read().await.get_base_directories();
-            tracing::info!(dirs = ?base_dirs, "Background sync started");
-
-            // Trigger embedder lazy-init now (in background, not blocking MCP startup)
-            let sync_embedder = sync_ctx.get_embedder().await;
-
-            for dir in &base_dirs {
-                if !dir.exists() {
-                    tracing::warn!(dir = %dir.display(), "Directory does not exist, skipping");
-                    continue;
-                }
-
-                tracing::info!(dir = %dir.display(), "Syncing directory");
-
-                let result = {
-                    let mut indexer = Indexer::new(
-                        sync_ctx.db.clone(),
-                        sync_embedder.as_ref(),
-                        sync_ctx.chunk_size,
-                        Arc::new(sync_ctx.config.read().await.clone()),
-                    );
-                    indexer.index_directory(dir, false).await
-                };
-
-                match result {
-                    Ok(result) => {
-                        tracing::info!(
-                            dir = %dir.display(),
-                            indexed = result.indexed,
-                            added = result.added,
-                            updated = result.updated,
-                            skipped = result.skipped,
-                            failed = result.failed,
-                            "Sync completed"
-                        );
-                    }
-                    Err(e) => {
-                        tracing::error!(dir = %dir.display(), error = %e, "Sync failed");
-                    }
-                }
-            }
-
+            tracing::info!("Background sync started");
+            resync::sync_base_directories(&sync_ctx).await;
             tracing::info!("Background sync finished");
         });
     } else {
         tracing::info!("Initial sync skipped (--skip-sync)");
     }
 
+    // 8b. Periodic re-sync, stopped once the server exits
+    let shutdown = CancellationToken::new();
+    if config.resync_interval_secs > 0 {
+        resync::spawn_periodic_resync(
+            mcp_ctx.clone(),
+            Duration::from_secs(config.resync_interval_secs),
+            shutdown.clone(),
+        );
+    }
+
     // 9. Start background file watcher (hot reload)
     rustrag::watcher::start_watcher(mcp_ctx.clone()).await;
 
@@ -157,6 +192,37 @@ async fn main() -> Result<()> {
             server.start().await?;
         }
     }
+    shutdown.cancel();
 
     Ok(())
 }
+
+/// Run the initial sync in dry-run mode and print its per-directory plan.
+async fn print_sync_plan(db: Arc<Db>, config: Arc<Config>, config_path: String) -> Result<()> {
+    let ctx = McpContext::new(db.clone(), config.clone(), config.chunk_size, config_path);
+    let embedder = ctx.get_embedder().await;
+    let mut indexer = Indexer::new(db, embedder.as_ref(), config.chunk_size, config.clone());
+
+    for dir in config.get_base_directories() {
+        if !dir.exists() {
+            println!("{}: does not exist, skipped", dir.display());
+            continue;
+        }
+        let plan = indexer
+            .index_directory(&dir, false, true, true)
+            .await
+            .map_err(|e| anyhow::anyhow!("{e}"))
+            .with_context(|| format!("Dry run failed for {}", dir.display()))?;
+        println!(
+            "{}: {} to add, {} to update, {} unchanged, {} to remove, {} generated and {} too large (left out)",
+            dir.display(),
+            plan.added,
+            plan.updated,
+            plan.skipped,
+            plan.removed,
+            plan.generated,
+            plan.skipped_large
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,374 @@
+/// Human-readable rendering of tool responses.
+///
+/// Tools return JSON by default; these helpers produce Markdown for clients
+/// where a person reads the output directly.
+use crate::db::models::WordMapping;
+use crate::db::search::{AdjacentChunks, ContextChunk, DocumentHit, SearchResult};
+use crate::indexer::code_parser::is_prose_symbol_type;
+use serde_json::Value;
+
+/// Maximum number of characters shown per result snippet.
+const SNIPPET_MAX_CHARS: usize = 500;
+
+/// Render merged `search` results (as built by the search tool) as Markdown.
+///
+/// Each result gets a `##` heading with its document name, followed by the
+/// similarity, optional symbol info and a snippet of the content. Occurrences
+/// of `terms` in prose snippets and symbol names are wrapped in `**bold**`;
+/// code blocks are left as-is since emphasis doesn't render inside fences.
+pub fn search_results_markdown(query: &str, results: &[Value], terms: &[&str]) -> String {
+    let mut out = format!("# Search results for \"{query}\"\n");
+    if results.is_empty() {
+        out.push_str("\nNo results found.\n");
+        return out;
+    }
+
+    for (i, r) in results.iter().enumerate() {
+        let document = r["document"].as_str().unwrap_or("(unknown)");
+        out.push_str(&format!("\n## {}. {}\n\n", i + 1, document));
+
+        if let Some(similarity) = r["similarity"].as_str() {
+            out.push_str(&format!("- **Similarity:** {similarity}\n"));
+        }
+        if let Some(symbol) = r["symbol_name"].as_str() {
+            let kind = r["symbol_type"].as_str().unwrap_or("symbol");
+            let lower = symbol.to_ascii_lowercase();
+            let matched = terms
+                .iter()
+                .any(|t| lower.contains(&t.to_ascii_lowercase()));
+            let symbol = if matched {
+                format!("**`{symbol}`**")
+            } else {
+                format!("`{symbol}`")
+            };
+            out.push_str(&format!("- **Symbol:** {symbol} ({kind})"));
+            if let (Some(start), Some(end)) = (r["start_line"].as_u64(), r["end_line"].as_u64()) {
+                out.push_str(&format!(", lines {start}-{end}"));
+            }
+            out.push('\n');
+        } else if let Some(line) = r["start_line"].as_u64() {
+            match r["end_line"].as_u64() {
+                Some(end) if end > line => {
+                    out.push_str(&format!("- **Lines:** {line}-{end}\n"));
+                }
+                _ => out.push_str(&format!("- **Line:** {line}\n")),
+            }
+        }
+        if r["stale"].as_bool() == Some(true) {
+            out.push_str("- **Stale:** file changed on disk since indexing\n");
+        }
+
+        let snippet = truncate_chars(r["content"].as_str().unwrap_or(""), SNIPPET_MAX_CHARS);
+        out.push('\n');
+        match r["language"].as_str() {
+            Some(lang) if !r["symbol_type"].as_str().is_some_and(is_prose_symbol_type) => {
+                out.push_str(&format!("```{lang}\n{snippet}\n```\n"));
+            }
+            _ => {
+                for line in snippet.lines() {
+                    out.push_str(&format!("> {}\n", highlight_terms(line, terms)));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// JSON entries for `search` with `names_only`: filename and best similarity
+/// rounded to `precision` decimals, no chunk content.
+pub fn document_hits_json(hits: &[DocumentHit], precision: usize) -> Vec<Value> {
+    hits.iter()
+        .map(|h| {
+            serde_json::json!({
+                "filename": h.filename,
+                "best_similarity": format!("{:.precision$}", h.best_similarity),
+            })
+        })
+        .collect()
+}
+
+/// JSON entry for one `search` hit: content, similarity rounded to
+/// `precision` decimals, symbol or line info, and the `adjacent` context
+/// chunks when requested.
+pub fn search_result_json(
+    r: &SearchResult,
+    precision: usize,
+    warn_stale: bool,
+    adjacent: Option<&AdjacentChunks>,
+) -> Value {
+    let mut obj = serde_json::json!({
+        "document": r.document_name,
+        "content": r.chunk_content,
+        "similarity": format!("{:.precision$}", r.similarity),
+        "distance": r.distance,
+        "position": r.position,
+    });
+    if let Some(title) = &r.title {
+        obj["title"] = serde_json::json!(title);
+    }
+    if let Some(meta) = &r.metadata {
+        obj["symbol_name"] = serde_json::json!(meta.symbol_name);
+        obj["symbol_type"] = serde_json::json!(meta.symbol_type);
+        obj["language"] = serde_json::json!(meta.language);
+        obj["start_line"] = serde_json::json!(meta.start_line);
+        obj["end_line"] = serde_json::json!(meta.end_line);
+        obj["parent_symbol"] = serde_json::json!(meta.parent_symbol);
+        obj["signature"] = serde_json::json!(meta.signature);
+        if let Some(doc) = &meta.doc_comment {
+            obj["doc_comment"] = serde_json::json!(doc);
+        }
+    } else if let Some(line) = r.start_line {
+        obj["start_line"] = serde_json::json!(line);
+        if let Some(end) = r.end_line {
+            obj["end_line"] = serde_json::json!(end);
+        }
+    }
+    if warn_stale {
+        obj["stale"] = serde_json::json!(r.is_stale());
+    }
+    if let Some(adjacent) = adjacent {
+        let to_json = |chunks: &[ContextChunk]| {
+            chunks
+                .iter()
+                .map(|c| serde_json::json!({"position": c.position, "content": c.content}))
+                .collect::<Vec<_>>()
+        };
+        obj["context_before"] = serde_json::json!(to_json(&adjacent.before));
+        obj["context_after"] = serde_json::json!(to_json(&adjacent.after));
+    }
+    obj
+}
+
+/// Render `names_only` search results as a Markdown list.
+pub fn document_hits_markdown(query: &str, hits: &[DocumentHit], precision: usize) -> String {
+    let mut out = format!("# Documents matching \"{query}\"\n\n");
+    if hits.is_empty() {
+        out.push_str("No results found.\n");
+        return out;
+    }
+    for (i, h) in hits.iter().enumerate() {
+        out.push_str(&format!(
+            "{}. {} ({:.precision$})\n",
+            i + 1,
+            h.filename,
+            h.best_similarity
+        ));
+    }
+    out
+}
+
+/// Wrap every occurrence of any of `terms` in `**bold**`.
+///
+/// Matching is ASCII case-insensitive; longer terms win when terms overlap.
+pub fn highlight_terms(text: &str, terms: &[&str]) -> String {
+    let mut terms: Vec<&str> = terms.iter().copied().filter(|t| !t.is_empty()).collect();
+    if terms.is_empty() {
+        return text.to_string();
+    }
+    terms.sort_by_key(|t| std::cmp::Reverse(t.len()));
+
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < text.len() {
+        let matched = terms.iter().find_map(|t| {
+            text.get(i..i + t.len())
+                .filter(|candidate| candidate.eq_ignore_ascii_case(t))
+        });
+        if let Some(m) = matched {
+            out.push_str("**");
+            out.push_str(m);
+            out.push_str("**");
+            i += m.len();
+        } else {
+            let ch = text[i..].chars().next().unwrap_or_default();
+            out.push(ch);
+            i += ch.len_utf8();
+        }
+    }
+    out
+}
+
+/// Render word mappings as CSV with a header row.
+///
+/// Fields containing commas, quotes or line breaks are quoted, with embedded
+/// quotes doubled (RFC 4180).
+pub fn word_mappings_csv(mappings: &[WordMapping]) -> String {
+    let mut out = String::from("source_word,target_word,source_lang,confidence,source_document\n");
+    for m in mappings {
+        let row = [
+            csv_field(&m.source_word),
+            csv_field(&m.target_word),
+            csv_field(&m.source_lang),
+            m.confidence.to_string(),
+            csv_field(m.source_document.as_deref().unwrap_or("")),
+        ];
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn truncate_chars(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((idx, _)) => format!("{}…", &s[..idx]),
+        None => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_results_markdown() {
+        let results = vec![
+            serde_json::json!({
+                "document": "docs/auth.md",
+                "content": "Tokens expire after one hour.",
+                "similarity": "0.8123",
+                "position": 0,
+                "start_line": 7,
+            }),
+            serde_json::json!({
+                "document": "src/auth.rs",
+                "content": "fn refresh() {}",
+                "similarity": "0.7001",
+                "position": 2,
+                "symbol_name": "refresh",
+                "symbol_type": "function",
+                "language": "rust",
+                "start_line": 10,
+                "end_line": 12,
+            }),
+        ];
+
+        let md = search_results_markdown("token refresh", &results, &[]);
+        assert!(md.starts_with("# Search results for \"token refresh\""));
+        assert!(md.contains("## 1. docs/auth.md"));
+        assert!(md.contains("## 2. src/auth.rs"));
+        assert!(md.contains("0.8123"));
+        assert!(md.contains("0.7001"));
+        assert!(md.contains("> Tokens expire after one hour."));
+        assert!(md.contains("```rust\nfn refresh() {}\n```"));
+        assert!(md.contains("`refresh` (function), lines 10-12"));
+        assert!(md.contains("- **Line:** 7\n"));
+    }
+
+    #[test]
+    fn test_search_results_markdown_empty() {
+        let md = search_results_markdown("nothing", &[], &[]);
+        assert!(md.contains("No results found."));
+    }
+
+    #[test]
+    fn test_search_results_markdown_highlights_terms() {
+        let results = vec![serde_json::json!({
+            "document": "docs/auth.md",
+            "content": "Tokens expire; refresh the token before expiry.",
+            "similarity": "0.8123",
+            "position": 0,
+        })];
+
+        let md = search_results_markdown("token refresh", &results, &["token", "refresh"]);
+        assert!(md.contains("> **Token**s expire; **refresh** the **token** before expiry."));
+        // Headings are not touched
+        assert!(md.contains("## 1. docs/auth.md"));
+
+        let code = vec![serde_json::json!({
+            "document": "src/auth.rs",
+            "content": "fn refresh_token() {}",
+            "similarity": "0.7001",
+            "symbol_name": "refresh_token",
+            "symbol_type": "function",
+            "language": "rust",
+        })];
+        let md = search_results_markdown("refresh", &code, &["refresh"]);
+        assert!(md.contains("**`refresh_token`** (function)"));
+        // Code blocks stay verbatim
+        assert!(md.contains("```rust\nfn refresh_token() {}\n```"));
+    }
+
+    #[test]
+    fn test_document_hits_json_omits_content() {
+        let hits = [
+            DocumentHit {
+                filename: "docs/a.md".to_string(),
+                best_similarity: 0.91234,
+            },
+            DocumentHit {
+                filename: "docs/b.md".to_string(),
+                best_similarity: 0.5,
+            },
+        ];
+        let json = document_hits_json(&hits, 4);
+        assert_eq!(
+            json,
+            [
+                serde_json::json!({"filename": "docs/a.md", "best_similarity": "0.9123"}),
+                serde_json::json!({"filename": "docs/b.md", "best_similarity": "0.5000"}),
+            ]
+        );
+        assert!(json.iter().all(|v| v.get("content").is_none()));
+
+        let md = document_hits_markdown("auth", &hits, 4);
+        assert!(md.contains("1. docs/a.md (0.9123)"));
+        assert!(md.contains("2. docs/b.md (0.5000)"));
+
+        let rounded = document_hits_json(&hits, 2);
+        assert_eq!(rounded[0]["best_similarity"], "0.91");
+        assert!(document_hits_markdown("auth", &hits, 2).contains("1. docs/a.md (0.91)"));
+    }
+
+    #[test]
+    fn test_highlight_terms() {
+        assert_eq!(highlight_terms("no match", &["xyz"]), "no match");
+        assert_eq!(
+            highlight_terms("認証トークン", &["トークン"]),
+            "認証**トークン**"
+        );
+        // Longer term wins over its prefix
+        assert_eq!(
+            highlight_terms("parse_config", &["parse", "parse_config"]),
+            "**parse_config**"
+        );
+    }
+
+    #[test]
+    fn test_word_mappings_csv_escaping() {
+        let mapping = |source: &str, target: &str, document: Option<&str>| WordMapping {
+            id: 0,
+            source_word: source.to_string(),
+            target_word: target.to_string(),
+            source_lang: "ja".to_string(),
+            confidence: 0.5,
+            source_document: document.map(str::to_string),
+        };
+        let csv = word_mappings_csv(&[
+            mapping("設定", "config", Some("docs/a.md")),
+            mapping("a,b", "say \"hi\"", None),
+        ]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "source_word,target_word,source_lang,confidence,source_document",
+                "設定,config,ja,0.5,docs/a.md",
+                "\"a,b\",\"say \"\"hi\"\"\",ja,0.5,",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncate_chars_multibyte() {
+        assert_eq!(truncate_chars("日本語テキスト", 3), "日本語…");
+        assert_eq!(truncate_chars("short", 10), "short");
+    }
+}
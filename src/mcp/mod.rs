@@ -1,2 +1,4 @@
+pub mod format;
+pub mod scope;
 pub mod server;
 pub mod tools;
@@ -0,0 +1,304 @@
+/// Per-request directory scope for multi-tenant HTTP mode.
+///
+/// A client sending the [`SCOPE_HEADER`] header is confined to that directory
+/// subtree: path arguments of every tool call must resolve inside it, `search`
+/// is filtered to it, and tools that work on the whole index are refused.
+/// Enforcement happens once in `AppTools::call_tool`, before dispatch.
+use crate::indexer::core::normalize_system_path;
+use rmcp::ErrorData as McpError;
+use rmcp::model::{Extensions, JsonObject};
+use std::path::{Path, PathBuf};
+
+/// HTTP header carrying the directory a request is confined to.
+pub const SCOPE_HEADER: &str = "x-rustrag-scope";
+
+/// Path-valued arguments checked for each tool that may run under a scope.
+///
+/// Tools not listed here operate on the whole index and are refused.
+const SCOPED_TOOL_ARGS: &[(&str, &[&str])] = &[
+    ("search", &["directory"]),
+    ("index", &["filepath", "filepaths", "directory"]),
+    ("manage_document", &["filename"]),
+    ("frontmatter", &["filepath"]),
+    ("index_git_changed", &["directory"]),
+    ("coverage", &["directory"]),
+    ("directory_summary", &["directory"]),
+    ("refresh_mtime", &["filename"]),
+    ("delete_by_directory", &["directory"]),
+    ("list_symbols", &["filename"]),
+    ("symbol_outline", &["filename"]),
+    ("add_frontmatter_bulk", &["directory"]),
+    ("move_document", &["old_filename", "new_filename"]),
+];
+
+/// Tools whose `directory` argument defaults to the scope root when omitted.
+const DEFAULT_TO_ROOT: &[&str] = &["search", "index_git_changed"];
+
+/// A directory subtree a request is confined to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scope {
+    /// Normalized absolute path, without a trailing slash.
+    root: String,
+}
+
+impl Scope {
+    /// Scope rooted at `dir`, which must be an existing directory.
+    pub fn new(dir: &str) -> Result<Self, String> {
+        let path = Path::new(dir);
+        if !path.is_dir() {
+            return Err(format!("scope directory does not exist: {dir}"));
+        }
+        let root = normalize_system_path(path);
+        let root = match root.trim_end_matches('/') {
+            "" => "/".to_string(),
+            r => r.to_string(),
+        };
+        Ok(Self { root })
+    }
+
+    /// Scope requested by the HTTP headers in `extensions`, if any.
+    ///
+    /// Returns `Ok(None)` for stdio sessions and HTTP requests without the header.
+    pub fn from_extensions(extensions: &Extensions) -> Result<Option<Self>, McpError> {
+        let Some(parts) = extensions.get::<axum::http::request::Parts>() else {
+            return Ok(None);
+        };
+        let Some(value) = parts.headers.get(SCOPE_HEADER) else {
+            return Ok(None);
+        };
+        let dir = value.to_str().map_err(|_| {
+            McpError::invalid_request(format!("{SCOPE_HEADER} header is not valid UTF-8"), None)
+        })?;
+        Self::new(dir)
+            .map(Some)
+            .map_err(|e| McpError::invalid_request(e, None))
+    }
+
+    /// Normalized root directory of the scope.
+    #[must_use]
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Whether `path` resolves to the scope root or somewhere below it.
+    #[must_use]
+    pub fn contains(&self, path: &str) -> bool {
+        self.resolve(path).is_some()
+    }
+
+    /// Check the arguments of a call to `tool`, rewriting path arguments to
+    /// their resolved form so the tool acts on exactly what was checked.
+    pub fn apply(&self, tool: &str, arguments: &mut Option<JsonObject>) -> Result<(), McpError> {
+        let Some((_, keys)) = SCOPED_TOOL_ARGS.iter().find(|(name, _)| *name == tool) else {
+            return Err(McpError::invalid_request(
+                format!("tool '{tool}' is not available in a session scoped to a directory"),
+                None,
+            ));
+        };
+        let args = arguments.get_or_insert_with(JsonObject::new);
+
+        for key in *keys {
+            let Some(value) = args.get(*key) else {
+                continue;
+            };
+            let Some(raw) = value.as_str() else {
+                // Leave type errors to the tool's own parameter parsing
+                continue;
+            };
+            let resolved = if *key == "filepaths" {
+                raw.split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|p| self.require(p))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(",")
+            } else {
+                self.require(raw)?
+            };
+            args.insert((*key).to_string(), resolved.into());
+        }
+
+        if DEFAULT_TO_ROOT.contains(&tool) && !args.contains_key("directory") {
+            args.insert("directory".to_string(), self.root.clone().into());
+        }
+        Ok(())
+    }
+
+    fn require(&self, path: &str) -> Result<String, McpError> {
+        self.resolve(path).ok_or_else(|| {
+            McpError::invalid_params(
+                format!("path is outside the session scope {}: {path}", self.root),
+                None,
+            )
+        })
+    }
+
+    /// Resolve `path` and return it normalized if it lies inside the scope.
+    fn resolve(&self, path: &str) -> Option<String> {
+        let resolved = resolve_lexically_safe(Path::new(path))?;
+        let normalized = normalize_system_path(&resolved);
+        let inside = normalized == self.root
+            || self.root == "/"
+            || normalized
+                .strip_prefix(&self.root)
+                .is_some_and(|rest| rest.starts_with('/'));
+        inside.then_some(normalized)
+    }
+}
+
+/// Absolute form of `path` with symlinks resolved for the part that exists.
+///
+/// Paths that don't exist yet (e.g. a deleted document) are resolved through
+/// their nearest existing ancestor; `..` in the non-existent remainder can't
+/// be resolved safely, so such paths are rejected.
+fn resolve_lexically_safe(path: &Path) -> Option<PathBuf> {
+    let absolute = std::path::absolute(path).ok()?;
+    let mut existing = absolute.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            let mut resolved = canonical;
+            resolved.extend(rest.iter().rev());
+            return Some(resolved);
+        }
+        rest.push(existing.file_name()?.to_owned());
+        existing = existing.parent()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+    use crate::db::search::SearchFilter;
+    use crate::embedder::Embedder;
+    use crate::embedder::mock::MockEmbedder;
+    use crate::indexer::core::Indexer;
+    use std::fs;
+    use std::sync::Arc;
+
+    fn args(value: serde_json::Value) -> Option<JsonObject> {
+        value.as_object().cloned()
+    }
+
+    #[tokio::test]
+    async fn test_scoped_session_cannot_search_or_index_outside() {
+        let temp = tempfile::tempdir().unwrap();
+        // `other` matches `tenant_a/%` under a case-insensitive LIKE
+        let tenant = temp.path().join("tenant_a");
+        let other = temp.path().join("TENANTXa");
+        fs::create_dir_all(&tenant).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(tenant.join("mine.md"), "# Mine\n\nShared secret topic.").unwrap();
+        fs::write(other.join("theirs.md"), "# Theirs\n\nShared secret topic.").unwrap();
+
+        let db = Arc::new(Db::open_in_memory().unwrap());
+        let embedder = MockEmbedder::default();
+        let mut indexer = Indexer::new(
+            db.clone(),
+            &embedder,
+            500,
+            Arc::new(crate::config::Config::default()),
+        );
+        for dir in [&tenant, &other] {
+            indexer
+                .index_directory(&normalize_system_path(dir), false, true, false)
+                .await
+                .unwrap();
+        }
+
+        let scope = Scope::new(tenant.to_str().unwrap()).unwrap();
+
+        // search without a directory is confined to the scope
+        let mut search = args(serde_json::json!({ "query": "secret" }));
+        scope.apply("search", &mut search).unwrap();
+        let directory = search.as_ref().unwrap()["directory"].as_str().unwrap();
+        let filter = SearchFilter {
+            directory: Some(directory),
+            file_pattern: None,
+            language: None,
+            symbol_type: None,
+        };
+        let query = embedder.embed("Shared secret topic.").unwrap();
+        let hits = db.search_with_filter(&query, 10, Some(&filter)).unwrap();
+        assert!(!hits.is_empty());
+        assert!(
+            hits.iter()
+                .all(|h| h.document_name.ends_with("tenant_a/mine.md")),
+            "{:?}",
+            hits.iter().map(|h| &h.document_name).collect::<Vec<_>>()
+        );
+
+        // search and index targeting another subtree are refused
+        let outside = other.to_string_lossy().to_string();
+        let mut search = args(serde_json::json!({ "query": "secret", "directory": outside }));
+        assert!(scope.apply("search", &mut search).is_err());
+        let mut index = args(serde_json::json!({ "directory": outside }));
+        assert!(scope.apply("index", &mut index).is_err());
+        let escape = tenant
+            .join("../TENANTXa/theirs.md")
+            .to_string_lossy()
+            .to_string();
+        let mut index = args(serde_json::json!({ "filepath": escape }));
+        assert!(scope.apply("index", &mut index).is_err());
+        let inside = tenant.join("mine.md").to_string_lossy().to_string();
+        let mut index = args(serde_json::json!({ "filepaths": format!("{inside},{escape}") }));
+        assert!(scope.apply("index", &mut index).is_err());
+
+        // index_git_changed only ever sees the scope root or a directory inside it
+        let mut git = args(serde_json::json!({ "directory": outside }));
+        assert!(scope.apply("index_git_changed", &mut git).is_err());
+        let mut git = args(serde_json::json!({}));
+        scope.apply("index_git_changed", &mut git).unwrap();
+        assert_eq!(git.unwrap()["directory"], scope.root());
+
+        // Whole-index tools are unavailable
+        let mut none = None;
+        assert!(scope.apply("list_documents", &mut none).is_err());
+        assert!(scope.apply("reindex_all", &mut none).is_err());
+
+        // Paths inside the scope pass and are normalized
+        let mut index = args(serde_json::json!({ "filepath": inside }));
+        scope.apply("index", &mut index).unwrap();
+        assert_eq!(
+            index.unwrap()["filepath"],
+            normalize_system_path(&tenant.join("mine.md"))
+        );
+    }
+
+    #[test]
+    fn test_contains_handles_missing_paths_and_siblings() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("a");
+        let sibling = temp.path().join("ab");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&sibling).unwrap();
+        let scope = Scope::new(root.to_str().unwrap()).unwrap();
+
+        assert!(scope.contains(root.to_str().unwrap()));
+        assert!(scope.contains(root.join("deleted/doc.md").to_str().unwrap()));
+        assert!(!scope.contains(sibling.to_str().unwrap()));
+        assert!(!scope.contains(root.join("gone/../../ab").to_str().unwrap()));
+        assert!(Scope::new(temp.path().join("missing").to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_from_extensions_reads_header() {
+        let temp = tempfile::tempdir().unwrap();
+        let request = axum::http::Request::builder()
+            .header(SCOPE_HEADER, temp.path().to_str().unwrap())
+            .body(())
+            .unwrap();
+        let (parts, ()) = request.into_parts();
+        let mut extensions = Extensions::new();
+        extensions.insert(parts);
+
+        let scope = Scope::from_extensions(&extensions).unwrap().unwrap();
+        assert_eq!(scope.root(), normalize_system_path(temp.path()));
+        assert!(
+            Scope::from_extensions(&Extensions::new())
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -54,7 +54,20 @@ impl McpContext {
                     dim = config.embedding.dimensions,
                     "API embedder initialized"
                 );
-                let embedder_arc = Arc::new(e) as Arc<dyn Embedder>;
+                let capacity = config.embedding.cache_capacity;
+                let embedder_arc = if capacity > 0 {
+                    Arc::new(crate::embedder::CachingEmbedder::new(e, capacity))
+                        as Arc<dyn Embedder>
+                } else {
+                    Arc::new(e) as Arc<dyn Embedder>
+                };
+                let embedder_arc = match config.embedding.embed_timeout_secs {
+                    0 => embedder_arc,
+                    secs => Arc::new(crate::embedder::TimeoutEmbedder::new(
+                        embedder_arc,
+                        std::time::Duration::from_secs(secs),
+                    )) as Arc<dyn Embedder>,
+                };
                 *write_guard = Some(embedder_arc.clone());
                 embedder_arc
             }
@@ -69,6 +82,11 @@ impl McpContext {
         }
     }
 
+    /// Use `embedder` from now on instead of the lazily created API embedder.
+    pub async fn set_embedder(&self, embedder: Arc<dyn Embedder>) {
+        *self.embedder.write().await = Some(embedder);
+    }
+
     /// Hot-reloads the configuration from disk and drops the embedder if embedding settings changed.
     pub async fn reload_config(&self, new_config: Config) {
         let mut config_guard = self.config.write().await;
@@ -78,7 +96,9 @@ impl McpContext {
             != new_config.embedding.api_url
             || config_guard.embedding.api_key != new_config.embedding.api_key
             || config_guard.embedding.api_model != new_config.embedding.api_model
-            || config_guard.embedding.dimensions != new_config.embedding.dimensions;
+            || config_guard.embedding.dimensions != new_config.embedding.dimensions
+            || config_guard.embedding.cache_capacity != new_config.embedding.cache_capacity
+            || config_guard.embedding.embed_timeout_secs != new_config.embedding.embed_timeout_secs;
 
         tracing::info!("Reloading configuration parameters in-memory...");
         *config_guard = new_config;
@@ -118,6 +138,17 @@ impl McpServer {
         Self { ctx }
     }
 
+    /// The tools this server offers, as listed by `tools/list`, without
+    /// starting a session: `{ name, version, tools: [{ name, description,
+    /// inputSchema }] }`, tools sorted by name.
+    pub fn tool_manifest() -> serde_json::Value {
+        serde_json::json!({
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "tools": AppTools::tool_manifest(),
+        })
+    }
+
     /// Start the MCP server on stdio transport (blocks until the client disconnects).
     pub async fn start(self) -> Result<()> {
         tracing::info!("Starting MCP server on stdio...");
@@ -192,3 +223,25 @@ impl McpServer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_manifest() {
+        let manifest = McpServer::tool_manifest();
+        let tools = manifest["tools"].as_array().unwrap();
+        let names: Vec<&str> = tools.iter().map(|t| t["name"].as_str().unwrap()).collect();
+        let mut sorted = names.clone();
+        sorted.sort_unstable();
+        assert_eq!(names, sorted);
+        assert!(names.len() >= 26);
+
+        let search = tools.iter().find(|t| t["name"] == "search").unwrap();
+        assert!(search["description"].as_str().unwrap().contains("search"));
+        assert_eq!(search["inputSchema"]["type"], "object");
+        assert!(search["inputSchema"]["properties"]["query"].is_object());
+        assert_eq!(McpServer::tool_manifest(), manifest);
+    }
+}
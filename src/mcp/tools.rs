@@ -1,6 +1,6 @@
 /// MCP Tool handlers for RustRAG.
 ///
-/// Implements 7 tools (consolidated from original 10):
+/// Implements 30 tools:
 /// 1. search           – vector similarity search
 /// 2. index            – index files (markdown or code, auto-detected by extension)
 /// 3. list_documents   – list indexed documents
@@ -8,13 +8,52 @@
 /// 5. frontmatter      – add or update YAML frontmatter
 /// 6. search_relations – search code symbol relations
 /// 7. build_dictionary – build multilingual word dictionary
-use crate::db::search::SearchFilter;
+/// 8. export_graph     – export the code relation graph as GraphViz DOT
+/// 9. index_git_changed – reindex files changed since a git ref (`git` feature)
+/// 10. schema_info     – report schema / sqlite-vec version and embedding dimension
+/// 11. stats           – aggregate index statistics
+/// 12. coverage        – list supported files in a directory that aren't indexed
+/// 13. reindex_all     – re-embed every indexed document from disk
+/// 14. directory_summary – per-subdirectory document, chunk and language counts
+/// 15. find_definition – look up a symbol's definition(s) by name
+/// 16. export_dictionary – export word mappings as JSON or CSV
+/// 17. refresh_mtime   – sync a document's stored mtime to disk without reindexing
+/// 18. link_relations  – resolve relation targets to the chunks defining them
+/// 19. delete_by_directory – remove every indexed document under a directory
+/// 20. word_mapping_sources – list the documents a dictionary mapping came from
+/// 21. list_symbols    – outline of the code symbols indexed from a file
+/// 22. reindex_matching – re-embed the indexed documents matching a glob pattern
+/// 23. centroid        – mean embedding of example texts and its nearest documents
+/// 24. expand_query    – preview dictionary expansion of a query
+/// 25. health_check    – report the active embedder and database status
+/// 26. symbol_outline  – nested parent/child symbol tree of a file
+/// 27. add_frontmatter_bulk – apply frontmatter to every markdown file in a directory
+/// 28. top_symbols     – most connected symbols by relation count
+/// 29. embedding_health – norm distribution of stored vectors, flags degenerate ones
+/// 30. move_document   – rename a document's path in the index without re-embedding
+use crate::config::ShortQueryAction;
+use crate::db::relations::GraphFilter;
+use crate::db::search::{
+    MIN_KEYWORD_LEN, RERANK_CANDIDATE_FACTOR, RankOptions, SearchFilter, SymbolNode,
+    dedup_near_identical, document_hits, drop_below_similarity, drop_nonpositive_similarity,
+    reciprocal_rank_fusion, rerank, symbol_outline,
+};
 use crate::frontmatter;
-use crate::indexer::core::{FileType, Indexer, classify_extension};
+use crate::indexer::core::{
+    FileType, Indexer, alias_duplicate, classify_file, code_relations, directory_coverage,
+    doc_chunk_embedding_text, doc_chunks, lock_file, markdown_doc_type, markdown_files,
+    markdown_title, normalize_system_path, store_markdown_document,
+};
 use crate::indexer::{
     code_parser::CodeParser,
+    content_hash::sha256_hex,
     dictionary::{self, DictionaryExtractor},
 };
+use crate::mcp::format::{
+    document_hits_json, document_hits_markdown, search_result_json, search_results_markdown,
+    word_mappings_csv,
+};
+use crate::mcp::scope::Scope;
 use crate::mcp::server::McpContext;
 use rmcp::handler::server::ServerHandler;
 use rmcp::handler::server::wrapper::Parameters;
@@ -36,10 +75,54 @@ struct SearchParams {
     query: String,
     /// Max results (default: 5)
     top_k: Option<usize>,
+    /// Page size; overrides top_k when both are given
+    limit: Option<usize>,
+    /// Skip this many ranked results, for paging (default: 0)
+    offset: Option<usize>,
     /// Limit search to a directory (e.g. 'docs/api')
     directory: Option<String>,
     /// Filter by filename glob pattern (e.g. 'api-*.md')
     file_pattern: Option<String>,
+    /// Only code results in this language (e.g. 'rust'); excludes markdown
+    /// and other prose results
+    language: Option<String>,
+    /// Only code results of this symbol type (e.g. 'function'); excludes
+    /// markdown and other prose results
+    symbol_type: Option<String>,
+    /// Output format: "json" | "markdown" (default: "json")
+    format: Option<String>,
+    /// Also return N chunks before/after each hit from the same document
+    context_window: Option<usize>,
+    /// Flag results whose file changed on disk since indexing (default: false)
+    warn_stale: Option<bool>,
+    /// Rank recently modified documents higher; weight of the time-decay
+    /// factor added to similarity (default: config `recency_boost`, 0 = off)
+    recency_boost: Option<f64>,
+    /// Return only the matched documents with their best similarity, without
+    /// chunk content (default: false)
+    names_only: Option<bool>,
+    /// Retrieval mode: "vector" (embedding similarity), "keyword" (full-text
+    /// BM25, for exact tokens like error codes or identifiers; similarity is
+    /// only computed when min_similarity is set, 0 otherwise) or "hybrid"
+    /// (both, merged by reciprocal rank fusion) (default: "vector")
+    mode: Option<String>,
+    /// Omit results with similarity <= 0 (default: config
+    /// `suppress_nonpositive_similarity`, on unless disabled)
+    suppress_nonpositive_similarity: Option<bool>,
+    /// Drop results repeating the content of a higher-ranked one, ignoring
+    /// case and whitespace (default: config `dedup_results`)
+    dedup_results: Option<bool>,
+    /// Drop results whose similarity (1 - cosine distance / 2) is below this
+    /// value; the response reports how many were dropped (default: none)
+    min_similarity: Option<f64>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ListDocumentsParams {
+    /// Skip this many documents, ordered by filename (default: 0)
+    offset: Option<usize>,
+    /// Max documents to return (default: 500)
+    limit: Option<usize>,
 }
 
 #[derive(Deserialize, JsonSchema)]
@@ -52,6 +135,9 @@ struct IndexParams {
     filepaths: Option<String>,
     /// Force re-index even if unchanged (default: false)
     force: Option<bool>,
+    /// Remove indexed documents under the directory whose file no longer
+    /// exists (default: true)
+    prune: Option<bool>,
 }
 
 #[derive(Deserialize, JsonSchema)]
@@ -68,6 +154,15 @@ struct FrontmatterParams {
     filepath: String,
     /// Mode: "add" or "update" (default: "update")
     mode: Option<String>,
+    #[serde(flatten)]
+    fields: FrontmatterFields,
+    /// Re-index the file after writing, so search sees the new metadata.
+    /// The frontmatter itself is never embedded, only the body (default: false)
+    reindex: Option<bool>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct FrontmatterFields {
     /// Domain: frontend | backend | mobile | infrastructure | other
     domain: Option<String>,
     /// Document type: spec | design | api | guide | note | other
@@ -91,6 +186,23 @@ struct SearchRelationsParams {
     direction: Option<String>,
 }
 
+#[derive(Deserialize, JsonSchema)]
+struct TopSymbolsParams {
+    /// Relation type filter: calls | imports | inherits (all if omitted)
+    relation_type: Option<String>,
+    /// Direction: incoming (most called/imported targets) | outgoing (symbols
+    /// with the most relations) (default: incoming)
+    direction: Option<String>,
+    /// Max number of symbols to return (default: 20)
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct EmbeddingHealthParams {
+    /// Max number of stored vectors to sample (default: 1000)
+    sample_size: Option<usize>,
+}
+
 #[derive(Deserialize, JsonSchema)]
 struct BuildDictionaryParams {
     /// Source language (default: ja)
@@ -101,6 +213,126 @@ struct BuildDictionaryParams {
     limit: Option<usize>,
 }
 
+#[derive(Deserialize, JsonSchema)]
+struct ExportGraphParams {
+    /// Only include relations whose source symbol is in this file
+    file: Option<String>,
+    /// Only include relations touching this symbol (its neighborhood)
+    symbol: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct IndexGitChangedParams {
+    /// Git ref to diff against (e.g. 'HEAD~1', 'main', a commit SHA)
+    since_ref: String,
+    /// Directory inside the repository; only changes below it are indexed
+    directory: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct CoverageParams {
+    /// Directory to check against the index
+    directory: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct FindDefinitionParams {
+    /// Symbol name to look up (case-insensitive)
+    symbol: String,
+    /// Only return definitions in this language (e.g. 'rust', 'python')
+    language: Option<String>,
+    /// Match the whole name (default: true); false matches substrings
+    exact: Option<bool>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ExportDictionaryParams {
+    /// Output format: "json" | "csv" (default: "json")
+    format: Option<String>,
+    /// Only export mappings for this source language (e.g. 'ja')
+    source_lang: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct DirectorySummaryParams {
+    /// Indexed directory to summarize
+    directory: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct RefreshMtimeParams {
+    /// Indexed file whose stored modification time should match the disk
+    filename: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct MoveDocumentParams {
+    /// Path the document is currently indexed under (may no longer exist on disk)
+    old_filename: String,
+    /// Path to store it under instead, e.g. after `git mv`
+    new_filename: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct DeleteByDirectoryParams {
+    /// Directory whose indexed documents should be removed (files on disk are untouched)
+    directory: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct WordMappingSourcesParams {
+    /// Source-language word of the mapping (e.g. '認証')
+    source_word: String,
+    /// English word it maps to (e.g. 'authentication')
+    target_word: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ListSymbolsParams {
+    /// Indexed file to outline
+    filename: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ReindexMatchingParams {
+    /// Glob over indexed filenames (e.g. 'api-*.md' or 'docs/**/*.md'); without a '/' it matches the file name in any directory
+    pattern: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct CentroidParams {
+    /// Example texts defining the topic; each is embedded and the vectors averaged
+    texts: Vec<String>,
+    /// Number of nearest documents to return (default: 5)
+    top_k: Option<usize>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ExpandQueryParams {
+    /// Query to expand
+    query: String,
+    /// Language of the query words to look up: ja | zh | ko | ...
+    /// (default: detected from the query)
+    source_lang: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct FrontmatterBulkParams {
+    /// Directory whose markdown files (recursively) get the frontmatter
+    directory: String,
+    /// Frontmatter fields to apply
+    metadata: FrontmatterFields,
+    /// Merge into files that already have frontmatter instead of skipping
+    /// them (default: false)
+    overwrite: Option<bool>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SymbolOutlineParams {
+    /// Indexed code file to outline
+    filename: String,
+}
+
 // ── Response helpers ─────────────────────────────────────────────────
 
 fn json_result(value: serde_json::Value) -> Result<CallToolResult, McpError> {
@@ -153,6 +385,10 @@ impl ServerHandler for AppTools {
         request: CallToolRequestParams,
         context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
+        let mut request = request;
+        if let Some(scope) = Scope::from_extensions(&context.extensions)? {
+            scope.apply(&request.name, &mut request.arguments)?;
+        }
         let tcc = rmcp::handler::server::tool::ToolCallContext::new(self, request, context);
         self.tool_router.call(tcc).await
     }
@@ -167,10 +403,26 @@ impl AppTools {
         }
     }
 
+    /// Name, description and input schema of every tool, sorted by name.
+    pub fn tool_manifest() -> Vec<serde_json::Value> {
+        let mut tools = Self::tool_router().list_all();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
+            .iter()
+            .map(|t| {
+                serde_json::json!({
+                    "name": t.name,
+                    "description": t.description,
+                    "inputSchema": t.input_schema,
+                })
+            })
+            .collect()
+    }
+
     // ── Tool 1: search ──────────────────────────────────────────────
 
     #[tool(
-        description = "Natural language vector search over indexed documents. Supports directory and filename pattern filters. If the response contains update_available, inform the user about the new version."
+        description = "Natural language vector search over indexed documents. Use mode='keyword' or mode='hybrid' for exact tokens like error codes or identifiers. Supports directory and filename pattern filters, names_only=true for just the matching documents, and format='markdown' for human-readable output. If the response contains update_available, inform the user about the new version."
     )]
     async fn search(&self, params: Parameters<SearchParams>) -> Result<CallToolResult, McpError> {
         let p = params.0;
@@ -180,7 +432,46 @@ impl AppTools {
                 None,
             ));
         }
-        let top_k = p.top_k.unwrap_or(5);
+        let top_k = p.limit.or(p.top_k).unwrap_or(5);
+        let offset = p.offset.unwrap_or(0);
+        let format = p.format.as_deref().unwrap_or("json");
+        if format != "json" && format != "markdown" {
+            return Err(McpError::invalid_params(
+                format!("unknown format: {format}. Use 'json' or 'markdown'."),
+                None,
+            ));
+        }
+        let mut mode = p.mode.clone().unwrap_or_else(|| "vector".to_string());
+        if !matches!(mode.as_str(), "vector" | "keyword" | "hybrid") {
+            return Err(McpError::invalid_params(
+                format!("unknown mode: {mode}. Use 'vector', 'keyword' or 'hybrid'."),
+                None,
+            ));
+        }
+        // Very short queries embed poorly: route them to keyword search or refuse them
+        let short_query = {
+            let config = self.ctx.config.read().await;
+            config
+                .short_query_action(&p.query)
+                .map(|action| (action, config.min_query_chars))
+        };
+        let rerouted = match short_query {
+            Some(_) if mode == "keyword" => false,
+            Some((ShortQueryAction::Error, min_chars)) => {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "query is shorter than {min_chars} characters and would match noise. \
+                         Use a longer, more descriptive query, or mode='keyword' for exact tokens."
+                    ),
+                    None,
+                ));
+            }
+            Some((ShortQueryAction::Keyword, _)) => {
+                mode = "keyword".to_string();
+                true
+            }
+            None => false,
+        };
 
         // Pre-clone context limits
         let embedder = self.ctx.get_embedder().await;
@@ -189,74 +480,228 @@ impl AppTools {
         let query_str = p.query.clone();
         let p_directory = p.directory.clone();
         let p_file_pattern = p.file_pattern.clone();
+        let p_language = p.language.clone();
+        let p_symbol_type = p.symbol_type.clone();
+        let names_only = p.names_only.unwrap_or(false);
+        // Context is never shown in names-only results, so don't fetch it
+        let context_window = if names_only {
+            0
+        } else {
+            p.context_window.unwrap_or(0)
+        };
+        let warn_stale = p.warn_stale.unwrap_or(false);
+        let (rank_opts, suppress_nonpositive, dedup, precision, embed_text) = {
+            let config = self.ctx.config.read().await;
+            let rank_opts = RankOptions {
+                recency_boost: p.recency_boost.unwrap_or(config.recency_boost),
+                doc_type_boosts: config.boost_doc_types.clone(),
+            };
+            let suppress = p
+                .suppress_nonpositive_similarity
+                .unwrap_or(config.suppress_nonpositive_similarity);
+            let dedup = p.dedup_results.unwrap_or(config.dedup_results);
+            let embed_text = if config.split_identifiers_in_query {
+                dictionary::expand_query_identifiers(&p.query)
+            } else {
+                p.query.clone()
+            };
+            let embed_text = config.query_text(&embed_text);
+            (
+                rank_opts,
+                suppress,
+                dedup,
+                config.similarity_precision,
+                embed_text,
+            )
+        };
+        if !rank_opts.recency_boost.is_finite() || rank_opts.recency_boost < 0.0 {
+            return Err(McpError::invalid_params(
+                "recency_boost must be a non-negative number".to_string(),
+                None,
+            ));
+        }
+        let min_similarity = p.min_similarity;
+        if min_similarity.is_some_and(|m| !m.is_finite()) {
+            return Err(McpError::invalid_params(
+                "min_similarity must be a finite number".to_string(),
+                None,
+            ));
+        }
 
-        let (results, keyword_results) = tokio::task::spawn_blocking(move || {
-            let query_vector = embedder
-                .embed(&query_str)
-                .map_err(|e| McpError::invalid_request(format!("embedding failed: {e}"), None))?;
+        let (results, keyword_results, context, dropped) = tokio::task::spawn_blocking(move || {
+            // Keyword search only needs the embedding for similarity scores
+            let query_vector = if mode != "keyword" || min_similarity.is_some() {
+                let v = embedder.embed(&embed_text).map_err(|e| {
+                    McpError::invalid_request(format!("embedding failed: {e}"), None)
+                })?;
+                Some(v)
+            } else {
+                None
+            };
 
             let filter = SearchFilter {
                 directory: p_directory.as_deref(),
                 file_pattern: p_file_pattern.as_deref(),
+                language: p_language.as_deref(),
+                symbol_type: p_symbol_type.as_deref(),
             };
-            let has_filter = filter.directory.is_some() || filter.file_pattern.is_some();
+            let has_filter = filter.directory.is_some()
+                || filter.file_pattern.is_some()
+                || filter.language.is_some()
+                || filter.symbol_type.is_some();
             let filter_ref = if has_filter { Some(&filter) } else { None };
 
-            let r = db
-                .search_with_filter(&query_vector, top_k, filter_ref)
-                .map_err(|e| McpError::internal_error(format!("search failed: {e}"), None))?;
+            // Hybrid fusion and dedup benefit from deeper candidate lists, like re-ranking
+            // Later pages are cut from the ranking of everything before them
+            let page_end = offset + top_k;
+            let fetch_k = if rank_opts.is_active() || mode == "hybrid" || dedup {
+                page_end * RERANK_CANDIDATE_FACTOR
+            } else {
+                page_end
+            };
+            let search_failed =
+                |e: rusqlite::Error| McpError::internal_error(format!("search failed: {e}"), None);
+            // Chunks under min_similarity, dropped before any re-ranking. A
+            // set, since hybrid mode can drop the same chunk from both lists.
+            let dropped = std::cell::RefCell::new(std::collections::HashSet::new());
+            let apply_min_similarity = |r: &mut Vec<_>| {
+                if let Some(min) = min_similarity {
+                    dropped.borrow_mut().extend(drop_below_similarity(r, min));
+                }
+            };
+            let vector_results = || {
+                let query_vector = query_vector
+                    .as_deref()
+                    .expect("query is embedded outside keyword mode");
+                let mut r = db
+                    .search_with_filter(query_vector, fetch_k, filter_ref)
+                    .map_err(search_failed)?;
+                apply_min_similarity(&mut r);
+                if suppress_nonpositive {
+                    drop_nonpositive_similarity(&mut r);
+                }
+                rerank(&mut r, &rank_opts, chrono::Utc::now());
+                Ok::<_, McpError>(r)
+            };
+            let keyword_results = || {
+                let mut r = db
+                    .search_keyword(&query_str, query_vector.as_deref(), fetch_k, filter_ref)
+                    .map_err(search_failed)?;
+                apply_min_similarity(&mut r);
+                Ok::<_, McpError>(r)
+            };
+            let mut r = match mode.as_str() {
+                "keyword" => keyword_results()?,
+                "hybrid" => {
+                    reciprocal_rank_fusion(vec![vector_results()?, keyword_results()?], fetch_k)
+                }
+                _ => vector_results()?,
+            };
+            if dedup {
+                dedup_near_identical(&mut r);
+            }
+            r.truncate(page_end);
+            r.drain(..offset.min(r.len()));
 
             let keywords: Vec<&str> = query_str.split_whitespace().collect();
-            let kr = db
-                .search_symbols_by_keywords(&keywords, top_k)
+            let mut kr = db
+                .search_symbols_by_keywords(&keywords, query_vector.as_deref(), page_end)
                 .unwrap_or_default();
+            kr.retain(|hit| {
+                filter.matches_directory(&hit.document_name)
+                    && filter.matches_code(hit.metadata.as_ref())
+            });
+            apply_min_similarity(&mut kr);
+            kr.drain(..offset.min(kr.len()));
+
+            let mut context = std::collections::HashMap::new();
+            if context_window > 0 {
+                for hit in r.iter().chain(kr.iter()) {
+                    if context.contains_key(&hit.chunk_id) {
+                        continue;
+                    }
+                    let adjacent = db
+                        .get_adjacent_chunks(hit.document_id, hit.position, context_window)
+                        .map_err(|e| {
+                            McpError::internal_error(format!("context lookup failed: {e}"), None)
+                        })?;
+                    context.insert(hit.chunk_id, adjacent);
+                }
+            }
 
-            Ok::<_, McpError>((r, kr))
+            let dropped = dropped.borrow().len();
+            Ok::<_, McpError>((r, kr, context, dropped))
         })
         .await
         .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))??;
         // removed drop(db)
 
-        // Check for updates (non-blocking, best-effort)
+        // Check for updates (cache only, refreshed in the background)
         let config_guard = self.ctx.config.read().await;
         let update_info = if config_guard.is_update_check_enabled() {
-            crate::updater::get_update_info(crate::updater::CURRENT_VERSION, &config_guard.db_path)
-                .await
+            crate::updater::get_update_info(
+                crate::updater::CURRENT_VERSION,
+                "",
+                config_guard.update_repo.as_deref(),
+            )
         } else {
             None
         };
         drop(config_guard);
 
+        let hits = names_only.then(|| document_hits(results.iter().chain(keyword_results.iter())));
+
         // Merge vector + keyword results, deduplicating by (document_name, position)
         let mut seen = std::collections::HashSet::new();
-        let results_json: Vec<serde_json::Value> = results
-            .iter()
-            .chain(keyword_results.iter())
-            .filter_map(|r| {
-                let key = (r.document_name.clone(), r.position);
-                if !seen.insert(key) {
-                    return None; // Already seen this chunk from vector search
-                }
-                let mut obj = serde_json::json!({
-                    "document": r.document_name,
-                    "content": r.chunk_content,
-                    "similarity": format!("{:.4}", r.similarity),
-                    "position": r.position,
-                });
-                if let Some(meta) = &r.metadata {
-                    obj["symbol_name"] = serde_json::json!(meta.symbol_name);
-                    obj["symbol_type"] = serde_json::json!(meta.symbol_type);
-                    obj["language"] = serde_json::json!(meta.language);
-                    obj["start_line"] = serde_json::json!(meta.start_line);
-                    obj["end_line"] = serde_json::json!(meta.end_line);
-                    obj["parent_symbol"] = serde_json::json!(meta.parent_symbol);
-                    obj["signature"] = serde_json::json!(meta.signature);
-                }
-                Some(obj)
-            })
-            .collect();
+        let results_json: Vec<serde_json::Value> = if let Some(hits) = &hits {
+            document_hits_json(hits, precision)
+        } else {
+            results
+                .iter()
+                .chain(keyword_results.iter())
+                // Already seen this chunk from vector search
+                .filter(|r| seen.insert((r.document_name.as_str(), r.position)))
+                .map(|r| search_result_json(r, precision, warn_stale, context.get(&r.chunk_id)))
+                .collect()
+        };
+
+        if format == "markdown" {
+            let terms: Vec<&str> = p
+                .query
+                .split_whitespace()
+                .filter(|t| t.len() >= MIN_KEYWORD_LEN)
+                .collect();
+            let mut text = match &hits {
+                Some(hits) => document_hits_markdown(&p.query, hits, precision),
+                None => search_results_markdown(&p.query, &results_json, &terms),
+            };
+            if min_similarity.is_some() {
+                text.push_str(&format!(
+                    "\n_{dropped} result(s) below min_similarity were dropped._\n"
+                ));
+            }
+            if let Some(info) = update_info {
+                text.push_str(&format!(
+                    "\n---\n\nUpdate available: {} → {} ({})\n",
+                    info.current_version, info.latest_version, info.url
+                ));
+            }
+            return Ok(CallToolResult::success(vec![Content::text(text)]));
+        }
 
         let mut response = serde_json::json!({ "results": results_json });
+        if min_similarity.is_some() {
+            response["dropped_below_min_similarity"] = serde_json::json!(dropped);
+        }
+        if rerouted {
+            response["mode"] = serde_json::json!("keyword");
+            response["note"] =
+                serde_json::json!("query below min_query_chars; ran a keyword search instead");
+        }
+        if p.offset.is_some() || p.limit.is_some() {
+            response["offset"] = serde_json::json!(offset);
+            response["limit"] = serde_json::json!(top_k);
+        }
         if let Some(info) = update_info {
             response["update_available"] = serde_json::json!({
                 "current_version": info.current_version,
@@ -271,7 +716,7 @@ impl AppTools {
     // ── Tool 2: index (merged index_markdown + index_code) ──────────
 
     #[tool(
-        description = "Index files (markdown or code). Auto-detects type by file extension. Supports single file, directory, or batch (comma-separated paths). Languages: Go, Python, TypeScript, JavaScript, Rust, Markdown."
+        description = "Index files (markdown or code). Auto-detects type by file extension. Supports single file, directory, or batch (comma-separated paths). Languages: Go, Python, TypeScript, JavaScript, Rust, Java, C, C++, Markdown."
     )]
     async fn index(&self, params: Parameters<IndexParams>) -> Result<CallToolResult, McpError> {
         let p = params.0;
@@ -353,6 +798,12 @@ impl AppTools {
             };
 
             let force = p.force.unwrap_or(false);
+            let prune = p.prune.unwrap_or(true);
+            let backup_path = if force {
+                backup_before_destructive(&self.ctx).await?
+            } else {
+                None
+            };
             let embedder = self.ctx.get_embedder().await;
             let config = self.ctx.config.read().await.clone();
             let mut indexer = Indexer::new(
@@ -362,12 +813,15 @@ impl AppTools {
                 Arc::new(config),
             );
 
-            let result = match indexer.index_directory(&canonical_dir, force).await {
+            let result = match indexer
+                .index_directory(&canonical_dir, force, prune, false)
+                .await
+            {
                 Ok(r) => r,
                 Err(e) => return error_result(&format!("directory indexing failed: {e}")),
             };
 
-            return json_result(serde_json::json!({
+            let mut response = serde_json::json!({
                 "success": true,
                 "message": "Directory indexing completed",
                 "directory": dir,
@@ -375,9 +829,15 @@ impl AppTools {
                 "files_added": result.added,
                 "files_updated": result.updated,
                 "files_skipped": result.skipped,
+                "files_generated": result.generated,
+                "files_too_large": result.skipped_large,
                 "files_removed": result.removed,
                 "files_failed": result.failed,
-            }));
+            });
+            if let Some(backup) = backup_path {
+                response["backup_path"] = serde_json::json!(backup);
+            }
+            return json_result(response);
         }
 
         error_result("unexpected state")
@@ -386,33 +846,48 @@ impl AppTools {
     // ── Tool 3: list_documents ──────────────────────────────────────
 
     #[tool(
-        description = "Retrieve list of indexed documents (limited to 500 results for stability)"
+        description = "Retrieve list of indexed documents, ordered by filename. Pages with offset/limit (default limit 500); total_count and has_more tell whether more pages follow."
     )]
-    async fn list_documents(&self) -> Result<CallToolResult, McpError> {
+    async fn list_documents(
+        &self,
+        params: Parameters<ListDocumentsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let offset = p.offset.unwrap_or(0);
+        let limit = p.limit.unwrap_or(500);
+
         let db = self.ctx.db.clone();
-        let docs = tokio::task::spawn_blocking(move || db.list_documents())
-            .await
-            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
-            .map_err(|e| McpError::internal_error(format!("list failed: {e}"), None))?;
+        let (total_count, docs) = tokio::task::spawn_blocking(move || {
+            Ok::<_, rusqlite::Error>((
+                db.count_documents()?,
+                db.list_documents_page(offset, limit)?,
+            ))
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+        .map_err(|e| McpError::internal_error(format!("list failed: {e}"), None))?;
 
-        let total_count = docs.len();
-        let limit = 500;
-        let has_more = total_count > limit;
+        let has_more = offset + docs.len() < total_count;
 
         let documents: Vec<serde_json::Value> = docs
             .iter()
-            .take(limit)
-            .map(|(filename, modified_at)| {
-                serde_json::json!({
-                    "filename": filename,
-                    "modified_at": modified_at.to_rfc3339(),
-                })
+            .map(|d| {
+                let mut entry = serde_json::json!({
+                    "filename": d.filename,
+                    "title": d.title,
+                    "modified_at": d.modified_at.to_rfc3339(),
+                });
+                if let Some(original) = &d.alias_of {
+                    entry["alias_of"] = serde_json::json!(original);
+                }
+                entry
             })
             .collect();
 
         json_result(serde_json::json!({
             "total_count": total_count,
             "has_more": has_more,
+            "offset": offset,
             "limit": limit,
             "documents": documents
         }))
@@ -436,27 +911,58 @@ impl AppTools {
         }
 
         let action = p.action.as_deref().unwrap_or("delete");
+        if action != "delete" && action != "reindex" {
+            return Err(McpError::invalid_params(
+                format!("unknown action: {action}. Use 'delete' or 'reindex'."),
+                None,
+            ));
+        }
+
+        let filename_path = Path::new(&p.filename);
+        let stored_name = stored_name(&p.filename);
+        if action == "reindex" && !filename_path.exists() {
+            return Err(McpError::invalid_params(
+                format!("file not found: {}", p.filename),
+                None,
+            ));
+        }
+
+        let db = self.ctx.db.clone();
+        let name = stored_name.clone();
+        let indexed = tokio::task::spawn_blocking(move || db.document_exists(&name))
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+            .map_err(|e| McpError::internal_error(format!("lookup failed: {e}"), None))?;
+        if action == "delete" && !indexed {
+            return error_result(&format!("document not indexed: {}", p.filename));
+        }
+        // Only back up when there are rows to delete
+        let backup_path = if indexed {
+            backup_before_destructive(&self.ctx).await?
+        } else {
+            None
+        };
 
-        match action {
+        let mut response = match action {
             "delete" => {
                 let db = self.ctx.db.clone();
-                let f_clone = p.filename.clone();
+                let f_clone = stored_name.clone();
                 tokio::task::spawn_blocking(move || db.delete_document(&f_clone))
                     .await
                     .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
                     .map_err(|e| McpError::internal_error(format!("delete failed: {e}"), None))?;
 
-                json_result(serde_json::json!({
+                serde_json::json!({
                     "success": true,
                     "action": "delete",
                     "message": "Document deleted successfully",
-                }))
+                })
             }
             "reindex" => {
                 // Delete from DB
                 {
                     let db = self.ctx.db.clone();
-                    let f_clone = p.filename.clone();
+                    let f_clone = stored_name.clone();
                     tokio::task::spawn_blocking(move || db.delete_document(&f_clone))
                         .await
                         .map_err(|e| {
@@ -468,33 +974,27 @@ impl AppTools {
                 }
 
                 // Re-index
-                let path = Path::new(&p.filename);
-                if !path.exists() {
-                    return Err(McpError::invalid_params(
-                        format!("file not found: {}", p.filename),
-                        None,
-                    ));
-                }
-
-                index_single_file(path, &p.filename, &self.ctx).await?;
+                index_single_file(filename_path, &p.filename, &self.ctx).await?;
 
-                json_result(serde_json::json!({
+                serde_json::json!({
                     "success": true,
                     "action": "reindex",
                     "message": "Document reindexed successfully",
-                }))
+                })
             }
-            _ => Err(McpError::invalid_params(
-                format!("unknown action: {action}. Use 'delete' or 'reindex'."),
-                None,
-            )),
+            _ => unreachable!("action validated above"),
+        };
+
+        if let Some(backup) = backup_path {
+            response["backup_path"] = serde_json::json!(backup);
         }
+        json_result(response)
     }
 
     // ── Tool 5: frontmatter (merged add + update) ───────────────────
 
     #[tool(
-        description = "Add or update metadata (frontmatter) of a markdown file. Mode: 'add' creates new frontmatter, 'update' modifies existing (default: 'update')."
+        description = "Add or update metadata (frontmatter) of a markdown file. Mode: 'add' creates new frontmatter, 'update' modifies existing (default: 'update'). Set reindex to refresh the file's search index afterwards."
     )]
     async fn frontmatter(
         &self,
@@ -508,33 +1008,43 @@ impl AppTools {
             ));
         }
 
-        let metadata = build_frontmatter_metadata(&p);
+        let metadata = build_frontmatter_metadata(&p.fields);
         let mode = p.mode.as_deref().unwrap_or("update");
+        let path = Path::new(&p.filepath);
 
-        match mode {
+        let mut response = match mode {
             "add" => {
-                frontmatter::add_frontmatter(Path::new(&p.filepath), &metadata)
+                frontmatter::add_frontmatter(path, &metadata)
                     .map_err(|e| McpError::internal_error(format!("{e}"), None))?;
-                json_result(serde_json::json!({
+                serde_json::json!({
                     "success": true,
                     "mode": "add",
                     "message": "Frontmatter added successfully",
-                }))
+                })
             }
             "update" => {
-                frontmatter::update_frontmatter(Path::new(&p.filepath), &metadata)
+                frontmatter::update_frontmatter(path, &metadata)
                     .map_err(|e| McpError::internal_error(format!("{e}"), None))?;
-                json_result(serde_json::json!({
+                serde_json::json!({
                     "success": true,
                     "mode": "update",
                     "message": "Frontmatter updated successfully",
-                }))
+                })
             }
-            _ => Err(McpError::invalid_params(
-                format!("unknown mode: {mode}. Use 'add' or 'update'."),
-                None,
-            )),
+            _ => {
+                return Err(McpError::invalid_params(
+                    format!("unknown mode: {mode}. Use 'add' or 'update'."),
+                    None,
+                ));
+            }
+        };
+
+        if p.reindex.unwrap_or(false) {
+            // Replaces the document's chunks in one transaction
+            index_single_file(path, &p.filepath, &self.ctx).await?;
+            response["reindexed"] = serde_json::json!(true);
         }
+        json_result(response)
     }
 
     // ── Tool 6: search_relations ────────────────────────────────────
@@ -604,7 +1114,7 @@ impl AppTools {
         let limit = p.limit.unwrap_or(100);
 
         let extractor = DictionaryExtractor::new();
-        let mut all_mappings: Vec<(String, String, String, f64, String)> = Vec::new();
+        let mut all_mappings = Vec::new();
 
         if let Some(doc_path) = &p.document {
             // Validate the document is indexed before reading
@@ -629,16 +1139,7 @@ impl AppTools {
             let content = std::fs::read_to_string(doc_path).map_err(|e| {
                 McpError::invalid_params(format!("failed to read {doc_path}: {e}"), None)
             })?;
-            let mappings = extractor.extract_from_content(&content, doc_path, source_lang);
-            for m in mappings {
-                all_mappings.push((
-                    m.source_word,
-                    m.target_word,
-                    m.source_lang.clone(),
-                    m.confidence as f64,
-                    m.source_document.clone(),
-                ));
-            }
+            all_mappings.extend(extractor.extract_from_content(&content, doc_path, source_lang));
         } else {
             // Extract from all indexed documents
             let db = self.ctx.db.clone();
@@ -657,20 +1158,30 @@ impl AppTools {
 
                 let lang = dictionary::detect_language(&content);
                 if lang == "mixed" || lang == source_lang {
-                    let mappings = extractor.extract_from_content(&content, doc_path, source_lang);
-                    for m in mappings {
-                        all_mappings.push((
-                            m.source_word,
-                            m.target_word,
-                            m.source_lang.clone(),
-                            m.confidence as f64, // Cast f32 to f64
-                            m.source_document.clone(),
-                        ));
-                    }
+                    all_mappings.extend(extractor.extract_from_content(
+                        &content,
+                        doc_path,
+                        source_lang,
+                    ));
                 }
             }
         }
 
+        let min_confidence = self.ctx.config.read().await.dictionary_min_confidence;
+        let filtered_count = dictionary::retain_confident(&mut all_mappings, min_confidence);
+        let all_mappings: Vec<(String, String, String, f64, String)> = all_mappings
+            .into_iter()
+            .map(|m| {
+                (
+                    m.source_word,
+                    m.target_word,
+                    m.source_lang,
+                    m.confidence as f64,
+                    m.source_document,
+                )
+            })
+            .collect();
+
         // Insert into DB
         let db = self.ctx.db.clone();
         let mappings_clone = all_mappings.clone();
@@ -697,30 +1208,1010 @@ impl AppTools {
         json_result(serde_json::json!({
             "success": true,
             "extracted_count": all_mappings.len(),
+            "filtered_count": filtered_count,
             "total_dictionary": total_count,
             "sample_mappings": sample,
         }))
     }
-}
 
-// ── Helper functions ─────────────────────────────────────────────────
+    // ── Tool 8: export_graph ───────────────────────────────────────
 
-fn build_frontmatter_metadata(p: &FrontmatterParams) -> frontmatter::Metadata {
-    let tags = p
-        .tags
-        .as_deref()
-        .unwrap_or("")
-        .split(',')
-        .map(|t| t.trim().to_string())
-        .filter(|t| !t.is_empty())
-        .collect();
+    #[tool(
+        description = "Export the code relation graph as GraphViz DOT (symbols as nodes, relations as typed/colored edges). Optionally scope to a file or a symbol's neighborhood."
+    )]
+    async fn export_graph(
+        &self,
+        params: Parameters<ExportGraphParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let db = self.ctx.db.clone();
 
-    frontmatter::Metadata {
+        let dot = tokio::task::spawn_blocking(move || {
+            let filter = GraphFilter {
+                file: p.file.as_deref(),
+                symbol: p.symbol.as_deref(),
+            };
+            db.export_relations_dot(Some(&filter))
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+        .map_err(|e| McpError::internal_error(format!("export failed: {e}"), None))?;
+
+        json_result(serde_json::json!({
+            "format": "dot",
+            "dot": dot,
+        }))
+    }
+
+    // ── Tool 9: index_git_changed ───────────────────────────────────
+
+    #[tool(
+        description = "Reindex only files under a directory that changed since a git ref (git diff --name-only <since_ref>), plus untracked files; files deleted since the ref are removed from the index. Requires the 'git' build feature."
+    )]
+    async fn index_git_changed(
+        &self,
+        params: Parameters<IndexGitChangedParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        if p.since_ref.is_empty() {
+            return Err(McpError::invalid_params(
+                "since_ref is required".to_string(),
+                None,
+            ));
+        }
+
+        if p.directory.is_empty() {
+            return Err(McpError::invalid_params(
+                "directory is required".to_string(),
+                None,
+            ));
+        }
+
+        #[cfg(feature = "git")]
+        {
+            let dir = p.directory.as_str();
+            let changed = match crate::indexer::git::changed_files(Path::new(dir), &p.since_ref) {
+                Ok(c) => c,
+                Err(e) => return error_result(&format!("git indexing failed: {e}")),
+            };
+            // Only back up when an indexed file was deleted since the ref
+            let deleted: Vec<String> = changed
+                .iter()
+                .filter(|f| !f.exists())
+                .map(|f| normalize_system_path(f))
+                .collect();
+            let db = self.ctx.db.clone();
+            let removes_rows = tokio::task::spawn_blocking(move || {
+                for f in &deleted {
+                    if db.document_exists(f)? {
+                        return Ok(true);
+                    }
+                }
+                Ok::<_, rusqlite::Error>(false)
+            })
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+            .map_err(|e| McpError::internal_error(format!("lookup failed: {e}"), None))?;
+            let backup_path = if removes_rows {
+                backup_before_destructive(&self.ctx).await?
+            } else {
+                None
+            };
+            let embedder = self.ctx.get_embedder().await;
+            let config = self.ctx.config.read().await.clone();
+            let mut indexer = Indexer::new(
+                self.ctx.db.clone(),
+                embedder.as_ref(),
+                self.ctx.chunk_size,
+                Arc::new(config),
+            );
+
+            let result = match indexer.index_git_changed(dir, &p.since_ref).await {
+                Ok(r) => r,
+                Err(e) => return error_result(&format!("git indexing failed: {e}")),
+            };
+
+            let mut response = serde_json::json!({
+                "success": true,
+                "since_ref": p.since_ref,
+                "files_indexed": result.indexed,
+                "files_added": result.added,
+                "files_updated": result.updated,
+                "files_skipped": result.skipped,
+                "files_removed": result.removed,
+            });
+            if let Some(backup) = backup_path {
+                response["backup_path"] = serde_json::json!(backup);
+            }
+            json_result(response)
+        }
+
+        #[cfg(not(feature = "git"))]
+        {
+            let _ = p.directory;
+            error_result(
+                "index_git_changed is unavailable: rustrag was built without the 'git' feature",
+            )
+        }
+    }
+
+    // ── Tool 10: schema_info ────────────────────────────────────────
+
+    #[tool(
+        description = "Report the index schema version, the sqlite-vec version and the embedding dimension, for migration and client compatibility checks."
+    )]
+    async fn schema_info(&self) -> Result<CallToolResult, McpError> {
+        let db = self.ctx.db.clone();
+        let (schema_version, vec_version, index_dimensions) =
+            tokio::task::spawn_blocking(move || {
+                Ok::<_, rusqlite::Error>((
+                    db.schema_version()?,
+                    db.sqlite_vec_version()?,
+                    db.embedding_dimensions()?,
+                ))
+            })
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+            .map_err(|e| McpError::internal_error(format!("schema query failed: {e}"), None))?;
+
+        let dimensions = self.ctx.config.read().await.embedding.dimensions;
+        json_result(serde_json::json!({
+            "schema_version": schema_version,
+            "supported_schema_version": crate::db::SCHEMA_VERSION,
+            "sqlite_vec_version": vec_version,
+            "embedding_dimensions": dimensions,
+            "index_embedding_dimensions": index_dimensions,
+        }))
+    }
+
+    // ── Tool 11: stats ──────────────────────────────────────────────
+
+    #[tool(
+        description = "Index health statistics: total documents, chunks, code symbols, relations, word mappings, per-language symbol counts and database file size."
+    )]
+    async fn stats(&self) -> Result<CallToolResult, McpError> {
+        let db = self.ctx.db.clone();
+        let stats = tokio::task::spawn_blocking(move || db.get_index_stats())
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+            .map_err(|e| McpError::internal_error(format!("stats query failed: {e}"), None))?;
+
+        let db_path = self.ctx.config.read().await.db_path.clone();
+        let db_size_bytes = std::fs::metadata(&db_path).ok().map(|m| m.len());
+
+        json_result(serde_json::json!({
+            "documents": stats.documents,
+            "chunks": stats.chunks,
+            "code_symbols": stats.code_symbols,
+            "relations": stats.relations,
+            "word_mappings": stats.word_mappings,
+            "symbols_by_language": stats.symbols_by_language,
+            "db_path": db_path,
+            "db_size_bytes": db_size_bytes,
+        }))
+    }
+
+    // ── Tool 12: coverage ───────────────────────────────────────────
+
+    #[tool(
+        description = "Check index coverage of a directory: counts supported files and lists those not yet indexed."
+    )]
+    async fn coverage(
+        &self,
+        params: Parameters<CoverageParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let dir = std::path::PathBuf::from(&params.0.directory);
+        if !dir.is_dir() {
+            return error_result(&format!("Directory does not exist: {}", dir.display()));
+        }
+
+        let db = self.ctx.db.clone();
+        let config = self.ctx.config.read().await.clone();
+        let report = tokio::task::spawn_blocking(move || {
+            directory_coverage(&db, &config, &dir).map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?;
+
+        match report {
+            Ok(r) => json_result(serde_json::json!({
+                "total_supported": r.total_supported,
+                "indexed": r.indexed,
+                "missing": r.missing,
+            })),
+            Err(e) => error_result(&format!("coverage check failed: {e}")),
+        }
+    }
+
+    // ── Tool 13: reindex_all ────────────────────────────────────────
+
+    #[tool(
+        description = "Rebuild the whole index: re-read, re-chunk and re-embed every indexed document from disk (e.g. after changing the embedding model or chunk_size). Documents whose file no longer exists are removed."
+    )]
+    async fn reindex_all(&self) -> Result<CallToolResult, McpError> {
+        let backup_path = backup_before_destructive(&self.ctx).await?;
+        let embedder = self.ctx.get_embedder().await;
+        let config = self.ctx.config.read().await.clone();
+        let indexer = Indexer::new(
+            self.ctx.db.clone(),
+            embedder.as_ref(),
+            self.ctx.chunk_size,
+            Arc::new(config),
+        );
+
+        let result = match indexer.reindex_all().await {
+            Ok(r) => r,
+            Err(e) => return error_result(&format!("reindex failed: {e}")),
+        };
+
+        let mut response = serde_json::json!({
+            "success": true,
+            "succeeded": result.succeeded.len(),
+            "failed_count": result.failed.len(),
+            "missing_count": result.missing.len(),
+            "failed": result.failed,
+            "missing": result.missing,
+        });
+        if let Some(backup) = backup_path {
+            response["backup_path"] = serde_json::json!(backup);
+        }
+        json_result(response)
+    }
+
+    // ── Tool 14: directory_summary ──────────────────────────────────
+
+    #[tool(
+        description = "High-level map of an indexed directory: for each subdirectory, the number of indexed documents, total chunks and code languages present."
+    )]
+    async fn directory_summary(
+        &self,
+        params: Parameters<DirectorySummaryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let dir = normalize_system_path(Path::new(&params.0.directory));
+        let db = self.ctx.db.clone();
+        let root = dir.clone();
+        let summary = tokio::task::spawn_blocking(move || db.directory_summary(&root))
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+            .map_err(|e| McpError::internal_error(format!("summary query failed: {e}"), None))?;
+
+        let directories: Vec<_> = summary
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "directory": s.directory,
+                    "documents": s.documents,
+                    "chunks": s.chunks,
+                    "languages": s.languages,
+                })
+            })
+            .collect();
+        json_result(serde_json::json!({
+            "root": dir,
+            "total_documents": summary.iter().map(|s| s.documents).sum::<usize>(),
+            "directories": directories,
+        }))
+    }
+
+    // ── Tool 15: find_definition ────────────────────────────────────
+
+    #[tool(
+        description = "Jump to a symbol's definition: returns file, line range, signature, parent symbol and full source of every indexed definition with that name. Matches case-insensitively; exact=false matches substrings."
+    )]
+    async fn find_definition(
+        &self,
+        params: Parameters<FindDefinitionParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        if p.symbol.trim().is_empty() {
+            return Err(McpError::invalid_params(
+                "symbol is required".to_string(),
+                None,
+            ));
+        }
+
+        let db = self.ctx.db.clone();
+        let symbol = p.symbol.trim().to_string();
+        let language = p.language.clone();
+        let exact = p.exact.unwrap_or(true);
+        let results = tokio::task::spawn_blocking(move || {
+            db.find_definitions(&symbol, language.as_deref(), exact)
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+        .map_err(|e| McpError::internal_error(format!("definition lookup failed: {e}"), None))?;
+
+        let definitions: Vec<_> = results
+            .iter()
+            .filter_map(|r| {
+                let meta = r.metadata.as_ref()?;
+                Some(serde_json::json!({
+                    "file": r.document_name,
+                    "symbol_name": meta.symbol_name,
+                    "symbol_type": meta.symbol_type,
+                    "language": meta.language,
+                    "start_line": meta.start_line,
+                    "end_line": meta.end_line,
+                    "signature": meta.signature,
+                    "doc_comment": meta.doc_comment,
+                    "parent_symbol": meta.parent_symbol,
+                    "content": r.chunk_content,
+                }))
+            })
+            .collect();
+
+        json_result(serde_json::json!({
+            "symbol": p.symbol,
+            "count": definitions.len(),
+            "definitions": definitions,
+        }))
+    }
+
+    // ── Tool 16: export_dictionary ──────────────────────────────────
+
+    #[tool(
+        description = "Export the word-mapping dictionary built by build_dictionary, ordered by confidence, as JSON or CSV."
+    )]
+    async fn export_dictionary(
+        &self,
+        params: Parameters<ExportDictionaryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let format = p.format.as_deref().unwrap_or("json");
+        if format != "json" && format != "csv" {
+            return Err(McpError::invalid_params(
+                format!("unknown format: {format}. Use 'json' or 'csv'."),
+                None,
+            ));
+        }
+
+        let db = self.ctx.db.clone();
+        let source_lang = p.source_lang.clone();
+        let mappings =
+            tokio::task::spawn_blocking(move || db.list_word_mappings(source_lang.as_deref()))
+                .await
+                .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+                .map_err(|e| {
+                    McpError::internal_error(format!("dictionary query failed: {e}"), None)
+                })?;
+
+        if format == "csv" {
+            return Ok(CallToolResult::success(vec![Content::text(
+                word_mappings_csv(&mappings),
+            )]));
+        }
+
+        let entries: Vec<_> = mappings
+            .iter()
+            .map(|m| {
+                serde_json::json!({
+                    "source_word": m.source_word,
+                    "target_word": m.target_word,
+                    "source_lang": m.source_lang,
+                    "confidence": m.confidence,
+                    "source_document": m.source_document,
+                })
+            })
+            .collect();
+        json_result(serde_json::json!({
+            "count": entries.len(),
+            "mappings": entries,
+        }))
+    }
+    // ── Tool 17: refresh_mtime ──────────────────────────────────────
+
+    #[tool(
+        description = "Update an indexed document's stored modification time from the file on disk without re-embedding it. Use when a file's mtime changed but its content didn't, so the next sync skips it."
+    )]
+    async fn refresh_mtime(
+        &self,
+        params: Parameters<RefreshMtimeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let path = Path::new(&p.filename);
+        let modified_at: chrono::DateTime<chrono::Utc> =
+            match std::fs::metadata(path).and_then(|m| m.modified()) {
+                Ok(t) => t.into(),
+                Err(e) => {
+                    return Err(McpError::invalid_params(
+                        format!("cannot read {}: {e}", p.filename),
+                        None,
+                    ));
+                }
+            };
+
+        let stored_name = normalize_system_path(path);
+        let db = self.ctx.db.clone();
+        let name = stored_name.clone();
+        let updated = tokio::task::spawn_blocking(move || db.touch_document(&name, modified_at))
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+            .map_err(|e| McpError::internal_error(format!("update failed: {e}"), None))?;
+
+        if !updated {
+            return error_result(&format!("document not indexed: {}", p.filename));
+        }
+        json_result(serde_json::json!({
+            "success": true,
+            "filename": stored_name,
+            "modified_at": modified_at.to_rfc3339(),
+        }))
+    }
+
+    // ── Tool 18: link_relations ─────────────────────────────────────
+
+    #[tool(
+        description = "Link code relations to the chunks defining their targets. Relations without a known target are matched by symbol name, preferring the caller's own file, then its language; ambiguous names stay unlinked. Reports how many relations were linked."
+    )]
+    async fn link_relations(&self) -> Result<CallToolResult, McpError> {
+        let db = self.ctx.db.clone();
+        let result = tokio::task::spawn_blocking(move || db.resolve_relation_targets())
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+            .map_err(|e| McpError::internal_error(format!("linking failed: {e}"), None))?;
+
+        json_result(serde_json::json!({
+            "linked": result.linked,
+            "ambiguous": result.ambiguous,
+            "unmatched": result.unmatched,
+        }))
+    }
+
+    // ── Tool 19: delete_by_directory ───────────────────────────────
+
+    #[tool(
+        description = "Remove every indexed document under a directory from the index, e.g. after deleting a folder. Files on disk are not touched. Returns the number of documents removed."
+    )]
+    async fn delete_by_directory(
+        &self,
+        params: Parameters<DeleteByDirectoryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        // Resolve like stored filenames; the directory may already be gone
+        let directory = stored_name(&p.directory);
+        let directory = directory.trim_end_matches('/').to_string();
+        if directory.is_empty() {
+            return Err(McpError::invalid_params(
+                "directory is required".to_string(),
+                None,
+            ));
+        }
+        let backup_path = backup_before_destructive(&self.ctx).await?;
+
+        let db = self.ctx.db.clone();
+        let prefix = format!("{directory}/");
+        let deleted = tokio::task::spawn_blocking(move || db.delete_documents_by_prefix(&prefix))
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+            .map_err(|e| McpError::internal_error(format!("delete failed: {e}"), None))?;
+
+        let mut response = serde_json::json!({
+            "success": true,
+            "directory": directory,
+            "deleted": deleted,
+        });
+        if let Some(backup) = backup_path {
+            response["backup_path"] = serde_json::json!(backup);
+        }
+        json_result(response)
+    }
+
+    // ── Tool 20: word_mapping_sources ──────────────────────────────
+
+    #[tool(
+        description = "List the documents a dictionary mapping (source word -> target word) was extracted from, with the confidence each gave. Useful for auditing build_dictionary results."
+    )]
+    async fn word_mapping_sources(
+        &self,
+        params: Parameters<WordMappingSourcesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let db = self.ctx.db.clone();
+        let (source_word, target_word) = (p.source_word.clone(), p.target_word.clone());
+        let sources = tokio::task::spawn_blocking(move || {
+            db.get_word_mapping_sources(&source_word, &target_word)
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+        .map_err(|e| McpError::internal_error(format!("lookup failed: {e}"), None))?;
+
+        let sources_json: Vec<serde_json::Value> = sources
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "document": s.document,
+                    "source_lang": s.source_lang,
+                    "confidence": s.confidence,
+                })
+            })
+            .collect();
+
+        json_result(serde_json::json!({
+            "source_word": p.source_word,
+            "target_word": p.target_word,
+            "sources": sources_json,
+            "count": sources_json.len(),
+        }))
+    }
+
+    // ── Tool 21: list_symbols ───────────────────────────────────────
+
+    #[tool(
+        description = "Outline of an indexed code file: every symbol with its type, signature, parent symbol and line range, ordered by line. No query needed."
+    )]
+    async fn list_symbols(
+        &self,
+        params: Parameters<ListSymbolsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let filename = stored_name(&p.filename);
+
+        let db = self.ctx.db.clone();
+        let f_clone = filename.clone();
+        let symbols = tokio::task::spawn_blocking(move || db.list_symbols_for_file(&f_clone))
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+            .map_err(|e| McpError::internal_error(format!("symbol lookup failed: {e}"), None))?;
+
+        let symbols_json: Vec<serde_json::Value> = symbols
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "symbol_name": s.symbol_name,
+                    "symbol_type": s.symbol_type,
+                    "language": s.language,
+                    "signature": s.signature,
+                    "parent_symbol": s.parent_symbol,
+                    "start_line": s.start_line,
+                    "end_line": s.end_line,
+                })
+            })
+            .collect();
+
+        json_result(serde_json::json!({
+            "filename": filename,
+            "count": symbols_json.len(),
+            "symbols": symbols_json,
+        }))
+    }
+
+    // ── Tool 22: reindex_matching ───────────────────────────────────
+
+    #[tool(
+        description = "Reindex only the indexed documents whose filename matches a glob pattern (e.g. 'api-*.md'), re-reading them from disk. Reports the outcome per file; documents whose file no longer exists are removed."
+    )]
+    async fn reindex_matching(
+        &self,
+        params: Parameters<ReindexMatchingParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        if p.pattern.trim().is_empty() {
+            return Err(McpError::invalid_params(
+                "pattern is required".to_string(),
+                None,
+            ));
+        }
+        let backup_path = backup_before_destructive(&self.ctx).await?;
+        let embedder = self.ctx.get_embedder().await;
+        let config = self.ctx.config.read().await.clone();
+        let indexer = Indexer::new(
+            self.ctx.db.clone(),
+            embedder.as_ref(),
+            self.ctx.chunk_size,
+            Arc::new(config),
+        );
+
+        let result = match indexer.reindex_matching(p.pattern.trim()).await {
+            Ok(r) => r,
+            Err(e) => return error_result(&format!("reindex failed: {e}")),
+        };
+
+        let outcome = |files: &[String], status: &str| {
+            files
+                .iter()
+                .map(|f| serde_json::json!({"filename": f, "status": status}))
+                .collect::<Vec<_>>()
+        };
+        let mut files = outcome(&result.succeeded, "reindexed");
+        files.extend(outcome(&result.failed, "failed"));
+        files.extend(outcome(&result.missing, "missing"));
+
+        let mut response = serde_json::json!({
+            "success": true,
+            "pattern": p.pattern,
+            "succeeded": result.succeeded.len(),
+            "failed_count": result.failed.len(),
+            "missing_count": result.missing.len(),
+            "files": files,
+        });
+        if let Some(backup) = backup_path {
+            response["backup_path"] = serde_json::json!(backup);
+        }
+        json_result(response)
+    }
+
+    // ── Tool 23: centroid ───────────────────────────────────────────
+
+    #[tool(
+        description = "Embed a set of example texts and return their normalized mean vector (the 'topic' they share) together with the indexed documents closest to it."
+    )]
+    async fn centroid(
+        &self,
+        params: Parameters<CentroidParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let config = self.ctx.config.read().await.clone();
+        let texts: Vec<String> = p
+            .texts
+            .iter()
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .map(|t| config.passage_text(t))
+            .collect();
+        if texts.is_empty() {
+            return Err(McpError::invalid_params(
+                "texts must contain at least one non-empty text".to_string(),
+                None,
+            ));
+        }
+        let top_k = p.top_k.unwrap_or(5).max(1);
+        let count = texts.len();
+
+        let embedder = self.ctx.get_embedder().await;
+        let db = self.ctx.db.clone();
+        let (center, hits) = tokio::task::spawn_blocking(move || {
+            let text_refs: Vec<&str> = texts.iter().map(|s| s.as_str()).collect();
+            let vectors = embedder
+                .embed_batch(&text_refs)
+                .map_err(|e| McpError::invalid_request(format!("embedding failed: {e}"), None))?;
+            let center = crate::embedder::centroid(&vectors).unwrap_or_default();
+            let hits = db
+                .nearest_documents(&center, top_k)
+                .map_err(|e| McpError::internal_error(format!("search failed: {e}"), None))?;
+            Ok::<_, McpError>((center, hits))
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))??;
+
+        json_result(serde_json::json!({
+            "count": count,
+            "dimensions": center.len(),
+            "centroid": center,
+            "documents": document_hits_json(&hits, config.similarity_precision),
+        }))
+    }
+
+    // ── Tool 24: expand_query ───────────────────────────────────────
+
+    #[tool(
+        description = "Preview query expansion with the word-mapping dictionary: looks up each query token's translations and returns the augmented query plus the substitutions made."
+    )]
+    async fn expand_query(
+        &self,
+        params: Parameters<ExpandQueryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        if p.query.trim().is_empty() {
+            return Err(McpError::invalid_params(
+                "query is required".to_string(),
+                None,
+            ));
+        }
+        let source_lang = p
+            .source_lang
+            .unwrap_or_else(|| dictionary::detect_language(&p.query).to_string());
+
+        let db = self.ctx.db.clone();
+        let query = p.query.clone();
+        let lang = source_lang.clone();
+        let (expanded, substitutions) = tokio::task::spawn_blocking(move || {
+            dictionary::expand_query_with_dictionary(&query, |token| {
+                db.lookup_word_mappings(token, Some(&lang))
+            })
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+        .map_err(|e| McpError::internal_error(format!("lookup failed: {e}"), None))?;
+
+        json_result(serde_json::json!({
+            "query": p.query,
+            "source_lang": source_lang,
+            "expanded_query": expanded,
+            "substitutions": substitutions
+                .iter()
+                .map(|s| serde_json::json!({"token": s.token, "targets": s.targets}))
+                .collect::<Vec<_>>(),
+        }))
+    }
+
+    // ── Tool 25: health_check ───────────────────────────────────────
+
+    #[tool(
+        description = "Report which embedder is active (flags the mock fallback, whose results are meaningless), its dimensions, and whether the database answers, with the sqlite-vec version and document/chunk counts."
+    )]
+    async fn health_check(&self) -> Result<CallToolResult, McpError> {
+        let embedder = self.ctx.get_embedder().await;
+        let model = self.ctx.config.read().await.embedding.api_model.clone();
+
+        let db = self.ctx.db.clone();
+        let database = tokio::task::spawn_blocking(move || {
+            Ok::<_, rusqlite::Error>((db.sqlite_vec_version()?, db.get_index_stats()?))
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?;
+
+        let healthy = !embedder.is_mock() && database.is_ok();
+        let database = match database {
+            Ok((vec_version, stats)) => serde_json::json!({
+                "ok": true,
+                "sqlite_vec_version": vec_version,
+                "documents": stats.documents,
+                "chunks": stats.chunks,
+            }),
+            Err(e) => serde_json::json!({"ok": false, "error": e.to_string()}),
+        };
+        json_result(serde_json::json!({
+            "status": if healthy { "ok" } else { "degraded" },
+            "embedder": {
+                "name": embedder.name(),
+                "mock": embedder.is_mock(),
+                "model": (!embedder.is_mock()).then_some(model),
+                "dimensions": embedder.dimensions(),
+            },
+            "database": database,
+        }))
+    }
+
+    // ── Tool 26: symbol_outline ─────────────────────────────────────
+
+    #[tool(
+        description = "Nested symbol tree of an indexed code file: methods under their struct or class, each with its type, signature and line range, ordered by line."
+    )]
+    async fn symbol_outline(
+        &self,
+        params: Parameters<SymbolOutlineParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let filename = stored_name(&p.filename);
+
+        let db = self.ctx.db.clone();
+        let f_clone = filename.clone();
+        let symbols = tokio::task::spawn_blocking(move || db.list_symbols_for_file(&f_clone))
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+            .map_err(|e| McpError::internal_error(format!("symbol lookup failed: {e}"), None))?;
+
+        let count = symbols.len();
+        let outline: Vec<serde_json::Value> = symbol_outline(symbols)
+            .iter()
+            .map(symbol_node_json)
+            .collect();
+        json_result(serde_json::json!({
+            "filename": filename,
+            "count": count,
+            "outline": outline,
+        }))
+    }
+
+    // ── Tool 27: add_frontmatter_bulk ───────────────────────────────
+
+    #[tool(
+        description = "Apply frontmatter to every markdown file under a directory. Files without frontmatter get it added; files that have some are skipped unless overwrite=true, which merges the given fields in. Reports the outcome per file."
+    )]
+    async fn add_frontmatter_bulk(
+        &self,
+        params: Parameters<FrontmatterBulkParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let dir = std::path::PathBuf::from(&p.directory);
+        if !dir.is_dir() {
+            return error_result(&format!("Directory does not exist: {}", dir.display()));
+        }
+        let metadata = build_frontmatter_metadata(&p.metadata);
+        let overwrite = p.overwrite.unwrap_or(false);
+
+        let config = self.ctx.config.read().await.clone();
+        let outcomes = tokio::task::spawn_blocking(move || {
+            markdown_files(&config, &dir)
+                .into_iter()
+                .map(|path| {
+                    let outcome = frontmatter::apply_frontmatter(&path, &metadata, overwrite);
+                    (normalize_system_path(&path), outcome)
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?;
+
+        let count = |kind: &str| outcomes.iter().filter(|(_, o)| o.as_str() == kind).count();
+        let files: Vec<serde_json::Value> = outcomes
+            .iter()
+            .map(|(file, outcome)| {
+                let mut entry = serde_json::json!({"file": file, "outcome": outcome.as_str()});
+                if let frontmatter::ApplyOutcome::Failed(e) = outcome {
+                    entry["error"] = serde_json::json!(e);
+                }
+                entry
+            })
+            .collect();
+        json_result(serde_json::json!({
+            "success": count("failed") == 0,
+            "directory": p.directory,
+            "added": count("added"),
+            "updated": count("updated"),
+            "skipped": count("skipped"),
+            "failed": count("failed"),
+            "files": files,
+        }))
+    }
+
+    // ── Tool 28: top_symbols ────────────────────────────────────────
+
+    #[tool(
+        description = "Find the most connected code symbols: the most called/imported/inherited targets (direction=incoming) or the symbols with the most outgoing relations (direction=outgoing), ranked by relation count. Useful to spot the hubs of a codebase."
+    )]
+    async fn top_symbols(
+        &self,
+        params: Parameters<TopSymbolsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let direction = p.direction.unwrap_or_else(|| "incoming".to_string());
+        if direction != "incoming" && direction != "outgoing" {
+            return Err(McpError::invalid_params(
+                format!("unknown direction: {direction}. Use 'incoming' or 'outgoing'."),
+                None,
+            ));
+        }
+        let limit = p.limit.unwrap_or(20);
+        let rel_type = p.relation_type;
+
+        let db = self.ctx.db.clone();
+        let (dir, rt) = (direction.clone(), rel_type.clone());
+        let symbols =
+            tokio::task::spawn_blocking(move || db.top_symbols(&dir, rt.as_deref(), limit))
+                .await
+                .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+                .map_err(|e| McpError::internal_error(format!("query failed: {e}"), None))?;
+
+        let symbols_json: Vec<serde_json::Value> = symbols
+            .iter()
+            .map(|s| serde_json::json!({"symbol": s.name, "count": s.count}))
+            .collect();
+        json_result(serde_json::json!({
+            "direction": direction,
+            "relation_type": rel_type,
+            "count": symbols_json.len(),
+            "symbols": symbols_json,
+        }))
+    }
+
+    // ── Tool 29: embedding_health ───────────────────────────────────
+
+    #[tool(
+        description = "Sample stored embedding vectors and report their norm distribution (min/median/max/mean) and how many are degenerate (near-zero, e.g. from a failed model). Normalized embeddings should have norms of about 1.0."
+    )]
+    async fn embedding_health(
+        &self,
+        params: Parameters<EmbeddingHealthParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let sample_size = params.0.sample_size.unwrap_or(1000);
+        let db = self.ctx.db.clone();
+        let stats = tokio::task::spawn_blocking(move || db.embedding_norm_stats(sample_size))
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+            .map_err(|e| McpError::internal_error(format!("stats failed: {e}"), None))?;
+
+        let round = |v: f64| (v * 1e4).round() / 1e4;
+        json_result(serde_json::json!({
+            "status": if stats.degenerate == 0 { "ok" } else { "degraded" },
+            "total_vectors": stats.total,
+            "sampled": stats.sampled,
+            "norm": {
+                "min": round(stats.min),
+                "median": round(stats.median),
+                "max": round(stats.max),
+                "mean": round(stats.mean),
+            },
+            "unit_norm": stats.unit_norm,
+            "degenerate": stats.degenerate,
+            "degenerate_chunk_ids": stats.degenerate_chunk_ids,
+        }))
+    }
+
+    // ── Tool 30: move_document ──────────────────────────────────────
+
+    #[tool(
+        description = "Rename an indexed document's path in place, keeping its chunks and embeddings. Use after moving or renaming a file (e.g. git mv) instead of re-indexing it and leaving the old path behind. Fails if the new path is already indexed."
+    )]
+    async fn move_document(
+        &self,
+        params: Parameters<MoveDocumentParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let old = stored_name(&p.old_filename);
+        let new = stored_name(&p.new_filename);
+        if old == new {
+            return error_result("old_filename and new_filename are the same document");
+        }
+
+        // Lock both names, in a fixed order, so indexing can't race the rename
+        let (first, second) = if old < new {
+            (&old, &new)
+        } else {
+            (&new, &old)
+        };
+        let _first = lock_file(first).await;
+        let _second = lock_file(second).await;
+
+        let db = self.ctx.db.clone();
+        let (old_name, new_name) = (old.clone(), new.clone());
+        let renamed = tokio::task::spawn_blocking(move || db.rename_document(&old_name, &new_name))
+            .await
+            .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?;
+
+        match renamed {
+            Ok(true) => json_result(serde_json::json!({
+                "success": true,
+                "old_filename": old,
+                "new_filename": new,
+            })),
+            Ok(false) => error_result(&format!("document not indexed: {old}")),
+            Err(e) if e.sqlite_error_code() == Some(rusqlite::ErrorCode::ConstraintViolation) => {
+                error_result(&format!(
+                    "conflict: {new} is already indexed; delete it first or pick another name"
+                ))
+            }
+            Err(e) => Err(McpError::internal_error(
+                format!("rename failed: {e}"),
+                None,
+            )),
+        }
+    }
+}
+
+// ── Helper functions ─────────────────────────────────────────────────
+
+/// Name a path argument is stored under: documents are indexed by canonical
+/// absolute path, so resolve it the same way when it exists; otherwise (e.g.
+/// a deleted file) only normalize the separators.
+fn stored_name(name: &str) -> String {
+    let path = Path::new(name);
+    if path.exists() {
+        normalize_system_path(path)
+    } else {
+        name.replace('\\', "/")
+    }
+}
+
+fn symbol_node_json(node: &SymbolNode) -> serde_json::Value {
+    let s = &node.symbol;
+    serde_json::json!({
+        "symbol_name": s.symbol_name,
+        "symbol_type": s.symbol_type,
+        "signature": s.signature,
+        "start_line": s.start_line,
+        "end_line": s.end_line,
+        "children": node.children.iter().map(symbol_node_json).collect::<Vec<_>>(),
+    })
+}
+
+fn build_frontmatter_metadata(p: &FrontmatterFields) -> frontmatter::Metadata {
+    let tags = p
+        .tags
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    frontmatter::Metadata {
         domain: p.domain.clone().unwrap_or_default(),
         doc_type: p.doc_type.clone().unwrap_or_default(),
         language: p.language.clone().unwrap_or_default(),
         tags,
         project: p.project.clone().unwrap_or_default(),
+        ..Default::default()
     }
 }
 
@@ -742,17 +2233,58 @@ async fn index_single_file(
         .and_then(|e| e.to_str())
         .unwrap_or_default();
 
-    match classify_extension(ext) {
-        Some(FileType::Markdown) => index_single_markdown_file(path, filepath, ctx).await,
+    // Store canonical absolute paths so same-named files in different roots
+    // don't collide on the unique filename column.
+    let db_path = normalize_system_path(path);
+    let (file_type, too_large) = {
+        let config = ctx.config.read().await;
+        let len = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        (
+            classify_file(ext, &config),
+            config.exceeds_max_file_size(ext, len),
+        )
+    };
+    if too_large {
+        return Err(McpError::invalid_params(
+            format!("file exceeds max_file_size_bytes: {filepath}"),
+            None,
+        ));
+    }
+    let _guard = lock_file(&db_path).await;
+
+    let mod_time = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_or_else(|_| chrono::Utc::now(), Into::into);
+    // Read once, so the stored content hash covers exactly what is chunked
+    let content = std::fs::read(path)
+        .map_err(|e| McpError::invalid_params(format!("read failed: {e}"), None))?;
+    let hash = sha256_hex(&content);
+    let original = if file_type.is_some() {
+        let config = ctx.config.read().await;
+        alias_duplicate(&ctx.db, &config, &hash, &db_path, mod_time)
+    } else {
+        None
+    };
+    if let Some(original) = original {
+        return json_result(serde_json::json!({
+            "success": true,
+            "message": "Same content as an indexed document; stored as an alias",
+            "file": filepath,
+            "alias_of": original,
+        }));
+    }
+
+    match file_type {
+        Some(FileType::Markdown) => index_single_markdown_file(content, hash, &db_path, ctx).await,
         Some(FileType::Code) => {
-            index_single_code_file(path, filepath, ctx).await?;
+            index_single_code_file(content, ext, hash, &db_path, ctx).await?;
             json_result(serde_json::json!({
                 "success": true,
                 "message": "Code file indexed successfully",
                 "file": filepath,
             }))
         }
-        Some(FileType::Text) => index_single_text_file(path, filepath, ctx).await,
+        Some(FileType::Text) => index_single_text_file(content, ext, hash, &db_path, ctx).await,
         None => Err(McpError::invalid_params(
             format!("unsupported file type: .{ext}"),
             None,
@@ -760,14 +2292,26 @@ async fn index_single_file(
     }
 }
 
-/// Index a single markdown file.
+/// Index a single markdown file from its `content`, whose SHA-256 is
+/// `content_hash`.
 async fn index_single_markdown_file(
-    path: &Path,
+    content: Vec<u8>,
+    content_hash: String,
     filepath: &str,
     ctx: &McpContext,
 ) -> Result<CallToolResult, McpError> {
-    let chunks = crate::indexer::markdown::parse_markdown(path, ctx.chunk_size)
+    use crate::indexer::markdown;
+
+    let config = ctx.config.read().await.clone();
+    let content = String::from_utf8(content)
         .map_err(|e| McpError::invalid_params(format!("parse failed: {e}"), None))?;
+    let chunks = markdown::parse_markdown_content(
+        &content,
+        ctx.chunk_size,
+        config.chunk_overlap,
+        config.chunk_strategy,
+        config.normalize_whitespace,
+    );
 
     if chunks.is_empty() {
         return json_result(serde_json::json!({
@@ -776,26 +2320,50 @@ async fn index_single_markdown_file(
         }));
     }
 
+    let mut code_blocks = if config.extract_markdown_code_blocks {
+        markdown::code_block_chunks(markdown::strip_bom(&content))
+    } else {
+        Vec::new()
+    };
+    if config.index_frontmatter {
+        code_blocks.extend(markdown::frontmatter_chunk(markdown::strip_bom(&content)));
+    }
+
     let embedder = ctx.get_embedder().await;
     let db_path = filepath.replace('\\', "/");
     let db = ctx.db.clone();
+    let doc_type = markdown_doc_type(&content);
+    let title = markdown_title(&content, &config);
 
     tokio::task::spawn_blocking(move || {
-        let text_refs: Vec<&str> = chunks.iter().map(|c| c.content.as_str()).collect();
+        let texts: Vec<String> = chunks
+            .iter()
+            .map(|c| config.passage_text(&c.content))
+            .chain(
+                code_blocks
+                    .iter()
+                    .map(|c| config.passage_text(&c.embedding_text_for(&config))),
+            )
+            .collect();
+        let text_refs: Vec<&str> = texts.iter().map(|s| s.as_str()).collect();
         let vectors = embedder
             .embed_batch(&text_refs)
             .map_err(|e| McpError::invalid_request(format!("embedding failed: {e}"), None))?;
 
-        let db_chunks: Vec<crate::db::models::Chunk> = chunks
-            .iter()
-            .map(|c| crate::db::models::Chunk {
-                position: c.position,
-                content: c.content.as_str(),
-            })
-            .collect();
-
-        db.insert_document(&db_path, chrono::Utc::now(), &db_chunks, &vectors)
-            .map_err(|e| McpError::internal_error(format!("DB insert failed: {e}"), None))?;
+        store_markdown_document(
+            &db,
+            &db_path,
+            chrono::Utc::now(),
+            Some(&content_hash),
+            &chunks,
+            &code_blocks,
+            &vectors,
+            &config,
+        )
+        .map_err(|e| McpError::internal_error(format!("DB insert failed: {e}"), None))?;
+        db.set_document_doc_type(&db_path, doc_type.as_deref())
+            .and_then(|()| db.set_document_title(&db_path, title.as_deref()))
+            .map_err(|e| McpError::internal_error(format!("DB update failed: {e}"), None))?;
 
         Ok::<_, McpError>(())
     })
@@ -809,17 +2377,23 @@ async fn index_single_markdown_file(
     }))
 }
 
-/// Index a single code file (parse AST + embed + insert).
+/// Index a single code file (parse AST + embed + insert) from its `source`,
+/// whose SHA-256 is `content_hash`.
 async fn index_single_code_file(
-    path: &Path,
+    source: Vec<u8>,
+    ext: &str,
+    content_hash: String,
     filepath: &str,
     ctx: &McpContext,
 ) -> Result<(), McpError> {
-    let mut parser = CodeParser::new()
-        .map_err(|e| McpError::internal_error(format!("parser init: {e}"), None))?;
+    let config = ctx.config.read().await.clone();
+    let mut parser = CodeParser::with_languages(config.enabled_languages.as_deref())
+        .map_err(|e| McpError::internal_error(format!("parser init: {e}"), None))?
+        .exclude_tests(config.exclude_test_code)
+        .max_chunk_tokens(config.max_code_chunk_tokens.clone());
 
     let code_chunks = parser
-        .parse_file(path)
+        .parse_source(&source, ext)
         .map_err(|e| McpError::invalid_params(format!("parse failed: {e}"), None))?;
 
     if code_chunks.is_empty() {
@@ -831,7 +2405,15 @@ async fn index_single_code_file(
     let db = ctx.db.clone();
 
     tokio::task::spawn_blocking(move || {
-        let text_refs: Vec<String> = code_chunks.iter().map(|c| c.get_embedding_text()).collect();
+        let docs = doc_chunks(&config, &code_chunks);
+        let text_refs: Vec<String> = code_chunks
+            .iter()
+            .map(|c| config.passage_text(&c.embedding_text_for(&config)))
+            .chain(
+                docs.iter()
+                    .map(|d| doc_chunk_embedding_text(&config, d.chunk.content)),
+            )
+            .collect();
         let text_str_refs: Vec<&str> = text_refs.iter().map(|s| s.as_str()).collect();
 
         let vectors = embedder
@@ -846,6 +2428,8 @@ async fn index_single_code_file(
                 chunk: crate::db::models::Chunk {
                     position: i,
                     content: &c.content,
+                    start_line: None,
+                    end_line: None,
                 },
                 symbol_name: Some(c.symbol_name.as_str()),
                 symbol_type: &c.symbol_type,
@@ -854,10 +2438,26 @@ async fn index_single_code_file(
                 end_line: Some(c.end_line),
                 parent_symbol: c.parent_symbol.as_deref(),
                 signature: Some(c.signature.as_str()),
+                doc_comment: c.doc_comment.as_deref(),
+                part: c.part,
             })
             .collect();
 
-        db.insert_code_document(&db_path, chrono::Utc::now(), &db_chunks, &vectors)
+        let chunk_ids = db
+            .insert_code_document_with_docs(
+                &db_path,
+                chrono::Utc::now(),
+                Some(&content_hash),
+                &db_chunks,
+                &docs,
+                &vectors,
+            )
+            .map_err(|e| McpError::internal_error(format!("DB insert failed: {e}"), None))?;
+        let relations = code_relations(&config, &source, &db_path, &code_chunks, &chunk_ids)
+            .map_err(|e| {
+                McpError::internal_error(format!("relation extraction failed: {e}"), None)
+            })?;
+        db.insert_relations(&relations)
             .map_err(|e| McpError::internal_error(format!("DB insert failed: {e}"), None))?;
 
         Ok::<_, McpError>(())
@@ -868,14 +2468,42 @@ async fn index_single_code_file(
     Ok(())
 }
 
-/// Index a single text/structured/document file.
+/// Back up the DB file if `auto_backup_before_destructive` is enabled,
+/// keeping the newest `max_backups`. Returns the backup path, or `None` when
+/// disabled or the DB is in-memory.
+async fn backup_before_destructive(ctx: &McpContext) -> Result<Option<String>, McpError> {
+    let keep = {
+        let config = ctx.config.read().await;
+        if !config.auto_backup_before_destructive {
+            return Ok(None);
+        }
+        config.max_backups
+    };
+    let db = ctx.db.clone();
+    let backup = tokio::task::spawn_blocking(move || db.backup(keep))
+        .await
+        .map_err(|e| McpError::internal_error(format!("blocking failed: {e}"), None))?
+        .map_err(|e| McpError::internal_error(format!("backup failed: {e}"), None))?;
+    Ok(backup.map(|p| p.to_string_lossy().replace('\\', "/")))
+}
+
+/// Index a single text/structured/document file from its `data`, whose
+/// SHA-256 is `content_hash`.
 async fn index_single_text_file(
-    path: &Path,
+    data: Vec<u8>,
+    ext: &str,
+    content_hash: String,
     filepath: &str,
     ctx: &McpContext,
 ) -> Result<CallToolResult, McpError> {
-    let chunks = crate::indexer::text_parser::extract_and_chunk(path, ctx.chunk_size)
-        .map_err(|e| McpError::invalid_params(format!("parse failed: {e}"), None))?;
+    let config = ctx.config.read().await.clone();
+    let chunks = crate::indexer::text_parser::extract_and_chunk_bytes(
+        &data,
+        ext,
+        ctx.chunk_size,
+        config.chunk_overlap,
+    )
+    .map_err(|e| McpError::invalid_params(format!("parse failed: {e}"), None))?;
 
     if chunks.is_empty() {
         return json_result(serde_json::json!({
@@ -889,7 +2517,11 @@ async fn index_single_text_file(
     let db = ctx.db.clone();
 
     tokio::task::spawn_blocking(move || {
-        let text_refs: Vec<&str> = chunks.iter().map(|c| c.content.as_str()).collect();
+        let texts: Vec<String> = chunks
+            .iter()
+            .map(|c| config.passage_text(&c.content))
+            .collect();
+        let text_refs: Vec<&str> = texts.iter().map(|s| s.as_str()).collect();
         let vectors = embedder
             .embed_batch(&text_refs)
             .map_err(|e| McpError::invalid_request(format!("embedding failed: {e}"), None))?;
@@ -899,11 +2531,19 @@ async fn index_single_text_file(
             .map(|c| crate::db::models::Chunk {
                 position: c.position,
                 content: c.content.as_str(),
+                start_line: c.start_line,
+                end_line: c.end_line,
             })
             .collect();
 
-        db.insert_document(&db_path, chrono::Utc::now(), &db_chunks, &vectors)
-            .map_err(|e| McpError::internal_error(format!("DB insert failed: {e}"), None))?;
+        db.insert_document(
+            &db_path,
+            chrono::Utc::now(),
+            Some(&content_hash),
+            &db_chunks,
+            &vectors,
+        )
+        .map_err(|e| McpError::internal_error(format!("DB insert failed: {e}"), None))?;
 
         Ok::<_, McpError>(())
     })
@@ -916,3 +2556,86 @@ async fn index_single_text_file(
         "file": filepath,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::Db;
+    use crate::db::models::Chunk;
+    use crate::embedder::{Embedder, EmbedderError};
+
+    /// Fails every call, like an unreachable embedding API.
+    struct DownEmbedder;
+
+    impl Embedder for DownEmbedder {
+        fn embed(&self, _text: &str) -> Result<Vec<f32>, EmbedderError> {
+            Err(EmbedderError::InferenceFailed("embedder is down".into()))
+        }
+
+        fn embed_batch(&self, _texts: &[&str]) -> Result<Vec<Vec<f32>>, EmbedderError> {
+            Err(EmbedderError::InferenceFailed("embedder is down".into()))
+        }
+
+        fn dimensions(&self) -> usize {
+            1024
+        }
+
+        fn name(&self) -> &str {
+            "down"
+        }
+    }
+
+    async fn short_query_tools(action: ShortQueryAction) -> AppTools {
+        let db = Arc::new(Db::open_in_memory().unwrap());
+        let chunk = Chunk {
+            position: 0,
+            content: "Axis x is horizontal.",
+            start_line: None,
+            end_line: None,
+        };
+        db.insert_document(
+            "/p/axes.md",
+            chrono::Utc::now(),
+            None,
+            &[chunk],
+            &[vec![0.1; 1024]],
+        )
+        .unwrap();
+        let config = Config {
+            min_query_chars: 3,
+            short_query: action,
+            update_check: Some(false),
+            ..Default::default()
+        };
+        let ctx = McpContext::new(db, Arc::new(config), 500, String::new());
+        ctx.set_embedder(Arc::new(DownEmbedder)).await;
+        AppTools::new(ctx)
+    }
+
+    fn search_params(query: &str) -> Parameters<SearchParams> {
+        Parameters(serde_json::from_value(serde_json::json!({ "query": query })).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_short_query_runs_keyword_search_without_embedding() {
+        let tools = short_query_tools(ShortQueryAction::Keyword).await;
+        let result = tools.search(search_params("x")).await.unwrap();
+        let text = &result.content[0].as_text().unwrap().text;
+        let response: serde_json::Value = serde_json::from_str(text).unwrap();
+        assert_eq!(response["mode"], "keyword");
+        assert_eq!(
+            response["note"],
+            "query below min_query_chars; ran a keyword search instead"
+        );
+        assert_eq!(response["results"][0]["document"], "/p/axes.md");
+    }
+
+    #[tokio::test]
+    async fn test_short_query_rejected() {
+        let tools = short_query_tools(ShortQueryAction::Error).await;
+        let err = tools.search(search_params("x")).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::INVALID_PARAMS);
+        assert!(err.message.contains("shorter than 3 characters"));
+    }
+}
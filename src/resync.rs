@@ -0,0 +1,158 @@
+//! Differential sync of the configured base directories, run once at
+//! startup and optionally again on a fixed interval.
+use crate::indexer::core::{IndexProgress, Indexer};
+use crate::mcp::server::McpContext;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// Files between "Sync progress" log lines during a sync.
+const SYNC_PROGRESS_INTERVAL: usize = 100;
+
+/// Sync every base directory of the current config, pruning documents whose
+/// file is gone, and log each directory's result.
+pub async fn sync_base_directories(ctx: &McpContext) {
+    let base_dirs = ctx.config.read().await.get_base_directories();
+    let embedder = ctx.get_embedder().await;
+
+    for dir in &base_dirs {
+        if !dir.exists() {
+            warn!(dir = %dir.display(), "Directory does not exist, skipping");
+            continue;
+        }
+
+        info!(dir = %dir.display(), "Syncing directory");
+
+        let report_progress = |p: &IndexProgress| {
+            if p.processed.is_multiple_of(SYNC_PROGRESS_INTERVAL) {
+                info!(
+                    dir = %dir.display(),
+                    processed = p.processed,
+                    total = p.total,
+                    added = p.added,
+                    updated = p.updated,
+                    skipped = p.skipped,
+                    failed = p.failed,
+                    "Sync progress"
+                );
+            }
+        };
+        let result = {
+            let mut indexer = Indexer::new(
+                ctx.db.clone(),
+                embedder.as_ref(),
+                ctx.chunk_size,
+                Arc::new(ctx.config.read().await.clone()),
+            );
+            indexer
+                .index_directory_with_progress(dir, false, true, false, Some(&report_progress))
+                .await
+        };
+
+        match result {
+            Ok(result) => {
+                info!(
+                    dir = %dir.display(),
+                    indexed = result.indexed,
+                    added = result.added,
+                    updated = result.updated,
+                    skipped = result.skipped,
+                    removed = result.removed,
+                    generated = result.generated,
+                    skipped_large = result.skipped_large,
+                    failed = result.failed,
+                    "Sync completed"
+                );
+            }
+            Err(e) => {
+                error!(dir = %dir.display(), error = %e, "Sync failed");
+            }
+        }
+    }
+}
+
+/// Spawn a task that re-runs [`sync_base_directories`] every `interval`,
+/// starting one interval from now, until `shutdown` is cancelled.
+///
+/// A sync in progress is finished before the task stops, so no document
+/// is left half-written.
+pub fn spawn_periodic_resync(
+    ctx: McpContext,
+    interval: Duration,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval_at(Instant::now() + interval, interval);
+        // A sync longer than the interval delays the next one instead of
+        // triggering a burst of catch-up runs
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        info!(
+            interval_secs = interval.as_secs_f64(),
+            "Periodic re-sync enabled"
+        );
+
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = ticker.tick() => {
+                    info!("Periodic re-sync started");
+                    sync_base_directories(&ctx).await;
+                    info!("Periodic re-sync finished");
+                }
+            }
+        }
+        info!("Periodic re-sync stopped");
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::Db;
+    use crate::embedder::mock::MockEmbedder;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_periodic_resync_picks_up_new_files() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("old.md"), "Indexed at startup").unwrap();
+        let config = Config {
+            document_patterns: vec![dir.path().to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        let db = Arc::new(Db::open_in_memory().unwrap());
+        let ctx = McpContext::new(db.clone(), Arc::new(config), 500, String::new());
+        ctx.set_embedder(Arc::new(MockEmbedder::default())).await;
+
+        sync_base_directories(&ctx).await;
+        assert_eq!(db.list_documents().unwrap().len(), 1);
+
+        let shutdown = CancellationToken::new();
+        let handle =
+            spawn_periodic_resync(ctx.clone(), Duration::from_millis(50), shutdown.clone());
+        fs::write(dir.path().join("new.md"), "Created after startup").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while db.list_documents().unwrap().len() < 2 {
+            assert!(Instant::now() < deadline, "new file was never synced");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(
+            db.list_documents()
+                .unwrap()
+                .keys()
+                .any(|name| name.ends_with("/new.md"))
+        );
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(10), handle)
+            .await
+            .expect("re-sync task did not stop")
+            .unwrap();
+    }
+}
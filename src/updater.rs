@@ -5,16 +5,17 @@
 /// Mirrors Go version's `internal/updater/updater.go`.
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 // ── Constants ────────────────────────────────────────────────────────
 
-const GITHUB_API_URL: &str = "https://api.github.com/repos/Michaol/RustRAG/releases/latest";
-const RELEASE_URL: &str = "https://github.com/Michaol/RustRAG/releases/latest";
+/// Repository checked when `update_repo` is unset or malformed.
+const DEFAULT_UPDATE_REPO: &str = "Michaol/RustRAG";
 const CHECK_INTERVAL_SECS: u64 = 24 * 60 * 60; // 24 hours
 const CACHE_FILENAME: &str = ".rustrag_update_check";
 
@@ -36,6 +37,10 @@ struct UpdateCache {
     last_check: u64,
     latest_version: String,
     notified_version: String,
+    /// Unix timestamp of the last notice, so MCP responses repeat it at most
+    /// once per interval
+    #[serde(default)]
+    notified_at: u64,
 }
 
 /// Information about an available update.
@@ -47,50 +52,96 @@ pub struct UpdateInfo {
     pub url: String,
 }
 
+/// GitHub URLs for a repository's latest release.
+#[derive(Debug, PartialEq)]
+struct ReleaseUrls {
+    api: String,
+    release: String,
+}
+
+impl ReleaseUrls {
+    /// URLs for `repo` (`owner/repo`), falling back to the default
+    /// repository when it is `None` or malformed.
+    fn for_repo(repo: Option<&str>) -> Self {
+        let repo = match repo.map(str::trim) {
+            Some(r) if is_valid_repo(r) => r,
+            Some(r) => {
+                tracing::warn!(
+                    "Ignoring malformed update_repo {r:?} (expected owner/repo), \
+                     using {DEFAULT_UPDATE_REPO}"
+                );
+                DEFAULT_UPDATE_REPO
+            }
+            None => DEFAULT_UPDATE_REPO,
+        };
+        Self {
+            api: format!("https://api.github.com/repos/{repo}/releases/latest"),
+            release: format!("https://github.com/{repo}/releases/latest"),
+        }
+    }
+}
+
 // ── Public API ───────────────────────────────────────────────────────
 
+/// Whether a background cache refresh started by [`get_update_info`] is running.
+static REFRESH_IN_FLIGHT: AtomicBool = AtomicBool::new(false);
+
 /// Get update info for inclusion in MCP responses.
 ///
-/// Returns `Some(UpdateInfo)` if a newer version is available
-/// and the user hasn't been notified within the last 24 hours.
-/// Returns `None` otherwise (no update, recently checked, or error).
-pub async fn get_update_info(current_version: &str, cache_dir: &str) -> Option<UpdateInfo> {
-    let cache = load_cache(cache_dir).unwrap_or_default();
+/// Answers from the 24-hour cache only, so it is cheap enough for every
+/// search: returns `Some(UpdateInfo)` when the cached latest release is newer
+/// than `current_version` and the user hasn't been notified of it within the
+/// last 24 hours. When the cache is stale, a refresh is spawned in the
+/// background (at most one at a time, and only inside a Tokio runtime) and
+/// its result is seen by later calls.
+/// `repo` overrides the GitHub repository (`owner/repo`) that is checked.
+pub fn get_update_info(
+    current_version: &str,
+    cache_dir: &str,
+    repo: Option<&str>,
+) -> Option<UpdateInfo> {
+    let urls = ReleaseUrls::for_repo(repo);
+    let mut cache = load_cache(cache_dir).unwrap_or_default();
 
-    // Already notified recently?
     let now = current_unix_secs();
-    if !cache.notified_version.is_empty()
-        && now.saturating_sub(cache.last_check) < CHECK_INTERVAL_SECS
+    if now.saturating_sub(cache.last_check) >= CHECK_INTERVAL_SECS
+        && let Ok(handle) = tokio::runtime::Handle::try_current()
+        && !REFRESH_IN_FLIGHT.swap(true, Ordering::AcqRel)
     {
-        return None;
+        let cache_dir = cache_dir.to_string();
+        let api_url = urls.api.clone();
+        handle.spawn(async move {
+            refresh_cache(&api_url, &cache_dir).await;
+            REFRESH_IN_FLIGHT.store(false, Ordering::Release);
+        });
     }
 
-    // Fetch latest
-    let release = fetch_latest_release().await.ok()?;
-    let latest_version = normalize_version(&release.tag_name).ok()?;
-
-    if !is_newer_version(&latest_version, current_version).unwrap_or(false) {
+    if !is_newer_version(&cache.latest_version, current_version).unwrap_or(false) {
         return None;
     }
-
-    // Update cache
-    let mut cache = cache;
-    cache.last_check = now;
-    cache.latest_version = latest_version.clone();
-    cache.notified_version = latest_version.clone();
+    // Already notified of this version recently?
+    if cache.notified_version == cache.latest_version
+        && now.saturating_sub(cache.notified_at) < CHECK_INTERVAL_SECS
+    {
+        return None;
+    }
+    cache.notified_version = cache.latest_version.clone();
+    cache.notified_at = now;
     let _ = save_cache(cache_dir, &cache);
 
     Some(UpdateInfo {
         available: true,
         current_version: current_version.to_string(),
-        latest_version,
-        url: RELEASE_URL.to_string(),
+        latest_version: cache.latest_version,
+        url: urls.release,
     })
 }
 
 /// Check for updates at startup. Prints a notice to stderr if a newer
 /// version is available. Errors are silently ignored (best-effort).
-pub async fn check_for_update(current_version: &str, cache_dir: &str) {
+/// `repo` overrides the GitHub repository (`owner/repo`) that is checked.
+pub async fn check_for_update(current_version: &str, cache_dir: &str, repo: Option<&str>) {
+    let urls = ReleaseUrls::for_repo(repo);
     let mut cache = load_cache(cache_dir).unwrap_or_default();
     let now = current_unix_secs();
 
@@ -101,15 +152,16 @@ pub async fn check_for_update(current_version: &str, cache_dir: &str) {
             && cache.notified_version != cache.latest_version
             && is_newer_version(&cache.latest_version, current_version).unwrap_or(false)
         {
-            print_update_notice(current_version, &cache.latest_version);
+            print_update_notice(current_version, &cache.latest_version, &urls.release);
             cache.notified_version = cache.latest_version.clone();
+            cache.notified_at = now;
             let _ = save_cache(cache_dir, &cache);
         }
         return;
     }
 
     // Fetch latest release
-    let release = match fetch_latest_release().await {
+    let release = match fetch_latest_release(&urls.api).await {
         Ok(r) => r,
         Err(e) => {
             tracing::debug!("Update check failed: {e}");
@@ -129,8 +181,9 @@ pub async fn check_for_update(current_version: &str, cache_dir: &str) {
     cache.latest_version = latest_version.clone();
 
     if is_newer_version(&latest_version, current_version).unwrap_or(false) {
-        print_update_notice(current_version, &latest_version);
+        print_update_notice(current_version, &latest_version, &urls.release);
         cache.notified_version = latest_version;
+        cache.notified_at = now;
     }
 
     let _ = save_cache(cache_dir, &cache);
@@ -138,19 +191,44 @@ pub async fn check_for_update(current_version: &str, cache_dir: &str) {
 
 // ── Internal helpers ─────────────────────────────────────────────────
 
-async fn fetch_latest_release() -> Result<GitHubRelease> {
-    let client = reqwest::Client::builder()
+/// Fetch the latest release into the cache. A failed fetch still counts as
+/// a check, so an offline machine retries once per interval, not per call.
+async fn refresh_cache(api_url: &str, cache_dir: &str) {
+    let mut cache = load_cache(cache_dir).unwrap_or_default();
+    cache.last_check = current_unix_secs();
+    match fetch_latest_release(api_url).await {
+        Ok(release) => match normalize_version(&release.tag_name) {
+            Ok(v) => cache.latest_version = v,
+            Err(e) => tracing::debug!("Invalid version tag {:?}: {e}", release.tag_name),
+        },
+        Err(e) => tracing::debug!("Update check failed: {e}"),
+    }
+    let _ = save_cache(cache_dir, &cache);
+}
+
+async fn fetch_latest_release(api_url: &str) -> Result<GitHubRelease> {
+    let client = crate::http::client_builder()
         .timeout(Duration::from_secs(5))
         .user_agent("rustrag-update-checker")
         .build()
-        .context("HTTP client build failed")?;
+        .with_context(|| {
+            format!(
+                "HTTP client build failed ({} TLS backend)",
+                crate::http::TLS_BACKEND
+            )
+        })?;
 
     let resp = client
-        .get(GITHUB_API_URL)
+        .get(api_url)
         .header("Accept", "application/vnd.github.v3+json")
         .send()
         .await
-        .context("GitHub API request failed")?;
+        .map_err(|e| {
+            anyhow!(
+                "GitHub API request failed: {}",
+                crate::http::describe_error(&e)
+            )
+        })?;
 
     if !resp.status().is_success() {
         bail!("GitHub API returned status {}", resp.status());
@@ -168,6 +246,19 @@ async fn fetch_latest_release() -> Result<GitHubRelease> {
     Ok(release)
 }
 
+/// Whether `repo` has the `owner/repo` shape GitHub accepts.
+fn is_valid_repo(repo: &str) -> bool {
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && part != "."
+            && part != ".."
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    matches!(repo.split_once('/'), Some((owner, name)) if valid_part(owner) && valid_part(name))
+}
+
 /// Extract and validate a semantic version string (e.g., "v1.2.3" → "1.2.3").
 fn normalize_version(version: &str) -> Result<String> {
     use std::sync::LazyLock;
@@ -238,9 +329,8 @@ fn current_unix_secs() -> u64 {
         .as_secs()
 }
 
-fn print_update_notice(current: &str, latest: &str) {
+fn print_update_notice(current: &str, latest: &str, url_line: &str) {
     let msg = format!("New version available: v{latest} (current: v{current})");
-    let url_line = RELEASE_URL;
     let width = msg.len().max(url_line.len()) + 4;
     let border = "─".repeat(width);
 
@@ -289,6 +379,39 @@ mod tests {
         assert!(normalize_version(CURRENT_VERSION).is_ok());
     }
 
+    #[test]
+    fn test_release_urls_for_repo() {
+        let default = ReleaseUrls::for_repo(None);
+        assert_eq!(
+            default.api,
+            "https://api.github.com/repos/Michaol/RustRAG/releases/latest"
+        );
+        assert_eq!(
+            default.release,
+            "https://github.com/Michaol/RustRAG/releases/latest"
+        );
+
+        let fork = ReleaseUrls::for_repo(Some("someone/rust-rag.fork"));
+        assert_eq!(
+            fork.api,
+            "https://api.github.com/repos/someone/rust-rag.fork/releases/latest"
+        );
+        assert_eq!(
+            fork.release,
+            "https://github.com/someone/rust-rag.fork/releases/latest"
+        );
+
+        for malformed in [
+            "", "noslash", "a/b/c", "/repo", "owner/", "../x", "a b/c", "o/r?x=1",
+        ] {
+            assert_eq!(
+                ReleaseUrls::for_repo(Some(malformed)),
+                default,
+                "{malformed}"
+            );
+        }
+    }
+
     #[test]
     fn test_cache_roundtrip() {
         let temp = tempfile::tempdir().unwrap();
@@ -298,6 +421,7 @@ mod tests {
             last_check: 1234567890,
             latest_version: "1.0.0".to_string(),
             notified_version: "1.0.0".to_string(),
+            notified_at: 1234567890,
         };
 
         save_cache(&dir, &cache).unwrap();
@@ -306,5 +430,44 @@ mod tests {
         assert_eq!(loaded.last_check, 1234567890);
         assert_eq!(loaded.latest_version, "1.0.0");
         assert_eq!(loaded.notified_version, "1.0.0");
+        assert_eq!(loaded.notified_at, 1234567890);
+    }
+
+    #[test]
+    fn test_get_update_info_reads_fresh_cache() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_string_lossy().to_string();
+        let cache = |latest: &str| UpdateCache {
+            last_check: current_unix_secs(),
+            latest_version: latest.to_string(),
+            notified_version: String::new(),
+            notified_at: 0,
+        };
+
+        // A fresh cache is answered without spawning a refresh (no runtime here)
+        save_cache(&dir, &cache("999.0.0")).unwrap();
+        let info = get_update_info("1.0.0", &dir, None).unwrap();
+        assert_eq!(info.latest_version, "999.0.0");
+        assert_eq!(
+            info.url,
+            "https://github.com/Michaol/RustRAG/releases/latest"
+        );
+        // Notified once per interval
+        assert!(get_update_info("1.0.0", &dir, None).is_none());
+        let mut notified = load_cache(&dir).unwrap();
+        assert_eq!(notified.notified_version, "999.0.0");
+        notified.notified_at -= CHECK_INTERVAL_SECS;
+        save_cache(&dir, &notified).unwrap();
+        assert!(get_update_info("1.0.0", &dir, None).is_some());
+
+        // A stale cache outside a runtime doesn't panic; no refresh is started
+        let mut stale = load_cache(&dir).unwrap();
+        stale.last_check = 0;
+        save_cache(&dir, &stale).unwrap();
+        assert!(get_update_info("1.0.0", &dir, None).is_none());
+        assert!(!REFRESH_IN_FLIGHT.load(Ordering::Acquire));
+
+        save_cache(&dir, &cache("1.0.0")).unwrap();
+        assert!(get_update_info("1.0.0", &dir, None).is_none());
     }
 }
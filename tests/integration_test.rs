@@ -43,7 +43,10 @@ async fn test_full_pipeline() {
 
     // 4. Index via Indexer
     let mut indexer = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(Config::default()));
-    let result = indexer.index_directory(&docs_dir, false).await.unwrap();
+    let result = indexer
+        .index_directory(&docs_dir, false, true, false)
+        .await
+        .unwrap();
 
     assert_eq!(result.added, 3, "Should index 3 markdown files");
     assert_eq!(result.indexed, 3, "Should report 3 indexed");
@@ -93,13 +96,19 @@ async fn test_full_pipeline() {
 
     // 7. Re-index (should skip unchanged files)
     let mut indexer2 = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(Config::default()));
-    let result2 = indexer2.index_directory(&docs_dir, false).await.unwrap();
+    let result2 = indexer2
+        .index_directory(&docs_dir, false, true, false)
+        .await
+        .unwrap();
     assert_eq!(result2.skipped, 3, "Should skip all 3 on second run");
     assert_eq!(result2.added, 0, "Should add 0 on second run");
 
     // 8. Force re-index
     let mut indexer3 = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(Config::default()));
-    let result3 = indexer3.index_directory(&docs_dir, true).await.unwrap();
+    let result3 = indexer3
+        .index_directory(&docs_dir, true, true, false)
+        .await
+        .unwrap();
     assert_eq!(result3.updated, 3, "Should update all 3 when forced");
     assert_eq!(result3.removed, 0, "Should have 0 removals when forced");
 
@@ -108,7 +117,10 @@ async fn test_full_pipeline() {
     std::fs::remove_file(&hello_path).unwrap();
 
     let mut indexer4 = Indexer::new(db_arc.clone(), &embedder, 500, Arc::new(Config::default()));
-    let result4 = indexer4.index_directory(&docs_dir, false).await.unwrap();
+    let result4 = indexer4
+        .index_directory(&docs_dir, false, true, false)
+        .await
+        .unwrap();
     assert_eq!(result4.removed, 1, "Should detect and remove 1 stale file");
     assert_eq!(
         result4.skipped, 2,
@@ -140,6 +152,66 @@ async fn test_full_pipeline() {
     );
 }
 
+/// Indexing a code file records the calls and imports of its symbols
+#[tokio::test]
+async fn test_index_code_records_relations() {
+    let temp_dir = tempdir().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    fs::create_dir_all(&src_dir).unwrap();
+    fs::write(
+        src_dir.join("app.rs"),
+        "use std::collections::HashMap;\n\nfn run() {\n    let mut m = HashMap::new();\n    helper(&mut m);\n}\n\nfn helper(m: &mut HashMap<u32, u32>) {\n    m.insert(1, 2);\n}\n",
+    )
+    .unwrap();
+
+    let db = Arc::new(Db::open_in_memory().unwrap());
+    let embedder = MockEmbedder::default();
+    let mut indexer = Indexer::new(db.clone(), &embedder, 500, Arc::new(Config::default()));
+    let result = indexer
+        .index_directory(&src_dir, false, true, false)
+        .await
+        .unwrap();
+    assert_eq!(result.indexed, 1);
+
+    let callers = db
+        .find_symbol_relations("helper", "incoming", Some("calls"))
+        .unwrap();
+    assert_eq!(callers.len(), 1, "{callers:?}");
+    assert_eq!(callers[0].source_name.as_deref(), Some("run"));
+    assert!(
+        callers[0]
+            .source_file
+            .as_deref()
+            .unwrap()
+            .ends_with("app.rs")
+    );
+
+    let outgoing = db
+        .find_symbol_relations("helper", "outgoing", None)
+        .unwrap();
+    assert!(
+        outgoing
+            .iter()
+            .any(|r| r.relation_type == "calls" && r.target_name == "insert"),
+        "{outgoing:?}"
+    );
+
+    let imports = db
+        .find_symbol_relations("std::collections::HashMap", "incoming", Some("imports"))
+        .unwrap();
+    assert_eq!(imports.len(), 1, "{imports:?}");
+
+    // Re-indexing replaces the relations instead of duplicating them
+    indexer
+        .index_directory(&src_dir, true, true, false)
+        .await
+        .unwrap();
+    let callers = db
+        .find_symbol_relations("helper", "incoming", Some("calls"))
+        .unwrap();
+    assert_eq!(callers.len(), 1, "{callers:?}");
+}
+
 /// Test config defaults and validation
 #[test]
 fn test_config_defaults_and_validation() {
@@ -175,6 +247,7 @@ fn test_frontmatter_round_trip() {
         language: "rust".to_string(),
         tags: vec!["test".to_string(), "integration".to_string()],
         project: "rustrag".to_string(),
+        ..Default::default()
     };
 
     rustrag::frontmatter::add_frontmatter(&file_path, &metadata).unwrap();
@@ -199,6 +272,7 @@ fn test_frontmatter_round_trip() {
         language: "typescript".to_string(),
         tags: vec!["updated".to_string()],
         project: "new-project".to_string(),
+        ..Default::default()
     };
 
     rustrag::frontmatter::update_frontmatter(&file_path, &updated_metadata).unwrap();
@@ -265,3 +339,46 @@ fn test_batch_embedding() {
         );
     }
 }
+
+/// `rustrag validate` prints the effective config and exits non-zero on errors
+#[test]
+fn test_validate_subcommand() {
+    let temp_dir = tempdir().unwrap();
+    let docs_dir = temp_dir.path().join("docs");
+    fs::create_dir_all(&docs_dir).unwrap();
+
+    let valid = temp_dir.path().join("valid.json");
+    fs::write(
+        &valid,
+        serde_json::json!({ "documents_dir": docs_dir, "chunk_size": 300 }).to_string(),
+    )
+    .unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_rustrag"))
+        .args(["validate", "--config"])
+        .arg(&valid)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let effective: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(effective["chunk_size"], 300);
+    // documents_dir is migrated into document_patterns
+    assert_eq!(
+        effective["document_patterns"][0],
+        docs_dir.to_string_lossy().as_ref()
+    );
+
+    let invalid = temp_dir.path().join("invalid.json");
+    fs::write(
+        &invalid,
+        serde_json::json!({ "document_patterns": [docs_dir], "chunk_size": 0 }).to_string(),
+    )
+    .unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_rustrag"))
+        .args(["validate", "--config"])
+        .arg(&invalid)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("chunk_size must be positive"));
+}